@@ -1,5 +1,11 @@
-use engine::{Engine, EngineOutput, InputBatch};
-use renderer_wgpu::Renderer;
+mod wheel;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use engine::api::{Clipboard, Document, Engine, EngineOutput, InputBatch, InputEvent, Vec2};
+use renderer_wgpu::{BlendMode, Renderer, sanitize_pixel_ratio};
+use serde::Serialize;
 use wasm_bindgen::prelude::*;
 
 #[wasm_bindgen]
@@ -11,27 +17,322 @@ pub fn version() -> String {
 pub struct App {
     engine: Engine,
     renderer: Renderer,
+    // events raised by App methods, applied ahead of the next tick's batch
+    pending_events: Vec<InputEvent>,
+    // physical canvas pixels per CSS pixel
+    device_pixel_ratio: f32,
+    // `on_event` callback and the serialized events it has yet to see
+    event_listener: Option<(js_sys::Function, Rc<RefCell<Vec<JsValue>>>)>,
 }
 
 #[wasm_bindgen]
 impl App {
+    /// # Arguments
+    /// * `canvas` - target canvas, its width/height attributes in physical pixels
+    /// * `device_pixel_ratio` - physical pixels per CSS pixel; defaults to 1
     #[wasm_bindgen]
-    pub async fn new(canvas: web_sys::HtmlCanvasElement) -> Result<App, JsValue> {
-        let renderer = Renderer::new(canvas).await?;
-        Ok(App {
-            engine: Engine::new(),
-            renderer,
-        })
+    pub async fn new(
+        canvas: web_sys::HtmlCanvasElement,
+        device_pixel_ratio: Option<f32>,
+    ) -> Result<App, JsValue> {
+        App::with_engine(canvas, Engine::new(), device_pixel_ratio).await
+    }
+
+    /// Open a saved document instead of the demo one.
+    ///
+    /// # Arguments
+    /// * `json` - document as produced by `Engine::export_document`
+    /// * `fit_to_content` - frame the whole document once the canvas has a size
+    /// * `device_pixel_ratio` - physical pixels per CSS pixel; defaults to 1
+    #[wasm_bindgen]
+    pub async fn new_with_document(
+        canvas: web_sys::HtmlCanvasElement,
+        json: String,
+        fit_to_content: bool,
+        device_pixel_ratio: Option<f32>,
+    ) -> Result<App, JsValue> {
+        let mut engine = Engine::new();
+        engine
+            .load_document(&json)
+            .map_err(|e| JsValue::from_str(&format!("Invalid document: {e}")))?;
+        if fit_to_content {
+            engine.fit_document_on_first_viewport();
+        }
+        App::with_engine(canvas, engine, device_pixel_ratio).await
+    }
+
+    /// Resize the drawing surface and the engine viewport together, e.g. from
+    /// a window or `ResizeObserver` callback. The engine works in CSS pixels,
+    /// so its viewport is the physical size divided by the pixel ratio.
+    ///
+    /// Zero dimensions are clamped to 1. When anything changed, the next
+    /// `tick` redraws even if the scene didn't, so the canvas isn't left stale.
+    ///
+    /// # Arguments
+    /// * `width`, `height` - new canvas size in physical pixels (CSS size times
+    ///   the device pixel ratio), not CSS pixels
+    /// * `device_pixel_ratio` - physical pixels per CSS pixel; `None` keeps the current one
+    #[wasm_bindgen]
+    pub fn resize(&mut self, width: u32, height: u32, device_pixel_ratio: Option<f32>) {
+        let ratio = device_pixel_ratio.map_or(self.device_pixel_ratio, sanitize_pixel_ratio);
+        let ratio_changed = ratio != self.device_pixel_ratio;
+        self.device_pixel_ratio = ratio;
+        self.renderer.set_device_pixel_ratio(ratio);
+        if self.renderer.resize(width, height) || ratio_changed {
+            self.engine.set_viewport(css_viewport(width, height, ratio));
+            self.engine.request_redraw();
+        }
     }
 
     #[wasm_bindgen]
     pub fn tick(&mut self, input_batch: JsValue) -> Result<JsValue, JsValue> {
-        let batch: InputBatch = serde_wasm_bindgen::from_value(input_batch)
+        let mut batch: InputBatch = serde_wasm_bindgen::from_value(input_batch)
             .map_err(|e| JsValue::from_str(&format!("Invalid InputBatch: {e}")))?;
+        if !self.pending_events.is_empty() {
+            let mut events = std::mem::take(&mut self.pending_events);
+            events.append(&mut batch.events);
+            batch.events = events;
+        }
 
         let out: EngineOutput = self.engine.tick(&batch);
-        self.renderer
-            .render(&out.camera, &out.render_scene, &out.overlay_scene)?;
+        self.deliver_events();
+        if out.needs_redraw
+            && let Err(err) =
+                self.renderer
+                    .render(&out.camera, &out.render_scene, &out.overlay_scene)
+        {
+            // The engine now believes this frame is on screen; make the next
+            // tick draw again instead of skipping an unchanged scene.
+            self.engine.request_redraw();
+            return Err(err);
+        }
         serde_wasm_bindgen::to_value(&out).map_err(|e| e.into())
     }
+
+    /// Queue camera events for a DOM wheel event; applied on the next `tick`.
+    ///
+    /// # Arguments
+    /// * `delta_x`, `delta_y` - wheel deltas in pixels
+    /// * `x`, `y` - pointer position in canvas pixels
+    /// * `shift`, `ctrl` - modifier keys held during the wheel event
+    #[wasm_bindgen]
+    pub fn on_wheel(
+        &mut self,
+        delta_x: f32,
+        delta_y: f32,
+        x: f32,
+        y: f32,
+        shift: bool,
+        ctrl: bool,
+    ) {
+        self.pending_events.extend(wheel::wheel_events(
+            Vec2::new(delta_x, delta_y),
+            Vec2::new(x, y),
+            shift,
+            ctrl,
+        ));
+    }
+
+    /// Call `callback` with every input event `tick` processes, serialized the
+    /// same way the host sends them. Replaces any previous callback.
+    ///
+    /// Events are delivered in order from a microtask once `tick` has
+    /// returned, so the callback may call back into this `App`.
+    #[wasm_bindgen]
+    pub fn on_event(&mut self, callback: js_sys::Function) {
+        let unseen = Rc::new(RefCell::new(Vec::new()));
+        let sink = Rc::clone(&unseen);
+        self.engine.set_event_observer(Box::new(move |event| {
+            if let Ok(value) = serde_wasm_bindgen::to_value(event) {
+                sink.borrow_mut().push(value);
+            }
+        }));
+        self.event_listener = Some((callback, unseen));
+    }
+
+    /// The current document as a plain JS object.
+    #[wasm_bindgen]
+    pub fn export_document(&self) -> Result<JsValue, JsValue> {
+        // json_compatible so node metadata comes out as objects, not `Map`s
+        let serializer = serde_wasm_bindgen::Serializer::json_compatible();
+        self.engine
+            .doc()
+            .serialize(&serializer)
+            .map_err(|e| e.into())
+    }
+
+    /// Replace the current document with `doc` (as returned by
+    /// `export_document`), clearing selection, drag and history.
+    #[wasm_bindgen]
+    pub fn import_document(&mut self, doc: JsValue) -> Result<(), JsValue> {
+        let doc: Document = serde_wasm_bindgen::from_value(doc)
+            .map_err(|e| JsValue::from_str(&format!("Invalid document: {e}")))?;
+        self.engine.replace_document(doc);
+        Ok(())
+    }
+
+    /// The selected nodes as a clipboard payload: a plain JS object of
+    /// `{ rects, texts }` in draw order, ready for `paste` here or in another
+    /// `App`. Locked rects are left out, as a cut leaves them behind.
+    #[wasm_bindgen]
+    pub fn copy_selection(&self) -> Result<JsValue, JsValue> {
+        clipboard_to_js(&self.engine.selection_clipboard())
+    }
+
+    /// The same payload as `copy_selection`, after which the copied nodes
+    /// are deleted as one undoable step.
+    #[wasm_bindgen]
+    pub fn cut(&mut self) -> Result<JsValue, JsValue> {
+        clipboard_to_js(&self.engine.cut_clipboard())
+    }
+
+    /// Append the nodes of a `copy_selection` payload with fresh ids, shifted
+    /// by the offset, as one undoable step, and select them.
+    ///
+    /// # Arguments
+    /// * `payload` - object from `copy_selection`, possibly of another document
+    /// * `offset_x`, `offset_y` - world-space shift applied to every pasted node
+    #[wasm_bindgen]
+    pub fn paste(&mut self, payload: JsValue, offset_x: f32, offset_y: f32) -> Result<(), JsValue> {
+        let clipboard: Clipboard = serde_wasm_bindgen::from_value(payload)
+            .map_err(|e| JsValue::from_str(&format!("Invalid clipboard payload: {e}")))?;
+        if clipboard.is_empty() {
+            return Err(JsValue::from_str(
+                "Invalid clipboard payload: it holds no nodes",
+            ));
+        }
+        self.engine.insert_nodes(
+            clipboard.rects,
+            clipboard.texts,
+            Vec2::new(offset_x, offset_y),
+        );
+        Ok(())
+    }
+
+    /// Raw id of the node a click at canvas point `x`, `y` (CSS pixels) would
+    /// hit, if any. Read-only: selection and drag state are left alone.
+    #[wasm_bindgen]
+    pub fn pick(&self, x: f32, y: f32) -> Option<u64> {
+        self.engine.pick_at(Vec2::new(x, y)).map(|id| id.0)
+    }
+
+    /// Change the MSAA sample count (1 disables it). Returns the count in use,
+    /// which is 1 when the device doesn't support the requested one.
+    #[wasm_bindgen]
+    pub fn set_sample_count(&mut self, count: u32) -> u32 {
+        let applied = self.renderer.set_sample_count(count);
+        self.engine.request_redraw();
+        applied
+    }
+
+    /// Draw selection chrome beneath node content instead of over it.
+    #[wasm_bindgen]
+    pub fn set_overlay_on_top(&mut self, on_top: bool) {
+        self.renderer.set_overlay_on_top(on_top);
+        self.engine.request_redraw();
+    }
+
+    /// Serialize the document's rects as SVG markup.
+    #[wasm_bindgen]
+    pub fn export_svg(&self) -> String {
+        self.engine.doc().to_svg()
+    }
+
+    /// Capture the document as PNG bytes at the canvas size, without
+    /// selection chrome. Resolves to a `Uint8Array`.
+    ///
+    /// The current scene is drawn before this returns and the promise holds
+    /// no borrow of the app, so `tick` keeps running while it is pending.
+    #[wasm_bindgen(unchecked_return_type = "Promise<Uint8Array>")]
+    pub fn export_png(&mut self) -> js_sys::Promise {
+        let capture = self
+            .renderer
+            .capture_png(&self.engine.camera, &self.engine.render_scene());
+        wasm_bindgen_futures::future_to_promise(async move {
+            let bytes = capture.await?;
+            Ok(js_sys::Uint8Array::from(bytes.as_slice()).into())
+        })
+    }
+
+    /// Toggle the world origin/axes debug overlay; takes effect on the next `tick`.
+    #[wasm_bindgen]
+    pub fn set_debug_axes(&mut self, enabled: bool) {
+        self.engine.set_debug_axes(enabled);
+    }
+
+    /// Show a background grid every `step` world units; `None` hides it.
+    #[wasm_bindgen]
+    pub fn set_grid_step(&mut self, step: Option<f32>) {
+        self.engine.set_grid_step(step);
+    }
+
+    /// Toggle skipping offscreen rects when rendering, e.g. to debug culling.
+    #[wasm_bindgen]
+    pub fn set_cull_offscreen(&mut self, enabled: bool) {
+        self.engine.set_cull_offscreen(enabled);
+        self.engine.request_redraw();
+    }
+
+    /// Abort any in-progress drag, e.g. when the page loses focus mid-gesture.
+    #[wasm_bindgen]
+    pub fn reset_drag_state(&mut self) {
+        self.engine.reset_drag_state();
+    }
+}
+
+impl App {
+    async fn with_engine(
+        canvas: web_sys::HtmlCanvasElement,
+        mut engine: Engine,
+        device_pixel_ratio: Option<f32>,
+    ) -> Result<App, JsValue> {
+        let device_pixel_ratio = sanitize_pixel_ratio(device_pixel_ratio.unwrap_or(1.0));
+        let viewport_px = css_viewport(canvas.width(), canvas.height(), device_pixel_ratio);
+        let mut renderer = Renderer::new(canvas, BlendMode::default()).await?;
+        renderer.set_device_pixel_ratio(device_pixel_ratio);
+        engine.set_viewport(viewport_px);
+
+        Ok(App {
+            engine,
+            renderer,
+            pending_events: Vec::new(),
+            device_pixel_ratio,
+            event_listener: None,
+        })
+    }
+
+    /// Hand the events buffered during `tick` to the `on_event` callback.
+    /// Deferred to a microtask: calling JS while this `App` is still borrowed
+    /// would make any call back into it panic.
+    fn deliver_events(&self) {
+        let Some((callback, unseen)) = &self.event_listener else {
+            return;
+        };
+        let events = std::mem::take(&mut *unseen.borrow_mut());
+        if events.is_empty() {
+            return;
+        }
+        let callback = callback.clone();
+        wasm_bindgen_futures::spawn_local(async move {
+            for value in events {
+                let _ = callback.call1(&JsValue::NULL, &value);
+            }
+        });
+    }
+}
+
+/// `clipboard` as a plain JS object, as `copy_selection` and `cut` hand it out.
+fn clipboard_to_js(clipboard: &Clipboard) -> Result<JsValue, JsValue> {
+    // json_compatible so node metadata comes out as objects, not `Map`s
+    let serializer = serde_wasm_bindgen::Serializer::json_compatible();
+    clipboard.serialize(&serializer).map_err(|e| e.into())
+}
+
+/// Engine viewport in CSS pixels for a canvas `width` x `height` physical
+/// pixels at `device_pixel_ratio`.
+fn css_viewport(width: u32, height: u32, device_pixel_ratio: f32) -> Vec2 {
+    Vec2::new(
+        width as f32 / device_pixel_ratio,
+        height as f32 / device_pixel_ratio,
+    )
 }
@@ -0,0 +1,96 @@
+use engine::api::{InputEvent, Vec2};
+
+/// Lower a DOM wheel event to engine camera events, following browser
+/// conventions: wheel pans vertically, shift+wheel pans horizontally, and
+/// ctrl+wheel zooms about the pointer. Zoom deltas go to the engine raw, as
+/// `WheelZoom`, so the delta-to-zoom mapping lives in one place.
+///
+/// # Arguments
+/// * `delta_px` - wheel `deltaX`/`deltaY` in pixels
+/// * `pointer_px` - pointer position in canvas pixels
+/// * `shift` - shift key held
+/// * `ctrl` - ctrl key held (or a pinch gesture)
+pub fn wheel_events(delta_px: Vec2, pointer_px: Vec2, shift: bool, ctrl: bool) -> Vec<InputEvent> {
+    if ctrl {
+        if delta_px.y == 0.0 {
+            return Vec::new();
+        }
+        return vec![InputEvent::WheelZoom {
+            pivot_px: pointer_px,
+            delta_y: delta_px.y,
+        }];
+    }
+
+    // Scrolling moves the view, the opposite of dragging the content.
+    let pan = if shift {
+        // Plain mice only report deltaY; some platforms already swap it to deltaX.
+        let horizontal = if delta_px.x != 0.0 {
+            delta_px.x
+        } else {
+            delta_px.y
+        };
+        Vec2::new(-horizontal, 0.0)
+    } else {
+        Vec2::new(-delta_px.x, -delta_px.y)
+    };
+
+    if pan.x == 0.0 && pan.y == 0.0 {
+        return Vec::new();
+    }
+    vec![InputEvent::CameraPanByScreenDelta { delta_px: pan }]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn pan_delta(events: &[InputEvent]) -> Vec2 {
+        match events {
+            [InputEvent::CameraPanByScreenDelta { delta_px }] => *delta_px,
+            other => panic!("expected a single pan event, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn plain_wheel_pans_vertically() {
+        let events = wheel_events(Vec2::new(0.0, 40.0), Vec2::new(10.0, 10.0), false, false);
+        assert_eq!(pan_delta(&events), Vec2::new(0.0, -40.0));
+    }
+
+    #[test]
+    fn shift_wheel_pans_horizontally() {
+        let events = wheel_events(Vec2::new(0.0, 40.0), Vec2::new(10.0, 10.0), true, false);
+        assert_eq!(pan_delta(&events), Vec2::new(-40.0, 0.0));
+
+        // Platforms that already swapped the axis keep deltaX.
+        let events = wheel_events(Vec2::new(25.0, 0.0), Vec2::new(10.0, 10.0), true, false);
+        assert_eq!(pan_delta(&events), Vec2::new(-25.0, 0.0));
+    }
+
+    #[test]
+    fn ctrl_wheel_zooms_about_the_pointer() {
+        let pointer = Vec2::new(120.0, 80.0);
+        let events = wheel_events(Vec2::new(0.0, -100.0), pointer, false, true);
+
+        match events.as_slice() {
+            [InputEvent::WheelZoom { pivot_px, delta_y }] => {
+                assert_eq!(*pivot_px, pointer);
+                assert_eq!(*delta_y, -100.0);
+            }
+            other => panic!("expected a single zoom event, got {other:?}"),
+        }
+
+        // Ctrl wins over shift.
+        let events = wheel_events(Vec2::new(0.0, 100.0), pointer, true, true);
+        assert!(matches!(
+            events.as_slice(),
+            [InputEvent::WheelZoom { delta_y, .. }] if *delta_y == 100.0
+        ));
+    }
+
+    #[test]
+    fn zero_delta_produces_no_events() {
+        assert!(wheel_events(Vec2::new(0.0, 0.0), Vec2::new(0.0, 0.0), false, false).is_empty());
+        assert!(wheel_events(Vec2::new(0.0, 0.0), Vec2::new(0.0, 0.0), false, true).is_empty());
+    }
+}
@@ -0,0 +1,49 @@
+//! Stable public surface for hosts embedding the engine.
+//!
+//! Hosts should depend on these re-exports rather than the crate root, which
+//! also exposes drag-state and history internals that may change freely.
+//!
+//! # Breaking change: document access
+//!
+//! `Engine::doc` is no longer a public field. Read the document with
+//! [`Engine::doc`] and edit it with [`Engine::doc_mut`], which drops the
+//! hit-testing index so it is rebuilt from the edited document. Hosts that
+//! wrote `engine.doc.rects` directly must move to these accessors.
+//!
+//! ```
+//! use engine::api::*;
+//!
+//! let mut engine = Engine::with_document(Document::new());
+//! let batch = InputBatch {
+//!     events: vec![InputEvent::CameraPanByScreenDelta {
+//!         delta_px: Vec2::new(10.0, 0.0),
+//!     }],
+//!     tool: ToolMode::Select,
+//!     ..Default::default()
+//! };
+//!
+//! let out: EngineOutput = engine.tick(&batch);
+//! let camera: Camera = out.camera;
+//! assert_eq!(camera.pan, Vec2::new(-10.0, 0.0));
+//!
+//! let mut doc = Document::new();
+//! let id: NodeId = doc.alloc_id();
+//! doc.rects.push(RectNode::new(
+//!     id,
+//!     Vec2::new(0.0, 0.0),
+//!     Vec2::new(10.0, 10.0),
+//!     Color::rgba(1.0, 0.0, 0.0, 1.0),
+//! ));
+//! ```
+
+pub use crate::camera::{Camera, WHEEL_ZOOM_SPEED};
+pub use crate::color::Color;
+pub use crate::engine::{Engine, EventObserver};
+pub use crate::input::{
+    AlignMode, Axis, CursorStyle, EngineOutput, InputBatch, InputEvent, MarqueeMode, ToolMode,
+};
+pub use crate::render_scene::{
+    HandleShape, InstanceShape, OverlayScene, OverlayTheme, RectInstance, RenderScene,
+    SelectionBox, TextInstance,
+};
+pub use crate::types::{Artboard, Clipboard, Document, NodeId, RectNode, TextNode, Vec2};
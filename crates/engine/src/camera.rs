@@ -2,6 +2,14 @@ use serde::{Deserialize, Serialize};
 
 use crate::types::Vec2;
 
+/// Zoom range enforced by every camera operation.
+pub const MIN_ZOOM: f32 = 0.05;
+pub const MAX_ZOOM: f32 = 64.0;
+
+/// Zoom response per wheel delta pixel: a wheel `deltaY` of `d` zooms by
+/// `exp(-d * WHEEL_ZOOM_SPEED)`, so scrolling up zooms in.
+pub const WHEEL_ZOOM_SPEED: f32 = 0.0015;
+
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct Camera {
     pub pan: Vec2,
@@ -36,14 +44,68 @@ impl Camera {
         )
     }
 
+    /// Convert many screen coordinates at once with [`Camera::screen_to_world`].
+    ///
+    /// # Arguments
+    /// * `points` - screen coordinates to convert
+    pub fn screen_to_world_batch(&self, points: &[Vec2]) -> Vec<Vec2> {
+        points.iter().map(|&p| self.screen_to_world(p)).collect()
+    }
+
+    /// Convert many world coordinates at once with [`Camera::world_to_screen`].
+    ///
+    /// # Arguments
+    /// * `points` - world coordinates to convert
+    pub fn world_to_screen_batch(&self, points: &[Vec2]) -> Vec<Vec2> {
+        points.iter().map(|&p| self.world_to_screen(p)).collect()
+    }
+
+    /// Convert world coordinate to normalized device coordinates for a canvas
+    /// of `canvas_px` pixels. `vs_main` in the renderer's `shader.wgsl` must
+    /// compute exactly this, or the overlay drifts off the scene.
+    ///
+    /// # Arguments
+    /// * `world` - coordinate to convert
+    /// * `canvas_px` - render target size in pixels
+    pub fn world_to_ndc(&self, world: Vec2, canvas_px: Vec2) -> [f32; 2] {
+        let screen = self.world_to_screen(world);
+        [
+            (screen.x / canvas_px.x) * 2.0 - 1.0,
+            1.0 - (screen.y / canvas_px.y) * 2.0,
+        ]
+    }
+
+    /// Inverse of [`Camera::world_to_ndc`].
+    ///
+    /// # Arguments
+    /// * `ndc` - normalized device coordinate, y up
+    /// * `canvas_px` - render target size in pixels
+    pub fn ndc_to_world(&self, ndc: [f32; 2], canvas_px: Vec2) -> Vec2 {
+        let screen = Vec2::new(
+            (ndc[0] + 1.0) * 0.5 * canvas_px.x,
+            (1.0 - ndc[1]) * 0.5 * canvas_px.y,
+        );
+        self.screen_to_world(screen)
+    }
+
     pub fn pan_by_screen_delta(&mut self, delta_px: Vec2) {
         self.pan.x -= delta_px.x / self.zoom;
         self.pan.y -= delta_px.y / self.zoom;
     }
 
     pub fn zoom_at_screen_point(&mut self, pivot_px: Vec2, zoom_multiplier: f32) {
+        self.set_zoom_at_screen_point(pivot_px, self.zoom * zoom_multiplier);
+    }
+
+    /// Set zoom to exactly `zoom` (clamped) while the world point under
+    /// `pivot_px` stays put.
+    ///
+    /// # Arguments
+    /// * `pivot_px` - screen point to hold fixed
+    /// * `zoom` - target zoom level
+    pub fn set_zoom_at_screen_point(&mut self, pivot_px: Vec2, zoom: f32) {
         let old_zoom = self.zoom;
-        let new_zoom = (self.zoom * zoom_multiplier).clamp(0.05, 64.0);
+        let new_zoom = zoom.clamp(MIN_ZOOM, MAX_ZOOM);
 
         if (new_zoom - old_zoom).abs() < f32::EPSILON {
             return;
@@ -58,4 +120,103 @@ impl Camera {
         self.pan.x = world_under_cursor.x - pivot_px.x / new_zoom;
         self.pan.y = world_under_cursor.y - pivot_px.y / new_zoom;
     }
+
+    /// Zoom so the world-space extent `world_len` spans `viewport_len` screen
+    /// pixels, centering the camera on `center`.
+    ///
+    /// # Arguments
+    /// * `center` - world point placed at the viewport center
+    /// * `world_len` - world-space length to fit (width or height)
+    /// * `viewport_len` - matching viewport dimension in screen pixels
+    /// * `viewport_px` - full viewport size in screen pixels
+    pub fn fit_extent(
+        &mut self,
+        center: Vec2,
+        world_len: f32,
+        viewport_len: f32,
+        viewport_px: Vec2,
+    ) {
+        if world_len <= 0.0 || viewport_len <= 0.0 {
+            return;
+        }
+
+        self.zoom = (viewport_len / world_len).clamp(MIN_ZOOM, MAX_ZOOM);
+        self.center_on(center, viewport_px);
+    }
+
+    /// Pan so `center` sits in the middle of the viewport at the current zoom.
+    pub fn center_on(&mut self, center: Vec2, viewport_px: Vec2) {
+        self.pan.x = center.x - viewport_px.x * 0.5 / self.zoom;
+        self.pan.y = center.y - viewport_px.y * 0.5 / self.zoom;
+    }
+}
+
+/// Progress curve of a [`CameraAnimation`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Easing {
+    Linear,
+    /// Starts and ends slowly (smoothstep).
+    #[default]
+    EaseInOut,
+}
+
+impl Easing {
+    /// Map linear progress `t` in `0..=1` onto the curve.
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseInOut => t * t * (3.0 - 2.0 * t),
+        }
+    }
+}
+
+/// CameraAnimation | eased transition between two cameras over a fixed time
+///
+/// Pan is interpolated linearly and zoom in log space, so each step scales
+/// the view by the same factor.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CameraAnimation {
+    pub from: Camera,
+    pub to: Camera,
+    pub elapsed_ms: f32,
+    pub duration_ms: f32,
+    pub easing: Easing,
+}
+
+impl CameraAnimation {
+    pub fn new(from: Camera, to: Camera, duration_ms: f32) -> Self {
+        Self {
+            from,
+            to,
+            elapsed_ms: 0.0,
+            duration_ms,
+            easing: Easing::default(),
+        }
+    }
+
+    pub fn advance(&mut self, dt_ms: f32) {
+        self.elapsed_ms = (self.elapsed_ms + dt_ms.max(0.0)).min(self.duration_ms);
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.elapsed_ms >= self.duration_ms
+    }
+
+    /// Camera at the current elapsed time; exactly `to` once finished.
+    pub fn sample(&self) -> Camera {
+        if self.is_finished() {
+            return self.to;
+        }
+        let t = self.easing.apply(self.elapsed_ms / self.duration_ms);
+        let lerp = |a: f32, b: f32| a + (b - a) * t;
+        Camera {
+            pan: Vec2::new(
+                lerp(self.from.pan.x, self.to.pan.x),
+                lerp(self.from.pan.y, self.to.pan.y),
+            ),
+            zoom: lerp(self.from.zoom.ln(), self.to.zoom.ln()).exp(),
+        }
+    }
 }
@@ -0,0 +1,177 @@
+use serde::{Deserialize, Serialize};
+
+/// Straight-alpha RGBA color with every channel in `0.0..=1.0`.
+///
+/// Serializes as a bare `[r, g, b, a]` array, the format documents used
+/// before this type existed.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(from = "[f32; 4]", into = "[f32; 4]")]
+pub struct Color {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32,
+}
+
+impl Color {
+    pub const WHITE: Color = Color {
+        r: 1.0,
+        g: 1.0,
+        b: 1.0,
+        a: 1.0,
+    };
+    pub const BLACK: Color = Color {
+        r: 0.0,
+        g: 0.0,
+        b: 0.0,
+        a: 1.0,
+    };
+    pub const TRANSPARENT: Color = Color {
+        r: 0.0,
+        g: 0.0,
+        b: 0.0,
+        a: 0.0,
+    };
+
+    /// Build a color from straight-alpha channels, clamping each into
+    /// `0.0..=1.0`. NaN becomes 0. Usable in `const` items.
+    pub const fn rgba(r: f32, g: f32, b: f32, a: f32) -> Self {
+        Self {
+            r: clamp_channel(r),
+            g: clamp_channel(g),
+            b: clamp_channel(b),
+            a: clamp_channel(a),
+        }
+    }
+
+    /// Parse `#RGB`, `#RGBA`, `#RRGGBB` or `#RRGGBBAA` (the `#` is optional).
+    /// Short forms repeat each digit, so `#f80` is `#ff8800`.
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        let hex = hex.strip_prefix('#').unwrap_or(hex);
+        if !hex.is_ascii() {
+            return None;
+        }
+        let digits = match hex.len() {
+            3 | 4 => 1,
+            6 | 8 => 2,
+            _ => return None,
+        };
+
+        let channel = |i: usize| -> Option<f32> {
+            let v = u8::from_str_radix(&hex[i * digits..(i + 1) * digits], 16).ok()?;
+            let v = if digits == 1 { v * 17 } else { v };
+            Some(v as f32 / 255.0)
+        };
+        let a = if hex.len() == 4 * digits {
+            channel(3)?
+        } else {
+            1.0
+        };
+
+        Some(Self::rgba(channel(0)?, channel(1)?, channel(2)?, a))
+    }
+
+    /// `#rrggbb` with each channel rounded to 8 bits; alpha is dropped.
+    pub fn to_hex_rgb(self) -> String {
+        let byte = |v: f32| (v * 255.0).round() as u8;
+        format!(
+            "#{:02x}{:02x}{:02x}",
+            byte(self.r),
+            byte(self.g),
+            byte(self.b)
+        )
+    }
+
+    /// Channels as `[r, g, b, a]`, the layout GPU instance data expects.
+    pub fn to_array(self) -> [f32; 4] {
+        [self.r, self.g, self.b, self.a]
+    }
+}
+
+const fn clamp_channel(v: f32) -> f32 {
+    if v.is_nan() { 0.0 } else { v.clamp(0.0, 1.0) }
+}
+
+impl From<[f32; 4]> for Color {
+    fn from(c: [f32; 4]) -> Self {
+        Self::rgba(c[0], c[1], c[2], c[3])
+    }
+}
+
+impl From<Color> for [f32; 4] {
+    fn from(c: Color) -> Self {
+        c.to_array()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn from_hex_parses_rgb_and_rgba_forms() {
+        assert_eq!(
+            Color::from_hex("#ff0000"),
+            Some(Color::rgba(1.0, 0.0, 0.0, 1.0))
+        );
+        assert_eq!(Color::from_hex("00ff0080").map(|c| c.g), Some(1.0));
+        assert_eq!(
+            Color::from_hex("#00ff0080").map(|c| c.a),
+            Some(128.0 / 255.0)
+        );
+        assert_eq!(Color::from_hex("#12345"), None);
+        assert_eq!(Color::from_hex("#gg0000"), None);
+    }
+
+    #[test]
+    fn from_hex_expands_short_forms() {
+        assert_eq!(Color::from_hex("#f80"), Color::from_hex("#ff8800"));
+        assert_eq!(
+            Color::from_hex("#0f08"),
+            Some(Color::rgba(0.0, 1.0, 0.0, 136.0 / 255.0))
+        );
+        assert_eq!(Color::from_hex("fff").map(|c| c.a), Some(1.0));
+        assert_eq!(Color::from_hex("#ff"), None);
+        assert_eq!(Color::from_hex("#fg0"), None);
+    }
+
+    #[test]
+    fn rgba_clamps_and_works_in_const_items() {
+        const OVER: Color = Color::rgba(2.0, -1.0, 0.25, f32::NAN);
+        assert_eq!(OVER.to_array(), [1.0, 0.0, 0.25, 0.0]);
+        let c = Color::rgba(1.5, -0.25, 0.5, f32::NAN);
+        assert_eq!(c.to_array(), [1.0, 0.0, 0.5, 0.0]);
+    }
+
+    #[test]
+    fn to_hex_rgb_rounds_to_nearest_byte() {
+        assert_eq!(Color::rgba(1.0, 0.0, 0.5, 0.3).to_hex_rgb(), "#ff0080");
+        assert_eq!(Color::rgba(0.2, 0.7, 0.9, 1.0).to_hex_rgb(), "#33b3e6");
+        let parsed = Color::from_hex("#12abef").unwrap();
+        assert_eq!(parsed.to_hex_rgb(), "#12abef");
+    }
+
+    #[test]
+    fn rect_instance_color_keeps_array_wire_format() {
+        let json = r#"{"pos":[0.0,0.0],"size":[1.0,1.0],"color":[1.5,0.5,0.0,1.0]}"#;
+        let instance: crate::render_scene::RectInstance =
+            serde_json::from_str(json).expect("instance parses");
+        assert_eq!(instance.color, Color::rgba(1.0, 0.5, 0.0, 1.0));
+
+        let value = serde_json::to_value(instance).expect("instance serializes");
+        assert_eq!(value["color"], serde_json::json!([1.0, 0.5, 0.0, 1.0]));
+    }
+
+    #[test]
+    fn color_serializes_as_array_and_round_trips() {
+        let c = Color::rgba(0.25, 0.5, 0.75, 1.0);
+        let json = serde_json::to_string(&c).expect("color serializes");
+        assert_eq!(json, "[0.25,0.5,0.75,1.0]");
+
+        let parsed: Color = serde_json::from_str(&json).expect("color parses");
+        assert_eq!(parsed, c);
+
+        let clamped: Color = serde_json::from_str("[2.0,0.5,-1.0,1.0]").expect("color parses");
+        assert_eq!(clamped.to_array(), [1.0, 0.5, 0.0, 1.0]);
+    }
+}
@@ -1,5 +1,7 @@
 use crate::types::{NodeId, Vec2};
 
+/// Resize corner, as `HandleHit` reported before it carried an [`Edge`].
+#[deprecated(note = "use `Edge`; corners are `Edge::NW`, `NE`, `SW` and `SE`")]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Corner {
     TL,
@@ -8,10 +10,58 @@ pub enum Corner {
     BR,
 }
 
+/// Where a world point lies relative to a node's bounds, as used by resize
+/// handles and cursor hints.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Edge {
+    N,
+    S,
+    E,
+    W,
+    NE,
+    NW,
+    SE,
+    SW,
+    Inside,
+    Outside,
+}
+
+impl Edge {
+    /// `true` for the eight resize handle regions (corners and edges).
+    pub fn is_handle(self) -> bool {
+        !matches!(self, Edge::Inside | Edge::Outside)
+    }
+
+    /// The resize corner for corner regions, `None` for edges and the interior.
+    #[deprecated(note = "match on the `Edge` corner variants instead")]
+    #[allow(deprecated)]
+    pub fn corner(self) -> Option<Corner> {
+        match self {
+            Edge::NW => Some(Corner::TL),
+            Edge::NE => Some(Corner::TR),
+            Edge::SW => Some(Corner::BL),
+            Edge::SE => Some(Corner::BR),
+            _ => None,
+        }
+    }
+}
+
+#[allow(deprecated)]
+impl From<Corner> for Edge {
+    fn from(corner: Corner) -> Self {
+        match corner {
+            Corner::TL => Edge::NW,
+            Corner::TR => Edge::NE,
+            Corner::BL => Edge::SW,
+            Corner::BR => Edge::SE,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct HandleHit {
     pub node_id: NodeId,
-    pub corner: Corner,
+    pub edge: Edge,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -21,11 +71,16 @@ pub struct PendingMarquee {
     pub additive: bool,
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone)]
 pub struct PendingSelectionMove {
+    // rect under the pointer
+    pub hit_id: NodeId,
     pub start_screen_px: Vec2,
     pub start_world: Vec2,
     pub previous_selection: Vec<NodeId>,
+    // press landed on an already-selected rect without shift: a click with no
+    // drag collapses the selection to `hit_id`
+    pub collapse_on_click: bool,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -56,6 +111,16 @@ pub struct SelectionDrag {
 
     // Snapshot selected rect index + original position to avoid cumulative drift
     pub origins: Vec<(NodeId, Vec2)>,
+    // smart guides the moved selection currently lines up with
+    pub guides: Vec<SnapGuide>,
+}
+
+/// World-space alignment line shown while a moved selection shares an edge
+/// or center with another rect.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SnapGuide {
+    pub start: Vec2,
+    pub end: Vec2,
 }
 
 #[derive(Debug, Clone)]
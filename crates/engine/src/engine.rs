@@ -1,79 +1,676 @@
-use std::collections::HashSet;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
 
-use crate::camera::Camera;
+use crate::camera::{Camera, CameraAnimation, WHEEL_ZOOM_SPEED};
+use crate::color::Color;
 use crate::drag::{
-    Corner, DragState, HandleHit, MarqueeDrag, PendingMarquee, PendingRectCreate, PendingResize,
-    PendingSelectionMove, RectCreateDrag, ResizeDrag, SelectionDrag,
+    DragState, Edge, HandleHit, MarqueeDrag, PendingMarquee, PendingRectCreate, PendingResize,
+    PendingSelectionMove, RectCreateDrag, ResizeDrag, SelectionDrag, SnapGuide,
 };
-use crate::input::{CursorStyle, EngineOutput, InputBatch, InputEvent};
-use crate::render_scene::{self, OverlayScene, RectInstance, RenderScene};
-use crate::types::{Document, NodeId, RectNode, Vec2};
-use crate::{RectGeometry, RectGeometryChange, ToolCommand, ToolMode};
+use crate::input::{
+    AlignMode, Axis, CursorStyle, EngineOutput, InputBatch, InputEvent, MarqueeMode, ModifierState,
+};
+use crate::render_scene::{
+    self, InstanceShape, OverlayScene, OverlayTheme, RectInstance, RenderScene, SelectionBox,
+    TextInstance,
+};
+use crate::spatial::SpatialIndex;
+use crate::types::{
+    Artboard, Clipboard, Document, NodeId, RectNode, TextNode, Vec2, demo_document, rotated_aabb,
+};
+use crate::{
+    RectColorChange, RectGeometry, RectGeometryChange, RectGroupChange, RectLockChange,
+    RectRotationChange, RectVisibilityChange, TextMoveChange, ToolCommand, ToolMode,
+};
+
+/// Grab radius around corner handles in screen pixels (slightly larger than
+/// the 8px handle drawn in the overlay).
+const HANDLE_HIT_PX: f32 = 12.0;
+
+/// Half-width of the grab band along a node's edges in screen pixels.
+const EDGE_HIT_PX: f32 = 4.0;
+
+/// Screen pixels a click may miss a rect by and still hit it, so thin or
+/// tiny rects stay clickable when zoomed out.
+const HIT_TOLERANCE_PX: f32 = 3.0;
+
+/// World units an arrow key moves the selection by; shift multiplies it by 10.
+const NUDGE_STEP: f32 = 1.0;
+
+/// Fill of rects created by dragging out or double-clicking.
+const NEW_RECT_COLOR: Color = Color::rgba(0.769, 0.769, 0.769, 1.0);
+
+/// World-space size of a rect created by double-clicking empty canvas.
+const DOUBLE_CLICK_RECT_SIZE: Vec2 = Vec2::new(100.0, 100.0);
+
+/// Screen pixels of the `pan_bounds` box kept on screen when the pan is clamped.
+const PAN_BOUNDS_VISIBLE_PX: f32 = 32.0;
+
+/// World-space shift applied by `DuplicateSelected` when given a zero offset.
+const DUPLICATE_OFFSET: Vec2 = Vec2::new(10.0, 10.0);
+
+/// Undo steps kept; pushing past this drops the oldest.
+const HISTORY_LIMIT: usize = 100;
+
+/// Callback installed with [`Engine::set_event_observer`].
+pub type EventObserver = Box<dyn FnMut(&InputEvent)>;
 
 pub struct Engine {
-    pub doc: Document,
+    // behind `doc_mut` so direct edits can't leave derived caches stale
+    doc: Document,
     pub camera: Camera,
     pub selected: Vec<NodeId>,
     pub drag_state: DragState,
     pub hover_screen_px: Option<Vec2>,
+    pub modifiers: ModifierState,
+    /// Canvas size in screen pixels; zero until the host reports it.
+    pub viewport_px: Vec2,
+    /// Whether a plain click on empty canvas clears the selection.
+    pub clear_on_empty_click: bool,
+    /// Whether a marquee selects the rects it touches or only those it
+    /// fully contains.
+    pub marquee_mode: MarqueeMode,
+    /// Angle increment, in degrees, that `InputEvent::SetRotation` snaps to
+    /// while shift is held.
+    pub rotation_snap_deg: f32,
+    /// Width/height ratio new rects are locked to while dragging them out.
+    /// Holding shift toggles the lock; with no ratio set, shift forces squares.
+    pub create_aspect: Option<f32>,
+    /// World-space grid pitch that moved and resized rects snap to when the
+    /// drag is released, and newly created rects snap to as they are drawn;
+    /// `None` disables snapping.
+    pub snap_grid: Option<f32>,
+    /// Keep moved and resized nodes inside the artboard that contained them
+    /// when the drag began.
+    pub clip_children_to_artboard: bool,
+    pub overlay_theme: OverlayTheme,
+    /// Draw the world origin and X/Y axes into the overlay, for debugging.
+    pub debug_axes: bool,
+    /// World-space spacing of the background grid; `None` hides it.
+    pub grid_step: Option<f32>,
+    /// Leave rects outside the viewport out of the render scene. Turn off to
+    /// inspect everything the document holds.
+    pub cull_offscreen: bool,
+    /// World-space `(min, max)` box the camera may not pan away from; `None`
+    /// pans freely. Enforced once the viewport size is known.
+    pub pan_bounds: Option<(Vec2, Vec2)>,
+    /// Length of the animated move for fit and reset camera commands; 0
+    /// jumps straight there.
+    pub camera_transition_ms: f32,
+    /// Longest gap, in `InputBatch::timestamp_ms` milliseconds, between two
+    /// presses on empty canvas that still counts as a double click.
+    pub double_click_ms: f64,
+    /// Farthest apart, in screen pixels, the two presses of a double click
+    /// may land.
+    pub double_click_px: f32,
+    /// Screen-pixel distance within which a moved selection's edges and
+    /// center snap to those of other rects; 0 turns smart guides off.
+    pub smart_guide_px: f32,
 
     undo_stack: Vec<ToolCommand>,
     redo_stack: Vec<ToolCommand>,
+
+    // frame the whole document once the first non-zero viewport arrives
+    fit_document_pending: bool,
+
+    // host callback run for every event `tick` processes
+    event_observer: Option<EventObserver>,
+
+    // set by events and edits since the last tick reported `needs_redraw`
+    redraw_pending: bool,
+
+    // camera the last tick reported, so direct `camera` writes still redraw
+    reported_camera: Option<Camera>,
+
+    // hit-test grid over `doc.rects`, built on first use after each edit
+    spatial_index: RefCell<Option<SpatialIndex>>,
+
+    // in-flight camera transition, advanced by `InputBatch::dt_ms`
+    camera_animation: Option<CameraAnimation>,
+
+    // time and screen position of the last press on empty canvas
+    last_empty_press: Option<(f64, Vec2)>,
 }
 
 impl Engine {
+    /// Create an engine showing the demo document.
     pub fn new() -> Self {
-        let mut doc = Document::new();
-
-        let rects = vec![
-            RectNode {
-                id: doc.alloc_id(),
-                pos: Vec2::new(100.0, 100.0),
-                size: Vec2::new(120.0, 80.0),
-                color: [0.2, 0.7, 0.9, 1.0],
-            },
-            RectNode {
-                id: doc.alloc_id(),
-                pos: Vec2::new(300.0, 220.0),
-                size: Vec2::new(140.0, 80.0),
-                color: [0.9, 0.3, 0.9, 1.0],
-            },
-            RectNode {
-                id: doc.alloc_id(),
-                pos: Vec2::new(600.0, 900.0),
-                size: Vec2::new(200.0, 100.0),
-                color: [0.5, 0.8, 0.4, 1.0],
-            },
-        ];
-
-        doc.rects = rects;
+        Self::with_document(demo_document())
+    }
 
+    /// Create an engine editing `doc` with a default camera and no selection.
+    ///
+    /// # Arguments
+    /// * `doc` - document to edit
+    pub fn with_document(doc: Document) -> Self {
         Self {
             doc,
             camera: Camera::default(),
             selected: vec![],
             drag_state: DragState::Idle,
             hover_screen_px: None,
+            modifiers: ModifierState::default(),
+            viewport_px: Vec2::default(),
+            clear_on_empty_click: true,
+            marquee_mode: MarqueeMode::default(),
+            rotation_snap_deg: 15.0,
+            create_aspect: None,
+            snap_grid: None,
+            clip_children_to_artboard: false,
+            overlay_theme: OverlayTheme::default(),
+            debug_axes: false,
+            grid_step: None,
+            cull_offscreen: true,
+            pan_bounds: None,
+            camera_transition_ms: 0.0,
+            double_click_ms: 500.0,
+            double_click_px: 4.0,
+            smart_guide_px: 6.0,
             undo_stack: Vec::new(),
             redo_stack: Vec::new(),
+            fit_document_pending: false,
+            event_observer: None,
+            spatial_index: RefCell::new(None),
+            redraw_pending: true,
+            reported_camera: None,
+            camera_animation: None,
+            last_empty_press: None,
+        }
+    }
+
+    /// Record the canvas size used by viewport-relative camera commands.
+    ///
+    /// # Arguments
+    /// * `viewport_px` - canvas width and height in screen pixels
+    pub fn set_viewport(&mut self, viewport_px: Vec2) {
+        self.viewport_px = viewport_px;
+        self.request_redraw();
+        if self.fit_document_pending && viewport_px.x > 0.0 && viewport_px.y > 0.0 {
+            self.fit_document_pending = false;
+            self.fit_document();
+        }
+    }
+
+    /// Frame the whole document as soon as the viewport size is known: now if
+    /// it already is, otherwise on the next non-zero `set_viewport`.
+    pub fn fit_document_on_first_viewport(&mut self) {
+        self.fit_document_pending = true;
+        self.set_viewport(self.viewport_px);
+    }
+
+    /// Zoom and pan so the whole document fits the viewport, centered.
+    pub fn fit_document(&mut self) {
+        if let Some((min, max)) = self.doc.bounds() {
+            self.fit_box(min, max, self.viewport_px, 0.0);
+        }
+    }
+
+    /// Frame every node inside `viewport_px` with a small margin, or reset to
+    /// the default camera when the document is empty.
+    ///
+    /// # Arguments
+    /// * `viewport_px` - canvas width and height in screen pixels
+    pub fn fit_to_content(&mut self, viewport_px: Vec2) {
+        // Screen-space gap kept between the content and each viewport edge.
+        const FIT_MARGIN_PX: f32 = 24.0;
+
+        match self.doc.bounds() {
+            Some((min, max)) => self.fit_box(min, max, viewport_px, FIT_MARGIN_PX),
+            None => self.camera = Camera::default(),
+        }
+    }
+
+    /// Fit the world box `min..max` inside `viewport_px` less `margin_px` on
+    /// every side (dropped if the viewport is too small for it), centered.
+    fn fit_box(&mut self, min: Vec2, max: Vec2, viewport_px: Vec2, margin_px: f32) {
+        let margin = if viewport_px.x > margin_px * 4.0 && viewport_px.y > margin_px * 4.0 {
+            margin_px
+        } else {
+            0.0
+        };
+        let avail = Vec2::new(viewport_px.x - margin * 2.0, viewport_px.y - margin * 2.0);
+        let (w, h) = (max.x - min.x, max.y - min.y);
+        let center = Vec2::new((min.x + max.x) * 0.5, (min.y + max.y) * 0.5);
+        // fit whichever axis is tighter so the other one has room to spare
+        if w * avail.y >= h * avail.x {
+            self.camera.fit_extent(center, w, avail.x, viewport_px);
+        } else {
+            self.camera.fit_extent(center, h, avail.y, viewport_px);
+        }
+    }
+
+    /// Register a callback that sees every event `tick` processes, before the
+    /// event is applied. Replaces any previous observer. It runs inside
+    /// `tick`, so observers that need the engine should buffer events and act
+    /// once `tick` returns.
+    ///
+    /// # Arguments
+    /// * `observer` - called once per event, in batch order
+    pub fn set_event_observer(&mut self, observer: EventObserver) {
+        self.event_observer = Some(observer);
+    }
+
+    /// Remove the callback installed by [`Engine::set_event_observer`].
+    pub fn clear_event_observer(&mut self) {
+        self.event_observer = None;
+    }
+
+    /// Force the next `tick` to report `needs_redraw`, for host-side changes
+    /// the engine can't observe: renderer settings, surface loss, or writes
+    /// to public fields other than `camera`.
+    pub fn request_redraw(&mut self) {
+        self.redraw_pending = true;
+    }
+
+    /// Show or hide the world origin crosshair and axes in the overlay.
+    ///
+    /// # Arguments
+    /// * `enabled` - `true` to draw the X axis in red and the Y axis in green
+    pub fn set_debug_axes(&mut self, enabled: bool) {
+        self.debug_axes = enabled;
+        self.request_redraw();
+    }
+
+    /// Show a background grid every `step` world units, or hide it with `None`.
+    /// Zoomed out far enough, lines are drawn at a multiple of `step` instead.
+    pub fn set_grid_step(&mut self, step: Option<f32>) {
+        self.grid_step = step;
+        self.request_redraw();
+    }
+
+    /// Choose whether rects entirely outside the viewport are skipped when
+    /// building the render scene. Has no effect until the viewport is known.
+    pub fn set_cull_offscreen(&mut self, enabled: bool) {
+        self.cull_offscreen = enabled;
+        self.request_redraw();
+    }
+
+    /// Move the camera to `target` over `duration_ms`, advanced by each
+    /// batch's `dt_ms`. Replaces any running animation; user pan and zoom
+    /// cancel it. A non-positive duration jumps straight there. Every frame
+    /// on the way is held to `pan_bounds`.
+    pub fn animate_camera_to(&mut self, target: Camera, duration_ms: f32) {
+        let target = self.clamped_to_pan_bounds(target);
+        if duration_ms > 0.0 {
+            self.camera_animation = Some(CameraAnimation::new(self.camera, target, duration_ms));
+        } else {
+            self.camera_animation = None;
+            self.camera = target;
+        }
+    }
+
+    /// Whether a camera animation is still running, so the host keeps ticking.
+    pub fn is_animating_camera(&self) -> bool {
+        self.camera_animation.is_some()
+    }
+
+    fn advance_camera_animation(&mut self, dt_ms: f32) {
+        let Some(animation) = self.camera_animation.as_mut() else {
+            return;
+        };
+        animation.advance(dt_ms);
+        let (sampled, finished) = (animation.sample(), animation.is_finished());
+        self.camera = self.clamped_to_pan_bounds(sampled);
+        if finished {
+            self.camera_animation = None;
+        }
+    }
+
+    /// Apply `change` to the camera, then animate to where it lands over
+    /// `camera_transition_ms` instead of jumping.
+    fn transition_camera(&mut self, change: impl FnOnce(&mut Self)) {
+        let from = self.camera;
+        change(self);
+        let target = self.camera;
+        self.camera = from;
+        self.animate_camera_to(target, self.camera_transition_ms);
+    }
+
+    /// Keep some of the `(min, max)` world box on screen after every pan and
+    /// zoom, or pan freely with `None`. Clamps the current camera right away.
+    pub fn set_pan_bounds(&mut self, bounds: Option<(Vec2, Vec2)>) {
+        self.pan_bounds = bounds;
+        self.clamp_pan_to_bounds();
+    }
+
+    /// Pull `camera.pan` back until at least `PAN_BOUNDS_VISIBLE_PX` of the
+    /// `pan_bounds` box (or all of it, if smaller) shows on each axis.
+    fn clamp_pan_to_bounds(&mut self) {
+        self.camera = self.clamped_to_pan_bounds(self.camera);
+    }
+
+    /// `camera` with its pan clamped as `clamp_pan_to_bounds` would.
+    fn clamped_to_pan_bounds(&self, mut camera: Camera) -> Camera {
+        let Some((min, max)) = self.pan_bounds else {
+            return camera;
+        };
+        if self.viewport_px.x <= 0.0 || self.viewport_px.y <= 0.0 {
+            return camera;
+        }
+        let keep = PAN_BOUNDS_VISIBLE_PX / camera.zoom;
+        let clamp_axis = |pan: f32, lo: f32, hi: f32, view: f32| {
+            let (lo, hi) = (lo.min(hi), lo.max(hi));
+            let keep = keep.min(hi - lo).min(view);
+            pan.clamp(lo - view + keep, hi - keep)
+        };
+        camera.pan = Vec2::new(
+            clamp_axis(camera.pan.x, min.x, max.x, self.viewport_px.x / camera.zoom),
+            clamp_axis(camera.pan.y, min.y, max.y, self.viewport_px.y / camera.zoom),
+        );
+        camera
+    }
+
+    /// Zoom so the document's width exactly fills the viewport width.
+    pub fn fit_width(&mut self) {
+        let Some((min, max)) = self.doc.bounds() else {
+            return;
+        };
+        let center = Vec2::new((min.x + max.x) * 0.5, (min.y + max.y) * 0.5);
+        self.camera
+            .fit_extent(center, max.x - min.x, self.viewport_px.x, self.viewport_px);
+    }
+
+    /// Zoom so the document's height exactly fills the viewport height.
+    pub fn fit_height(&mut self) {
+        let Some((min, max)) = self.doc.bounds() else {
+            return;
+        };
+        let center = Vec2::new((min.x + max.x) * 0.5, (min.y + max.y) * 0.5);
+        self.camera
+            .fit_extent(center, max.y - min.y, self.viewport_px.y, self.viewport_px);
+    }
+
+    /// Zoom keeping the world point at the viewport center fixed, for zoom
+    /// buttons and shortcuts that have no cursor position. No-op until the
+    /// host has reported a viewport size.
+    ///
+    /// # Arguments
+    /// * `multiplier` - zoom factor; values above 1 zoom in
+    pub fn set_camera_zoom_about_center(&mut self, multiplier: f32) {
+        if self.viewport_px.x <= 0.0 || self.viewport_px.y <= 0.0 {
+            return;
+        }
+        let center = Vec2::new(self.viewport_px.x * 0.5, self.viewport_px.y * 0.5);
+        self.camera.zoom_at_screen_point(center, multiplier);
+    }
+
+    /// Constrain a rotation angle as `InputEvent::SetRotation` does: snapped
+    /// to the nearest `rotation_snap_deg` increment while shift is held, free
+    /// otherwise (or when the increment is not positive).
+    ///
+    /// # Arguments
+    /// * `angle_deg` - unconstrained angle in degrees
+    pub fn constrain_rotation_deg(&self, angle_deg: f32) -> f32 {
+        if !self.modifiers.shift || self.rotation_snap_deg <= 0.0 {
+            return angle_deg;
         }
+
+        (angle_deg / self.rotation_snap_deg).round() * self.rotation_snap_deg
     }
 
     /// Check if position collides with the shape objects.
     ///
+    /// The topmost rect within `HIT_TOLERANCE_PX` screen pixels of `world` is
+    /// hit, even over a lower rect that contains it exactly; the margin is
+    /// converted at the current zoom, so it shrinks as you zoom in.
+    ///
     /// # Arguments
     /// * `world` - pointer coordinate in world space
     pub fn check_collide_rects(&self, world: Vec2) -> Option<NodeId> {
-        for rect in self.doc.rects.iter().rev() {
-            let min_x = rect.pos.x;
-            let min_y = rect.pos.y;
-            let max_x = rect.pos.x + rect.size.x;
-            let max_y = rect.pos.y + rect.size.y;
-            if world.x >= min_x && world.x <= max_x && world.y >= min_y && world.y <= max_y {
-                return Some(rect.id);
+        // Text labels draw above every rect.
+        for text in self.doc.texts.iter().rev() {
+            let size = text.approx_size();
+            if world.x >= text.pos.x
+                && world.x <= text.pos.x + size.x
+                && world.y >= text.pos.y
+                && world.y <= text.pos.y + size.y
+            {
+                return Some(text.id);
+            }
+        }
+        let tolerance = self.hit_tolerance();
+        let reach = Vec2::new(tolerance, tolerance);
+        let candidates = self.with_spatial_index(|index| {
+            index.query_box(
+                Vec2::new(world.x - reach.x, world.y - reach.y),
+                Vec2::new(world.x + reach.x, world.y + reach.y),
+            )
+        });
+        candidates
+            .iter()
+            .rev()
+            .filter_map(|&i| self.doc.rects.get(i))
+            .filter(|rect| rect.visible && !rect.locked)
+            .find(|rect| rect.contains_point_within(world, tolerance))
+            .map(|rect| rect.id)
+    }
+
+    /// The node a click at `screen_px` would hit, without changing any state:
+    /// topmost wins, and hidden and locked rects are skipped, exactly as in
+    /// [`Engine::check_collide_rects`]. Resize handles are not considered.
+    ///
+    /// # Arguments
+    /// * `screen_px` - point in screen pixels, as pointer events report it
+    pub fn pick_at(&self, screen_px: Vec2) -> Option<NodeId> {
+        self.check_collide_rects(self.camera.screen_to_world(screen_px))
+    }
+
+    /// World-space width of the `HIT_TOLERANCE_PX` margin at the current zoom.
+    fn hit_tolerance(&self) -> f32 {
+        if self.camera.zoom > 0.0 {
+            HIT_TOLERANCE_PX / self.camera.zoom
+        } else {
+            0.0
+        }
+    }
+
+    /// Drop the cached hit-test index so the next query rebuilds it.
+    fn invalidate_spatial_index(&mut self) {
+        *self.spatial_index.get_mut() = None;
+    }
+
+    fn with_spatial_index<R>(&self, f: impl FnOnce(&SpatialIndex) -> R) -> R {
+        let mut cached = self.spatial_index.borrow_mut();
+        // An index over a different number of rects would hand out indices
+        // past the end; rebuild rather than trust it.
+        if cached
+            .as_ref()
+            .is_some_and(|index| index.rect_count() != self.doc.rects.len())
+        {
+            *cached = None;
+        }
+        f(cached.get_or_insert_with(|| SpatialIndex::build(&self.doc.rects)))
+    }
+
+    /// Select the single rect closest to `world`, measured to its nearest edge,
+    /// as long as it lies within `max_dist`. Clears the selection otherwise.
+    ///
+    /// Intended for touch input where a tap may land just outside a shape.
+    ///
+    /// # Arguments
+    /// * `world` - tap coordinate in world space
+    /// * `max_dist` - maximum world-space distance from a rect's bounds
+    pub fn select_nearest(&mut self, world: Vec2, max_dist: f32) {
+        let mut nearest: Option<(NodeId, f32)> = None;
+
+        // Topmost first so ties resolve to the rect drawn on top.
+        for rect in self
+            .doc
+            .rects
+            .iter()
+            .rev()
+            .filter(|rect| rect.visible && !rect.locked)
+        {
+            // Distance is unchanged by rotation, so measure in the rect's frame.
+            let world = rect.to_local(world);
+            let dx = (rect.pos.x - world.x)
+                .max(world.x - (rect.pos.x + rect.size.x))
+                .max(0.0);
+            let dy = (rect.pos.y - world.y)
+                .max(world.y - (rect.pos.y + rect.size.y))
+                .max(0.0);
+            let dist = (dx * dx + dy * dy).sqrt();
+
+            if dist <= max_dist && nearest.is_none_or(|(_, best)| dist < best) {
+                nearest = Some((rect.id, dist));
+            }
+        }
+
+        self.selected.clear();
+        if let Some((id, _)) = nearest {
+            self.selected.push(id);
+        }
+        self.request_redraw();
+    }
+
+    /// All pairs of rects whose world bounds strictly intersect (touching
+    /// edges do not count); rotated rects use their [`RectNode::world_aabb`].
+    /// Each pair is ordered `(lower, upper)` in draw order.
+    ///
+    /// Uses a sweep along x so sparse documents stay close to linear.
+    pub fn overlapping_pairs(&self) -> Vec<(NodeId, NodeId)> {
+        let mut order: Vec<(usize, (Vec2, Vec2))> = self
+            .doc
+            .rects
+            .iter()
+            .map(RectNode::world_aabb)
+            .enumerate()
+            .collect();
+        order.sort_by(|a, b| a.1.0.x.total_cmp(&b.1.0.x));
+
+        let mut pairs: Vec<(usize, usize)> = Vec::new();
+        for (i, (a_idx, a)) in order.iter().enumerate() {
+            for (b_idx, b) in &order[i + 1..] {
+                if b.0.x >= a.1.x {
+                    break;
+                }
+                if boxes_intersect(*a, *b) {
+                    pairs.push(((*a_idx).min(*b_idx), (*a_idx).max(*b_idx)));
+                }
+            }
+        }
+
+        pairs.sort_unstable();
+        pairs
+            .into_iter()
+            .map(|(lower, upper)| (self.doc.rects[lower].id, self.doc.rects[upper].id))
+            .collect()
+    }
+
+    /// Nudge overlapping rects apart as one undoable edit.
+    ///
+    /// For each overlapping pair the upper rect is pushed out of the lower one
+    /// along the axis of least penetration. Locked rects never move: when the
+    /// upper rect is locked the lower one is pushed instead, and pairs of
+    /// locked rects are left overlapping. A single pass is made, so dense
+    /// clusters may need repeated calls to separate completely.
+    pub fn resolve_overlaps(&mut self) {
+        let pairs = self.overlapping_pairs();
+        if pairs.is_empty() {
+            return;
+        }
+
+        let mut before: Vec<(NodeId, RectGeometry)> = Vec::new();
+
+        for (lower_id, upper_id) in pairs {
+            let (Some(lower), Some(upper)) =
+                (self.rect(lower_id).cloned(), self.rect(upper_id).cloned())
+            else {
+                continue;
+            };
+            // an earlier nudge may already have separated this pair
+            if !boxes_intersect(lower.world_aabb(), upper.world_aabb()) {
+                continue;
+            }
+            // `upper` is the one that moves from here on
+            let (lower, upper) = if upper.locked {
+                (upper, lower)
+            } else {
+                (lower, upper)
+            };
+            if upper.locked {
+                continue;
+            }
+            let upper_id = upper.id;
+            let (lower_min, lower_max) = lower.world_aabb();
+            let (upper_min, upper_max) = upper.world_aabb();
+
+            // compare doubled centers to pick the push direction on each axis
+            let push_right = (lower_min.x + lower_max.x) <= (upper_min.x + upper_max.x);
+            let push_down = (lower_min.y + lower_max.y) <= (upper_min.y + upper_max.y);
+
+            let dx = if push_right {
+                lower_max.x - upper_min.x
+            } else {
+                lower_min.x - upper_max.x
+            };
+            let dy = if push_down {
+                lower_max.y - upper_min.y
+            } else {
+                lower_min.y - upper_max.y
+            };
+
+            if !before.iter().any(|(id, _)| *id == upper_id) {
+                before.push((upper_id, RectGeometry::from_rect(&upper)));
+            }
+
+            if let Some(rect) = self.rect_mut(upper_id) {
+                if dx.abs() <= dy.abs() {
+                    rect.pos.x += dx;
+                } else {
+                    rect.pos.y += dy;
+                }
             }
         }
-        None
+
+        let changes: Vec<RectGeometryChange> = before
+            .into_iter()
+            .filter_map(|(id, before)| self.geometry_change_for_rect(id, before))
+            .collect();
+
+        if !changes.is_empty() {
+            self.push_history(ToolCommand::SetRectsGeometry {
+                changes,
+                texts: Vec::new(),
+            });
+        }
+    }
+
+    /// Replace every rect color matching a source color with its target, as a
+    /// single undoable edit. Colors matching no source are left untouched.
+    ///
+    /// # Arguments
+    /// * `mapping` - `(source, target)` pairs; the first matching source wins
+    /// * `tolerance` - maximum per-channel difference still counted as a match
+    pub fn remap_colors(&mut self, mapping: &[(Color, Color)], tolerance: f32) {
+        let changes: Vec<RectColorChange> = self
+            .doc
+            .rects
+            .iter()
+            .filter_map(|rect| {
+                let (_, target) = mapping.iter().find(|(source, _)| {
+                    source
+                        .to_array()
+                        .iter()
+                        .zip(rect.color.to_array())
+                        .all(|(a, b)| (a - b).abs() <= tolerance)
+                })?;
+
+                (rect.color != *target).then_some(RectColorChange {
+                    id: rect.id,
+                    before: rect.color,
+                    after: *target,
+                })
+            })
+            .collect();
+
+        if changes.is_empty() {
+            return;
+        }
+
+        let command = ToolCommand::SetRectsColor { changes };
+        self.apply_command(&command, true);
+        self.push_history(command);
     }
 
     /// Apply a selection change.
@@ -82,16 +679,21 @@ impl Engine {
     /// * `hit` - The `NodeId` that was interacted with, or `None` if empty space was clicked.
     /// * `shift` - `true` if the shift key was held down (typically used for multi-selection).
     pub fn apply_selection(&mut self, hit: Option<NodeId>, shift: bool) {
+        self.request_redraw();
         match (hit, shift) {
             (Some(id), false) => {
-                self.selected.clear();
-                self.selected.push(id);
+                self.selected = self.group_members(id);
             }
             (Some(id), true) => {
-                if let Some(idx) = self.selected.iter().position(|&v| v == id) {
-                    self.selected.swap_remove(idx);
+                let members = self.group_members(id);
+                if self.selected.contains(&id) {
+                    self.selected.retain(|v| !members.contains(v));
                 } else {
-                    self.selected.push(id);
+                    for member in members {
+                        if !self.selected.contains(&member) {
+                            self.selected.push(member);
+                        }
+                    }
                 }
             }
             (None, false) => {
@@ -101,73 +703,458 @@ impl Engine {
         }
     }
 
-    /// Process a batch of input events and return the new engine output.
+    /// `id` followed by the other rects in its group, in draw order; just `id`
+    /// when it is ungrouped.
+    fn group_members(&self, id: NodeId) -> Vec<NodeId> {
+        let mut members = vec![id];
+        if let Some(group) = self.rect(id).and_then(|rect| rect.group_id) {
+            members.extend(
+                self.doc
+                    .rects
+                    .iter()
+                    .filter(|rect| rect.group_id == Some(group) && rect.id != id)
+                    .map(|rect| rect.id),
+            );
+        }
+        members
+    }
+
+    /// Screen-space bounding box `(min, max)` of a node, for positioning host UI
+    /// such as popovers over it. `None` if the node does not exist.
     ///
     /// # Arguments
-    /// * `batch` - list of input events to process
-    pub fn tick(&mut self, batch: &InputBatch) -> EngineOutput {
-        let drag_threshold_px: f32 = 6.0;
-        let drag_threshold_sq: f32 = drag_threshold_px * drag_threshold_px;
+    /// * `id` - node to measure
+    pub fn node_screen_rect(&self, id: NodeId) -> Option<(Vec2, Vec2)> {
+        let (pos, size) = self.node_bounds(id)?;
+        let corners = [
+            pos,
+            Vec2::new(pos.x + size.x, pos.y),
+            Vec2::new(pos.x, pos.y + size.y),
+            Vec2::new(pos.x + size.x, pos.y + size.y),
+        ]
+        .map(|corner| self.camera.world_to_screen(corner));
+
+        // AABB of all four corners so the result stays correct once nodes can rotate.
+        let mut min = corners[0];
+        let mut max = corners[0];
+        for corner in &corners[1..] {
+            min.x = min.x.min(corner.x);
+            min.y = min.y.min(corner.y);
+            max.x = max.x.max(corner.x);
+            max.y = max.y.max(corner.y);
+        }
+        Some((min, max))
+    }
 
-        for ev in &batch.events {
-            match *ev {
-                InputEvent::CameraPanByScreenDelta { delta_px } => {
-                    self.camera.pan_by_screen_delta(delta_px);
-                }
-                InputEvent::CameraZoomAtScreenPoint {
-                    pivot_px,
-                    zoom_multiplier,
-                } => {
-                    self.camera.zoom_at_screen_point(pivot_px, zoom_multiplier);
-                }
-                InputEvent::PointerDown {
-                    screen_px,
-                    shift,
-                    button: _,
-                } => {
-                    let world = self.camera.screen_to_world(screen_px);
+    /// Attach host-specific data to a rect. Not recorded in undo history.
+    /// Returns `false` if no rect has that id.
+    ///
+    /// # Arguments
+    /// * `id` - rect to annotate
+    /// * `key` - metadata key; an existing value is overwritten
+    /// * `value` - value to store
+    pub fn set_meta(&mut self, id: NodeId, key: &str, value: &str) -> bool {
+        let Some(rect) = self.rect_mut(id) else {
+            return false;
+        };
+        rect.meta.insert(key.to_owned(), value.to_owned());
+        true
+    }
 
-                    // handle rect create takes priority
-                    if batch.tool == ToolMode::Rect {
-                        self.drag_state = DragState::PendingRectCreate(PendingRectCreate {
-                            start_screen_px: screen_px,
-                            start_world: world,
-                            previous_selection: self.selected.clone(),
-                        });
-                        continue;
-                    }
+    /// Host-specific data previously stored with [`Engine::set_meta`].
+    ///
+    /// # Arguments
+    /// * `id` - rect to look up
+    /// * `key` - metadata key
+    pub fn get_meta(&self, id: NodeId, key: &str) -> Option<&str> {
+        self.rect(id)?.meta.get(key).map(String::as_str)
+    }
 
-                    // Handle hit takes priority over rect selection
-                    if batch.tool == ToolMode::Select
-                        && let Some(handle_hit) = self.check_collide_handle(world)
-                    {
-                        self.drag_state = DragState::PendingResize(PendingResize {
-                            handle: handle_hit,
-                            start_screen_px: screen_px,
-                            start_world: world,
-                        });
-                        continue;
-                    }
+    /// Restack the selection to sit directly above `target` in draw order,
+    /// keeping the selected nodes' relative order. One undoable step.
+    ///
+    /// # Arguments
+    /// * `target` - unselected node to place the selection above
+    pub fn move_selection_above(&mut self, target: NodeId) {
+        self.restack_selection(target, true);
+    }
+
+    /// Restack the selection to sit directly below `target` in draw order,
+    /// keeping the selected nodes' relative order. One undoable step.
+    ///
+    /// # Arguments
+    /// * `target` - unselected node to place the selection below
+    pub fn move_selection_below(&mut self, target: NodeId) {
+        self.restack_selection(target, false);
+    }
+
+    /// Abort any in-progress drag and revert its uncommitted edits, returning
+    /// to `Idle`. Hosts call this when they may have missed a pointer-up,
+    /// e.g. on window blur or a visibility change.
+    pub fn reset_drag_state(&mut self) {
+        self.rollback_active_drag();
+        self.request_redraw();
+    }
+
+    /// Replace the selection from outside the pointer flow, e.g. a layers panel.
+    ///
+    /// Ids missing from the document are dropped, duplicates collapse, and the
+    /// result is stored in document (paint) order.
+    ///
+    /// # Arguments
+    /// * `ids` - requested selection, in any order
+    pub fn set_selected(&mut self, ids: Vec<NodeId>) {
+        let requested: HashSet<NodeId> = ids.into_iter().collect();
+        self.selected = self
+            .doc
+            .rects
+            .iter()
+            .map(|rect| rect.id)
+            .chain(self.doc.texts.iter().map(|text| text.id))
+            .filter(|id| requested.contains(id))
+            .collect();
+        self.request_redraw();
+    }
+
+    /// Export the current document as compact JSON.
+    pub fn export_document(&self) -> String {
+        self.doc.to_json()
+    }
+
+    /// Export the current document as indented, human-editable JSON.
+    pub fn export_document_pretty(&self) -> String {
+        self.doc.to_json_pretty()
+    }
+
+    /// The document as `tick` would draw it right now, for hosts that render
+    /// outside the frame loop, such as export.
+    pub fn render_scene(&self) -> RenderScene {
+        let cull_bounds = self.visible_world_bounds().filter(|_| self.cull_offscreen);
+        RenderScene {
+            rects: self
+                .doc
+                .rects
+                .iter()
+                .filter(|r| r.visible)
+                .filter(|r| {
+                    cull_bounds.is_none_or(|(min, max)| {
+                        let (lo, hi) = r.world_aabb();
+                        lo.x < max.x && hi.x > min.x && lo.y < max.y && hi.y > min.y
+                    })
+                })
+                .map(|r| RectInstance {
+                    rotation: r.rotation,
+                    stroke_color: r.stroke_color,
+                    stroke_width: r.stroke_width,
+                    ..RectInstance::solid([r.pos.x, r.pos.y], [r.size.x, r.size.y], r.color)
+                })
+                .collect(),
+            grid: self.grid_rects(),
+            texts: self
+                .doc
+                .texts
+                .iter()
+                .map(|t| TextInstance {
+                    pos: [t.pos.x, t.pos.y],
+                    content: t.content.clone(),
+                    size: t.size,
+                    color: t.color,
+                })
+                .collect(),
+        }
+    }
+
+    /// The document being edited.
+    pub fn doc(&self) -> &Document {
+        &self.doc
+    }
+
+    /// Mutable access to the document, for edits no input event covers.
+    /// Caches derived from it are dropped, so hit testing sees the change.
+    pub fn doc_mut(&mut self) -> &mut Document {
+        self.invalidate_spatial_index();
+        self.request_redraw();
+        &mut self.doc
+    }
+
+    /// Replace the current document with one parsed from `json` (compact or
+    /// pretty). Selection, any active drag, and history are reset.
+    pub fn load_document(&mut self, json: &str) -> serde_json::Result<()> {
+        self.replace_document(Document::from_json(json)?);
+        Ok(())
+    }
+
+    /// Replace the current document with `doc`, as `load_document` does for
+    /// JSON. `next_id` is raised past every id in use before editing resumes.
+    ///
+    /// # Arguments
+    /// * `doc` - document to edit from now on
+    pub fn replace_document(&mut self, mut doc: Document) {
+        doc.repair_next_id();
+        self.doc = doc;
+        self.invalidate_spatial_index();
+        self.request_redraw();
+        self.selected.clear();
+        self.drag_state = DragState::Idle;
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+    }
+
+    /// Append nodes from JSON into the current document, unlike
+    /// `load_document` which replaces it. Ids in the payload are ignored and
+    /// fresh ones allocated; the imported nodes end up selected.
+    ///
+    /// # Arguments
+    /// * `json` - a [`Clipboard`] object as `copy_selection` writes it, or a
+    ///   plain JSON array of `RectNode`s
+    /// * `offset` - world-space translation applied to every imported node
+    pub fn merge_nodes(&mut self, json: &str, offset: Vec2) -> serde_json::Result<Vec<NodeId>> {
+        #[derive(serde::Deserialize)]
+        #[serde(untagged)]
+        enum Payload {
+            Nodes(Clipboard),
+            Rects(Vec<RectNode>),
+        }
+
+        let clipboard = match serde_json::from_str(json)? {
+            Payload::Nodes(clipboard) => clipboard,
+            Payload::Rects(rects) => Clipboard {
+                rects,
+                texts: Vec::new(),
+            },
+        };
+        Ok(self.insert_nodes(clipboard.rects, clipboard.texts, offset))
+    }
+
+    /// Append already parsed rects and text labels, e.g. a clipboard payload,
+    /// as one undoable step and select them, returning their new ids. The
+    /// nodes' own ids are never trusted; fresh ones are allocated, so payloads
+    /// from another document are safe. Copies of a group form a new group.
+    ///
+    /// # Arguments
+    /// * `rects` - rects as carried by [`Engine::selection_clipboard`],
+    ///   possibly from another engine
+    /// * `texts` - text labels to append above the existing ones
+    /// * `offset` - world-space translation applied to every inserted node
+    pub fn insert_nodes(
+        &mut self,
+        rects: Vec<RectNode>,
+        texts: Vec<TextNode>,
+        offset: Vec2,
+    ) -> Vec<NodeId> {
+        if rects.is_empty() && texts.is_empty() {
+            return Vec::new();
+        }
+
+        // Copies of a group form a new group rather than joining the original.
+        let mut groups: HashMap<u64, u64> = HashMap::new();
+        let rects: Vec<RectNode> = rects
+            .into_iter()
+            .map(|mut rect| {
+                rect.id = self.doc.alloc_id();
+                rect.pos.x += offset.x;
+                rect.pos.y += offset.y;
+                rect.group_id = rect.group_id.map(|group| {
+                    *groups
+                        .entry(group)
+                        .or_insert_with(|| self.doc.alloc_group_id())
+                });
+                rect
+            })
+            .collect();
+        let texts: Vec<TextNode> = texts
+            .into_iter()
+            .map(|mut text| {
+                text.id = self.doc.alloc_id();
+                text.pos.x += offset.x;
+                text.pos.y += offset.y;
+                text
+            })
+            .collect();
+        let ids: Vec<NodeId> = rects
+            .iter()
+            .map(|rect| rect.id)
+            .chain(texts.iter().map(|text| text.id))
+            .collect();
+
+        let command = ToolCommand::InsertRects {
+            rects,
+            texts,
+            previous_selection: self.selected.clone(),
+            next_selection: ids.clone(),
+        };
+        self.apply_command(&command, true);
+        self.push_history(command);
+
+        ids
+    }
+
+    /// The selected nodes a delete would remove, in draw order: unlocked
+    /// rects and text labels. Locked rects stay behind on a cut, so they are
+    /// left out of the copy as well.
+    pub fn selection_clipboard(&self) -> Clipboard {
+        let selected: HashSet<NodeId> = self.selected.iter().copied().collect();
+        Clipboard {
+            rects: self
+                .doc
+                .rects
+                .iter()
+                .filter(|rect| !rect.locked && selected.contains(&rect.id))
+                .cloned()
+                .collect(),
+            texts: self
+                .doc
+                .texts
+                .iter()
+                .filter(|text| selected.contains(&text.id))
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// Serialize [`Engine::selection_clipboard`] as JSON. The result can be
+    /// pasted back with [`Engine::merge_nodes`].
+    pub fn copy_selection(&self) -> String {
+        serde_json::to_string(&self.selection_clipboard()).expect("nodes serialize to JSON")
+    }
+
+    /// Take the selection as a clipboard and delete it as one undoable step.
+    pub fn cut_clipboard(&mut self) -> Clipboard {
+        let clipboard = self.selection_clipboard();
+        self.delete_selection();
+        clipboard
+    }
+
+    /// Copy the selection like [`Engine::copy_selection`], then delete it as
+    /// one undoable step.
+    pub fn cut_selection(&mut self) -> String {
+        serde_json::to_string(&self.cut_clipboard()).expect("nodes serialize to JSON")
+    }
+
+    /// Apply a recorded sequence of batches in order, as if each arrived
+    /// through `tick`. Returns the output of the last batch, if any.
+    ///
+    /// # Arguments
+    /// * `batches` - batches previously captured from a live session
+    pub fn replay(&mut self, batches: &[InputBatch]) -> Option<EngineOutput> {
+        batches.iter().map(|batch| self.tick(batch)).last()
+    }
+
+    /// Process a batch of input events and return the new engine output.
+    ///
+    /// # Arguments
+    /// * `batch` - list of input events to process
+    pub fn tick(&mut self, batch: &InputBatch) -> EngineOutput {
+        let drag_threshold_px: f32 = 6.0;
+        let drag_threshold_sq: f32 = drag_threshold_px * drag_threshold_px;
+
+        // Any event may change what is drawn; comparing whole frames to find
+        // out would cost more than the redraw it saves.
+        if !batch.events.is_empty() {
+            self.request_redraw();
+        }
+
+        for ev in &batch.events {
+            if let Some(observer) = self.event_observer.as_mut() {
+                observer(ev);
+            }
+
+            match *ev {
+                InputEvent::CameraPanByScreenDelta { delta_px } => {
+                    self.camera_animation = None;
+                    self.camera.pan_by_screen_delta(delta_px);
+                    self.clamp_pan_to_bounds();
+                }
+                InputEvent::CameraZoomAtScreenPoint {
+                    pivot_px,
+                    zoom_multiplier,
+                } => {
+                    self.camera_animation = None;
+                    self.camera.zoom_at_screen_point(pivot_px, zoom_multiplier);
+                    self.clamp_pan_to_bounds();
+                }
+                InputEvent::WheelZoom { pivot_px, delta_y } => {
+                    let zoom_multiplier = (-delta_y * WHEEL_ZOOM_SPEED).exp();
+                    self.camera_animation = None;
+                    self.camera.zoom_at_screen_point(pivot_px, zoom_multiplier);
+                    self.clamp_pan_to_bounds();
+                }
+                InputEvent::PointerDown {
+                    screen_px,
+                    shift,
+                    button: _,
+                } => {
+                    self.modifiers.shift = shift;
+                    let world = self.camera.screen_to_world(screen_px);
+                    // only a press on empty canvas keeps a double click going
+                    let previous_press = self.last_empty_press.take();
+
+                    // handle rect create takes priority
+                    if batch.tool == ToolMode::Rect {
+                        self.drag_state = DragState::PendingRectCreate(PendingRectCreate {
+                            start_screen_px: screen_px,
+                            start_world: world,
+                            previous_selection: self.selected.clone(),
+                        });
+                        continue;
+                    }
+
+                    // Handle hit takes priority over rect selection
+                    if batch.tool == ToolMode::Select
+                        && let Some(handle_hit) = self.check_collide_handle(world)
+                    {
+                        self.drag_state = DragState::PendingResize(PendingResize {
+                            handle: handle_hit,
+                            start_screen_px: screen_px,
+                            start_world: world,
+                        });
+                        continue;
+                    }
 
                     let hit = self.check_collide_rects(world);
+                    let press = batch.timestamp_ms.filter(|_| hit.is_none());
+                    let double_click = press.is_some_and(|now| {
+                        self.completes_double_click(previous_press, now, screen_px)
+                    });
+                    // A third press starts a new pair instead of creating another rect.
+                    if !double_click {
+                        self.last_empty_press = press.map(|now| (now, screen_px));
+                    }
 
                     self.drag_state = if let Some(hit_id) = hit {
                         // mouse down on a rect (hit)
-                        let hit_was_selected = self.selected.contains(&hit_id);
+                        let previous_selection = self.selected.clone();
+                        // keep a multi-selection intact so the whole group can be dragged
+                        let collapse_on_click = self.selected.contains(&hit_id) && !shift;
+                        if !collapse_on_click {
+                            self.apply_selection(Some(hit_id), shift);
+                        }
 
-                        if hit_was_selected && !shift {
+                        // a shift-click that toggled the rect off has nothing to drag
+                        if self.selected.contains(&hit_id) {
                             DragState::PendingSelectionMove(PendingSelectionMove {
+                                hit_id,
                                 start_screen_px: screen_px,
                                 start_world: world,
-                                previous_selection: self.selected.clone(),
+                                previous_selection,
+                                collapse_on_click,
                             })
                         } else {
-                            self.apply_selection(Some(hit_id), shift);
                             DragState::Idle
                         }
+                    } else if double_click {
+                        // second press of a double click on empty canvas
+                        let pos = Vec2::new(
+                            world.x - DOUBLE_CLICK_RECT_SIZE.x * 0.5,
+                            world.y - DOUBLE_CLICK_RECT_SIZE.y * 0.5,
+                        );
+                        self.create_rect(pos, DOUBLE_CLICK_RECT_SIZE, NEW_RECT_COLOR);
+                        DragState::Idle
                     } else {
                         // mouse down on empty space with `select` tool
-                        self.apply_selection(None, shift);
+                        if self.clear_on_empty_click {
+                            self.apply_selection(None, shift);
+                        }
                         DragState::PendingMarquee(PendingMarquee {
                             start_screen_px: screen_px,
                             start_world: world,
@@ -200,11 +1187,24 @@ impl Engine {
                     let drag_state = std::mem::replace(&mut self.drag_state, DragState::Idle);
 
                     let command = match drag_state {
+                        DragState::PendingSelectionMove(pending) => {
+                            // Press preserved the selection so a group could be
+                            // dragged; a plain click without drag collapses it.
+                            if pending.collapse_on_click {
+                                self.selected = self.group_members(pending.hit_id);
+                            }
+                            None
+                        }
                         DragState::SelectionMove(drag) => {
+                            if drag.guides.is_empty() {
+                                for (id, origin) in &drag.origins {
+                                    self.snap_committed_rect(*id, *origin, None);
+                                }
+                            }
                             let changes: Vec<RectGeometryChange> = drag
                                 .origins
-                                .into_iter()
-                                .filter_map(|(id, origin_pos)| {
+                                .iter()
+                                .filter_map(|&(id, origin_pos)| {
                                     let before = RectGeometry {
                                         pos: origin_pos,
                                         size: self.rect(id)?.size,
@@ -212,21 +1212,42 @@ impl Engine {
                                     self.geometry_change_for_rect(id, before)
                                 })
                                 .collect();
+                            let texts: Vec<TextMoveChange> = drag
+                                .origins
+                                .iter()
+                                .filter_map(|&(id, before)| {
+                                    let after = self.text(id)?.pos;
+                                    (after != before).then_some(TextMoveChange {
+                                        id,
+                                        before,
+                                        after,
+                                    })
+                                })
+                                .collect();
 
-                            (!changes.is_empty())
-                                .then_some(ToolCommand::SetRectsGeometry { changes })
+                            (!changes.is_empty() || !texts.is_empty())
+                                .then_some(ToolCommand::SetRectsGeometry { changes, texts })
                         }
-                        DragState::Resize(drag) => self
-                            .geometry_change_for_rect(
+                        DragState::Resize(drag) => {
+                            self.snap_committed_rect(
+                                drag.handle.node_id,
+                                drag.origin_pos,
+                                Some(drag.origin_size),
+                            );
+                            self.geometry_change_for_rect(
                                 drag.handle.node_id,
                                 RectGeometry {
                                     pos: drag.origin_pos,
                                     size: drag.origin_size,
                                 },
                             )
-                            .map(|change| ToolCommand::SetRectsGeometry {
-                                changes: vec![change],
-                            }),
+                            .map(|change| {
+                                ToolCommand::SetRectsGeometry {
+                                    changes: vec![change],
+                                    texts: Vec::new(),
+                                }
+                            })
+                        }
                         DragState::RectCreate(drag) => {
                             let min_size = 1.0f32;
 
@@ -238,12 +1259,12 @@ impl Engine {
                             let w = raw_w.max(min_size);
                             let h = raw_h.max(min_size);
 
-                            let rect = RectNode {
-                                id: self.doc.alloc_id(),
-                                pos: Vec2::new(min_x, min_y),
-                                size: Vec2::new(w, h),
-                                color: [0.769, 0.769, 0.769, 1.0],
-                            };
+                            let rect = RectNode::new(
+                                self.doc.alloc_id(),
+                                Vec2::new(min_x, min_y),
+                                Vec2::new(w, h),
+                                NEW_RECT_COLOR,
+                            );
 
                             Some(ToolCommand::CreateRect {
                                 next_selection: vec![rect.id],
@@ -264,14 +1285,11 @@ impl Engine {
                 InputEvent::PointerCancel => {
                     self.rollback_active_drag();
                 }
+                InputEvent::SetFill { color } => {
+                    self.set_selection_fill(color);
+                }
                 InputEvent::SetSelectionFill { color } => {
-                    let selected: HashSet<NodeId> = self.selected.iter().copied().collect();
-
-                    for rect in &mut self.doc.rects {
-                        if selected.contains(&rect.id) {
-                            rect.color = [color.r, color.g, color.b, color.a];
-                        }
-                    }
+                    self.set_selection_fill(color.into());
                 }
                 InputEvent::Undo => {
                     self.undo();
@@ -297,55 +1315,132 @@ impl Engine {
                     self.apply_command(&command, true);
                     self.push_history(command);
                 }
+                InputEvent::BringToFront => {
+                    self.stack_selection_at_end(true);
+                }
+                InputEvent::SendToBack => {
+                    self.stack_selection_at_end(false);
+                }
+                InputEvent::RaiseOne => {
+                    self.step_selection(true);
+                }
+                InputEvent::LowerOne => {
+                    self.step_selection(false);
+                }
                 InputEvent::DeleteSelected => {
-                    let selected_ids: HashSet<NodeId> = self.selected.iter().copied().collect();
-                    let rects: Vec<(RectNode, usize)> = self
+                    self.delete_selection();
+                }
+                InputEvent::SelectAll => {
+                    self.rollback_active_drag();
+                    self.selected = self
                         .doc
                         .rects
                         .iter()
-                        .enumerate()
-                        .filter_map(|(idx, rect)| {
-                            selected_ids.contains(&rect.id).then_some((*rect, idx))
-                        })
+                        .filter(|rect| rect.visible && !rect.locked)
+                        .map(|rect| rect.id)
+                        .chain(self.doc.texts.iter().map(|text| text.id))
                         .collect();
-
-                    if rects.is_empty() {
-                        continue;
+                }
+                InputEvent::ClearSelection => {
+                    self.rollback_active_drag();
+                    self.selected.clear();
+                }
+                InputEvent::DuplicateSelected { offset } => {
+                    self.duplicate_selection(offset);
+                }
+                InputEvent::Group => {
+                    if self.selected.len() >= 2 {
+                        let group = self.doc.alloc_group_id();
+                        self.set_selection_group(Some(group));
                     }
-
-                    let command = ToolCommand::Delete {
-                        rects,
-                        previous_selection: self.selected.clone(),
-                        next_selection: Vec::new(),
-                    };
-
-                    self.apply_command(&command, true);
-                    self.push_history(command);
+                }
+                InputEvent::Ungroup => {
+                    self.set_selection_group(None);
+                }
+                InputEvent::SetVisible { visible } => {
+                    self.set_selection_visible(visible);
+                }
+                InputEvent::SetLocked { locked } => {
+                    self.set_selection_locked(locked);
+                }
+                InputEvent::Align { mode } => {
+                    self.align_selection(mode);
+                }
+                InputEvent::Distribute { axis } => {
+                    self.distribute_selection(axis);
+                }
+                InputEvent::KeyDown { ref key, shift } => {
+                    self.modifiers.apply_key(key, true);
+
+                    let step = if shift { NUDGE_STEP * 10.0 } else { NUDGE_STEP };
+                    match key.as_str() {
+                        "Escape" => self.escape(),
+                        "ArrowLeft" => self.nudge_selection(Vec2::new(-step, 0.0)),
+                        "ArrowRight" => self.nudge_selection(Vec2::new(step, 0.0)),
+                        "ArrowUp" => self.nudge_selection(Vec2::new(0.0, -step)),
+                        "ArrowDown" => self.nudge_selection(Vec2::new(0.0, step)),
+                        _ => {}
+                    }
+                }
+                InputEvent::KeyUp { ref key } => {
+                    self.modifiers.apply_key(key, false);
+                }
+                InputEvent::FitWidth => {
+                    self.transition_camera(Self::fit_width);
+                }
+                InputEvent::FitHeight => {
+                    self.transition_camera(Self::fit_height);
+                }
+                InputEvent::ZoomAboutCenter { multiplier } => {
+                    self.camera_animation = None;
+                    self.set_camera_zoom_about_center(multiplier);
+                    self.clamp_pan_to_bounds();
+                }
+                InputEvent::FitToContent { viewport_px } => {
+                    self.transition_camera(|engine| engine.fit_to_content(viewport_px));
+                }
+                InputEvent::ResetCamera => {
+                    self.transition_camera(|engine| engine.camera = Camera::default());
+                }
+                InputEvent::ZoomToActualSize { pivot_px } => {
+                    self.camera_animation = None;
+                    self.camera.set_zoom_at_screen_point(pivot_px, 1.0);
+                    self.clamp_pan_to_bounds();
+                }
+                InputEvent::CreateRect { world, size, color } => {
+                    self.create_rect(world, size, color);
+                }
+                InputEvent::SetBounds { id, pos, size } => {
+                    self.set_rect_bounds(id, pos, size);
+                }
+                InputEvent::SetRotation { id, degrees } => {
+                    self.set_rect_rotation(id, degrees);
+                }
+                InputEvent::SetSnapGrid { size } => {
+                    self.snap_grid = size;
                 }
             }
         }
 
-        let render_scene = RenderScene {
-            rects: self
-                .doc
-                .rects
-                .iter()
-                .map(|r| RectInstance {
-                    pos: [r.pos.x, r.pos.y],
-                    size: [r.size.x, r.size.y],
-                    color: r.color,
-                })
-                .collect(),
-        };
+        // After the events, so a pan or zoom in this batch wins over the animation.
+        if let Some(dt_ms) = batch.dt_ms {
+            self.advance_camera_animation(dt_ms);
+        }
 
+        let render_scene = self.render_scene();
         let overlay_scene = self.update_overlay_scene(&batch.tool);
         let cursor = self.compute_cursor(&batch.tool);
 
+        let needs_redraw = self.redraw_pending || self.reported_camera != Some(self.camera);
+        self.redraw_pending = false;
+        self.reported_camera = Some(self.camera);
+
         EngineOutput {
             camera: self.camera,
             render_scene,
             overlay_scene,
             cursor,
+            needs_redraw,
         }
     }
 
@@ -381,7 +1476,7 @@ impl Engine {
     }
 
     /// Returns the handle hit if `world` is within grab distance of any
-    /// corner handle of the single selected rect. Returns `None` if
+    /// corner or edge handle of the single selected rect. Returns `None` if
     /// nothing is selected, more than one rect is selected, or the point
     /// misses all handles.
     pub fn check_collide_handle(&self, world: Vec2) -> Option<HandleHit> {
@@ -391,191 +1486,404 @@ impl Engine {
         }
 
         let id = self.selected[0];
-        let rect = self.doc.rects.iter().find(|r| r.id == id)?;
+        if self.rect(id).is_some_and(|rect| rect.locked) {
+            return None;
+        }
+        let edge = self.hit_test_edge(id, world);
+
+        edge.is_handle().then_some(HandleHit { node_id: id, edge })
+    }
+
+    /// Classify `world` against the bounds of node `id`.
+    ///
+    /// Corner regions use the handle grab radius, edges a thinner band that
+    /// straddles the outline; both are sized in screen pixels so they stay
+    /// constant under zoom. Returns `Edge::Outside` for unknown ids.
+    pub fn hit_test_edge(&self, id: NodeId, world: Vec2) -> Edge {
+        let Some(rect) = self.rect(id) else {
+            return Edge::Outside;
+        };
         let (x, y, w, h) = (rect.pos.x, rect.pos.y, rect.size.x, rect.size.y);
+        // Handles turn with the rect, so classify in its unrotated frame.
+        let world = rect.to_local(world);
 
-        // hit radius in world units (slightly larger than visual handle — 8px in init_overlay_scene)
-        let hit_px = 12.0_f32;
-        let hit_r = hit_px / self.camera.zoom;
+        let corner_r = HANDLE_HIT_PX / self.camera.zoom;
+        let band = EDGE_HIT_PX / self.camera.zoom;
 
         let corners = [
-            (Vec2::new(x, y), Corner::TL),
-            (Vec2::new(x + w, y), Corner::TR),
-            (Vec2::new(x, y + h), Corner::BL),
-            (Vec2::new(x + w, y + h), Corner::BR),
+            (Vec2::new(x, y), Edge::NW),
+            (Vec2::new(x + w, y), Edge::NE),
+            (Vec2::new(x, y + h), Edge::SW),
+            (Vec2::new(x + w, y + h), Edge::SE),
         ];
 
-        for (center, corner) in corners {
-            if (world.x - center.x).abs() <= hit_r && (world.y - center.y).abs() <= hit_r {
-                return Some(HandleHit {
-                    node_id: id,
-                    corner,
-                });
+        for (center, edge) in corners {
+            if (world.x - center.x).abs() <= corner_r && (world.y - center.y).abs() <= corner_r {
+                return edge;
             }
         }
 
-        None
+        let within_x = world.x >= x && world.x <= x + w;
+        let within_y = world.y >= y && world.y <= y + h;
+
+        if within_x && (world.y - y).abs() <= band {
+            Edge::N
+        } else if within_x && (world.y - (y + h)).abs() <= band {
+            Edge::S
+        } else if within_y && (world.x - (x + w)).abs() <= band {
+            Edge::E
+        } else if within_y && (world.x - x).abs() <= band {
+            Edge::W
+        } else if within_x && within_y {
+            Edge::Inside
+        } else {
+            Edge::Outside
+        }
     }
 
-    fn update_overlay_scene(&self, tool_mode: &ToolMode) -> OverlayScene {
-        let outline_px = 2.0;
-        let handle_px = 8.0;
-        let outline = outline_px / self.camera.zoom;
-        let handle = handle_px / self.camera.zoom;
-        let outline_color = [0.95, 0.95, 0.95, 1.0];
-        let handle_color = [0.1, 0.6, 1.0, 1.0];
-        let mut overlay_rects = Vec::new();
-        for id in &self.selected {
-            if matches!(tool_mode, ToolMode::Rect) {
-                break;
+    fn active_snap_grid(&self) -> Option<f32> {
+        self.snap_grid
+            .filter(|grid| *grid > 0.0 && grid.is_finite())
+    }
+
+    /// Round a dragged rect's position, and its size too after a resize, to
+    /// the snap grid as the drag commits. Moves and resizes follow the
+    /// pointer freely until then, so rounding never jitters mid-drag.
+    ///
+    /// # Arguments
+    /// * `origin_pos` - position when the drag began; with the size, picks
+    ///   the artboard the rect must stay inside
+    /// * `resized_from` - size when a resize began; `None` for a move
+    fn snap_committed_rect(&mut self, id: NodeId, origin_pos: Vec2, resized_from: Option<Vec2>) {
+        let Some(grid) = self.active_snap_grid() else {
+            return;
+        };
+        let Some(rect) = self.rect(id) else {
+            return;
+        };
+        let mut pos = snap_point(rect.pos, Some(grid));
+        let mut size = rect.size;
+        if resized_from.is_some() {
+            let snapped = snap_point(size, Some(grid));
+            size = Vec2::new(snapped.x.max(grid), snapped.y.max(grid));
+        }
+        let origin_size = resized_from.unwrap_or(rect.size);
+        if rect.rotation != 0.0 {
+            // Rounding can nudge a turned footprint over an edge it was
+            // clipped to; leave the rect unsnapped rather than push it out.
+            let rotation = rect.rotation;
+            if let Some(artboard) = self.rotated_clip_artboard(origin_pos, origin_size, rotation)
+                && !fits_rotated(&artboard, pos, size, rotation)
+            {
+                return;
+            }
+            if let Some(rect) = self.rect_mut(id) {
+                rect.pos = pos;
+                rect.size = size;
+            }
+            return;
+        }
+        let artboard = self
+            .clip_children_to_artboard
+            .then(|| self.doc.containing_artboard(origin_pos, origin_size))
+            .flatten();
+        if let Some(artboard) = artboard {
+            if resized_from.is_some() {
+                (pos, size) = clamp_to_artboard(pos, size, &artboard);
+            } else {
+                // Rounding may step just past an edge; step back inside.
+                let max = Vec2::new(
+                    artboard.pos.x + artboard.size.x - size.x,
+                    artboard.pos.y + artboard.size.y - size.y,
+                );
+                pos = Vec2::new(
+                    pos.x.min(max.x).max(artboard.pos.x),
+                    pos.y.min(max.y).max(artboard.pos.y),
+                );
             }
+        }
+        if let Some(rect) = self.rect_mut(id) {
+            rect.pos = pos;
+            rect.size = size;
+        }
+    }
 
-            let Some(rect) = self.doc.rects.iter().find(|r| r.id == *id) else {
-                continue;
-            };
-            let x = rect.pos.x;
-            let y = rect.pos.y;
-            let w = rect.size.x;
-            let h = rect.size.y;
-            // outline
-            overlay_rects.push(RectInstance {
-                pos: [x, y],
-                size: [w, outline],
-                color: outline_color,
-            });
-            overlay_rects.push(RectInstance {
-                pos: [x, y + h - outline],
-                size: [w, outline],
-                color: outline_color,
-            });
-            overlay_rects.push(RectInstance {
-                pos: [x, y],
-                size: [outline, h],
-                color: outline_color,
-            });
-            overlay_rects.push(RectInstance {
-                pos: [x + w - outline, y],
-                size: [outline, h],
-                color: outline_color,
-            });
-            // handles
-            overlay_rects.push(RectInstance {
-                pos: [x - handle * 0.5, y - handle * 0.5],
-                size: [handle, handle],
-                color: handle_color,
-            });
-            overlay_rects.push(RectInstance {
-                pos: [x + w - handle * 0.5, y - handle * 0.5],
-                size: [handle, handle],
-                color: handle_color,
-            });
-            overlay_rects.push(RectInstance {
-                pos: [x - handle * 0.5, y + h - handle * 0.5],
-                size: [handle, handle],
-                color: handle_color,
-            });
-            overlay_rects.push(RectInstance {
-                pos: [x + w - handle * 0.5, y + h - handle * 0.5],
-                size: [handle, handle],
-                color: handle_color,
-            });
+    /// Faint grid intersection dots around where the active move or create
+    /// drag will land once released. Empty unless snapping is on and such a
+    /// drag is active.
+    fn snap_preview_rects(&self) -> Vec<RectInstance> {
+        // Grid cells drawn on each side of the drag target.
+        const PREVIEW_RADIUS_CELLS: i32 = 3;
+        // Skip the preview once dots would sit closer than this on screen.
+        const MIN_DOT_SPACING_PX: f32 = 6.0;
+
+        let Some(grid) = self.active_snap_grid() else {
+            return Vec::new();
+        };
+        let target = match &self.drag_state {
+            DragState::SelectionMove(drag) => match drag.origins.first() {
+                Some((id, _)) => match self.node_pos(*id) {
+                    Some(pos) => snap_point(pos, Some(grid)),
+                    None => return Vec::new(),
+                },
+                None => return Vec::new(),
+            },
+            DragState::RectCreate(drag) => drag.current_world,
+            _ => return Vec::new(),
+        };
+        if grid * self.camera.zoom < MIN_DOT_SPACING_PX {
+            return Vec::new();
         }
 
-        if let DragState::Marquee(drag) = &self.drag_state {
-            let min_x = drag.start_world.x.min(drag.current_world.x);
-            let min_y = drag.start_world.y.min(drag.current_world.y);
-            let max_x = drag.start_world.x.max(drag.current_world.x);
-            let max_y = drag.start_world.y.max(drag.current_world.y);
-
-            let w = (max_x - min_x).max(0.0);
-            let h = (max_y - min_y).max(0.0);
-
-            let fill_color = [0.2, 0.6, 1.0, 0.08];
-            let outline_color = [0.2, 0.6, 1.0, 0.9];
-            let outline_px = 1.0 / self.camera.zoom;
-
-            // fill
-            overlay_rects.push(RectInstance {
-                pos: [min_x, min_y],
-                size: [w, h],
-                color: fill_color,
-            });
+        let radius = grid * PREVIEW_RADIUS_CELLS as f32;
+        let mut min = Vec2::new(target.x - radius, target.y - radius);
+        let mut max = Vec2::new(target.x + radius, target.y + radius);
+        if self.viewport_px.x > 0.0 && self.viewport_px.y > 0.0 {
+            let view_max = self.camera.screen_to_world(self.viewport_px);
+            min = Vec2::new(min.x.max(self.camera.pan.x), min.y.max(self.camera.pan.y));
+            max = Vec2::new(max.x.min(view_max.x), max.y.min(view_max.y));
+        }
 
-            // outline (4 thin rects)
-            overlay_rects.push(RectInstance {
-                pos: [min_x, min_y],
-                size: [w, outline_px],
-                color: outline_color,
-            });
-            overlay_rects.push(RectInstance {
-                pos: [min_x, max_y - outline_px],
-                size: [w, outline_px],
-                color: outline_color,
-            });
-            overlay_rects.push(RectInstance {
-                pos: [min_x, min_y],
-                size: [outline_px, h],
-                color: outline_color,
-            });
-            overlay_rects.push(RectInstance {
-                pos: [max_x - outline_px, min_y],
-                size: [outline_px, h],
-                color: outline_color,
-            });
+        let dot = 2.0 / self.camera.zoom;
+        let dot_color = Color::rgba(1.0, 1.0, 1.0, 0.35);
+        let mut dots = Vec::new();
+        let mut gy = (min.y / grid).ceil() * grid;
+        while gy <= max.y {
+            let mut gx = (min.x / grid).ceil() * grid;
+            while gx <= max.x {
+                dots.push(RectInstance::solid(
+                    [gx - dot * 0.5, gy - dot * 0.5],
+                    [dot, dot],
+                    dot_color,
+                ));
+                gx += grid;
+            }
+            gy += grid;
         }
+        dots
+    }
 
-        if let DragState::RectCreate(drag) = &self.drag_state {
-            let min_x = drag.start_world.x.min(drag.current_world.x);
-            let min_y = drag.start_world.y.min(drag.current_world.y);
-            let max_x = drag.start_world.x.max(drag.current_world.x);
-            let max_y = drag.start_world.y.max(drag.current_world.y);
-
-            let w = (max_x - min_x).max(0.0);
-            let h = (max_y - min_y).max(0.0);
-
-            let fill_color = [0.2, 0.6, 1.0, 0.08];
-            let outline_color = [0.2, 0.6, 1.0, 0.9];
-            let outline_px = 1.0 / self.camera.zoom;
-
-            // fill
-            overlay_rects.push(RectInstance {
-                pos: [min_x, min_y],
-                size: [w, h],
-                color: fill_color,
-            });
+    /// X axis, Y axis, then an origin marker, as 1px-wide overlay lines across
+    /// the visible world. Empty unless `debug_axes` is on and the viewport is known.
+    fn debug_axes_rects(&self) -> Vec<RectInstance> {
+        // Origin marker half-extent in screen pixels.
+        const ORIGIN_MARKER_PX: f32 = 6.0;
 
-            // outline (4 thin rects)
-            overlay_rects.push(RectInstance {
-                pos: [min_x, min_y],
-                size: [w, outline_px],
-                color: outline_color,
-            });
-            overlay_rects.push(RectInstance {
-                pos: [min_x, max_y - outline_px],
-                size: [w, outline_px],
-                color: outline_color,
-            });
-            overlay_rects.push(RectInstance {
-                pos: [min_x, min_y],
-                size: [outline_px, h],
-                color: outline_color,
-            });
-            overlay_rects.push(RectInstance {
-                pos: [max_x - outline_px, min_y],
-                size: [outline_px, h],
-                color: outline_color,
-            });
+        if !self.debug_axes {
+            return Vec::new();
+        }
+        let Some((min, max)) = self.visible_world_bounds() else {
+            return Vec::new();
+        };
+        let line = 1.0 / self.camera.zoom;
+        let marker = ORIGIN_MARKER_PX / self.camera.zoom;
+        vec![
+            RectInstance::solid(
+                [min.x, -line * 0.5],
+                [max.x - min.x, line],
+                Color::rgba(1.0, 0.2, 0.2, 0.8),
+            ),
+            RectInstance::solid(
+                [-line * 0.5, min.y],
+                [line, max.y - min.y],
+                Color::rgba(0.2, 1.0, 0.2, 0.8),
+            ),
+            RectInstance {
+                shape: InstanceShape::Ellipse,
+                ..RectInstance::solid(
+                    [-marker, -marker],
+                    [marker * 2.0, marker * 2.0],
+                    Color::rgba(1.0, 1.0, 1.0, 0.8),
+                )
+            },
+        ]
+    }
+
+    /// World-space `(min, max)` corners of what the viewport shows, or `None`
+    /// before the host has reported a viewport size.
+    fn visible_world_bounds(&self) -> Option<(Vec2, Vec2)> {
+        if self.viewport_px.x <= 0.0 || self.viewport_px.y <= 0.0 {
+            return None;
+        }
+        Some((
+            self.camera.pan,
+            self.camera.screen_to_world(self.viewport_px),
+        ))
+    }
+
+    /// Background grid lines crossing the visible world, `grid_step` apart,
+    /// with the step doubled until lines sit at least `MIN_SPACING_PX` apart
+    /// on screen. Empty unless a step is set and the viewport is known.
+    fn grid_rects(&self) -> Vec<RectInstance> {
+        const MIN_SPACING_PX: f32 = 8.0;
+        const GRID_COLOR: Color = Color::rgba(1.0, 1.0, 1.0, 0.06);
+
+        let Some(mut step) = self
+            .grid_step
+            .filter(|step| *step > 0.0 && step.is_finite())
+        else {
+            return Vec::new();
+        };
+        let Some((min, max)) = self.visible_world_bounds() else {
+            return Vec::new();
+        };
+        while step * self.camera.zoom < MIN_SPACING_PX {
+            step *= 2.0;
+        }
+
+        let line = 1.0 / self.camera.zoom;
+        let line_rect = |pos: [f32; 2], size: [f32; 2]| RectInstance::solid(pos, size, GRID_COLOR);
+
+        let mut rects = Vec::new();
+        for i in (min.x / step).ceil() as i64..=(max.x / step).floor() as i64 {
+            let x = i as f32 * step;
+            rects.push(line_rect([x - line * 0.5, min.y], [line, max.y - min.y]));
+        }
+        for i in (min.y / step).ceil() as i64..=(max.y / step).floor() as i64 {
+            let y = i as f32 * step;
+            rects.push(line_rect([min.x, y - line * 0.5], [max.x - min.x, line]));
+        }
+        rects
+    }
+
+    fn update_overlay_scene(&self, tool_mode: &ToolMode) -> OverlayScene {
+        let outline_color = Color::rgba(0.95, 0.95, 0.95, 1.0);
+        let handle_color = Color::rgba(0.1, 0.6, 1.0, 1.0);
+        let per_node_handles = self.overlay_theme.show_per_node_handles || self.selected.len() < 2;
+        let handle_shape = self.overlay_theme.handle_shape.instance_shape();
+        let mut selection_boxes = Vec::new();
+        let mut overlay_rects = Vec::new();
+
+        if !matches!(tool_mode, ToolMode::Rect) {
+            for id in &self.selected {
+                if self.rect(*id).is_some_and(|rect| !rect.visible) {
+                    continue;
+                }
+                // Rects are outlined in their own rotated frame.
+                let Some((pos, size, rotation)) = self
+                    .rect(*id)
+                    .map(|rect| (rect.pos, rect.size, rect.rotation))
+                    .or_else(|| self.node_bounds(*id).map(|(pos, size)| (pos, size, 0.0)))
+                else {
+                    continue;
+                };
+                selection_boxes.push(SelectionBox {
+                    pos: [pos.x, pos.y],
+                    size: [size.x, size.y],
+                    outline_color,
+                    handle_color: per_node_handles.then_some(handle_color),
+                    handle_shape,
+                    rotation,
+                });
+            }
+
+            if !per_node_handles && let Some((min, max)) = self.selection_bounds() {
+                selection_boxes.push(SelectionBox {
+                    pos: [min.x, min.y],
+                    size: [max.x - min.x, max.y - min.y],
+                    outline_color: handle_color,
+                    handle_color: Some(handle_color),
+                    handle_shape,
+                    rotation: 0.0,
+                });
+            }
+        }
+
+        if let DragState::Marquee(drag) = &self.drag_state {
+            // pre-selection tint over every node the marquee currently touches
+            let candidate_color = Color::rgba(0.2, 0.6, 1.0, 0.18);
+            for id in self.marquee_candidates() {
+                if let Some(rect) = self.rect(id) {
+                    overlay_rects.push(RectInstance {
+                        rotation: rect.rotation,
+                        ..RectInstance::solid(
+                            [rect.pos.x, rect.pos.y],
+                            [rect.size.x, rect.size.y],
+                            candidate_color,
+                        )
+                    });
+                }
+            }
+
+            overlay_rects.extend(self.drag_box_rects(drag.start_world, drag.current_world));
         }
 
+        if let DragState::RectCreate(drag) = &self.drag_state {
+            overlay_rects.extend(self.drag_box_rects(drag.start_world, drag.current_world));
+        }
+
+        overlay_rects.extend(self.snap_preview_rects());
+        overlay_rects.extend(self.smart_guide_rects());
+        overlay_rects.extend(self.debug_axes_rects());
+
         render_scene::OverlayScene {
+            selection_boxes,
             rects: overlay_rects,
+            labels: self.dimension_labels(tool_mode),
         }
     }
 
-    /// Update marquee selection bounds and recompute the selected set.
-    fn update_marquee_selection(&mut self) {
-        let DragState::Marquee(drag) = &mut self.drag_state else {
-            return;
+    /// Tinted fill plus a 1px outline (as four thin rects) for the box a
+    /// marquee or rect-create drag spans from `start` to `current`.
+    fn drag_box_rects(&self, start: Vec2, current: Vec2) -> [RectInstance; 5] {
+        let fill_color = Color::rgba(0.2, 0.6, 1.0, 0.08);
+        let outline_color = Color::rgba(0.2, 0.6, 1.0, 0.9);
+        let outline_px = 1.0 / self.camera.zoom;
+
+        let (min_x, min_y) = (start.x.min(current.x), start.y.min(current.y));
+        let (max_x, max_y) = (start.x.max(current.x), start.y.max(current.y));
+        let w = (max_x - min_x).max(0.0);
+        let h = (max_y - min_y).max(0.0);
+
+        [
+            RectInstance::solid([min_x, min_y], [w, h], fill_color),
+            RectInstance::solid([min_x, min_y], [w, outline_px], outline_color),
+            RectInstance::solid([min_x, max_y - outline_px], [w, outline_px], outline_color),
+            RectInstance::solid([min_x, min_y], [outline_px, h], outline_color),
+            RectInstance::solid([max_x - outline_px, min_y], [outline_px, h], outline_color),
+        ]
+    }
+
+    /// "W × H" label in world units centered under a lone selected rect,
+    /// kept a fixed screen size. Reads the live size, so it tracks a resize
+    /// drag as it happens.
+    fn dimension_labels(&self, tool_mode: &ToolMode) -> Vec<TextInstance> {
+        const LABEL_PX: f32 = 11.0;
+        const GAP_PX: f32 = 6.0;
+
+        if matches!(tool_mode, ToolMode::Rect) {
+            return Vec::new();
+        }
+        let [id] = self.selected[..] else {
+            return Vec::new();
+        };
+        let Some(rect) = self.rect(id).filter(|rect| rect.visible) else {
+            return Vec::new();
+        };
+        let (min, max) = rect.world_aabb();
+        let content = format!(
+            "{} \u{d7} {}",
+            format_dimension(rect.size.x),
+            format_dimension(rect.size.y)
+        );
+        let size = LABEL_PX / self.camera.zoom;
+        let width = content.chars().count() as f32 * size * TextNode::APPROX_ADVANCE_EM;
+        vec![TextInstance {
+            pos: [
+                (min.x + max.x - width) * 0.5,
+                max.y + GAP_PX / self.camera.zoom,
+            ],
+            content,
+            size,
+            color: Color::rgba(0.1, 0.6, 1.0, 1.0),
+        }]
+    }
+
+    /// Nodes the active marquee selects under `marquee_mode`, in document
+    /// order. Empty when no marquee drag is in progress.
+    pub fn marquee_candidates(&self) -> Vec<NodeId> {
+        let DragState::Marquee(drag) = &self.drag_state else {
+            return Vec::new();
         };
 
         let min_x = drag.start_world.x.min(drag.current_world.x);
@@ -583,25 +1891,59 @@ impl Engine {
         let max_x = drag.start_world.x.max(drag.current_world.x);
         let max_y = drag.start_world.y.max(drag.current_world.y);
 
+        let contain = self
+            .marquee_mode
+            .resolve(drag.start_world, drag.current_world)
+            == MarqueeMode::Contain;
+        let selects = |pos: Vec2, size: Vec2| {
+            if contain {
+                pos.x >= min_x
+                    && pos.x + size.x <= max_x
+                    && pos.y >= min_y
+                    && pos.y + size.y <= max_y
+            } else {
+                pos.x < max_x && pos.x + size.x > min_x && pos.y < max_y && pos.y + size.y > min_y
+            }
+        };
+
+        let candidates = self.with_spatial_index(|index| {
+            index.query_box(Vec2::new(min_x, min_y), Vec2::new(max_x, max_y))
+        });
+        // Rotated rects are tested by their world AABB, which may reach a
+        // little past the drawn corners.
+        let rects = candidates
+            .iter()
+            .filter_map(|&i| self.doc.rects.get(i))
+            .filter(|rect| rect.visible && !rect.locked)
+            .filter(|rect| {
+                let (lo, hi) = rect.world_aabb();
+                selects(lo, Vec2::new(hi.x - lo.x, hi.y - lo.y))
+            })
+            .map(|rect| rect.id);
+        let texts = self
+            .doc
+            .texts
+            .iter()
+            .filter(|text| selects(text.pos, text.approx_size()))
+            .map(|text| text.id);
+        rects.chain(texts).collect()
+    }
+
+    /// Update marquee selection bounds and recompute the selected set.
+    fn update_marquee_selection(&mut self) {
+        let DragState::Marquee(drag) = &self.drag_state else {
+            return;
+        };
+
         let mut selected = if drag.additive {
             self.selected.clone()
         } else {
             Vec::new()
         };
 
-        for rect in &self.doc.rects {
-            let rect_min_x = rect.pos.x;
-            let rect_min_y = rect.pos.y;
-            let rect_max_x = rect.pos.x + rect.size.x;
-            let rect_max_y = rect.pos.y + rect.size.y;
-
-            let intersects = rect_min_x < max_x
-                && rect_max_x > min_x
-                && rect_min_y < max_y
-                && rect_max_y > min_y;
-
-            if intersects && !selected.contains(&rect.id) {
-                selected.push(rect.id);
+        for id in self.marquee_candidates() {
+            if !selected.contains(&id) {
+                selected.push(id);
             }
         }
 
@@ -610,7 +1952,8 @@ impl Engine {
 
     /// Update rect positions when `DragState` is `SelectionMove`.
     fn apply_selection_drag(&mut self) {
-        let (dx, dy, origins) = match &self.drag_state {
+        // Grid snapping waits for the release, in `snap_committed_rect`.
+        let (mut dx, mut dy, origins) = match &self.drag_state {
             DragState::SelectionMove(drag) => (
                 drag.current_world.x - drag.start_world.x,
                 drag.current_world.y - drag.start_world.y,
@@ -619,12 +1962,178 @@ impl Engine {
             _ => return,
         };
 
-        for (node_id, origin) in &origins {
-            if let Some(rect) = self.doc.rects.iter_mut().find(|rect| rect.id == *node_id) {
-                rect.pos.x = origin.x + dx;
-                rect.pos.y = origin.y + dy;
+        // Pull the selection box onto nearby edges and centers of the other
+        // rects; a move released on a guide keeps it instead of the grid.
+        let moved_box = self.origin_bounds(&origins);
+        let targets = match moved_box {
+            Some(_) if self.smart_guide_px > 0.0 => self.smart_guide_targets(),
+            _ => Vec::new(),
+        };
+        if let Some((min, max)) = moved_box {
+            let tolerance = self.smart_guide_px / self.camera.zoom;
+            let nearest = |lines: [f32; 3], pick: fn(&(Vec2, Vec2)) -> [f32; 3]| {
+                targets
+                    .iter()
+                    .flat_map(pick)
+                    .flat_map(|target| lines.map(|line| target - line))
+                    .filter(|offset| offset.abs() <= tolerance)
+                    .min_by(|a, b| a.abs().total_cmp(&b.abs()))
+            };
+            let moved = (
+                Vec2::new(min.x + dx, min.y + dy),
+                Vec2::new(max.x + dx, max.y + dy),
+            );
+            dx += nearest(box_lines_x(&moved), box_lines_x).unwrap_or(0.0);
+            dy += nearest(box_lines_y(&moved), box_lines_y).unwrap_or(0.0);
+        }
+
+        // Clamp the shared delta so every node stays inside its artboard.
+        if self.clip_children_to_artboard {
+            for (node_id, origin) in &origins {
+                let size = match self.rect(*node_id) {
+                    Some(rect) => Some(rect.size),
+                    None => self.text(*node_id).map(TextNode::approx_size),
+                };
+                let Some(size) = size else {
+                    continue;
+                };
+                let Some(artboard) = self.doc.containing_artboard(*origin, size) else {
+                    continue;
+                };
+                dx = dx
+                    .min(artboard.pos.x + artboard.size.x - (origin.x + size.x))
+                    .max(artboard.pos.x - origin.x);
+                dy = dy
+                    .min(artboard.pos.y + artboard.size.y - (origin.y + size.y))
+                    .max(artboard.pos.y - origin.y);
             }
         }
+
+        for (node_id, origin) in &origins {
+            self.set_node_pos(*node_id, Vec2::new(origin.x + dx, origin.y + dy));
+        }
+
+        let guides = match moved_box {
+            Some((min, max)) => smart_guides(
+                (
+                    Vec2::new(min.x + dx, min.y + dy),
+                    Vec2::new(max.x + dx, max.y + dy),
+                ),
+                &targets,
+            ),
+            None => Vec::new(),
+        };
+        if let DragState::SelectionMove(drag) = &mut self.drag_state {
+            drag.guides = guides;
+        }
+    }
+
+    /// World-space `(min, max)` box the moved nodes had at their drag
+    /// origins; `None` if none of them exist any more.
+    fn origin_bounds(&self, origins: &[(NodeId, Vec2)]) -> Option<(Vec2, Vec2)> {
+        let mut boxes = origins.iter().filter_map(|(id, origin)| {
+            let (min, size) = self.node_bounds(*id)?;
+            let pos = self.node_pos(*id)?;
+            let shift = Vec2::new(origin.x - pos.x, origin.y - pos.y);
+            Some((
+                Vec2::new(min.x + shift.x, min.y + shift.y),
+                Vec2::new(min.x + size.x + shift.x, min.y + size.y + shift.y),
+            ))
+        });
+        let (mut min, mut max) = boxes.next()?;
+        for (lo, hi) in boxes {
+            min = Vec2::new(min.x.min(lo.x), min.y.min(lo.y));
+            max = Vec2::new(max.x.max(hi.x), max.y.max(hi.y));
+        }
+        Some((min, max))
+    }
+
+    /// World AABBs of the visible, unselected rects a moved selection can
+    /// align with, limited to the viewport once its size is known.
+    fn smart_guide_targets(&self) -> Vec<(Vec2, Vec2)> {
+        let selected: HashSet<NodeId> = self.selected.iter().copied().collect();
+        let view = self.visible_world_bounds();
+        self.doc
+            .rects
+            .iter()
+            .filter(|rect| rect.visible && !selected.contains(&rect.id))
+            .map(|rect| rect.world_aabb())
+            .filter(|(min, max)| match view {
+                Some((view_min, view_max)) => {
+                    max.x >= view_min.x
+                        && min.x <= view_max.x
+                        && max.y >= view_min.y
+                        && min.y <= view_max.y
+                }
+                None => true,
+            })
+            .collect()
+    }
+
+    /// Guide lines of the active move drag, one screen pixel wide.
+    fn smart_guide_rects(&self) -> Vec<RectInstance> {
+        const GUIDE_COLOR: Color = Color::rgba(1.0, 0.25, 0.55, 1.0);
+
+        let DragState::SelectionMove(drag) = &self.drag_state else {
+            return Vec::new();
+        };
+        let line = 1.0 / self.camera.zoom;
+        drag.guides
+            .iter()
+            .map(|guide| {
+                let vertical = guide.start.x == guide.end.x;
+                let (pos, size) = if vertical {
+                    (
+                        [guide.start.x - line * 0.5, guide.start.y],
+                        [line, guide.end.y - guide.start.y],
+                    )
+                } else {
+                    (
+                        [guide.start.x, guide.start.y - line * 0.5],
+                        [guide.end.x - guide.start.x, line],
+                    )
+                };
+                RectInstance::solid(pos, size, GUIDE_COLOR)
+            })
+            .collect()
+    }
+
+    /// World-space `(min, max)` box around every selected node.
+    fn selection_bounds(&self) -> Option<(Vec2, Vec2)> {
+        self.bounds_of(&self.selected)
+    }
+
+    /// World-space `(min, max)` box around the given nodes. Ids that don't
+    /// exist are skipped; `None` if none of them do.
+    ///
+    /// # Arguments
+    /// * `ids` - nodes to enclose, of any kind
+    pub fn bounds_of(&self, ids: &[NodeId]) -> Option<(Vec2, Vec2)> {
+        let mut bounds = ids.iter().filter_map(|id| self.node_bounds(*id));
+        let (pos, size) = bounds.next()?;
+        let mut min = pos;
+        let mut max = Vec2::new(pos.x + size.x, pos.y + size.y);
+        for (pos, size) in bounds {
+            min.x = min.x.min(pos.x);
+            min.y = min.y.min(pos.y);
+            max.x = max.x.max(pos.x + size.x);
+            max.y = max.y.max(pos.y + size.y);
+        }
+        Some((min, max))
+    }
+
+    /// World-space `(pos, size)` of any node kind; rotated rects use their
+    /// world AABB and text its approximate extent.
+    fn node_bounds(&self, id: NodeId) -> Option<(Vec2, Vec2)> {
+        if let Some(rect) = self.rect(id) {
+            let (min, max) = rect.world_aabb();
+            return Some((min, Vec2::new(max.x - min.x, max.y - min.y)));
+        }
+        self.doc
+            .texts
+            .iter()
+            .find(|text| text.id == id)
+            .map(|text| (text.pos, text.approx_size()))
     }
 
     fn rect_index(&self, id: NodeId) -> Option<usize> {
@@ -636,422 +2145,1356 @@ impl Engine {
     }
 
     fn rect_mut(&mut self, id: NodeId) -> Option<&mut RectNode> {
+        self.invalidate_spatial_index();
         self.doc.rects.iter_mut().find(|rect| rect.id == id)
     }
 
-    fn push_history(&mut self, command: ToolCommand) {
-        self.undo_stack.push(command);
-        self.redo_stack.clear();
+    fn text(&self, id: NodeId) -> Option<&TextNode> {
+        self.doc.texts.iter().find(|text| text.id == id)
     }
 
-    fn apply_command(&mut self, command: &ToolCommand, forward: bool) {
-        match command {
-            ToolCommand::CreateRect {
-                rect,
-                previous_selection,
-                next_selection,
-            } => {
-                if forward {
-                    if self.rect_index(rect.id).is_none() {
-                        self.doc.rects.push(*rect);
-                    }
-                    self.selected = next_selection.clone();
-                } else {
-                    if let Some(idx) = self.rect_index(rect.id) {
-                        self.doc.rects.remove(idx);
-                    }
-                    self.selected = previous_selection.clone();
-                }
-            }
-            ToolCommand::SetRectsGeometry { changes } => {
-                for change in changes {
-                    let geometry = if forward { change.after } else { change.before };
-                    if let Some(rect) = self.rect_mut(change.id) {
-                        rect.pos = geometry.pos;
-                        rect.size = geometry.size;
-                    }
-                }
-            }
-            ToolCommand::BringForward(node_ids) => {
-                self.reorder_selected(node_ids, forward);
-            }
-            ToolCommand::SendBackward(node_ids) => {
-                self.reorder_selected(node_ids, !forward);
-            }
-            ToolCommand::Delete {
-                rects,
-                previous_selection,
-                next_selection,
-            } => {
-                let deleted_ids: HashSet<NodeId> = rects.iter().map(|r| r.0.id).collect();
-                if forward {
-                    self.doc
-                        .rects
-                        .retain(|rect| !deleted_ids.contains(&rect.id));
-                    self.selected.retain(|id| !deleted_ids.contains(id));
-
-                    self.selected = next_selection.clone();
-                } else {
-                    let mut restored = rects.clone();
-                    restored.sort_by_key(|(_, original_index)| *original_index);
+    fn text_mut(&mut self, id: NodeId) -> Option<&mut TextNode> {
+        self.doc.texts.iter_mut().find(|text| text.id == id)
+    }
 
-                    for (rect, original_index) in restored {
-                        if self.rect_index(rect.id).is_none() {
-                            let insert_at = original_index.min(self.doc.rects.len());
-                            self.doc.rects.insert(insert_at, rect);
-                        }
-                    }
-                    self.selected = previous_selection.clone();
-                }
-            }
+    /// Position of a rect or text label.
+    fn node_pos(&self, id: NodeId) -> Option<Vec2> {
+        match self.rect(id) {
+            Some(rect) => Some(rect.pos),
+            None => self.text(id).map(|text| text.pos),
         }
     }
 
-    fn rollback_active_drag(&mut self) {
-        enum Rollback {
-            SelectionMove(Vec<(NodeId, Vec2)>),
-            Resize {
-                node_id: NodeId,
-                origin_pos: Vec2,
-                origin_size: Vec2,
-            },
-            None,
+    /// Move a rect or text label to `pos`.
+    fn set_node_pos(&mut self, id: NodeId, pos: Vec2) {
+        if let Some(rect) = self.rect_mut(id) {
+            rect.pos = pos;
+        } else if let Some(text) = self.text_mut(id) {
+            text.pos = pos;
         }
+    }
 
-        let drag_state = std::mem::replace(&mut self.drag_state, DragState::Idle);
-
-        let rollback = match drag_state {
-            DragState::SelectionMove(drag) => Rollback::SelectionMove(drag.origins),
-            DragState::Resize(drag) => Rollback::Resize {
-                node_id: drag.handle.node_id,
-                origin_pos: drag.origin_pos,
-                origin_size: drag.origin_size,
-            },
-            _ => Rollback::None,
-        };
+    /// Move every selected node by `delta` world units as one undoable step.
+    fn nudge_selection(&mut self, delta: Vec2) {
+        self.move_selected_nodes(|_, pos, _| Vec2::new(pos.x + delta.x, pos.y + delta.y));
+    }
 
-        match rollback {
-            Rollback::SelectionMove(origins) => {
-                for (id, origin) in origins {
-                    if let Some(rect) = self.rect_mut(id) {
-                        rect.pos = origin;
-                    }
-                }
-            }
-            Rollback::Resize {
-                node_id,
-                origin_pos,
-                origin_size,
-            } => {
-                if let Some(rect) = self.rect_mut(node_id) {
-                    rect.pos = origin_pos;
-                    rect.size = origin_size;
-                }
-            }
-            _ => {}
+    /// Line up the selected nodes' edges or centers with the selection bounds.
+    /// Needs at least two selected nodes.
+    fn align_selection(&mut self, mode: AlignMode) {
+        let ids: Vec<NodeId> = self
+            .selected_boxes()
+            .into_iter()
+            .map(|(id, _, _)| id)
+            .collect();
+        if ids.len() < 2 {
+            return;
         }
+        let Some((min, max)) = self.bounds_of(&ids) else {
+            return;
+        };
+        let center = Vec2::new((min.x + max.x) * 0.5, (min.y + max.y) * 0.5);
+
+        self.move_selected_nodes(|_, pos, size| match mode {
+            AlignMode::LeftEdges => Vec2::new(min.x, pos.y),
+            AlignMode::RightEdges => Vec2::new(max.x - size.x, pos.y),
+            AlignMode::TopEdges => Vec2::new(pos.x, min.y),
+            AlignMode::BottomEdges => Vec2::new(pos.x, max.y - size.y),
+            AlignMode::HorizontalCenters => Vec2::new(center.x - size.x * 0.5, pos.y),
+            AlignMode::VerticalCenters => Vec2::new(pos.x, center.y - size.y * 0.5),
+        });
     }
 
-    fn undo(&mut self) {
-        if !matches!(self.drag_state, DragState::Idle) {
+    /// Space the selected nodes evenly along `axis`, keeping the first and last
+    /// (by min coordinate) in place. Needs at least three selected nodes.
+    fn distribute_selection(&mut self, axis: Axis) {
+        let along = |v: Vec2| match axis {
+            Axis::Horizontal => v.x,
+            Axis::Vertical => v.y,
+        };
+        let mut spans: Vec<(NodeId, f32, f32)> = self
+            .selected_boxes()
+            .into_iter()
+            .map(|(id, pos, size)| (id, along(pos), along(size)))
+            .collect();
+        if spans.len() < 3 {
             return;
         }
-
-        if let Some(command) = self.undo_stack.pop() {
-            self.apply_command(&command, false);
-            self.redo_stack.push(command);
+        spans.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+        let (_, first_min, first_len) = spans[0];
+        let (_, last_min, last_len) = spans[spans.len() - 1];
+        let total_len: f32 = spans.iter().map(|(_, _, len)| len).sum();
+        let gap = (last_min + last_len - first_min - total_len) / (spans.len() - 1) as f32;
+
+        let mut targets: HashMap<NodeId, f32> = HashMap::new();
+        let mut cursor = first_min + first_len + gap;
+        for &(id, _, len) in &spans[1..spans.len() - 1] {
+            targets.insert(id, cursor);
+            cursor += len + gap;
         }
+
+        self.move_selected_nodes(|id, pos, _| match (targets.get(&id), axis) {
+            (Some(&min), Axis::Horizontal) => Vec2::new(min, pos.y),
+            (Some(&min), Axis::Vertical) => Vec2::new(pos.x, min),
+            (None, _) => pos,
+        });
     }
 
-    fn redo(&mut self) {
+    /// `(id, pos, size)` of every selected node, rects in draw order and then
+    /// text labels; text uses its approximate extent.
+    fn selected_boxes(&self) -> Vec<(NodeId, Vec2, Vec2)> {
+        let selected: HashSet<NodeId> = self.selected.iter().copied().collect();
+        self.doc
+            .rects
+            .iter()
+            .filter(|rect| selected.contains(&rect.id))
+            .map(|rect| (rect.id, rect.pos, rect.size))
+            .chain(
+                self.doc
+                    .texts
+                    .iter()
+                    .filter(|text| selected.contains(&text.id))
+                    .map(|text| (text.id, text.pos, text.approx_size())),
+            )
+            .collect()
+    }
+
+    /// Move each selected node to `target(id, pos, size)` as one undoable
+    /// step, leaving sizes alone. Locked rects stay put. Ignored mid-drag so a
+    /// command can't fight the pointer.
+    fn move_selected_nodes(&mut self, target: impl Fn(NodeId, Vec2, Vec2) -> Vec2) {
         if !matches!(self.drag_state, DragState::Idle) {
             return;
         }
 
-        if let Some(command) = self.redo_stack.pop() {
-            self.apply_command(&command, true);
-            self.undo_stack.push(command);
+        let selected: HashSet<NodeId> = self.selected.iter().copied().collect();
+        let changes: Vec<RectGeometryChange> = self
+            .doc
+            .rects
+            .iter()
+            .filter(|rect| !rect.locked && selected.contains(&rect.id))
+            .map(|rect| RectGeometryChange {
+                id: rect.id,
+                before: RectGeometry::from_rect(rect),
+                after: RectGeometry {
+                    pos: target(rect.id, rect.pos, rect.size),
+                    size: rect.size,
+                },
+            })
+            .filter(|change| change.before != change.after)
+            .collect();
+        let texts: Vec<TextMoveChange> = self
+            .doc
+            .texts
+            .iter()
+            .filter(|text| selected.contains(&text.id))
+            .map(|text| TextMoveChange {
+                id: text.id,
+                before: text.pos,
+                after: target(text.id, text.pos, text.approx_size()),
+            })
+            .filter(|change| change.before != change.after)
+            .collect();
+
+        if changes.is_empty() && texts.is_empty() {
+            return;
         }
+
+        let command = ToolCommand::SetRectsGeometry { changes, texts };
+        self.apply_command(&command, true);
+        self.push_history(command);
     }
 
-    fn geometry_change_for_rect(
-        &self,
-        id: NodeId,
-        before: RectGeometry,
-    ) -> Option<RectGeometryChange> {
-        let rect = self.rect(id)?;
-        let after = RectGeometry::from_rect(rect);
-        if before != after {
-            Some(RectGeometryChange { id, before, after })
-        } else {
-            None
+    /// Set one rect's geometry as an undoable step, clamping `size` like
+    /// `create_rect` does. Ignored mid-drag, as nudges are.
+    fn set_rect_bounds(&mut self, id: NodeId, pos: Vec2, size: Vec2) {
+        let min_size = 1.0f32;
+        let finite = [pos.x, pos.y, size.x, size.y].iter().all(|v| v.is_finite());
+        if !finite || !matches!(self.drag_state, DragState::Idle) {
+            return;
         }
-    }
-
-    fn apply_selection_resize(&mut self) {
-        let (corner, rect_idx, dx, dy, origin_pos, origin_size) = match &self.drag_state {
-            DragState::Resize(d) => (
-                d.handle.corner,
-                d.rect_idx,
-                d.current_world.x - d.start_world.x,
-                d.current_world.y - d.start_world.y,
-                d.origin_pos,
-                d.origin_size,
-            ),
-            _ => return,
+        let Some(rect) = self.rect(id).filter(|rect| !rect.locked) else {
+            return;
         };
-
-        let min_size = 1.0_f32;
-        let (new_pos, new_size) =
-            Self::compute_resize(corner, dx, dy, origin_pos, origin_size, min_size);
-
-        if let Some(rect) = self.doc.rects.get_mut(rect_idx) {
-            rect.pos = new_pos;
-            rect.size = new_size;
+        let change = RectGeometryChange {
+            id,
+            before: RectGeometry::from_rect(rect),
+            after: RectGeometry {
+                pos,
+                size: Vec2::new(size.x.max(min_size), size.y.max(min_size)),
+            },
+        };
+        if change.before == change.after {
+            return;
         }
-    }
-
-    fn compute_resize(
-        corner: Corner,
-        dx: f32,
-        dy: f32,
-        origin_pos: Vec2,
-        origin_size: Vec2,
-        min_size: f32,
-    ) -> (Vec2, Vec2) {
-        let mut pos = origin_pos;
-        let mut size = origin_size;
 
-        // Helper: clamp one axis. Returns (new_origin, new_size).
-        // If the dragged edge would pass the opposite edge, pin at min_size.
-        let clamp_axis = |origin: f32, length: f32, delta: f32, anchor_end: bool| -> (f32, f32) {
-            if anchor_end {
-                // Right/bottom edge moves — origin stays, size changes
-                let new_len = (length + delta).max(min_size);
-                (origin, new_len)
-            } else {
-                // Left/top edge moves — origin shifts, size shrinks
-                let mut new_origin = origin + delta;
-                let mut new_len = length - delta;
-                if new_len < min_size {
-                    new_len = min_size;
-                    new_origin = origin + length - min_size;
-                }
-                (new_origin, new_len)
-            }
+        let command = ToolCommand::SetRectsGeometry {
+            changes: vec![change],
+            texts: Vec::new(),
         };
-
-        let anchor_left = matches!(corner, Corner::TR | Corner::BR);
-        let anchor_top = matches!(corner, Corner::BL | Corner::BR);
-
-        let (px, sx) = clamp_axis(origin_pos.x, origin_size.x, dx, anchor_left);
-        let (py, sy) = clamp_axis(origin_pos.y, origin_size.y, dy, anchor_top);
-        pos.x = px;
-        pos.y = py;
-        size.x = sx;
-        size.y = sy;
-
-        (pos, size)
+        self.apply_command(&command, true);
+        self.push_history(command);
     }
 
-    /// Determine the cursor style to show based on current hover position and drag state.
-    pub fn compute_cursor(&self, tool_mode: &ToolMode) -> CursorStyle {
-        // Show the rect create cross hair cursor
-        if matches!(
-            self.drag_state,
-            DragState::PendingRectCreate(_) | DragState::RectCreate(_)
-        ) {
-            return CursorStyle::Crosshair;
-        }
-
-        // During an active move drag, always show the move cursor.
-        if matches!(
-            self.drag_state,
-            DragState::SelectionMove(_) | DragState::PendingSelectionMove(_)
-        ) {
-            return CursorStyle::Move;
+    /// Turn one rect to `degrees`, constrained by `constrain_rotation_deg`,
+    /// as an undoable step. Ignored mid-drag, as `set_rect_bounds` is.
+    fn set_rect_rotation(&mut self, id: NodeId, degrees: f32) {
+        if !degrees.is_finite() || !matches!(self.drag_state, DragState::Idle) {
+            return;
         }
-
-        // If hovering over a handle, show the appropriate resize cursor.
-        if matches!(tool_mode, ToolMode::Select)
-            && let Some(screen_px) = self.hover_screen_px
-        {
-            let world = self.camera.screen_to_world(screen_px);
-            if let Some(hit) = self.check_collide_handle(world) {
-                return match hit.corner {
-                    Corner::TL | Corner::BR => CursorStyle::ResizeTlBr,
-                    Corner::TR | Corner::BL => CursorStyle::ResizeTrBl,
-                };
-            }
+        let Some(rect) = self.rect(id).filter(|rect| !rect.locked) else {
+            return;
+        };
+        let change = RectRotationChange {
+            id,
+            before: rect.rotation,
+            after: self.constrain_rotation_deg(degrees).to_radians(),
+        };
+        if change.before == change.after {
+            return;
         }
 
-        CursorStyle::Default
+        let command = ToolCommand::SetRectsRotation {
+            changes: vec![change],
+        };
+        self.apply_command(&command, true);
+        self.push_history(command);
     }
 
-    fn update_move_drag(&mut self, screen_px: Vec2, world: Vec2, drag_threshold_sq: f32) {
-        let next: Option<DragState> = match &self.drag_state {
-            DragState::PendingSelectionMove(pending) => {
-                let dx = screen_px.x - pending.start_screen_px.x;
-                let dy = screen_px.y - pending.start_screen_px.y;
-                let dist_sq = dx * dx + dy * dy;
-
-                if dist_sq >= drag_threshold_sq {
-                    let selected_ids: HashSet<NodeId> = self.selected.iter().copied().collect();
-                    let origins: Vec<(NodeId, Vec2)> = self
-                        .doc
-                        .rects
-                        .iter()
-                        .filter_map(|rect| {
-                            selected_ids
-                                .contains(&rect.id)
-                                .then_some((rect.id, rect.pos))
-                        })
-                        .collect();
+    /// Whether a press at `now` and `screen_px` lands within `double_click_ms`
+    /// and `double_click_px` of the `previous` one.
+    fn completes_double_click(
+        &self,
+        previous: Option<(f64, Vec2)>,
+        now: f64,
+        screen_px: Vec2,
+    ) -> bool {
+        previous.is_some_and(|(then, at)| {
+            let (dx, dy) = (screen_px.x - at.x, screen_px.y - at.y);
+            (0.0..=self.double_click_ms).contains(&(now - then))
+                && dx * dx + dy * dy <= self.double_click_px * self.double_click_px
+        })
+    }
 
-                    Some(DragState::SelectionMove(SelectionDrag {
-                        start_world: pending.start_world,
-                        current_world: world,
-                        origins,
-                    }))
-                } else {
-                    None
-                }
-            }
-            DragState::SelectionMove(d) => {
-                let mut d = d.clone();
-                d.current_world = world;
-                Some(DragState::SelectionMove(d))
-            }
-            _ => None,
+    /// Append a rect on top of the draw order and select it, as one undoable
+    /// step. Degenerate sizes are clamped to the same 1-unit minimum as a
+    /// drag-created rect.
+    fn create_rect(&mut self, pos: Vec2, size: Vec2, color: Color) {
+        let min_size = 1.0f32;
+        // `max` maps NaN to the minimum as well
+        let size = Vec2::new(size.x.max(min_size), size.y.max(min_size));
+        let rect = RectNode::new(self.doc.alloc_id(), pos, size, color);
+
+        let command = ToolCommand::CreateRect {
+            next_selection: vec![rect.id],
+            previous_selection: self.selected.clone(),
+            rect,
         };
-
-        if let Some(state) = next {
-            self.drag_state = state;
-            self.apply_selection_drag();
-        }
+        self.apply_command(&command, true);
+        self.push_history(command);
     }
 
-    fn update_resize_drag(&mut self, screen_px: Vec2, world: Vec2, drag_threshold_sq: f32) {
-        let next: Option<DragState> = match &self.drag_state {
-            DragState::PendingResize(pending) => {
-                let dx = screen_px.x - pending.start_screen_px.x;
-                let dy = screen_px.y - pending.start_screen_px.y;
-                let dist_sq = dx * dx + dy * dy;
-
-                if dist_sq >= drag_threshold_sq {
-                    let rect_idx = self
-                        .doc
-                        .rects
-                        .iter()
-                        .position(|r| r.id == pending.handle.node_id)
-                        .unwrap();
+    /// Delete every selected node as one undoable step. An active drag is
+    /// rolled back first so undo restores the nodes where they started.
+    fn delete_selection(&mut self) {
+        // Locked rects survive the delete and stay selected.
+        let selected_ids: HashSet<NodeId> = self.selected.iter().copied().collect();
+        let deletable = |rect: &RectNode| !rect.locked && selected_ids.contains(&rect.id);
+        let selected_text = |text: &TextNode| selected_ids.contains(&text.id);
+        if !self.doc.rects.iter().any(deletable) && !self.doc.texts.iter().any(selected_text) {
+            return;
+        }
+        self.rollback_active_drag();
 
-                    let rect = &self.doc.rects[rect_idx];
+        let rects: Vec<(RectNode, usize)> = self
+            .doc
+            .rects
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, rect)| deletable(rect).then_some((rect.clone(), idx)))
+            .collect();
+        let texts: Vec<(TextNode, usize)> = self
+            .doc
+            .texts
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, text)| selected_text(text).then_some((text.clone(), idx)))
+            .collect();
+        let next_selection = self
+            .selected
+            .iter()
+            .copied()
+            .filter(|id| self.rect(*id).is_some_and(|rect| rect.locked))
+            .collect();
 
-                    Some(DragState::Resize(ResizeDrag {
-                        handle: pending.handle,
-                        start_world: pending.start_world,
-                        current_world: world,
-                        origin_pos: rect.pos,   // snapshot
-                        origin_size: rect.size, // snapshot
-                        rect_idx,
-                    }))
-                } else {
-                    None
-                }
-            }
-            DragState::Resize(drag) => {
-                let mut drag = drag.clone();
-                drag.current_world = world;
-                Some(DragState::Resize(drag))
-            }
-            _ => None,
+        let command = ToolCommand::Delete {
+            rects,
+            texts,
+            previous_selection: self.selected.clone(),
+            next_selection,
         };
 
-        if let Some(state) = next {
-            self.drag_state = state;
-            self.apply_selection_resize();
-        }
+        self.apply_command(&command, true);
+        self.push_history(command);
     }
 
-    fn update_rect_create_drag(&mut self, screen_px: Vec2, world: Vec2, drag_threshold_sq: f32) {
-        let next: Option<DragState> = match &self.drag_state {
-            DragState::PendingRectCreate(pending) => {
-                let dx = screen_px.x - pending.start_screen_px.x;
-                let dy = screen_px.y - pending.start_screen_px.y;
-                let dist_sq = dx * dx + dy * dy;
+    /// Set `group_id` on every selected rect as one undoable step.
+    fn set_selection_group(&mut self, group: Option<u64>) {
+        let changes: Vec<RectGroupChange> = self
+            .doc
+            .rects
+            .iter()
+            .filter(|rect| self.selected.contains(&rect.id) && rect.group_id != group)
+            .map(|rect| RectGroupChange {
+                id: rect.id,
+                before: rect.group_id,
+                after: group,
+            })
+            .collect();
+        if changes.is_empty() {
+            return;
+        }
 
-                if dist_sq >= drag_threshold_sq {
-                    Some(DragState::RectCreate(RectCreateDrag {
-                        start_world: pending.start_world,
-                        current_world: world,
-                        previous_selection: pending.previous_selection.clone(),
-                    }))
-                } else {
-                    None
-                }
-            }
-            DragState::RectCreate(drag) => {
-                let mut drag = drag.clone();
-                drag.current_world = world;
-                Some(DragState::RectCreate(drag))
-            }
-            _ => None,
-        };
+        let command = ToolCommand::SetRectsGroup { changes };
+        self.apply_command(&command, true);
+        self.push_history(command);
+    }
 
-        if let Some(state) = next {
-            self.drag_state = state;
+    /// Show or hide every selected rect as one undoable step, locked ones
+    /// included.
+    fn set_selection_visible(&mut self, visible: bool) {
+        let changes: Vec<RectVisibilityChange> = self
+            .doc
+            .rects
+            .iter()
+            .filter(|rect| self.selected.contains(&rect.id) && rect.visible != visible)
+            .map(|rect| RectVisibilityChange {
+                id: rect.id,
+                before: rect.visible,
+                after: visible,
+            })
+            .collect();
+        if changes.is_empty() {
+            return;
         }
-    }
 
-    fn reorder_selected(&mut self, node_ids: &[NodeId], to_front: bool) {
-        let selected_ids: HashSet<NodeId> = node_ids.iter().copied().collect();
-        let mut indices: Vec<usize> = selected_ids
+        // Hidden rects can't be seen or hit, so they leave the selection
+        // rather than be moved or deleted unseen.
+        let hidden: HashSet<NodeId> = changes
             .iter()
-            .filter_map(|id| self.rect_index(*id))
+            .filter(|change| !change.after)
+            .map(|change| change.id)
             .collect();
+        let command = ToolCommand::SetRectsVisible { changes };
+        self.apply_command(&command, true);
+        self.push_history(command);
+        self.selected.retain(|id| !hidden.contains(id));
+    }
 
-        if to_front {
-            indices.sort_unstable_by(|a, b| b.cmp(a));
+    /// Recolor every selected rect as one undoable step. Locked rects are
+    /// recolored too: locking guards placement, not style.
+    fn set_selection_fill(&mut self, color: Color) {
+        let changes: Vec<RectColorChange> = self
+            .doc
+            .rects
+            .iter()
+            .filter(|rect| self.selected.contains(&rect.id) && rect.color != color)
+            .map(|rect| RectColorChange {
+                id: rect.id,
+                before: rect.color,
+                after: color,
+            })
+            .collect();
+        if changes.is_empty() {
+            return;
+        }
 
-            for idx in indices {
-                if idx + 1 >= self.doc.rects.len() {
-                    continue;
-                }
-                if selected_ids.contains(&self.doc.rects[idx + 1].id) {
-                    continue;
-                }
-                self.doc.rects.swap(idx, idx + 1);
-            }
-        } else {
-            indices.sort_unstable();
+        let command = ToolCommand::SetRectsColor { changes };
+        self.apply_command(&command, true);
+        self.push_history(command);
+    }
 
-            for idx in indices {
-                if idx == 0 {
-                    continue;
-                }
-                if selected_ids.contains(&self.doc.rects[idx - 1].id) {
-                    continue;
-                }
-                self.doc.rects.swap(idx, idx - 1);
-            }
+    /// Lock or unlock every selected rect as one undoable step.
+    fn set_selection_locked(&mut self, locked: bool) {
+        let changes: Vec<RectLockChange> = self
+            .doc
+            .rects
+            .iter()
+            .filter(|rect| self.selected.contains(&rect.id) && rect.locked != locked)
+            .map(|rect| RectLockChange {
+                id: rect.id,
+                before: rect.locked,
+                after: locked,
+            })
+            .collect();
+        if changes.is_empty() {
+            return;
         }
-    }
-}
 
-impl Default for Engine {
-    fn default() -> Self {
-        Self::new()
+        let command = ToolCommand::SetRectsLocked { changes };
+        self.apply_command(&command, true);
+        self.push_history(command);
     }
-}
 
-#[cfg(test)]
-mod test {
-    use super::*;
+    /// Append offset copies of the selected nodes, in draw order, as one
+    /// undoable step and select them. Skipped mid-drag.
+    fn duplicate_selection(&mut self, offset: Vec2) {
+        if !matches!(self.drag_state, DragState::Idle) {
+            return;
+        }
+        let offset = if offset == Vec2::default() {
+            DUPLICATE_OFFSET
+        } else {
+            offset
+        };
+        let rects: Vec<RectNode> = self
+            .doc
+            .rects
+            .iter()
+            .filter(|rect| self.selected.contains(&rect.id))
+            .cloned()
+            .collect();
+        let texts: Vec<TextNode> = self
+            .doc
+            .texts
+            .iter()
+            .filter(|text| self.selected.contains(&text.id))
+            .cloned()
+            .collect();
+        self.insert_nodes(rects, texts, offset);
+    }
 
-    fn assert_approx(a: f32, b: f32, eps: f32) {
-        if (a - b).abs() > eps {
-            panic!("Expected {a} ~= {b} (eps {eps})");
+    fn push_history(&mut self, command: ToolCommand) {
+        self.request_redraw();
+        self.undo_stack.push(command);
+        if self.undo_stack.len() > HISTORY_LIMIT {
+            self.undo_stack.remove(0);
+        }
+        self.redo_stack.clear();
+    }
+
+    fn apply_command(&mut self, command: &ToolCommand, forward: bool) {
+        self.invalidate_spatial_index();
+        self.request_redraw();
+        match command {
+            ToolCommand::CreateRect {
+                rect,
+                previous_selection,
+                next_selection,
+            } => {
+                if forward {
+                    if self.rect_index(rect.id).is_none() {
+                        self.doc.rects.push(rect.clone());
+                    }
+                    self.selected = next_selection.clone();
+                } else {
+                    if let Some(idx) = self.rect_index(rect.id) {
+                        self.doc.rects.remove(idx);
+                    }
+                    self.selected = previous_selection.clone();
+                }
+            }
+            ToolCommand::InsertRects {
+                rects,
+                texts,
+                previous_selection,
+                next_selection,
+            } => {
+                if forward {
+                    for rect in rects {
+                        if self.rect_index(rect.id).is_none() {
+                            self.doc.rects.push(rect.clone());
+                        }
+                    }
+                    for text in texts {
+                        if self.text(text.id).is_none() {
+                            self.doc.texts.push(text.clone());
+                        }
+                    }
+                    self.selected = next_selection.clone();
+                } else {
+                    let inserted: HashSet<NodeId> = rects
+                        .iter()
+                        .map(|rect| rect.id)
+                        .chain(texts.iter().map(|text| text.id))
+                        .collect();
+                    self.doc.rects.retain(|rect| !inserted.contains(&rect.id));
+                    self.doc.texts.retain(|text| !inserted.contains(&text.id));
+                    self.selected = previous_selection.clone();
+                }
+            }
+            ToolCommand::SetRectsGeometry { changes, texts } => {
+                for change in changes {
+                    let geometry = if forward { change.after } else { change.before };
+                    if let Some(rect) = self.rect_mut(change.id) {
+                        rect.pos = geometry.pos;
+                        rect.size = geometry.size;
+                    }
+                }
+                for change in texts {
+                    if let Some(text) = self.text_mut(change.id) {
+                        text.pos = if forward { change.after } else { change.before };
+                    }
+                }
+            }
+            ToolCommand::SetRectsColor { changes } => {
+                for change in changes {
+                    if let Some(rect) = self.rect_mut(change.id) {
+                        rect.color = if forward { change.after } else { change.before };
+                    }
+                }
+            }
+            ToolCommand::SetRectsGroup { changes } => {
+                for change in changes {
+                    if let Some(rect) = self.rect_mut(change.id) {
+                        rect.group_id = if forward { change.after } else { change.before };
+                    }
+                }
+            }
+            ToolCommand::SetRectsVisible { changes } => {
+                for change in changes {
+                    if let Some(rect) = self.rect_mut(change.id) {
+                        rect.visible = if forward { change.after } else { change.before };
+                    }
+                }
+            }
+            ToolCommand::SetRectsLocked { changes } => {
+                for change in changes {
+                    if let Some(rect) = self.rect_mut(change.id) {
+                        rect.locked = if forward { change.after } else { change.before };
+                    }
+                }
+            }
+            ToolCommand::SetRectsRotation { changes } => {
+                for change in changes {
+                    if let Some(rect) = self.rect_mut(change.id) {
+                        rect.rotation = if forward { change.after } else { change.before };
+                    }
+                }
+            }
+            ToolCommand::BringForward(node_ids) => {
+                self.reorder_selected(node_ids, forward);
+            }
+            ToolCommand::SendBackward(node_ids) => {
+                self.reorder_selected(node_ids, !forward);
+            }
+            ToolCommand::Restack { before, after } => {
+                self.apply_draw_order(if forward { after } else { before });
+            }
+            ToolCommand::Delete {
+                rects,
+                texts,
+                previous_selection,
+                next_selection,
+            } => {
+                let deleted_ids: HashSet<NodeId> = rects
+                    .iter()
+                    .map(|r| r.0.id)
+                    .chain(texts.iter().map(|t| t.0.id))
+                    .collect();
+                if forward {
+                    self.doc
+                        .rects
+                        .retain(|rect| !deleted_ids.contains(&rect.id));
+                    self.doc
+                        .texts
+                        .retain(|text| !deleted_ids.contains(&text.id));
+                    self.selected.retain(|id| !deleted_ids.contains(id));
+
+                    self.selected = next_selection.clone();
+                } else {
+                    let mut restored = rects.clone();
+                    restored.sort_by_key(|(_, original_index)| *original_index);
+
+                    for (rect, original_index) in restored {
+                        if self.rect_index(rect.id).is_none() {
+                            let insert_at = original_index.min(self.doc.rects.len());
+                            self.doc.rects.insert(insert_at, rect);
+                        }
+                    }
+
+                    let mut restored = texts.clone();
+                    restored.sort_by_key(|(_, original_index)| *original_index);
+
+                    for (text, original_index) in restored {
+                        if self.text(text.id).is_none() {
+                            let insert_at = original_index.min(self.doc.texts.len());
+                            self.doc.texts.insert(insert_at, text);
+                        }
+                    }
+                    self.selected = previous_selection.clone();
+                }
+            }
+        }
+    }
+
+    fn rollback_active_drag(&mut self) {
+        enum Rollback {
+            SelectionMove(Vec<(NodeId, Vec2)>),
+            Resize {
+                node_id: NodeId,
+                origin_pos: Vec2,
+                origin_size: Vec2,
+            },
+            None,
+        }
+
+        let drag_state = std::mem::replace(&mut self.drag_state, DragState::Idle);
+
+        let rollback = match drag_state {
+            DragState::SelectionMove(drag) => Rollback::SelectionMove(drag.origins),
+            DragState::Resize(drag) => Rollback::Resize {
+                node_id: drag.handle.node_id,
+                origin_pos: drag.origin_pos,
+                origin_size: drag.origin_size,
+            },
+            _ => Rollback::None,
+        };
+
+        match rollback {
+            Rollback::SelectionMove(origins) => {
+                for (id, origin) in origins {
+                    self.set_node_pos(id, origin);
+                }
+            }
+            Rollback::Resize {
+                node_id,
+                origin_pos,
+                origin_size,
+            } => {
+                if let Some(rect) = self.rect_mut(node_id) {
+                    rect.pos = origin_pos;
+                    rect.size = origin_size;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Escape precedence: cancel an active drag (reverting its edits), else
+    /// clear the selection, else do nothing.
+    fn escape(&mut self) {
+        if !matches!(self.drag_state, DragState::Idle) {
+            self.rollback_active_drag();
+        } else {
+            self.selected.clear();
+        }
+    }
+
+    fn undo(&mut self) {
+        if !matches!(self.drag_state, DragState::Idle) {
+            return;
+        }
+
+        if let Some(command) = self.undo_stack.pop() {
+            self.apply_command(&command, false);
+            self.redo_stack.push(command);
+        }
+    }
+
+    fn redo(&mut self) {
+        if !matches!(self.drag_state, DragState::Idle) {
+            return;
+        }
+
+        if let Some(command) = self.redo_stack.pop() {
+            self.apply_command(&command, true);
+            self.undo_stack.push(command);
+        }
+    }
+
+    fn geometry_change_for_rect(
+        &self,
+        id: NodeId,
+        before: RectGeometry,
+    ) -> Option<RectGeometryChange> {
+        let rect = self.rect(id)?;
+        let after = RectGeometry::from_rect(rect);
+        if before != after {
+            Some(RectGeometryChange { id, before, after })
+        } else {
+            None
+        }
+    }
+
+    fn apply_selection_resize(&mut self) {
+        let (edge, rect_idx, dx, dy, origin_pos, origin_size) = match &self.drag_state {
+            DragState::Resize(d) => (
+                d.handle.edge,
+                d.rect_idx,
+                d.current_world.x - d.start_world.x,
+                d.current_world.y - d.start_world.y,
+                d.origin_pos,
+                d.origin_size,
+            ),
+            _ => return,
+        };
+        let rotation = self
+            .doc
+            .rects
+            .get(rect_idx)
+            .map_or(0.0, |rect| rect.rotation);
+        let min_size = 1.0_f32;
+
+        if rotation != 0.0 {
+            // Resize in the rect's own frame, then shift so the anchored
+            // edge or corner stays put in world space.
+            let local = Vec2::new(dx, dy).rotated(-rotation);
+            let shift = self.modifiers.shift;
+            let resize_by = |t: f32| {
+                let (mut new_pos, mut new_size) = Self::compute_resize(
+                    edge,
+                    local.x * t,
+                    local.y * t,
+                    origin_pos,
+                    origin_size,
+                    min_size,
+                );
+                if shift {
+                    (new_pos, new_size) = keep_resize_aspect(
+                        edge,
+                        new_pos,
+                        new_size,
+                        origin_pos,
+                        origin_size,
+                        min_size,
+                    );
+                }
+                let center_shift = Vec2::new(
+                    (new_pos.x + new_size.x * 0.5) - (origin_pos.x + origin_size.x * 0.5),
+                    (new_pos.y + new_size.y * 0.5) - (origin_pos.y + origin_size.y * 0.5),
+                );
+                let turned = center_shift.rotated(rotation);
+                let pos = Vec2::new(
+                    new_pos.x + turned.x - center_shift.x,
+                    new_pos.y + turned.y - center_shift.y,
+                );
+                (pos, new_size)
+            };
+            let (mut new_pos, mut new_size) = resize_by(1.0);
+
+            // The turned footprint can't be intersected with the artboard
+            // directly, so back the drag off until it fits again.
+            if let Some(artboard) = self.rotated_clip_artboard(origin_pos, origin_size, rotation)
+                && !fits_rotated(&artboard, new_pos, new_size, rotation)
+            {
+                let (mut inside, mut outside) = (0.0_f32, 1.0_f32);
+                for _ in 0..24 {
+                    let mid = (inside + outside) * 0.5;
+                    let (pos, size) = resize_by(mid);
+                    if fits_rotated(&artboard, pos, size, rotation) {
+                        inside = mid;
+                    } else {
+                        outside = mid;
+                    }
+                }
+                (new_pos, new_size) = resize_by(inside);
+            }
+
+            self.invalidate_spatial_index();
+            if let Some(rect) = self.doc.rects.get_mut(rect_idx) {
+                rect.pos = new_pos;
+                rect.size = new_size;
+            }
+            return;
+        }
+
+        let (mut new_pos, mut new_size) =
+            Self::compute_resize(edge, dx, dy, origin_pos, origin_size, min_size);
+        if self.modifiers.shift {
+            (new_pos, new_size) =
+                keep_resize_aspect(edge, new_pos, new_size, origin_pos, origin_size, min_size);
+        }
+
+        if self.clip_children_to_artboard
+            && let Some(artboard) = self.doc.containing_artboard(origin_pos, origin_size)
+        {
+            (new_pos, new_size) = clamp_to_artboard(new_pos, new_size, &artboard);
+        }
+
+        self.invalidate_spatial_index();
+        if let Some(rect) = self.doc.rects.get_mut(rect_idx) {
+            rect.pos = new_pos;
+            rect.size = new_size;
+        }
+    }
+
+    /// Artboard a rotated rect must stay inside while clipping is on: the
+    /// one containing its turned footprint when the drag began.
+    fn rotated_clip_artboard(
+        &self,
+        origin_pos: Vec2,
+        origin_size: Vec2,
+        rotation: f32,
+    ) -> Option<Artboard> {
+        if !self.clip_children_to_artboard {
+            return None;
+        }
+        let (min, max) = rotated_aabb(origin_pos, origin_size, rotation);
+        self.doc
+            .containing_artboard(min, Vec2::new(max.x - min.x, max.y - min.y))
+    }
+
+    fn compute_resize(
+        edge: Edge,
+        dx: f32,
+        dy: f32,
+        origin_pos: Vec2,
+        origin_size: Vec2,
+        min_size: f32,
+    ) -> (Vec2, Vec2) {
+        let mut pos = origin_pos;
+        let mut size = origin_size;
+
+        // Helper: resize one axis. Returns (new_origin, new_size).
+        // Dragging an edge past the opposite one flips the rect there, so the
+        // size stays positive; it never collapses below min_size.
+        let clamp_axis = |origin: f32, length: f32, delta: f32, anchor_end: bool| -> (f32, f32) {
+            // `anchor_end`: right/bottom edge moves and the origin is the anchor;
+            // otherwise the left/top edge moves and the far edge is the anchor.
+            let (anchor, moved) = if anchor_end {
+                (origin, origin + length + delta)
+            } else {
+                (origin + length, origin + delta)
+            };
+            let new_len = (moved - anchor).abs().max(min_size);
+            // on the anchor itself, keep the rect on its original side
+            let after = if moved == anchor {
+                anchor_end
+            } else {
+                moved > anchor
+            };
+            if after {
+                (anchor, new_len)
+            } else {
+                (anchor - new_len, new_len)
+            }
+        };
+
+        let ((moves_x, anchor_left), (moves_y, anchor_top)) = resize_axes(edge);
+
+        if moves_x {
+            let (px, sx) = clamp_axis(origin_pos.x, origin_size.x, dx, anchor_left);
+            pos.x = px;
+            size.x = sx;
+        }
+        if moves_y {
+            let (py, sy) = clamp_axis(origin_pos.y, origin_size.y, dy, anchor_top);
+            pos.y = py;
+            size.y = sy;
+        }
+
+        (pos, size)
+    }
+
+    /// Determine the cursor style to show based on current hover position and drag state.
+    pub fn compute_cursor(&self, tool_mode: &ToolMode) -> CursorStyle {
+        // Show the rect create cross hair cursor
+        if matches!(
+            self.drag_state,
+            DragState::PendingRectCreate(_) | DragState::RectCreate(_)
+        ) {
+            return CursorStyle::Crosshair;
+        }
+
+        // During an active move drag, always show the move cursor.
+        if matches!(
+            self.drag_state,
+            DragState::SelectionMove(_) | DragState::PendingSelectionMove(_)
+        ) {
+            return CursorStyle::Move;
+        }
+
+        // If hovering over a handle, show the appropriate resize cursor.
+        if matches!(tool_mode, ToolMode::Select)
+            && let Some(screen_px) = self.hover_screen_px
+        {
+            let world = self.camera.screen_to_world(screen_px);
+            if let Some(hit) = self.check_collide_handle(world) {
+                return match hit.edge {
+                    Edge::NW | Edge::SE => CursorStyle::ResizeTlBr,
+                    Edge::NE | Edge::SW => CursorStyle::ResizeTrBl,
+                    Edge::N | Edge::S => CursorStyle::ResizeNs,
+                    _ => CursorStyle::ResizeEw,
+                };
+            }
+        }
+
+        CursorStyle::Default
+    }
+
+    fn update_move_drag(&mut self, screen_px: Vec2, world: Vec2, drag_threshold_sq: f32) {
+        let next: Option<DragState> = match &self.drag_state {
+            DragState::PendingSelectionMove(pending) => {
+                let dx = screen_px.x - pending.start_screen_px.x;
+                let dy = screen_px.y - pending.start_screen_px.y;
+                let dist_sq = dx * dx + dy * dy;
+
+                if dist_sq >= drag_threshold_sq {
+                    let selected_ids: HashSet<NodeId> = self.selected.iter().copied().collect();
+                    let texts = self
+                        .doc
+                        .texts
+                        .iter()
+                        .filter(|text| selected_ids.contains(&text.id))
+                        .map(|text| (text.id, text.pos));
+                    let origins: Vec<(NodeId, Vec2)> = self
+                        .doc
+                        .rects
+                        .iter()
+                        .filter_map(|rect| {
+                            (!rect.locked && selected_ids.contains(&rect.id))
+                                .then_some((rect.id, rect.pos))
+                        })
+                        .chain(texts)
+                        .collect();
+
+                    Some(DragState::SelectionMove(SelectionDrag {
+                        start_world: pending.start_world,
+                        current_world: world,
+                        origins,
+                        guides: Vec::new(),
+                    }))
+                } else {
+                    None
+                }
+            }
+            DragState::SelectionMove(d) => {
+                let mut d = d.clone();
+                d.current_world = world;
+                Some(DragState::SelectionMove(d))
+            }
+            _ => None,
+        };
+
+        if let Some(state) = next {
+            self.drag_state = state;
+            self.apply_selection_drag();
+        }
+    }
+
+    fn update_resize_drag(&mut self, screen_px: Vec2, world: Vec2, drag_threshold_sq: f32) {
+        let next: Option<DragState> = match &self.drag_state {
+            DragState::PendingResize(pending) => {
+                let dx = screen_px.x - pending.start_screen_px.x;
+                let dy = screen_px.y - pending.start_screen_px.y;
+                let dist_sq = dx * dx + dy * dy;
+
+                if dist_sq >= drag_threshold_sq {
+                    let rect_idx = self
+                        .doc
+                        .rects
+                        .iter()
+                        .position(|r| r.id == pending.handle.node_id)
+                        .unwrap();
+
+                    let rect = &self.doc.rects[rect_idx];
+
+                    Some(DragState::Resize(ResizeDrag {
+                        handle: pending.handle,
+                        start_world: pending.start_world,
+                        current_world: world,
+                        origin_pos: rect.pos,   // snapshot
+                        origin_size: rect.size, // snapshot
+                        rect_idx,
+                    }))
+                } else {
+                    None
+                }
+            }
+            DragState::Resize(drag) => {
+                let mut drag = drag.clone();
+                drag.current_world = world;
+                Some(DragState::Resize(drag))
+            }
+            _ => None,
+        };
+
+        if let Some(state) = next {
+            self.drag_state = state;
+            self.apply_selection_resize();
+        }
+    }
+
+    fn update_rect_create_drag(&mut self, screen_px: Vec2, world: Vec2, drag_threshold_sq: f32) {
+        let aspect = match (self.create_aspect, self.modifiers.shift) {
+            (Some(aspect), false) => Some(aspect),
+            (None, true) => Some(1.0),
+            _ => None,
+        };
+        let grid = self.active_snap_grid();
+        let world = snap_point(world, grid);
+        let constrain = |start: Vec2| match aspect {
+            Some(aspect) => constrain_to_aspect(start, world, aspect),
+            None => world,
+        };
+
+        let next: Option<DragState> = match &self.drag_state {
+            DragState::PendingRectCreate(pending) => {
+                let dx = screen_px.x - pending.start_screen_px.x;
+                let dy = screen_px.y - pending.start_screen_px.y;
+                let dist_sq = dx * dx + dy * dy;
+
+                if dist_sq >= drag_threshold_sq {
+                    let start_world = snap_point(pending.start_world, grid);
+                    Some(DragState::RectCreate(RectCreateDrag {
+                        start_world,
+                        current_world: constrain(start_world),
+                        previous_selection: pending.previous_selection.clone(),
+                    }))
+                } else {
+                    None
+                }
+            }
+            DragState::RectCreate(drag) => {
+                let mut drag = drag.clone();
+                drag.current_world = constrain(drag.start_world);
+                Some(DragState::RectCreate(drag))
+            }
+            _ => None,
+        };
+
+        if let Some(state) = next {
+            self.drag_state = state;
+        }
+    }
+
+    fn restack_selection(&mut self, target: NodeId, above: bool) {
+        let selected: HashSet<NodeId> = self.selected.iter().copied().collect();
+        if selected.is_empty() || selected.contains(&target) || self.rect(target).is_none() {
+            return;
+        }
+
+        let before: Vec<NodeId> = self.doc.rects.iter().map(|rect| rect.id).collect();
+        let (moved, mut after): (Vec<NodeId>, Vec<NodeId>) =
+            before.iter().partition(|id| selected.contains(id));
+        let Some(target_idx) = after.iter().position(|id| *id == target) else {
+            return;
+        };
+        let insert_at = if above { target_idx + 1 } else { target_idx };
+        after.splice(insert_at..insert_at, moved);
+        self.commit_restack(before, after);
+    }
+
+    /// Move the selected rects to the top (or bottom) of the draw order as one
+    /// contiguous run, keeping their relative order.
+    fn stack_selection_at_end(&mut self, top: bool) {
+        let selected: HashSet<NodeId> = self.selected.iter().copied().collect();
+        let before: Vec<NodeId> = self.doc.rects.iter().map(|rect| rect.id).collect();
+        let (mut moved, mut rest): (Vec<NodeId>, Vec<NodeId>) =
+            before.iter().partition(|id| selected.contains(id));
+        let after = if top {
+            rest.append(&mut moved);
+            rest
+        } else {
+            moved.append(&mut rest);
+            moved
+        };
+        self.commit_restack(before, after);
+    }
+
+    /// Move every selected rect one place up (`up`) or down the draw order
+    /// as a single undoable restack.
+    fn step_selection(&mut self, up: bool) {
+        let selected: HashSet<NodeId> = self.selected.iter().copied().collect();
+        let before: Vec<NodeId> = self.doc.rects.iter().map(|rect| rect.id).collect();
+        let mut after = before.clone();
+        step_order(&mut after, &selected, up);
+        self.commit_restack(before, after);
+    }
+
+    fn commit_restack(&mut self, before: Vec<NodeId>, after: Vec<NodeId>) {
+        if after == before {
+            return;
+        }
+
+        let command = ToolCommand::Restack { before, after };
+        self.apply_command(&command, true);
+        self.push_history(command);
+    }
+
+    /// Reorder `doc.rects` to follow `order` (bottom to top). Ids missing from
+    /// `order` keep their relative order after the listed ones.
+    fn apply_draw_order(&mut self, order: &[NodeId]) {
+        let rank = |id: NodeId| order.iter().position(|o| *o == id).unwrap_or(order.len());
+        self.invalidate_spatial_index();
+        self.doc.rects.sort_by_key(|rect| rank(rect.id));
+    }
+
+    fn reorder_selected(&mut self, node_ids: &[NodeId], to_front: bool) {
+        self.invalidate_spatial_index();
+        let selected_ids: HashSet<NodeId> = node_ids.iter().copied().collect();
+        let mut indices: Vec<usize> = selected_ids
+            .iter()
+            .filter_map(|id| self.rect_index(*id))
+            .collect();
+
+        if to_front {
+            indices.sort_unstable_by(|a, b| b.cmp(a));
+
+            for idx in indices {
+                if idx + 1 >= self.doc.rects.len() {
+                    continue;
+                }
+                if selected_ids.contains(&self.doc.rects[idx + 1].id) {
+                    continue;
+                }
+                self.doc.rects.swap(idx, idx + 1);
+            }
+        } else {
+            indices.sort_unstable();
+
+            for idx in indices {
+                if idx == 0 {
+                    continue;
+                }
+                if selected_ids.contains(&self.doc.rects[idx - 1].id) {
+                    continue;
+                }
+                self.doc.rects.swap(idx, idx - 1);
+            }
+        }
+    }
+}
+
+/// Swap each `selected` id in `order` one place toward the end (`up`) or
+/// the start. Walking from the side the ids move toward means a selected id
+/// only ever swaps with an unselected one, so runs of selected ids shift
+/// together and never leapfrog, and a run pinned at the bound stays put.
+fn step_order(order: &mut [NodeId], selected: &HashSet<NodeId>, up: bool) {
+    let len = order.len();
+    for step in 1..len {
+        let (from, to) = if up {
+            (len - 1 - step, len - step)
+        } else {
+            (step, step - 1)
+        };
+        if selected.contains(&order[from]) && !selected.contains(&order[to]) {
+            order.swap(from, to);
+        }
+    }
+}
+
+/// Whether `pos`/`size` turned by `rotation` lies entirely within `artboard`.
+fn fits_rotated(artboard: &Artboard, pos: Vec2, size: Vec2, rotation: f32) -> bool {
+    let (min, max) = rotated_aabb(pos, size, rotation);
+    artboard.contains(min, Vec2::new(max.x - min.x, max.y - min.y))
+}
+
+/// Intersect `pos`/`size` with `artboard`'s bounds.
+fn clamp_to_artboard(pos: Vec2, size: Vec2, artboard: &Artboard) -> (Vec2, Vec2) {
+    let min_x = pos.x.max(artboard.pos.x);
+    let min_y = pos.y.max(artboard.pos.y);
+    let max_x = (pos.x + size.x).min(artboard.pos.x + artboard.size.x);
+    let max_y = (pos.y + size.y).min(artboard.pos.y + artboard.size.y);
+    (
+        Vec2::new(min_x, min_y),
+        Vec2::new(max_x - min_x, max_y - min_y),
+    )
+}
+
+/// Which axes a resize handle drags, as `((moves_x, anchor_left), (moves_y,
+/// anchor_top))`. Edge handles leave the perpendicular axis untouched.
+fn resize_axes(edge: Edge) -> ((bool, bool), (bool, bool)) {
+    let x = match edge {
+        Edge::E | Edge::NE | Edge::SE => (true, true),
+        Edge::W | Edge::NW | Edge::SW => (true, false),
+        _ => (false, false),
+    };
+    let y = match edge {
+        Edge::S | Edge::SW | Edge::SE => (true, true),
+        Edge::N | Edge::NW | Edge::NE => (true, false),
+        _ => (false, false),
+    };
+    (x, y)
+}
+
+/// Grow or shrink a corner resize result uniformly so it keeps the aspect
+/// ratio of `origin_size`, following whichever axis was scaled further. The
+/// corner opposite the handle stays fixed, including after a flip. Edge
+/// handles and rects with a zero side are returned unchanged.
+fn keep_resize_aspect(
+    edge: Edge,
+    pos: Vec2,
+    size: Vec2,
+    origin_pos: Vec2,
+    origin_size: Vec2,
+    min_size: f32,
+) -> (Vec2, Vec2) {
+    let ((moves_x, anchor_left), (moves_y, anchor_top)) = resize_axes(edge);
+    if !(moves_x && moves_y) || origin_size.x <= 0.0 || origin_size.y <= 0.0 {
+        return (pos, size);
+    }
+
+    let scale = (size.x / origin_size.x)
+        .max(size.y / origin_size.y)
+        .max(min_size / origin_size.x.min(origin_size.y));
+    let new_size = Vec2::new(origin_size.x * scale, origin_size.y * scale);
+
+    // Keep the rect on the side of the anchor it was resized to.
+    let place = |origin: f32, length: f32, anchor_start: bool, start: f32, len: f32, new_len| {
+        let anchor = if anchor_start {
+            origin
+        } else {
+            origin + length
+        };
+        if start + len * 0.5 < anchor {
+            anchor - new_len
+        } else {
+            anchor
+        }
+    };
+    let new_pos = Vec2::new(
+        place(
+            origin_pos.x,
+            origin_size.x,
+            anchor_left,
+            pos.x,
+            size.x,
+            new_size.x,
+        ),
+        place(
+            origin_pos.y,
+            origin_size.y,
+            anchor_top,
+            pos.y,
+            size.y,
+            new_size.y,
+        ),
+    );
+    (new_pos, new_size)
+}
+
+/// World length rounded to two decimals, without trailing zeros.
+fn format_dimension(value: f32) -> String {
+    let rounded = (value * 100.0).round() / 100.0;
+    // `+ 0.0` turns a rounded -0 into 0.
+    format!("{}", rounded + 0.0)
+}
+
+/// Left edge, center and right edge of a `(min, max)` box.
+fn box_lines_x((min, max): &(Vec2, Vec2)) -> [f32; 3] {
+    [min.x, (min.x + max.x) * 0.5, max.x]
+}
+
+/// Top edge, center and bottom edge of a `(min, max)` box.
+fn box_lines_y((min, max): &(Vec2, Vec2)) -> [f32; 3] {
+    [min.y, (min.y + max.y) * 0.5, max.y]
+}
+
+/// Guides for every edge or center `selection` shares with one of the
+/// `targets`, each spanning the selection and the boxes it lines up with.
+fn smart_guides(selection: (Vec2, Vec2), targets: &[(Vec2, Vec2)]) -> Vec<SnapGuide> {
+    // Lines closer than this count as aligned, absorbing float drift.
+    const ALIGN_EPSILON: f32 = 1e-3;
+
+    let aligned = |line: f32, lines: fn(&(Vec2, Vec2)) -> [f32; 3]| -> Vec<&(Vec2, Vec2)> {
+        targets
+            .iter()
+            .filter(|target| {
+                lines(target)
+                    .iter()
+                    .any(|t| (t - line).abs() <= ALIGN_EPSILON)
+            })
+            .collect()
+    };
+    let (sel_min, sel_max) = selection;
+    let mut guides = Vec::new();
+    for x in box_lines_x(&selection) {
+        let matches = aligned(x, box_lines_x);
+        if matches.is_empty() {
+            continue;
+        }
+        let top = matches
+            .iter()
+            .fold(sel_min.y, |top, (min, _)| top.min(min.y));
+        let bottom = matches
+            .iter()
+            .fold(sel_max.y, |bottom, (_, max)| bottom.max(max.y));
+        guides.push(SnapGuide {
+            start: Vec2::new(x, top),
+            end: Vec2::new(x, bottom),
+        });
+    }
+    for y in box_lines_y(&selection) {
+        let matches = aligned(y, box_lines_y);
+        if matches.is_empty() {
+            continue;
+        }
+        let left = matches
+            .iter()
+            .fold(sel_min.x, |left, (min, _)| left.min(min.x));
+        let right = matches
+            .iter()
+            .fold(sel_max.x, |right, (_, max)| right.max(max.x));
+        guides.push(SnapGuide {
+            start: Vec2::new(left, y),
+            end: Vec2::new(right, y),
+        });
+    }
+    // A zero-size side puts its center on its edges.
+    guides.dedup();
+    guides
+}
+
+/// Round `point` to the nearest intersection of a `grid`-pitch grid.
+fn snap_point(point: Vec2, grid: Option<f32>) -> Vec2 {
+    match grid {
+        Some(grid) => Vec2::new(
+            (point.x / grid).round() * grid,
+            (point.y / grid).round() * grid,
+        ),
+        None => point,
+    }
+}
+
+/// Move `current` so the box spanned from `start` has the given width/height
+/// ratio. The longer side wins, and the drag direction on each axis is kept.
+fn constrain_to_aspect(start: Vec2, current: Vec2, aspect: f32) -> Vec2 {
+    if aspect <= 0.0 || !aspect.is_finite() {
+        return current;
+    }
+
+    let dx = current.x - start.x;
+    let dy = current.y - start.y;
+    let mut w = dx.abs();
+    let mut h = dy.abs();
+
+    if w >= h * aspect {
+        h = w / aspect;
+    } else {
+        w = h * aspect;
+    }
+
+    Vec2::new(
+        start.x + w.copysign(if dx == 0.0 { 1.0 } else { dx }),
+        start.y + h.copysign(if dy == 0.0 { 1.0 } else { dy }),
+    )
+}
+
+/// Strict AABB intersection; rects that only share an edge do not intersect.
+/// Whether two `(min, max)` boxes strictly intersect; touching edges do not.
+fn boxes_intersect((a_min, a_max): (Vec2, Vec2), (b_min, b_max): (Vec2, Vec2)) -> bool {
+    a_min.x < b_max.x && a_max.x > b_min.x && a_min.y < b_max.y && a_max.y > b_min.y
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::input::RgbaColor;
+    use crate::render_scene::HandleShape;
+    use crate::types::TextNode;
+
+    fn assert_approx(a: f32, b: f32, eps: f32) {
+        if (a - b).abs() > eps {
+            panic!("Expected {a} ~= {b} (eps {eps})");
         }
     }
 
@@ -1061,588 +3504,4837 @@ mod test {
     }
 
     #[test]
-    fn world_screen_roundtrip_is_stable() {
-        let cam = Camera {
-            pan: Vec2::new(100.0, -50.0),
-            zoom: 2.5,
-        };
+    fn world_screen_roundtrip_is_stable() {
+        let cam = Camera {
+            pan: Vec2::new(100.0, -50.0),
+            zoom: 2.5,
+        };
+
+        let world = Vec2::new(12.0, 34.0);
+        let screen = cam.world_to_screen(world);
+        let world2 = cam.screen_to_world(screen);
+
+        assert_vec2_approx(world2, world, 1e-4);
+    }
+
+    #[test]
+    fn create_rect_event_appends_on_top_and_selects_it() {
+        let mut engine = engine_with_two_rects();
+        engine.selected = vec![engine.doc.rects[0].id];
+        let color = Color::rgba(0.0, 1.0, 0.0, 1.0);
+
+        engine.tick(&InputBatch {
+            events: vec![
+                InputEvent::CreateRect {
+                    world: Vec2::new(10.0, 20.0),
+                    size: Vec2::new(30.0, 40.0),
+                    color,
+                },
+                InputEvent::CreateRect {
+                    world: Vec2::new(0.0, 0.0),
+                    size: Vec2::new(0.0, -5.0),
+                    color,
+                },
+            ],
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+
+        assert_eq!(engine.doc.rects.len(), 4);
+        let created = &engine.doc.rects[2];
+        assert_vec2_approx(created.pos, Vec2::new(10.0, 20.0), 1e-6);
+        assert_vec2_approx(created.size, Vec2::new(30.0, 40.0), 1e-6);
+        assert_eq!(created.color, color);
+
+        let degenerate = &engine.doc.rects[3];
+        assert_vec2_approx(degenerate.size, Vec2::new(1.0, 1.0), 1e-6);
+        assert_eq!(engine.selected, vec![degenerate.id]);
+    }
+
+    #[test]
+    fn undo_redo_walk_through_create_then_move() {
+        let mut engine = engine_with_one_rect();
+        let tick = |engine: &mut Engine, events: Vec<InputEvent>| {
+            engine.tick(&InputBatch {
+                events,
+                tool: ToolMode::Select,
+                ..Default::default()
+            });
+        };
+
+        tick(
+            &mut engine,
+            vec![InputEvent::CreateRect {
+                world: Vec2::new(300.0, 300.0),
+                size: Vec2::new(50.0, 50.0),
+                color: Color::WHITE,
+            }],
+        );
+        let created = engine.doc.rects[1].id;
+        start_move_drag(
+            &mut engine,
+            Vec2::new(320.0, 320.0),
+            Vec2::new(340.0, 350.0),
+        );
+        tick(
+            &mut engine,
+            vec![InputEvent::PointerUp {
+                screen_px: Vec2::new(340.0, 350.0),
+                button: 0,
+            }],
+        );
+        assert_vec2_approx(engine.doc.rects[1].pos, Vec2::new(320.0, 330.0), 1e-6);
+
+        tick(&mut engine, vec![InputEvent::Undo]);
+        assert_vec2_approx(engine.doc.rects[1].pos, Vec2::new(300.0, 300.0), 1e-6);
+        tick(&mut engine, vec![InputEvent::Undo]);
+        assert_eq!(engine.doc.rects.len(), 1);
+        assert!(engine.selected.is_empty());
+
+        tick(&mut engine, vec![InputEvent::Redo]);
+        assert_eq!(engine.selected, vec![created]);
+        assert_vec2_approx(engine.doc.rects[1].pos, Vec2::new(300.0, 300.0), 1e-6);
+        tick(&mut engine, vec![InputEvent::Redo]);
+        assert_vec2_approx(engine.doc.rects[1].pos, Vec2::new(320.0, 330.0), 1e-6);
+
+        // A fresh edit discards the redo branch.
+        tick(
+            &mut engine,
+            vec![InputEvent::Undo, InputEvent::DeleteSelected],
+        );
+        tick(&mut engine, vec![InputEvent::Redo]);
+        assert_eq!(engine.doc.rects.len(), 1);
+    }
+
+    #[test]
+    fn history_is_capped_dropping_oldest_steps() {
+        let mut engine = engine_with_one_rect();
+        let events = (0..HISTORY_LIMIT + 5)
+            .map(|i| InputEvent::CreateRect {
+                world: Vec2::new(i as f32, 0.0),
+                size: Vec2::new(10.0, 10.0),
+                color: Color::WHITE,
+            })
+            .chain((0..HISTORY_LIMIT + 5).map(|_| InputEvent::Undo))
+            .collect();
+        engine.tick(&InputBatch {
+            events,
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+
+        assert_eq!(engine.undo_stack.len(), 0);
+        assert_eq!(engine.redo_stack.len(), HISTORY_LIMIT);
+        // The first five creates fell off the history and can't be undone.
+        assert_eq!(engine.doc.rects.len(), 1 + 5);
+    }
+
+    fn delete_batch() -> InputBatch {
+        InputBatch {
+            events: vec![InputEvent::DeleteSelected],
+            tool: ToolMode::Select,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn delete_selected_removes_only_selected_rects() {
+        let mut engine = engine_with_stacked_rects();
+        let ids: Vec<NodeId> = engine.doc.rects.iter().map(|r| r.id).collect();
+        engine.selected = vec![ids[0], ids[2]];
+
+        engine.tick(&delete_batch());
+
+        let remaining: Vec<NodeId> = engine.doc.rects.iter().map(|r| r.id).collect();
+        assert_eq!(remaining, vec![ids[1]]);
+        assert!(engine.selected.is_empty());
+
+        // Ids are never reused after a delete.
+        let next = engine.doc.alloc_id();
+        assert!(ids.iter().all(|id| id.0 < next.0));
+    }
+
+    #[test]
+    fn delete_with_empty_selection_is_a_no_op() {
+        let mut engine = engine_with_two_rects();
+        let before = engine.doc.rects.clone();
+
+        engine.tick(&delete_batch());
+
+        assert_eq!(engine.doc.rects, before);
+        assert!(engine.undo_stack.is_empty());
+    }
+
+    #[test]
+    fn delete_mid_drag_cancels_drag_and_undo_restores_origin() {
+        let mut engine = engine_with_one_rect();
+        engine.selected = vec![engine.doc.rects[0].id];
+        let original = engine.doc.rects.clone();
+        start_move_drag(
+            &mut engine,
+            Vec2::new(100.0, 100.0),
+            Vec2::new(140.0, 130.0),
+        );
+        assert!(matches!(engine.drag_state, DragState::SelectionMove(_)));
+
+        engine.tick(&delete_batch());
+        assert!(engine.doc.rects.is_empty());
+        assert!(matches!(engine.drag_state, DragState::Idle));
+
+        engine.undo();
+        assert_eq!(engine.doc.rects, original);
+    }
+
+    #[test]
+    fn cut_removes_selection_and_pastes_back_with_new_ids() {
+        let mut engine = engine_with_two_rects();
+        let locked = RectNode {
+            locked: true,
+            ..RectNode::new(
+                engine.doc.alloc_id(),
+                Vec2::new(500.0, 50.0),
+                Vec2::new(50.0, 50.0),
+                Color::WHITE,
+            )
+        };
+        engine.doc_mut().rects.push(locked.clone());
+        let label = TextNode {
+            id: engine.doc.alloc_id(),
+            pos: Vec2::new(300.0, 300.0),
+            content: "Hello".to_string(),
+            size: 20.0,
+            color: Color::BLACK,
+        };
+        engine.doc.texts.push(label.clone());
+        let originals = engine.doc.rects.clone();
+        let ids = order_of(&engine);
+        engine.set_selected(ids.into_iter().chain([label.id]).collect());
+
+        let json = engine.cut_selection();
+        // The locked rect survives the cut and stays out of the clipboard.
+        assert_eq!(engine.doc.rects, vec![locked.clone()]);
+        assert!(engine.doc.texts.is_empty());
+        assert_eq!(engine.selected, vec![locked.id]);
+
+        let pasted = engine.merge_nodes(&json, Vec2::new(0.0, 0.0)).unwrap();
+        assert_eq!(pasted.len(), 3);
+        assert_eq!(engine.doc.rects.len(), 3);
+        for (rect, original) in engine.doc.rects[1..].iter().zip(&originals) {
+            assert_ne!(rect.id, original.id);
+            assert_eq!(rect.pos, original.pos);
+            assert_eq!(rect.size, original.size);
+            assert_eq!(rect.color, original.color);
+        }
+        let text = &engine.doc.texts[0];
+        assert_ne!(text.id, label.id);
+        assert_eq!((text.pos, &text.content), (label.pos, &label.content));
+
+        // The cut itself is a single history step.
+        engine.undo();
+        engine.undo();
+        assert_eq!(engine.doc.rects, originals);
+        assert_eq!(engine.doc.texts, vec![label]);
+    }
+
+    #[test]
+    fn wheel_zoom_keeps_pivot_fixed_in_both_directions() {
+        let mut engine = engine_with_one_rect();
+        let pivot = Vec2::new(200.0, 150.0);
+        let wheel = |engine: &mut Engine, delta_y: f32| {
+            engine.tick(&InputBatch {
+                events: vec![InputEvent::WheelZoom {
+                    pivot_px: pivot,
+                    delta_y,
+                }],
+                tool: ToolMode::Select,
+                ..Default::default()
+            });
+        };
+        let anchor = engine.camera.screen_to_world(pivot);
+
+        wheel(&mut engine, -100.0);
+        assert_approx(engine.camera.zoom, (100.0 * WHEEL_ZOOM_SPEED).exp(), 1e-5);
+        assert_vec2_approx(engine.camera.screen_to_world(pivot), anchor, 1e-3);
+
+        wheel(&mut engine, 200.0);
+        assert!(engine.camera.zoom < 1.0);
+        assert_vec2_approx(engine.camera.screen_to_world(pivot), anchor, 1e-3);
+    }
+
+    #[test]
+    fn batch_conversions_match_per_point_conversion() {
+        let camera = Camera {
+            pan: Vec2::new(-40.0, 25.0),
+            zoom: 2.5,
+        };
+        let screen = [
+            Vec2::new(0.0, 0.0),
+            Vec2::new(120.0, 80.0),
+            Vec2::new(-15.5, 300.25),
+        ];
+
+        let world = camera.screen_to_world_batch(&screen);
+        assert_eq!(world.len(), screen.len());
+        for (batch, point) in world.iter().zip(screen) {
+            assert_vec2_approx(*batch, camera.screen_to_world(point), 1e-6);
+        }
+
+        let back = camera.world_to_screen_batch(&world);
+        for (batch, point) in back.iter().zip(world) {
+            assert_vec2_approx(*batch, camera.world_to_screen(point), 1e-6);
+        }
+    }
+
+    #[test]
+    fn rect_instance_without_corner_radius_deserializes_sharp() {
+        let json = r#"{"pos":[1,2],"size":[3,4],"color":[1,1,1,1]}"#;
+        let instance: RectInstance = serde_json::from_str(json).unwrap();
+        assert_eq!(instance.corner_radius, 0.0);
+        assert_eq!(instance.shape, InstanceShape::Rect);
+    }
+
+    #[test]
+    fn world_to_ndc_matches_shader_formula() {
+        let cam = Camera {
+            pan: Vec2::new(-40.0, 25.0),
+            zoom: 1.75,
+        };
+        let canvas = Vec2::new(800.0, 600.0);
+
+        for world in [
+            Vec2::new(-40.0, 25.0),
+            Vec2::new(0.0, 0.0),
+            Vec2::new(123.5, -78.25),
+            Vec2::new(417.0, 367.0),
+        ] {
+            // Same arithmetic as vs_main in shader.wgsl.
+            let screen_x = (world.x - cam.pan.x) * cam.zoom;
+            let screen_y = (world.y - cam.pan.y) * cam.zoom;
+            let expected = [
+                (screen_x / canvas.x) * 2.0 - 1.0,
+                1.0 - (screen_y / canvas.y) * 2.0,
+            ];
+
+            let ndc = cam.world_to_ndc(world, canvas);
+            assert_approx(ndc[0], expected[0], 1e-6);
+            assert_approx(ndc[1], expected[1], 1e-6);
+            assert_vec2_approx(cam.ndc_to_world(ndc, canvas), world, 1e-3);
+        }
+
+        assert_eq!(cam.world_to_ndc(cam.pan, canvas), [-1.0, 1.0]);
+    }
+
+    #[test]
+    fn pan_by_screen_delta_moves_world_origin_expected_direction() {
+        let mut cam = Camera {
+            pan: Vec2::new(0.0, 0.0),
+            zoom: 2.0,
+        };
+
+        // Drag pointer right/down by 20px/10px => camera should pan left/up in world units.
+        cam.pan_by_screen_delta(Vec2::new(20.0, 10.0));
+        assert_vec2_approx(cam.pan, Vec2::new(-10.0, -5.0), 1e-6);
+    }
+
+    #[test]
+    fn zoom_at_cursor_keeps_world_point_under_cursor_fixed() {
+        let mut cam = Camera {
+            pan: Vec2::new(10.0, 20.0),
+            zoom: 2.0,
+        };
+
+        let pivot_screen = Vec2::new(300.0, 120.0);
+        let world_before = cam.screen_to_world(pivot_screen);
+
+        cam.zoom_at_screen_point(pivot_screen, 1.5);
+
+        let world_after = cam.screen_to_world(pivot_screen);
+        assert_vec2_approx(world_after, world_before, 1e-4);
+    }
+
+    #[test]
+    fn tick_applies_pan_event() {
+        let mut engine = Engine {
+            camera: Camera {
+                pan: Vec2::new(0.0, 0.0),
+                zoom: 2.0,
+            },
+            ..Engine::with_document(Document::new())
+        };
+
+        let batch = InputBatch {
+            events: vec![InputEvent::CameraPanByScreenDelta {
+                delta_px: Vec2::new(20.0, 10.0),
+            }],
+            tool: ToolMode::Select,
+            ..Default::default()
+        };
+
+        engine.tick(&batch);
+
+        // same expectations as the direct Camera test
+        assert_vec2_approx(engine.camera.pan, Vec2::new(-10.0, -5.0), 1e-6);
+        assert_approx(engine.camera.zoom, 2.0, 1e-6);
+    }
+
+    #[test]
+    fn tick_applies_zoom_event_and_preserves_world_point_under_cursor() {
+        let mut engine = Engine {
+            camera: Camera {
+                pan: Vec2::new(10.0, 20.0),
+                zoom: 2.0,
+            },
+            ..Engine::with_document(Document::new())
+        };
+
+        let pivot = Vec2::new(300.0, 120.0);
+        let world_before = engine.camera.screen_to_world(pivot);
+
+        let batch = InputBatch {
+            events: vec![InputEvent::CameraZoomAtScreenPoint {
+                pivot_px: pivot,
+                zoom_multiplier: 1.5,
+            }],
+            tool: ToolMode::Select,
+            ..Default::default()
+        };
+
+        engine.tick(&batch);
+
+        assert_approx(engine.camera.zoom, 3.0, 1e-6);
+        let world_after = engine.camera.screen_to_world(pivot);
+        assert_vec2_approx(world_after, world_before, 1e-4);
+    }
+
+    #[test]
+    fn tick_applies_events_in_order() {
+        let mut engine = Engine::new();
+        engine.camera.pan = Vec2::new(5.0, -7.0);
+        engine.camera.zoom = 2.0;
+
+        let batch = InputBatch {
+            events: vec![
+                InputEvent::CameraPanByScreenDelta {
+                    delta_px: Vec2::new(40.0, 0.0),
+                },
+                InputEvent::CameraZoomAtScreenPoint {
+                    pivot_px: Vec2::new(100.0, 50.0),
+                    zoom_multiplier: 0.5,
+                },
+            ],
+            tool: ToolMode::Select,
+            ..Default::default()
+        };
+
+        // expected result: applying the same ops directly, in the same order
+        let mut expected = engine.camera;
+        expected.pan_by_screen_delta(Vec2::new(40.0, 0.0));
+        expected.zoom_at_screen_point(Vec2::new(100.0, 50.0), 0.5);
+
+        engine.tick(&batch);
+
+        assert_vec2_approx(engine.camera.pan, expected.pan, 1e-5);
+        assert_approx(engine.camera.zoom, expected.zoom, 1e-6);
+    }
+
+    #[test]
+    fn hit_test_picks_topmost_rect() {
+        let engine = Engine::new();
+        let top_id = engine.doc.rects[2].id;
+        let hit = engine.check_collide_rects(Vec2::new(610.0, 910.0));
+        assert_eq!(hit, Some(top_id));
+    }
+
+    #[test]
+    fn selection_rules_apply_correcly() {
+        let mut engine = Engine::new();
+        let id = engine.doc.rects[0].id;
+
+        engine.apply_selection(Some(id), false);
+        assert_eq!(engine.selected, vec![id]);
+
+        engine.apply_selection(Some(id), true);
+        assert!(engine.selected.is_empty());
+
+        engine.apply_selection(None, false);
+        assert!(engine.selected.is_empty());
+    }
+
+    /// Helper: build a minimal Engine with a single 100x100 rect at (50, 50).
+    fn engine_with_one_rect() -> Engine {
+        let mut doc = Document::new();
+        let id = doc.alloc_id();
+        doc.rects.push(RectNode::new(
+            id,
+            Vec2::new(50.0, 50.0),
+            Vec2::new(100.0, 100.0),
+            Color::rgba(1.0, 0.0, 0.0, 1.0),
+        ));
+        Engine::with_document(doc)
+    }
+
+    /// Helper: build a minimal Engine with two non-overlapping 100x100 rects.
+    fn engine_with_two_rects() -> Engine {
+        let mut doc = Document::new();
+        let id0 = doc.alloc_id();
+        let id1 = doc.alloc_id();
+        doc.rects.push(RectNode::new(
+            id0,
+            Vec2::new(50.0, 50.0),
+            Vec2::new(100.0, 100.0),
+            Color::rgba(1.0, 0.0, 0.0, 1.0),
+        ));
+        doc.rects.push(RectNode::new(
+            id1,
+            Vec2::new(300.0, 50.0),
+            Vec2::new(100.0, 100.0),
+            Color::rgba(0.0, 0.0, 1.0, 1.0),
+        ));
+        Engine::with_document(doc)
+    }
+
+    // -----------------------------------------------------------------------
+    // Selection move — single selection
+    // -----------------------------------------------------------------------
+
+    /// Clicking a rect selects it (PendingSelectionMove after PointerDown on
+    /// an already-selected rect). Moving beyond the drag threshold actually
+    /// moves the rect. PointerUp finalises the position.
+    #[test]
+    fn single_selection_drag_moves_rect() {
+        let mut engine = engine_with_one_rect();
+        let id = engine.doc.rects[0].id;
+        let origin = engine.doc.rects[0].pos;
+
+        // First click: select the rect (hit on unselected → apply_selection).
+        engine.tick(&InputBatch {
+            events: vec![InputEvent::PointerDown {
+                screen_px: Vec2::new(100.0, 100.0), // inside rect (50..150, 50..150)
+                shift: false,
+                button: 0,
+            }],
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+        assert_eq!(engine.selected, vec![id]);
+
+        // Second click on the now-selected rect → PendingSelectionMove.
+        engine.tick(&InputBatch {
+            events: vec![InputEvent::PointerDown {
+                screen_px: Vec2::new(100.0, 100.0),
+                shift: false,
+                button: 0,
+            }],
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+        assert!(matches!(
+            engine.drag_state,
+            DragState::PendingSelectionMove(_)
+        ));
+
+        // Move beyond 6 px threshold → SelectionMove + rect displaced.
+        // World == screen at zoom=1, pan=(0,0).  Delta in world = (30, 20).
+        engine.tick(&InputBatch {
+            events: vec![InputEvent::PointerMove {
+                screen_px: Vec2::new(130.0, 120.0),
+                buttons: 1,
+            }],
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+        assert!(matches!(engine.drag_state, DragState::SelectionMove(_)));
+        let pos_mid = engine.doc.rects[0].pos;
+        assert_vec2_approx(pos_mid, Vec2::new(origin.x + 30.0, origin.y + 20.0), 1e-4);
+
+        // Continue dragging further.
+        engine.tick(&InputBatch {
+            events: vec![InputEvent::PointerMove {
+                screen_px: Vec2::new(160.0, 150.0),
+                buttons: 1,
+            }],
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+        let pos_far = engine.doc.rects[0].pos;
+        assert_vec2_approx(pos_far, Vec2::new(origin.x + 60.0, origin.y + 50.0), 1e-4);
+
+        // Release pointer → Idle, position retained.
+        engine.tick(&InputBatch {
+            events: vec![InputEvent::PointerUp {
+                screen_px: Vec2::new(160.0, 150.0),
+                button: 0,
+            }],
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+        assert!(matches!(engine.drag_state, DragState::Idle));
+        assert_vec2_approx(engine.doc.rects[0].pos, pos_far, 1e-4);
+    }
+
+    /// A move below the 6 px drag threshold must NOT start SelectionMove and
+    /// must NOT displace the rect.
+    #[test]
+    fn single_selection_drag_below_threshold_does_not_move_rect() {
+        let mut engine = engine_with_one_rect();
+        let id = engine.doc.rects[0].id;
+        let origin = engine.doc.rects[0].pos;
+
+        // Select then enter PendingSelectionMove.
+        engine.tick(&InputBatch {
+            events: vec![InputEvent::PointerDown {
+                screen_px: Vec2::new(100.0, 100.0),
+                shift: false,
+                button: 0,
+            }],
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+        engine.tick(&InputBatch {
+            events: vec![InputEvent::PointerDown {
+                screen_px: Vec2::new(100.0, 100.0),
+                shift: false,
+                button: 0,
+            }],
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+        assert!(matches!(
+            engine.drag_state,
+            DragState::PendingSelectionMove(_)
+        ));
+        assert_eq!(engine.selected, vec![id]);
+
+        // Move only 3 px — below 6 px threshold.
+        engine.tick(&InputBatch {
+            events: vec![InputEvent::PointerMove {
+                screen_px: Vec2::new(103.0, 100.0),
+                buttons: 1,
+            }],
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+        // Should still be pending, not moved.
+        assert!(matches!(
+            engine.drag_state,
+            DragState::PendingSelectionMove(_)
+        ));
+        assert_vec2_approx(engine.doc.rects[0].pos, origin, 1e-4);
+    }
+
+    /// Cancelling a drag mid-flight must stop the move (state → Idle) but the
+    /// rect keeps its last displaced position (no automatic rollback).
+    #[test]
+    fn single_selection_drag_cancel_stops_move() {
+        let mut engine = engine_with_one_rect();
+        let origin = engine.doc.rects[0].pos;
+
+        // Select.
+        engine.tick(&InputBatch {
+            events: vec![InputEvent::PointerDown {
+                screen_px: Vec2::new(100.0, 100.0),
+                shift: false,
+                button: 0,
+            }],
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+        // Enter PendingSelectionMove.
+        engine.tick(&InputBatch {
+            events: vec![InputEvent::PointerDown {
+                screen_px: Vec2::new(100.0, 100.0),
+                shift: false,
+                button: 0,
+            }],
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+        // Start move.
+        engine.tick(&InputBatch {
+            events: vec![InputEvent::PointerMove {
+                screen_px: Vec2::new(150.0, 100.0),
+                buttons: 1,
+            }],
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+        assert!(matches!(engine.drag_state, DragState::SelectionMove(_)));
+        let pos_after_move = engine.doc.rects[0].pos;
+        assert_vec2_approx(pos_after_move, Vec2::new(origin.x + 50.0, origin.y), 1e-4);
+
+        // Cancel.
+        engine.tick(&InputBatch {
+            events: vec![InputEvent::PointerCancel],
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+        assert!(matches!(engine.drag_state, DragState::Idle));
+        assert_vec2_approx(engine.doc.rects[0].pos, origin, 1e-4);
+    }
+
+    // -----------------------------------------------------------------------
+    // Selection move — multi-selection
+    // -----------------------------------------------------------------------
+
+    /// Dragging one of multiple selected rects moves ALL of them by the same
+    /// world-space delta, preserving their relative positions.
+    #[test]
+    fn multi_selection_drag_moves_all_selected_rects() {
+        let mut engine = engine_with_two_rects();
+        let id0 = engine.doc.rects[0].id;
+        let id1 = engine.doc.rects[1].id;
+        let origin0 = engine.doc.rects[0].pos;
+        let origin1 = engine.doc.rects[1].pos;
+
+        // Select rect 0.
+        engine.tick(&InputBatch {
+            events: vec![InputEvent::PointerDown {
+                screen_px: Vec2::new(100.0, 100.0), // inside rect 0
+                shift: false,
+                button: 0,
+            }],
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+        // Add rect 1 to selection with shift-click.
+        engine.tick(&InputBatch {
+            events: vec![InputEvent::PointerDown {
+                screen_px: Vec2::new(350.0, 100.0), // inside rect 1
+                shift: true,
+                button: 0,
+            }],
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+        assert!(engine.selected.contains(&id0));
+        assert!(engine.selected.contains(&id1));
+
+        // Click on rect 0 again (already selected, no shift) → PendingSelectionMove.
+        engine.tick(&InputBatch {
+            events: vec![InputEvent::PointerDown {
+                screen_px: Vec2::new(100.0, 100.0),
+                shift: false,
+                button: 0,
+            }],
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+        assert!(matches!(
+            engine.drag_state,
+            DragState::PendingSelectionMove(_)
+        ));
+
+        // Drag beyond threshold (dx=40, dy=25 world units at zoom=1).
+        engine.tick(&InputBatch {
+            events: vec![InputEvent::PointerMove {
+                screen_px: Vec2::new(140.0, 125.0),
+                buttons: 1,
+            }],
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+        assert!(matches!(engine.drag_state, DragState::SelectionMove(_)));
+
+        let pos0 = engine.doc.rects[0].pos;
+        let pos1 = engine.doc.rects[1].pos;
+        assert_vec2_approx(pos0, Vec2::new(origin0.x + 40.0, origin0.y + 25.0), 1e-4);
+        assert_vec2_approx(pos1, Vec2::new(origin1.x + 40.0, origin1.y + 25.0), 1e-4);
+
+        // Release.
+        engine.tick(&InputBatch {
+            events: vec![InputEvent::PointerUp {
+                screen_px: Vec2::new(140.0, 125.0),
+                button: 0,
+            }],
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+        assert!(matches!(engine.drag_state, DragState::Idle));
+        // Positions retained.
+        assert_vec2_approx(engine.doc.rects[0].pos, pos0, 1e-4);
+        assert_vec2_approx(engine.doc.rects[1].pos, pos1, 1e-4);
+    }
+
+    /// Only selected rects are moved; unselected rects remain in place.
+    #[test]
+    fn multi_selection_drag_does_not_move_unselected_rect() {
+        let mut engine = engine_with_two_rects();
+        let origin1 = engine.doc.rects[1].pos;
+
+        // Select only rect 0.
+        engine.tick(&InputBatch {
+            events: vec![InputEvent::PointerDown {
+                screen_px: Vec2::new(100.0, 100.0),
+                shift: false,
+                button: 0,
+            }],
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+        // Enter PendingSelectionMove on rect 0.
+        engine.tick(&InputBatch {
+            events: vec![InputEvent::PointerDown {
+                screen_px: Vec2::new(100.0, 100.0),
+                shift: false,
+                button: 0,
+            }],
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+        // Drag.
+        engine.tick(&InputBatch {
+            events: vec![InputEvent::PointerMove {
+                screen_px: Vec2::new(150.0, 100.0),
+                buttons: 1,
+            }],
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+
+        // Rect 1 (unselected) must not have moved.
+        assert_vec2_approx(engine.doc.rects[1].pos, origin1, 1e-4);
+    }
+
+    /// Dragging preserves no cumulative drift across multiple sequential move
+    /// events: the final position equals origin + total_delta, not a sum of
+    /// per-frame deltas applied on top of each other.
+    #[test]
+    fn selection_drag_no_cumulative_drift() {
+        let mut engine = engine_with_one_rect();
+        let origin = engine.doc.rects[0].pos;
+
+        // Select.
+        engine.tick(&InputBatch {
+            events: vec![InputEvent::PointerDown {
+                screen_px: Vec2::new(100.0, 100.0),
+                shift: false,
+                button: 0,
+            }],
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+        // PendingSelectionMove.
+        engine.tick(&InputBatch {
+            events: vec![InputEvent::PointerDown {
+                screen_px: Vec2::new(100.0, 100.0),
+                shift: false,
+                button: 0,
+            }],
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+
+        // Many small moves — each frame advances 1 px.
+        for i in 1..=50u32 {
+            engine.tick(&InputBatch {
+                events: vec![InputEvent::PointerMove {
+                    screen_px: Vec2::new(100.0 + i as f32, 100.0),
+                    buttons: 1,
+                }],
+                tool: ToolMode::Select,
+                ..Default::default()
+            });
+        }
+
+        // Final position should be origin + 50 px, NOT origin + sum(1..=50).
+        assert_vec2_approx(
+            engine.doc.rects[0].pos,
+            Vec2::new(origin.x + 50.0, origin.y),
+            1e-3,
+        );
+    }
+
+    #[test]
+    fn cursor_defaults_to_default_with_not_hover() {
+        let engine = engine_with_one_rect();
+        let cursor = engine.compute_cursor(&ToolMode::Select);
+        assert_eq!(cursor, CursorStyle::Default);
+    }
+
+    #[test]
+    fn cursor_is_resize_tl_br_when_hovering_tl_handle() {
+        let mut engine = engine_with_one_rect();
+        let id = engine.doc.rects[0].id;
+        engine.selected = vec![id];
+
+        engine.hover_screen_px = Some(Vec2::new(50.0, 50.0));
+        let cursor = engine.compute_cursor(&ToolMode::Select);
+        assert_eq!(cursor, CursorStyle::ResizeTlBr);
+    }
+
+    #[test]
+    fn cursor_is_resize_tr_bl_when_hovering_tr_handle() {
+        let mut engine = engine_with_one_rect();
+        let id = engine.doc.rects[0].id;
+        engine.selected = vec![id];
+
+        engine.hover_screen_px = Some(Vec2::new(150.0, 50.0));
+        let cursor = engine.compute_cursor(&ToolMode::Select);
+        assert_eq!(cursor, CursorStyle::ResizeTrBl);
+    }
+
+    #[test]
+    fn cursor_is_default_when_outside_handle_radius() {
+        let mut engine = engine_with_one_rect();
+        let id = engine.doc.rects[0].id;
+        engine.selected = vec![id];
+        // Far from any handle
+        engine.hover_screen_px = Some(Vec2::new(100.0, 100.0)); // center of rect
+        let cursor = engine.compute_cursor(&ToolMode::Select);
+        assert_eq!(cursor, CursorStyle::Default);
+    }
+
+    #[test]
+    fn cursor_is_move_during_selection_drag() {
+        let mut engine = engine_with_one_rect();
+        engine.drag_state = DragState::PendingSelectionMove(PendingSelectionMove {
+            hit_id: engine.doc.rects[0].id,
+            start_screen_px: Vec2::new(100.0, 100.0),
+            start_world: Vec2::new(100.0, 100.0),
+            previous_selection: vec![],
+            collapse_on_click: false,
+        });
+        let cursor = engine.compute_cursor(&ToolMode::Select);
+        assert_eq!(cursor, CursorStyle::Move);
+    }
+
+    fn start_move_drag(engine: &mut Engine, from: Vec2, to: Vec2) -> EngineOutput {
+        engine.tick(&InputBatch {
+            events: vec![
+                InputEvent::PointerDown {
+                    screen_px: from,
+                    shift: false,
+                    button: 0,
+                },
+                InputEvent::PointerMove {
+                    screen_px: to,
+                    buttons: 1,
+                },
+            ],
+            tool: ToolMode::Select,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn pressing_unselected_rect_promotes_to_move_past_threshold() {
+        let mut engine = engine_with_one_rect();
+        let id = engine.doc.rects[0].id;
+
+        // 5px is inside the 6px threshold: selected, but not moved.
+        start_move_drag(
+            &mut engine,
+            Vec2::new(100.0, 100.0),
+            Vec2::new(104.0, 103.0),
+        );
+        assert_eq!(engine.selected, vec![id]);
+        assert!(matches!(
+            engine.drag_state,
+            DragState::PendingSelectionMove(_)
+        ));
+        assert_vec2_approx(engine.doc.rects[0].pos, Vec2::new(50.0, 50.0), 1e-6);
+
+        engine.tick(&InputBatch {
+            events: vec![InputEvent::PointerMove {
+                screen_px: Vec2::new(130.0, 120.0),
+                buttons: 1,
+            }],
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+        assert!(matches!(engine.drag_state, DragState::SelectionMove(_)));
+        assert_vec2_approx(engine.doc.rects[0].pos, Vec2::new(80.0, 70.0), 1e-6);
+    }
+
+    #[test]
+    fn sub_threshold_click_on_unselected_rect_moves_nothing() {
+        let mut engine = engine_with_two_rects();
+        let before = engine.doc.rects.clone();
+
+        start_move_drag(
+            &mut engine,
+            Vec2::new(100.0, 100.0),
+            Vec2::new(103.0, 102.0),
+        );
+        engine.tick(&InputBatch {
+            events: vec![InputEvent::PointerUp {
+                screen_px: Vec2::new(103.0, 102.0),
+                button: 0,
+            }],
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+
+        assert_eq!(engine.doc.rects, before);
+        assert_eq!(engine.selected, vec![before[0].id]);
+        assert!(matches!(engine.drag_state, DragState::Idle));
+        assert!(engine.undo_stack.is_empty());
+    }
+
+    #[test]
+    fn shift_press_moves_selection_as_it_stands_after_toggle() {
+        let mut engine = engine_with_two_rects();
+        let ids: Vec<NodeId> = engine.doc.rects.iter().map(|r| r.id).collect();
+        engine.selected = vec![ids[0]];
+
+        // Shift-press adds rect 1, then the drag carries both.
+        engine.tick(&InputBatch {
+            events: vec![
+                InputEvent::PointerDown {
+                    screen_px: Vec2::new(350.0, 100.0),
+                    shift: true,
+                    button: 0,
+                },
+                InputEvent::PointerMove {
+                    screen_px: Vec2::new(360.0, 110.0),
+                    buttons: 1,
+                },
+                InputEvent::PointerUp {
+                    screen_px: Vec2::new(360.0, 110.0),
+                    button: 0,
+                },
+            ],
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+        assert_eq!(engine.selected, ids);
+        assert_vec2_approx(engine.doc.rects[0].pos, Vec2::new(60.0, 60.0), 1e-6);
+        assert_vec2_approx(engine.doc.rects[1].pos, Vec2::new(310.0, 60.0), 1e-6);
+
+        // Shift-press toggling rect 1 back off leaves nothing to drag.
+        engine.tick(&InputBatch {
+            events: vec![
+                InputEvent::PointerDown {
+                    screen_px: Vec2::new(360.0, 110.0),
+                    shift: true,
+                    button: 0,
+                },
+                InputEvent::PointerMove {
+                    screen_px: Vec2::new(390.0, 140.0),
+                    buttons: 1,
+                },
+            ],
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+        assert_eq!(engine.selected, vec![ids[0]]);
+        assert!(matches!(engine.drag_state, DragState::Idle));
+        assert_vec2_approx(engine.doc.rects[1].pos, Vec2::new(310.0, 60.0), 1e-6);
+    }
+
+    #[test]
+    fn selection_move_applies_total_delta_from_origins_at_any_zoom() {
+        let mut engine = engine_with_two_rects();
+        engine.camera.zoom = 2.0;
+        engine.selected = engine.doc.rects.iter().map(|r| r.id).collect();
+
+        // Rect 0 spans screen (100, 100)..(300, 300) at 2x.
+        start_move_drag(
+            &mut engine,
+            Vec2::new(150.0, 150.0),
+            Vec2::new(170.0, 150.0),
+        );
+        assert!(matches!(engine.drag_state, DragState::SelectionMove(_)));
+
+        let moves = [(190.0, 160.0), (175.0, 171.0), (210.0, 190.0)];
+        let mut events: Vec<InputEvent> = moves
+            .iter()
+            .map(|&(x, y)| InputEvent::PointerMove {
+                screen_px: Vec2::new(x, y),
+                buttons: 1,
+            })
+            .collect();
+        events.push(InputEvent::PointerUp {
+            screen_px: Vec2::new(210.0, 190.0),
+            button: 0,
+        });
+        engine.tick(&InputBatch {
+            events,
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+
+        // 60x40 screen px at 2x is a 30x20 world move.
+        assert!(matches!(engine.drag_state, DragState::Idle));
+        assert_vec2_approx(engine.doc.rects[0].pos, Vec2::new(80.0, 70.0), 1e-4);
+        assert_vec2_approx(engine.doc.rects[1].pos, Vec2::new(330.0, 70.0), 1e-4);
+    }
+
+    fn snap_dot_count(overlay: &OverlayScene) -> usize {
+        overlay
+            .rects
+            .iter()
+            .filter(|r| r.color == Color::rgba(1.0, 1.0, 1.0, 0.35))
+            .count()
+    }
+
+    #[test]
+    fn snapped_move_lands_on_grid_and_shows_preview_dots() {
+        let mut engine = engine_with_one_rect();
+        engine.selected = vec![engine.doc.rects[0].id];
+        engine.snap_grid = Some(10.0);
+
+        let out = start_move_drag(&mut engine, Vec2::new(100.0, 100.0), Vec2::new(113.0, 96.0));
+
+        // Free while dragging, with 7x7 intersections around it at radius 3 cells.
+        assert_vec2_approx(engine.doc.rects[0].pos, Vec2::new(63.0, 46.0), 1e-4);
+        assert_eq!(snap_dot_count(&out.overlay_scene), 49);
+
+        tick_events(
+            &mut engine,
+            vec![InputEvent::PointerUp {
+                screen_px: Vec2::new(113.0, 96.0),
+                button: 0,
+            }],
+        );
+        assert_vec2_approx(engine.doc.rects[0].pos, Vec2::new(60.0, 50.0), 1e-4);
+
+        // One undo step back to the pre-drag position.
+        tick_events(&mut engine, vec![InputEvent::Undo]);
+        assert_vec2_approx(engine.doc.rects[0].pos, Vec2::new(50.0, 50.0), 1e-4);
+    }
+
+    #[test]
+    fn set_snap_grid_event_toggles_snapping_for_released_moves() {
+        let mut engine = engine_with_one_rect();
+        engine.selected = vec![engine.doc.rects[0].id];
+        let drag_and_release = |engine: &mut Engine, from: Vec2, to: Vec2| {
+            start_move_drag(engine, from, to);
+            engine.tick(&InputBatch {
+                events: vec![InputEvent::PointerUp {
+                    screen_px: to,
+                    button: 0,
+                }],
+                tool: ToolMode::Select,
+                ..Default::default()
+            });
+        };
+
+        engine.tick(&InputBatch {
+            events: vec![InputEvent::SetSnapGrid { size: Some(25.0) }],
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+        drag_and_release(&mut engine, Vec2::new(100.0, 100.0), Vec2::new(111.0, 87.0));
+        assert!(matches!(engine.drag_state, DragState::Idle));
+        assert_vec2_approx(engine.doc.rects[0].pos, Vec2::new(50.0, 25.0), 1e-4);
+
+        engine.tick(&InputBatch {
+            events: vec![InputEvent::SetSnapGrid { size: None }],
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+        drag_and_release(&mut engine, Vec2::new(60.0, 40.0), Vec2::new(73.0, 47.0));
+        assert_eq!(engine.snap_grid, None);
+        assert_vec2_approx(engine.doc.rects[0].pos, Vec2::new(63.0, 32.0), 1e-4);
+    }
+
+    #[test]
+    fn snap_preview_is_absent_without_snapping_or_drag() {
+        let mut engine = engine_with_one_rect();
+        engine.selected = vec![engine.doc.rects[0].id];
+        let out = start_move_drag(&mut engine, Vec2::new(100.0, 100.0), Vec2::new(113.0, 96.0));
+        assert_eq!(snap_dot_count(&out.overlay_scene), 0);
+
+        let mut engine = engine_with_one_rect();
+        engine.snap_grid = Some(10.0);
+        let out = engine.tick(&InputBatch {
+            events: vec![InputEvent::PointerMove {
+                screen_px: Vec2::new(100.0, 100.0),
+                buttons: 0,
+            }],
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+        assert_eq!(snap_dot_count(&out.overlay_scene), 0);
+    }
+
+    #[test]
+    fn snapped_create_rounds_both_corners_to_grid() {
+        let mut engine = Engine {
+            snap_grid: Some(10.0),
+            ..Engine::with_document(Document::new())
+        };
+
+        drag_create_rect(&mut engine, Vec2::new(12.0, 18.0), Vec2::new(87.0, 61.0));
+
+        let rect = engine.doc.rects[0].clone();
+        assert_vec2_approx(rect.pos, Vec2::new(10.0, 20.0), 1e-4);
+        assert_vec2_approx(rect.size, Vec2::new(80.0, 40.0), 1e-4);
+    }
+
+    #[test]
+    fn move_stops_at_artboard_edge_when_clipping() {
+        let mut engine = engine_with_one_rect();
+        engine.doc.artboards.push(Artboard {
+            pos: Vec2::new(0.0, 0.0),
+            size: Vec2::new(200.0, 200.0),
+        });
+        engine.clip_children_to_artboard = true;
+        engine.selected = vec![engine.doc.rects[0].id];
+
+        start_move_drag(&mut engine, Vec2::new(100.0, 100.0), Vec2::new(250.0, 90.0));
+
+        assert_vec2_approx(engine.doc.rects[0].pos, Vec2::new(100.0, 40.0), 1e-4);
+    }
+
+    #[test]
+    fn resize_is_clamped_to_artboard_when_clipping() {
+        let mut engine = engine_with_one_rect();
+        engine.doc.artboards.push(Artboard {
+            pos: Vec2::new(0.0, 0.0),
+            size: Vec2::new(200.0, 200.0),
+        });
+        engine.clip_children_to_artboard = true;
+        engine.selected = vec![engine.doc.rects[0].id];
+
+        // Drag the south-east corner well past the artboard.
+        start_move_drag(
+            &mut engine,
+            Vec2::new(150.0, 150.0),
+            Vec2::new(400.0, 400.0),
+        );
+
+        let rect = engine.doc.rects[0].clone();
+        assert_vec2_approx(rect.pos, Vec2::new(50.0, 50.0), 1e-4);
+        assert_vec2_approx(rect.size, Vec2::new(150.0, 150.0), 1e-4);
+    }
+
+    #[test]
+    fn fit_to_content_frames_rect_bounds_with_margin() {
+        let mut doc = Document::new();
+        for (x, y) in [(5000.0, -3000.0), (5400.0, -2800.0)] {
+            let id = doc.alloc_id();
+            doc.rects.push(RectNode::new(
+                id,
+                Vec2::new(x, y),
+                Vec2::new(100.0, 100.0),
+                Color::WHITE,
+            ));
+        }
+        let mut engine = Engine::with_document(doc);
+        let viewport = Vec2::new(800.0, 600.0);
+
+        engine.tick(&InputBatch {
+            events: vec![InputEvent::FitToContent {
+                viewport_px: viewport,
+            }],
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+
+        // Box spans (5000, -3000)..(5500, -2700).
+        let min = engine.camera.world_to_screen(Vec2::new(5000.0, -3000.0));
+        let max = engine.camera.world_to_screen(Vec2::new(5500.0, -2700.0));
+        for corner in [min, max] {
+            assert!(corner.x > 0.0 && corner.x < viewport.x, "{corner:?}");
+            assert!(corner.y > 0.0 && corner.y < viewport.y, "{corner:?}");
+        }
+        // Width-bound: the margin is all that's left on the left and right.
+        assert_approx(min.x, viewport.x - max.x, 1e-3);
+        assert!(min.x > 0.0);
+    }
+
+    #[test]
+    fn fit_to_content_respects_zoom_clamp_and_resets_when_empty() {
+        let mut engine = engine_with_one_rect();
+        engine.doc.rects[0].size = Vec2::new(0.5, 0.5);
+        engine.fit_to_content(Vec2::new(800.0, 600.0));
+        assert_approx(engine.camera.zoom, crate::camera::MAX_ZOOM, 1e-6);
+
+        engine.doc.rects.clear();
+        engine.fit_to_content(Vec2::new(800.0, 600.0));
+        assert_eq!(engine.camera, Camera::default());
+    }
+
+    #[test]
+    fn pending_fit_frames_loaded_document_on_first_viewport() {
+        let json = engine_with_two_rects().export_document();
+
+        let mut engine = Engine::new();
+        engine.load_document(&json).unwrap();
+        engine.fit_document_on_first_viewport();
+        assert_eq!(engine.camera, Camera::default());
+
+        // Document spans (50, 50)..(400, 150): width-bound in an 700x600 viewport.
+        engine.set_viewport(Vec2::new(700.0, 600.0));
+        assert_approx(engine.camera.zoom, 2.0, 1e-5);
+        let min = engine.camera.world_to_screen(Vec2::new(50.0, 50.0));
+        let max = engine.camera.world_to_screen(Vec2::new(400.0, 150.0));
+        assert_vec2_approx(min, Vec2::new(0.0, 200.0), 1e-3);
+        assert_vec2_approx(max, Vec2::new(700.0, 400.0), 1e-3);
+
+        // Only the first viewport triggers the fit.
+        let framed = engine.camera;
+        engine.set_viewport(Vec2::new(900.0, 600.0));
+        assert_eq!(engine.camera, framed);
+    }
+
+    #[test]
+    fn event_observer_sees_every_event_in_order() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        let mut engine = engine_with_one_rect();
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let sink = Rc::clone(&seen);
+        engine.set_event_observer(Box::new(move |ev| {
+            sink.borrow_mut().push(format!("{ev:?}"))
+        }));
+
+        let events = vec![
+            InputEvent::PointerMove {
+                screen_px: Vec2::new(10.0, 10.0),
+                buttons: 0,
+            },
+            InputEvent::CameraPanByScreenDelta {
+                delta_px: Vec2::new(5.0, 0.0),
+            },
+            InputEvent::Undo,
+        ];
+        let expected: Vec<String> = events.iter().map(|ev| format!("{ev:?}")).collect();
+        engine.tick(&InputBatch {
+            events: events.clone(),
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+        assert_eq!(*seen.borrow(), expected);
+
+        engine.clear_event_observer();
+        engine.tick(&InputBatch {
+            events: events.clone(),
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+        assert_eq!(seen.borrow().len(), 3);
+    }
+
+    fn resize_corner(from: Vec2, to: Vec2) -> RectNode {
+        let mut engine = engine_with_one_rect();
+        engine.selected = vec![engine.doc.rects[0].id];
+        start_move_drag(&mut engine, from, to);
+        assert!(matches!(engine.drag_state, DragState::Resize(_)));
+        engine.tick(&InputBatch {
+            events: vec![InputEvent::PointerUp {
+                screen_px: to,
+                button: 0,
+            }],
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+        assert!(matches!(engine.drag_state, DragState::Idle));
+        engine.doc.rects[0].clone()
+    }
+
+    #[test]
+    fn each_corner_resizes_with_opposite_corner_fixed() {
+        // Rect spans (50, 50)..(150, 150); each corner is pulled 20 out and 10 up/down.
+        let cases = [
+            ((50.0, 50.0), (30.0, 40.0), (30.0, 40.0)),
+            ((150.0, 50.0), (170.0, 40.0), (50.0, 40.0)),
+            ((50.0, 150.0), (30.0, 160.0), (30.0, 50.0)),
+            ((150.0, 150.0), (170.0, 160.0), (50.0, 50.0)),
+        ];
+        for ((fx, fy), (tx, ty), (px, py)) in cases {
+            let rect = resize_corner(Vec2::new(fx, fy), Vec2::new(tx, ty));
+            assert_vec2_approx(rect.pos, Vec2::new(px, py), 1e-4);
+            assert_vec2_approx(rect.size, Vec2::new(120.0, 110.0), 1e-4);
+        }
+    }
+
+    #[test]
+    fn dragging_corner_past_opposite_corner_flips_with_positive_size() {
+        // SE corner dragged up-left past the NW anchor at (50, 50).
+        let rect = resize_corner(Vec2::new(150.0, 150.0), Vec2::new(20.0, 10.0));
+        assert_vec2_approx(rect.pos, Vec2::new(20.0, 10.0), 1e-4);
+        assert_vec2_approx(rect.size, Vec2::new(30.0, 40.0), 1e-4);
+
+        // NW corner dragged down-right past the SE anchor at (150, 150).
+        let rect = resize_corner(Vec2::new(50.0, 50.0), Vec2::new(190.0, 170.0));
+        assert_vec2_approx(rect.pos, Vec2::new(150.0, 150.0), 1e-4);
+        assert_vec2_approx(rect.size, Vec2::new(40.0, 20.0), 1e-4);
+    }
+
+    #[test]
+    fn released_resize_snaps_pos_and_size_to_grid() {
+        let mut engine = engine_with_one_rect();
+        engine.doc.rects[0].pos = Vec2::new(48.0, 48.0);
+        engine.snap_grid = Some(8.0);
+        engine.selected = vec![engine.doc.rects[0].id];
+
+        // Drag the south-east corner to an off-grid point.
+        start_move_drag(
+            &mut engine,
+            Vec2::new(148.0, 148.0),
+            Vec2::new(171.0, 163.0),
+        );
+        assert_approx(engine.doc.rects[0].size.x, 123.0, 1e-4);
+        tick_events(
+            &mut engine,
+            vec![InputEvent::PointerUp {
+                screen_px: Vec2::new(171.0, 163.0),
+                button: 0,
+            }],
+        );
+
+        let rect = engine.doc.rects[0].clone();
+        assert_vec2_approx(rect.pos, Vec2::new(48.0, 48.0), 1e-4);
+        assert_approx(rect.pos.x + rect.size.x, 168.0, 1e-4);
+        assert_approx(rect.pos.y + rect.size.y, 160.0, 1e-4);
+        assert_approx(rect.size.x % 8.0, 0.0, 1e-4);
+        assert_approx(rect.size.y % 8.0, 0.0, 1e-4);
+    }
+
+    #[test]
+    fn marquee_candidates_match_intersected_rects_and_are_tinted() {
+        let mut engine = engine_with_two_rects();
+        let first = engine.doc.rects[0].id;
+        engine.drag_state = DragState::Marquee(MarqueeDrag {
+            start_world: Vec2::new(0.0, 0.0),
+            current_world: Vec2::new(60.0, 60.0),
+            additive: false,
+        });
+
+        assert_eq!(engine.marquee_candidates(), vec![first]);
+
+        let overlay = engine.update_overlay_scene(&ToolMode::Select);
+        let tinted = overlay
+            .rects
+            .iter()
+            .filter(|r| r.color == Color::rgba(0.2, 0.6, 1.0, 0.18))
+            .count();
+        assert_eq!(tinted, 1);
+
+        engine.drag_state = DragState::Idle;
+        assert!(engine.marquee_candidates().is_empty());
+    }
+
+    #[test]
+    fn multi_selection_without_per_node_handles_only_handles_group_box() {
+        let mut engine = engine_with_two_rects();
+        engine.selected = engine.doc.rects.iter().map(|r| r.id).collect();
+        let handle_size = |overlay: &OverlayScene| {
+            overlay
+                .instances(1.0)
+                .iter()
+                .filter(|r| r.size == [8.0, 8.0])
+                .map(|r| r.pos)
+                .collect::<Vec<_>>()
+        };
+
+        let with_handles = engine.update_overlay_scene(&ToolMode::Select);
+        assert_eq!(handle_size(&with_handles).len(), 16);
+
+        engine.overlay_theme.show_per_node_handles = false;
+        let group_only = engine.update_overlay_scene(&ToolMode::Select);
+        let handles = handle_size(&group_only);
+
+        // Group box spans 50..400 x 50..150; only its 8 handles remain.
+        assert_eq!(handles.len(), 8);
+        assert!(handles.contains(&[46.0, 46.0]));
+        assert!(handles.contains(&[396.0, 146.0]));
+    }
+
+    /// Run `events` once as a single batch and once as one batch per event,
+    /// returning both engines.
+    fn run_single_and_split(
+        setup: impl Fn() -> Engine,
+        events: Vec<InputEvent>,
+    ) -> (Engine, Engine) {
+        let mut single = setup();
+        single.tick(&InputBatch {
+            events: events.clone(),
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+
+        let mut split = setup();
+        for event in events {
+            split.tick(&InputBatch {
+                events: vec![event],
+                tool: ToolMode::Select,
+                ..Default::default()
+            });
+        }
+
+        (single, split)
+    }
+
+    #[test]
+    fn selection_move_split_across_batches_matches_single_batch() {
+        let setup = || {
+            let mut engine = engine_with_two_rects();
+            engine.selected = engine.doc.rects.iter().map(|r| r.id).collect();
+            engine
+        };
+        let (single, split) = run_single_and_split(
+            setup,
+            vec![
+                InputEvent::PointerDown {
+                    screen_px: Vec2::new(100.0, 100.0),
+                    shift: false,
+                    button: 0,
+                },
+                InputEvent::PointerMove {
+                    screen_px: Vec2::new(120.0, 110.0),
+                    buttons: 1,
+                },
+                InputEvent::PointerMove {
+                    screen_px: Vec2::new(140.0, 130.0),
+                    buttons: 1,
+                },
+                InputEvent::PointerUp {
+                    screen_px: Vec2::new(140.0, 130.0),
+                    button: 0,
+                },
+            ],
+        );
+
+        assert_eq!(split.doc, single.doc);
+        assert_eq!(split.selected, single.selected);
+        assert!(matches!(split.drag_state, DragState::Idle));
+        // Origins were snapshotted once, so the total delta is applied exactly once.
+        assert_vec2_approx(split.doc.rects[0].pos, Vec2::new(90.0, 80.0), 1e-4);
+        assert_vec2_approx(split.doc.rects[1].pos, Vec2::new(340.0, 80.0), 1e-4);
+    }
+
+    #[test]
+    fn marquee_split_across_batches_matches_single_batch() {
+        let (single, split) = run_single_and_split(
+            engine_with_two_rects,
+            vec![
+                InputEvent::PointerDown {
+                    screen_px: Vec2::new(10.0, 10.0),
+                    shift: false,
+                    button: 0,
+                },
+                InputEvent::PointerMove {
+                    screen_px: Vec2::new(60.0, 60.0),
+                    buttons: 1,
+                },
+                InputEvent::PointerMove {
+                    screen_px: Vec2::new(320.0, 80.0),
+                    buttons: 1,
+                },
+                InputEvent::PointerUp {
+                    screen_px: Vec2::new(320.0, 80.0),
+                    button: 0,
+                },
+            ],
+        );
+
+        assert_eq!(split.selected, single.selected);
+        assert_eq!(split.selected.len(), 2);
+        assert_eq!(split.doc, single.doc);
+    }
+
+    #[test]
+    fn reset_drag_state_mid_move_reverts_positions_and_goes_idle() {
+        let mut engine = engine_with_one_rect();
+        engine.selected = vec![engine.doc.rects[0].id];
+        let origin = engine.doc.rects[0].pos;
+
+        start_move_drag(
+            &mut engine,
+            Vec2::new(100.0, 100.0),
+            Vec2::new(160.0, 140.0),
+        );
+        assert!(matches!(engine.drag_state, DragState::SelectionMove(_)));
+
+        engine.reset_drag_state();
+
+        assert!(matches!(engine.drag_state, DragState::Idle));
+        assert_vec2_approx(engine.doc.rects[0].pos, origin, 1e-6);
+    }
+
+    #[test]
+    fn circle_handle_theme_marks_handle_instances_as_ellipses() {
+        let mut engine = engine_with_one_rect();
+        engine.selected = vec![engine.doc.rects[0].id];
+        let ellipses = |overlay: &OverlayScene| {
+            overlay
+                .instances(1.0)
+                .iter()
+                .filter(|r| r.shape == InstanceShape::Ellipse)
+                .count()
+        };
+
+        let square = engine.update_overlay_scene(&ToolMode::Select);
+        assert_eq!(ellipses(&square), 0);
+
+        engine.overlay_theme.handle_shape = HandleShape::Circle;
+        let circle = engine.update_overlay_scene(&ToolMode::Select);
+        assert_eq!(ellipses(&circle), 8);
+        assert!(
+            circle
+                .instances(1.0)
+                .iter()
+                .filter(|r| r.shape == InstanceShape::Ellipse)
+                .all(|r| r.size == [8.0, 8.0])
+        );
+    }
+
+    /// Helper: three stacked 100x100 rects offset by 20px, bottom to top.
+    fn engine_with_stacked_rects() -> Engine {
+        let rects: Vec<(Vec2, Vec2)> = (0..3)
+            .map(|i| {
+                let offset = i as f32 * 20.0;
+                (Vec2::new(offset, offset), Vec2::new(100.0, 100.0))
+            })
+            .collect();
+        engine_with_unselected_rects_at(&rects)
+    }
+
+    fn order_of(engine: &Engine) -> Vec<NodeId> {
+        engine.doc.rects.iter().map(|r| r.id).collect()
+    }
+
+    #[test]
+    fn bring_to_front_moves_selection_on_top_contiguously() {
+        let mut engine = engine_with_stacked_rects();
+        let ids = order_of(&engine);
+        // (50, 50) is covered by all three rects; the top one wins.
+        assert_eq!(
+            engine.check_collide_rects(Vec2::new(50.0, 50.0)),
+            Some(ids[2])
+        );
+
+        engine.selected = vec![ids[1], ids[0]];
+        engine.tick(&InputBatch {
+            events: vec![InputEvent::BringToFront],
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+
+        assert_eq!(order_of(&engine), vec![ids[2], ids[0], ids[1]]);
+        assert_eq!(
+            engine.check_collide_rects(Vec2::new(50.0, 50.0)),
+            Some(ids[1])
+        );
+
+        engine.undo();
+        assert_eq!(order_of(&engine), ids);
+    }
+
+    #[test]
+    fn send_to_back_moves_selection_to_bottom_contiguously() {
+        let mut engine = engine_with_stacked_rects();
+        let ids = order_of(&engine);
+
+        engine.selected = vec![ids[2], ids[1]];
+        engine.tick(&InputBatch {
+            events: vec![InputEvent::SendToBack],
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+
+        assert_eq!(order_of(&engine), vec![ids[1], ids[2], ids[0]]);
+        assert_eq!(
+            engine.check_collide_rects(Vec2::new(50.0, 50.0)),
+            Some(ids[0])
+        );
+
+        // Already at the back: no extra history entry.
+        let history = engine.undo_stack.len();
+        engine.tick(&InputBatch {
+            events: vec![InputEvent::SendToBack],
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+        assert_eq!(engine.undo_stack.len(), history);
+    }
+
+    #[test]
+    fn move_selection_above_restacks_bottom_node_over_middle() {
+        let mut engine = engine_with_stacked_rects();
+        let ids: Vec<NodeId> = engine.doc.rects.iter().map(|r| r.id).collect();
+        // Covered by bottom and middle only.
+        let probe = Vec2::new(30.0, 30.0);
+        assert_eq!(engine.check_collide_rects(probe), Some(ids[1]));
+
+        engine.selected = vec![ids[0]];
+        engine.move_selection_above(ids[1]);
+
+        assert_eq!(engine.check_collide_rects(probe), Some(ids[0]));
+        let order: Vec<NodeId> = engine.doc.rects.iter().map(|r| r.id).collect();
+        assert_eq!(order, vec![ids[1], ids[0], ids[2]]);
+
+        engine.tick(&InputBatch {
+            events: vec![InputEvent::Undo],
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+        let order: Vec<NodeId> = engine.doc.rects.iter().map(|r| r.id).collect();
+        assert_eq!(order, ids);
+    }
+
+    #[test]
+    fn move_selection_below_ignores_selected_or_missing_targets() {
+        let mut engine = engine_with_stacked_rects();
+        let ids: Vec<NodeId> = engine.doc.rects.iter().map(|r| r.id).collect();
+        engine.selected = vec![ids[2], ids[1]];
+
+        engine.move_selection_below(ids[1]);
+        engine.move_selection_below(NodeId(999));
+        let order: Vec<NodeId> = engine.doc.rects.iter().map(|r| r.id).collect();
+        assert_eq!(order, ids);
+
+        engine.move_selection_below(ids[0]);
+        let order: Vec<NodeId> = engine.doc.rects.iter().map(|r| r.id).collect();
+        assert_eq!(order, vec![ids[1], ids[2], ids[0]]);
+    }
+
+    #[test]
+    fn large_selection_emits_one_compact_box_per_node() {
+        let mut engine = engine_with_rect_grid();
+        engine.selected = engine.doc.rects.iter().take(1000).map(|r| r.id).collect();
+
+        let overlay = engine.update_overlay_scene(&ToolMode::Select);
+
+        // Previously 12 full instances per node went over the wire.
+        assert_eq!(overlay.selection_boxes.len(), 1000);
+        assert!(overlay.rects.is_empty());
+        assert_eq!(overlay.instance_count(), 12_000);
+        assert_eq!(overlay.instances(engine.camera.zoom).len(), 12_000);
+    }
+
+    #[test]
+    fn selection_box_expands_to_outline_then_handles() {
+        let mut engine = engine_with_one_rect();
+        engine.selected = vec![engine.doc.rects[0].id];
+        engine.camera.zoom = 2.0;
+
+        let instances = engine
+            .update_overlay_scene(&ToolMode::Select)
+            .instances(engine.camera.zoom);
+
+        assert_eq!(instances.len(), 12);
+        // Top outline bar is 2 screen px thick.
+        assert_eq!(instances[0].pos, [50.0, 50.0]);
+        assert_eq!(instances[0].size, [100.0, 1.0]);
+        // Bottom-right corner handle is 8 screen px, centred on the corner.
+        assert_eq!(instances[7].pos, [148.0, 148.0]);
+        assert_eq!(instances[7].size, [4.0, 4.0]);
+    }
+
+    #[test]
+    fn debug_axes_add_origin_geometry_only_when_enabled() {
+        let mut engine = engine_with_one_rect();
+        engine.set_viewport(Vec2::new(800.0, 600.0));
+        engine.camera.pan = Vec2::new(-100.0, -50.0);
+        let batch = InputBatch {
+            events: vec![],
+            tool: ToolMode::Select,
+            ..Default::default()
+        };
+        assert!(engine.tick(&batch).overlay_scene.rects.is_empty());
+
+        engine.set_debug_axes(true);
+        let out = engine.tick(&batch);
+        assert!(out.needs_redraw);
+        let rects = &out.overlay_scene.rects;
+        assert_eq!(rects.len(), 3);
+        // X axis spans the visible width through y = 0.
+        assert_approx(rects[0].pos[0], -100.0, 1e-4);
+        assert_approx(rects[0].size[0], 800.0, 1e-4);
+        assert_approx(rects[0].pos[1] + rects[0].size[1] * 0.5, 0.0, 1e-6);
+        // Y axis spans the visible height through x = 0.
+        assert_approx(rects[1].pos[1], -50.0, 1e-4);
+        assert_approx(rects[1].size[1], 600.0, 1e-4);
+        assert_approx(rects[1].pos[0] + rects[1].size[0] * 0.5, 0.0, 1e-6);
+        assert_eq!(rects[2].shape, InstanceShape::Ellipse);
+    }
+
+    #[test]
+    fn grid_lines_cover_only_the_visible_world() {
+        let mut engine = engine_with_one_rect();
+        engine.set_viewport(Vec2::new(800.0, 600.0));
+        engine.camera.pan = Vec2::new(-15.0, 30.0);
+        engine.camera.zoom = 2.0;
+        let batch = InputBatch {
+            events: vec![],
+            tool: ToolMode::Select,
+            ..Default::default()
+        };
+        assert!(engine.tick(&batch).render_scene.grid.is_empty());
+
+        // Visible world is x -15..385, y 30..330.
+        engine.set_grid_step(Some(50.0));
+        let grid = engine.tick(&batch).render_scene.grid;
+        let centers = |vertical: bool| -> Vec<f32> {
+            grid.iter()
+                .filter(|r| (r.size[0] < r.size[1]) == vertical)
+                .map(|r| {
+                    let axis = if vertical { 0 } else { 1 };
+                    r.pos[axis] + r.size[axis] * 0.5
+                })
+                .collect()
+        };
+        assert_eq!(grid.len(), 8 + 6);
+        assert_eq!(
+            centers(true),
+            vec![0.0, 50.0, 100.0, 150.0, 200.0, 250.0, 300.0, 350.0]
+        );
+        assert_eq!(
+            centers(false),
+            vec![50.0, 100.0, 150.0, 200.0, 250.0, 300.0]
+        );
+        // One screen pixel thick, spanning the visible world.
+        assert_approx(grid[0].size[0], 0.5, 1e-6);
+        assert_approx(grid[0].pos[1], 30.0, 1e-4);
+        assert_approx(grid[0].size[1], 300.0, 1e-4);
+    }
+
+    #[test]
+    fn grid_step_coarsens_when_lines_would_crowd() {
+        let mut engine = engine_with_one_rect();
+        engine.set_viewport(Vec2::new(800.0, 600.0));
+        engine.set_grid_step(Some(10.0));
+        engine.camera.zoom = 0.25;
+        let grid = engine
+            .tick(&InputBatch {
+                events: vec![],
+                tool: ToolMode::Select,
+                ..Default::default()
+            })
+            .render_scene
+            .grid;
+
+        // 10 world units is 2.5px; doubling twice gives 40 (10px on screen).
+        // Visible world is 0..3200 by 0..2400.
+        assert_eq!(grid.len(), 81 + 61);
+        assert_approx(grid[1].pos[0] + grid[1].size[0] * 0.5, 40.0, 1e-4);
+    }
+
+    #[test]
+    fn offscreen_rects_are_culled_from_render_scene() {
+        let mut doc = Document::new();
+        // Inside, straddling the right edge, fully right, fully above.
+        for pos in [
+            Vec2::new(100.0, 100.0),
+            Vec2::new(750.0, 300.0),
+            Vec2::new(900.0, 100.0),
+            Vec2::new(100.0, -200.0),
+        ] {
+            let id = doc.alloc_id();
+            doc.rects.push(RectNode::new(
+                id,
+                pos,
+                Vec2::new(100.0, 100.0),
+                Color::rgba(0.5, 0.5, 0.5, 1.0),
+            ));
+        }
+        let mut engine = Engine::with_document(doc);
+        let batch = InputBatch {
+            events: vec![],
+            tool: ToolMode::Select,
+            ..Default::default()
+        };
+        // No viewport yet: nothing can be judged offscreen.
+        assert_eq!(engine.tick(&batch).render_scene.rects.len(), 4);
+
+        engine.set_viewport(Vec2::new(800.0, 600.0));
+        engine.selected = vec![engine.doc.rects[2].id];
+        let out = engine.tick(&batch);
+        let positions: Vec<[f32; 2]> = out.render_scene.rects.iter().map(|r| r.pos).collect();
+        assert_eq!(positions, vec![[100.0, 100.0], [750.0, 300.0]]);
+        // Offscreen selection still gets its chrome.
+        assert_eq!(out.overlay_scene.selection_boxes.len(), 1);
+
+        engine.set_cull_offscreen(false);
+        assert_eq!(engine.tick(&batch).render_scene.rects.len(), 4);
+    }
+
+    /// Deterministic xorshift stream in `0.0..1.0` for randomized layouts.
+    fn pseudo_random(seed: &mut u32) -> f32 {
+        *seed ^= *seed << 13;
+        *seed ^= *seed >> 17;
+        *seed ^= *seed << 5;
+        (*seed >> 8) as f32 / (1u32 << 24) as f32
+    }
+
+    fn brute_force_hit(engine: &Engine, world: Vec2) -> Option<NodeId> {
+        let pad = engine.hit_tolerance();
+        engine.doc.rects.iter().rev().find_map(|r| {
+            (world.x >= r.pos.x - pad
+                && world.x <= r.pos.x + r.size.x + pad
+                && world.y >= r.pos.y - pad
+                && world.y <= r.pos.y + r.size.y + pad)
+                .then_some(r.id)
+        })
+    }
+
+    fn brute_force_marquee(engine: &Engine, min: Vec2, max: Vec2) -> Vec<NodeId> {
+        engine
+            .doc
+            .rects
+            .iter()
+            .filter(|r| {
+                r.pos.x < max.x
+                    && r.pos.x + r.size.x > min.x
+                    && r.pos.y < max.y
+                    && r.pos.y + r.size.y > min.y
+            })
+            .map(|r| r.id)
+            .collect()
+    }
+
+    fn assert_index_matches_brute_force(engine: &mut Engine, seed: &mut u32) {
+        for _ in 0..300 {
+            let world = Vec2::new(
+                pseudo_random(seed) * 2400.0 - 200.0,
+                pseudo_random(seed) * 2400.0 - 200.0,
+            );
+            assert_eq!(
+                engine.check_collide_rects(world),
+                brute_force_hit(engine, world),
+                "hit at {world:?}"
+            );
+        }
+        for _ in 0..50 {
+            let a = Vec2::new(pseudo_random(seed) * 2000.0, pseudo_random(seed) * 2000.0);
+            let b = Vec2::new(pseudo_random(seed) * 2000.0, pseudo_random(seed) * 2000.0);
+            engine.drag_state = DragState::Marquee(MarqueeDrag {
+                start_world: a,
+                current_world: b,
+                additive: false,
+            });
+            let min = Vec2::new(a.x.min(b.x), a.y.min(b.y));
+            let max = Vec2::new(a.x.max(b.x), a.y.max(b.y));
+            assert_eq!(
+                engine.marquee_candidates(),
+                brute_force_marquee(engine, min, max),
+                "marquee {a:?}..{b:?}"
+            );
+        }
+        engine.drag_state = DragState::Idle;
+    }
+
+    #[test]
+    fn spatial_index_matches_brute_force_through_edits() {
+        let mut seed = 0x9e37_79b9;
+        let mut doc = Document::new();
+        for _ in 0..400 {
+            let id = doc.alloc_id();
+            // Mostly small rects, with the odd one spanning many index cells.
+            let scale = if pseudo_random(&mut seed) < 0.05 {
+                3000.0
+            } else {
+                150.0
+            };
+            doc.rects.push(RectNode::new(
+                id,
+                Vec2::new(
+                    pseudo_random(&mut seed) * 2000.0,
+                    pseudo_random(&mut seed) * 2000.0,
+                ),
+                Vec2::new(
+                    1.0 + pseudo_random(&mut seed) * scale,
+                    1.0 + pseudo_random(&mut seed) * scale,
+                ),
+                Color::rgba(0.5, 0.5, 0.5, 1.0),
+            ));
+        }
+        let mut engine = Engine::with_document(doc);
+        assert_index_matches_brute_force(&mut engine, &mut seed);
+
+        let tick = |engine: &mut Engine, events: Vec<InputEvent>| {
+            engine.tick(&InputBatch {
+                events,
+                tool: ToolMode::Select,
+                ..Default::default()
+            });
+        };
+        for round in 0..5 {
+            let idx = (pseudo_random(&mut seed) * engine.doc.rects.len() as f32) as usize;
+            engine.selected = vec![engine.doc.rects[idx].id];
+            let delta = Vec2::new(
+                pseudo_random(&mut seed) * 600.0 - 300.0,
+                pseudo_random(&mut seed) * 600.0 - 300.0,
+            );
+            let create_at = Vec2::new(
+                pseudo_random(&mut seed) * 2000.0,
+                pseudo_random(&mut seed) * 2000.0,
+            );
+            let events = match round % 3 {
+                0 => vec![InputEvent::KeyDown {
+                    key: "ArrowRight".to_string(),
+                    shift: true,
+                }],
+                1 => vec![InputEvent::DeleteSelected],
+                _ => vec![
+                    InputEvent::CreateRect {
+                        world: create_at,
+                        size: Vec2::new(400.0, 80.0),
+                        color: Color::rgba(0.2, 0.4, 0.6, 1.0),
+                    },
+                    InputEvent::BringToFront,
+                ],
+            };
+            tick(&mut engine, events);
+            let rect = engine.doc.rects[idx.min(engine.doc.rects.len() - 1)].clone();
+            engine.selected = vec![rect.id];
+            // Drag it by its center when nothing above covers that point.
+            let center = Vec2::new(
+                rect.pos.x + rect.size.x * 0.5,
+                rect.pos.y + rect.size.y * 0.5,
+            );
+            let grab = engine.camera.world_to_screen(center);
+            let to = Vec2::new(grab.x + delta.x, grab.y + delta.y);
+            if engine.check_collide_rects(center) == Some(rect.id) {
+                start_move_drag(&mut engine, grab, to);
+                tick(
+                    &mut engine,
+                    vec![InputEvent::PointerUp {
+                        screen_px: to,
+                        button: 0,
+                    }],
+                );
+            }
+            assert_index_matches_brute_force(&mut engine, &mut seed);
+
+            tick(&mut engine, vec![InputEvent::Undo]);
+            assert_index_matches_brute_force(&mut engine, &mut seed);
+        }
+    }
+
+    #[test]
+    fn bounds_of_encloses_only_the_given_ids() {
+        let engine = engine_with_stacked_rects();
+        let ids: Vec<NodeId> = engine.doc.rects.iter().map(|rect| rect.id).collect();
+
+        // Skips the bottom rect at (0, 0).
+        let (min, max) = engine.bounds_of(&[ids[1], ids[2]]).unwrap();
+        assert_vec2_approx(min, Vec2::new(20.0, 20.0), 1e-6);
+        assert_vec2_approx(max, Vec2::new(140.0, 140.0), 1e-6);
+
+        assert!(engine.bounds_of(&[NodeId(999)]).is_none());
+        assert!(engine.bounds_of(&[]).is_none());
+    }
+
+    #[test]
+    fn load_repairs_stale_next_id() {
+        let mut value = serde_json::to_value(&engine_with_two_rects().doc).unwrap();
+        value["next_id"] = serde_json::json!(1);
+        let json = serde_json::to_string(&value).unwrap();
+
+        let mut engine = engine_with_one_rect();
+        engine.selected = vec![engine.doc.rects[0].id];
+        engine.load_document(&json).unwrap();
+        assert!(engine.selected.is_empty());
+        assert_eq!(engine.doc.next_id, 3);
+
+        let ids: Vec<NodeId> = engine.doc.rects.iter().map(|r| r.id).collect();
+        assert!(!ids.contains(&engine.doc.alloc_id()));
+
+        // An already-ahead counter is kept, so deleted ids stay retired.
+        value["next_id"] = serde_json::json!(40);
+        engine.replace_document(serde_json::from_value(value).unwrap());
+        assert_eq!(engine.doc.next_id, 40);
+    }
+
+    #[test]
+    fn load_rejects_malformed_document() {
+        let mut engine = engine_with_one_rect();
+        let err = engine.load_document(r#"{"next_id": 1, "rects": [{"id": 1}]}"#);
+        assert!(err.is_err());
+        assert_eq!(engine.doc.rects.len(), 1);
+    }
+
+    #[test]
+    fn meta_survives_export_and_load() {
+        let mut engine = engine_with_one_rect();
+        let id = engine.doc.rects[0].id;
+        assert!(engine.set_meta(id, "row", "42"));
+        assert!(!engine.set_meta(NodeId(99), "row", "7"));
+
+        let json = engine.export_document();
+        let mut loaded = Engine::new();
+        loaded.load_document(&json).unwrap();
+
+        assert_eq!(loaded.get_meta(id, "row"), Some("42"));
+        assert_eq!(loaded.get_meta(id, "missing"), None);
+    }
+
+    #[test]
+    fn node_screen_rect_projects_corners_through_camera() {
+        let mut engine = engine_with_one_rect();
+        engine.camera = Camera {
+            pan: Vec2::new(25.0, 40.0),
+            zoom: 2.0,
+        };
+        let id = engine.doc.rects[0].id;
+
+        let (min, max) = engine.node_screen_rect(id).expect("rect exists");
+
+        assert_vec2_approx(min, Vec2::new(50.0, 20.0), 1e-4);
+        assert_vec2_approx(max, Vec2::new(250.0, 220.0), 1e-4);
+        assert!(engine.node_screen_rect(NodeId(999)).is_none());
+    }
+
+    #[test]
+    fn marquee_over_text_bounds_selects_the_text_node() {
+        let mut engine = engine_with_one_rect();
+        let text_id = engine.doc.alloc_id();
+        // 5 chars at size 20 => roughly 60 x 24 world units.
+        engine.doc.texts.push(TextNode {
+            id: text_id,
+            pos: Vec2::new(300.0, 300.0),
+            content: "Hello".to_string(),
+            size: 20.0,
+            color: Color::rgba(0.0, 0.0, 0.0, 1.0),
+        });
+
+        let out = engine.tick(&InputBatch {
+            events: vec![
+                InputEvent::PointerDown {
+                    screen_px: Vec2::new(340.0, 290.0),
+                    shift: false,
+                    button: 0,
+                },
+                InputEvent::PointerMove {
+                    screen_px: Vec2::new(380.0, 310.0),
+                    buttons: 1,
+                },
+                InputEvent::PointerUp {
+                    screen_px: Vec2::new(380.0, 310.0),
+                    button: 0,
+                },
+            ],
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+
+        assert_eq!(engine.selected, vec![text_id]);
+        assert_eq!(out.render_scene.texts.len(), 1);
+        assert_eq!(out.render_scene.texts[0].content, "Hello");
+        assert_eq!(
+            engine.check_collide_rects(Vec2::new(350.0, 310.0)),
+            Some(text_id)
+        );
+        assert_eq!(engine.check_collide_rects(Vec2::new(370.0, 310.0)), None);
+
+        let doc = Document::from_json(&engine.export_document()).expect("document parses");
+        assert_eq!(doc.texts, engine.doc.texts);
+    }
+
+    #[test]
+    fn set_selected_drops_unknown_ids_and_uses_document_order() {
+        let mut engine = engine_with_two_rects();
+        let first = engine.doc.rects[0].id;
+        let second = engine.doc.rects[1].id;
+
+        engine.set_selected(vec![second, NodeId(999), first, second]);
+
+        assert_eq!(engine.selected, vec![first, second]);
+    }
+
+    #[test]
+    fn replaying_a_recorded_session_reaches_the_same_state() {
+        let session = vec![
+            InputBatch {
+                events: vec![
+                    InputEvent::PointerDown {
+                        screen_px: Vec2::new(10.0, 10.0),
+                        shift: false,
+                        button: 0,
+                    },
+                    InputEvent::PointerMove {
+                        screen_px: Vec2::new(90.0, 70.0),
+                        buttons: 1,
+                    },
+                    InputEvent::PointerUp {
+                        screen_px: Vec2::new(90.0, 70.0),
+                        button: 0,
+                    },
+                ],
+                tool: ToolMode::Rect,
+                timestamp_ms: Some(0.0),
+                ..Default::default()
+            },
+            InputBatch {
+                events: vec![
+                    InputEvent::PointerDown {
+                        screen_px: Vec2::new(50.0, 40.0),
+                        shift: false,
+                        button: 0,
+                    },
+                    InputEvent::PointerMove {
+                        screen_px: Vec2::new(120.0, 40.0),
+                        buttons: 1,
+                    },
+                    InputEvent::PointerUp {
+                        screen_px: Vec2::new(120.0, 40.0),
+                        button: 0,
+                    },
+                    InputEvent::CameraPanByScreenDelta {
+                        delta_px: Vec2::new(25.0, -5.0),
+                    },
+                ],
+                tool: ToolMode::Select,
+                timestamp_ms: Some(16.7),
+                ..Default::default()
+            },
+        ];
+
+        let mut live = Engine::with_document(Document::new());
+        for batch in &session {
+            live.tick(batch);
+        }
+
+        let recorded = serde_json::to_string(&session).expect("session serializes");
+        let loaded: Vec<InputBatch> = serde_json::from_str(&recorded).expect("session parses");
+        let mut replayed = Engine::with_document(Document::new());
+        let out = replayed.replay(&loaded);
+
+        assert!(out.is_some());
+        assert_eq!(replayed.doc, live.doc);
+        assert_eq!(replayed.selected, live.selected);
+        assert_eq!(replayed.camera, live.camera);
+        assert_eq!(replayed.doc.rects[0].pos, Vec2::new(80.0, 10.0));
+    }
+
+    /// Stress fixture: a 400x250 grid of 10x10 rects on a 20px pitch, so ids
+    /// map to cells as `row * 400 + col + 1`.
+    fn engine_with_rect_grid() -> Engine {
+        let rects: Vec<(Vec2, Vec2)> = (0..250)
+            .flat_map(|row| {
+                (0..400).map(move |col| {
+                    (
+                        Vec2::new(col as f32 * 20.0, row as f32 * 20.0),
+                        Vec2::new(10.0, 10.0),
+                    )
+                })
+            })
+            .collect();
+        engine_with_unselected_rects_at(&rects)
+    }
+
+    #[test]
+    fn hundred_thousand_rect_scene_keeps_hit_testing_and_marquee_correct() {
+        let mut engine = engine_with_rect_grid();
+        assert_eq!(engine.doc.rects.len(), 100_000);
+
+        let out = engine.tick(&InputBatch {
+            events: vec![
+                InputEvent::PointerDown {
+                    screen_px: Vec2::new(137.0 * 20.0 + 5.0, 42.0 * 20.0 + 5.0),
+                    shift: false,
+                    button: 0,
+                },
+                InputEvent::PointerUp {
+                    screen_px: Vec2::new(137.0 * 20.0 + 5.0, 42.0 * 20.0 + 5.0),
+                    button: 0,
+                },
+            ],
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+        assert_eq!(engine.selected, vec![NodeId(42 * 400 + 137 + 1)]);
+        assert_eq!(out.render_scene.rects.len(), 100_000);
+
+        // Marquee from the gap before cell (2, 3) to the gap after cell (4, 5).
+        engine.tick(&InputBatch {
+            events: vec![
+                InputEvent::PointerDown {
+                    screen_px: Vec2::new(35.0, 55.0),
+                    shift: false,
+                    button: 0,
+                },
+                InputEvent::PointerMove {
+                    screen_px: Vec2::new(95.0, 115.0),
+                    buttons: 1,
+                },
+                InputEvent::PointerUp {
+                    screen_px: Vec2::new(95.0, 115.0),
+                    button: 0,
+                },
+            ],
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+
+        let mut selected: Vec<u64> = engine.selected.iter().map(|id| id.0).collect();
+        selected.sort_unstable();
+        let expected: Vec<u64> = (3..=5)
+            .flat_map(|row| (2..=4).map(move |col| row * 400 + col + 1))
+            .collect();
+        assert_eq!(selected, expected);
+    }
+
+    fn drag_create_rect(engine: &mut Engine, from: Vec2, to: Vec2) {
+        engine.tick(&InputBatch {
+            events: vec![
+                InputEvent::PointerDown {
+                    screen_px: from,
+                    shift: false,
+                    button: 0,
+                },
+                InputEvent::PointerMove {
+                    screen_px: to,
+                    buttons: 1,
+                },
+                InputEvent::PointerUp {
+                    screen_px: to,
+                    button: 0,
+                },
+            ],
+            tool: ToolMode::Rect,
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn create_aspect_forces_square_rects_regardless_of_drag_shape() {
+        let mut engine = Engine {
+            create_aspect: Some(1.0),
+            ..Engine::with_document(Document::new())
+        };
+
+        drag_create_rect(&mut engine, Vec2::new(10.0, 10.0), Vec2::new(110.0, 40.0));
+        drag_create_rect(
+            &mut engine,
+            Vec2::new(300.0, 300.0),
+            Vec2::new(280.0, 200.0),
+        );
+
+        let wide = engine.doc.rects[0].clone();
+        assert_vec2_approx(wide.pos, Vec2::new(10.0, 10.0), 1e-4);
+        assert_vec2_approx(wide.size, Vec2::new(100.0, 100.0), 1e-4);
+
+        // Dragging up-left keeps the start point as the far corner.
+        let tall = engine.doc.rects[1].clone();
+        assert_vec2_approx(tall.pos, Vec2::new(200.0, 200.0), 1e-4);
+        assert_vec2_approx(tall.size, Vec2::new(100.0, 100.0), 1e-4);
+    }
+
+    #[test]
+    fn shift_toggles_create_aspect_lock() {
+        let mut engine = Engine {
+            create_aspect: Some(2.0),
+            ..Engine::with_document(Document::new())
+        };
+        engine.modifiers.shift = true;
+        engine.drag_state = DragState::PendingRectCreate(PendingRectCreate {
+            start_screen_px: Vec2::new(0.0, 0.0),
+            start_world: Vec2::new(0.0, 0.0),
+            previous_selection: vec![],
+        });
+
+        engine.update_rect_create_drag(Vec2::new(50.0, 80.0), Vec2::new(50.0, 80.0), 0.0);
+
+        let DragState::RectCreate(drag) = &engine.drag_state else {
+            panic!("expected an active rect create drag");
+        };
+        assert_vec2_approx(drag.current_world, Vec2::new(50.0, 80.0), 1e-4);
+    }
+
+    #[test]
+    fn east_edge_drag_resizes_width_only() {
+        let mut engine = engine_with_one_rect();
+        let id = engine.doc.rects[0].id;
+        engine.selected = vec![id];
+
+        engine.tick(&InputBatch {
+            events: vec![
+                // East edge midpoint of the 50..150 rect.
+                InputEvent::PointerDown {
+                    screen_px: Vec2::new(150.0, 100.0),
+                    shift: false,
+                    button: 0,
+                },
+                InputEvent::PointerMove {
+                    screen_px: Vec2::new(180.0, 120.0),
+                    buttons: 1,
+                },
+                InputEvent::PointerUp {
+                    screen_px: Vec2::new(180.0, 120.0),
+                    button: 0,
+                },
+            ],
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+
+        let rect = engine.doc.rects[0].clone();
+        assert_vec2_approx(rect.pos, Vec2::new(50.0, 50.0), 1e-4);
+        assert_vec2_approx(rect.size, Vec2::new(130.0, 100.0), 1e-4);
+    }
+
+    #[test]
+    fn cursor_is_resize_ew_when_hovering_east_edge() {
+        let mut engine = engine_with_one_rect();
+        let id = engine.doc.rects[0].id;
+        engine.selected = vec![id];
+
+        engine.hover_screen_px = Some(Vec2::new(150.0, 100.0));
+        let cursor = engine.compute_cursor(&ToolMode::Select);
+        assert_eq!(cursor, CursorStyle::ResizeEw);
+    }
+
+    #[test]
+    fn hit_test_edge_distinguishes_edge_band_from_corner() {
+        let engine = engine_with_one_rect();
+        let id = engine.doc.rects[0].id;
+
+        // East edge band, away from both east corners (rect spans 50..150).
+        assert_eq!(engine.hit_test_edge(id, Vec2::new(151.0, 100.0)), Edge::E);
+        // Inside the NE corner grab radius.
+        assert_eq!(engine.hit_test_edge(id, Vec2::new(152.0, 52.0)), Edge::NE);
+        assert_eq!(
+            engine.hit_test_edge(id, Vec2::new(100.0, 100.0)),
+            Edge::Inside
+        );
+        assert_eq!(
+            engine.hit_test_edge(id, Vec2::new(300.0, 100.0)),
+            Edge::Outside
+        );
+    }
+
+    fn select_all_demo_rects(engine: &mut Engine) -> Vec<NodeId> {
+        let ids: Vec<NodeId> = engine.doc.rects.iter().map(|r| r.id).collect();
+        engine.selected = ids.clone();
+        ids
+    }
+
+    #[test]
+    fn click_without_drag_collapses_multi_selection_to_clicked_rect() {
+        let mut engine = Engine::new();
+        let ids = select_all_demo_rects(&mut engine);
+
+        engine.tick(&InputBatch {
+            events: vec![
+                // inside the first demo rect (100..220, 100..180)
+                InputEvent::PointerDown {
+                    screen_px: Vec2::new(150.0, 140.0),
+                    shift: false,
+                    button: 0,
+                },
+                InputEvent::PointerUp {
+                    screen_px: Vec2::new(150.0, 140.0),
+                    button: 0,
+                },
+            ],
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+
+        assert_eq!(engine.selected, vec![ids[0]]);
+    }
+
+    fn click(engine: &mut Engine, screen_px: Vec2, shift: bool) {
+        engine.tick(&InputBatch {
+            events: vec![
+                InputEvent::PointerDown {
+                    screen_px,
+                    shift,
+                    button: 0,
+                },
+                InputEvent::PointerUp {
+                    screen_px,
+                    button: 0,
+                },
+            ],
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+    }
+
+    fn tick_events(engine: &mut Engine, events: Vec<InputEvent>) {
+        engine.tick(&InputBatch {
+            events,
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn clicking_a_group_member_selects_the_whole_group() {
+        let mut engine = Engine::new();
+        let ids: Vec<NodeId> = engine.doc.rects.iter().map(|r| r.id).collect();
+        engine.selected = vec![ids[2], ids[0]];
+        tick_events(&mut engine, vec![InputEvent::Group]);
+        let group = engine.doc.rects[0].group_id;
+        assert!(group.is_some());
+        assert_eq!(engine.doc.rects[2].group_id, group);
+        assert_eq!(engine.doc.rects[1].group_id, None);
+
+        // Hit testing still names the one rect under the pointer.
+        assert_eq!(
+            engine.check_collide_rects(Vec2::new(150.0, 140.0)),
+            Some(ids[0])
+        );
+        engine.selected.clear();
+        click(&mut engine, Vec2::new(150.0, 140.0), false);
+        assert_eq!(engine.selected, vec![ids[0], ids[2]]);
+
+        // Plain click on the ungrouped rect selects only it.
+        click(&mut engine, Vec2::new(350.0, 250.0), false);
+        assert_eq!(engine.selected, vec![ids[1]]);
+
+        // Shift-click adds, then removes, the group as a unit.
+        click(&mut engine, Vec2::new(700.0, 950.0), true);
+        assert_eq!(engine.selected, vec![ids[1], ids[2], ids[0]]);
+        click(&mut engine, Vec2::new(150.0, 140.0), true);
+        assert_eq!(engine.selected, vec![ids[1]]);
+    }
+
+    #[test]
+    fn ungroup_clears_group_and_is_undoable() {
+        let mut engine = Engine::new();
+        let ids: Vec<NodeId> = engine.doc.rects.iter().map(|r| r.id).collect();
+        engine.selected = ids.clone();
+        tick_events(&mut engine, vec![InputEvent::Group]);
+
+        engine.selected = vec![ids[1]];
+        tick_events(&mut engine, vec![InputEvent::Ungroup]);
+
+        assert_eq!(engine.doc.rects[1].group_id, None);
+        click(&mut engine, Vec2::new(350.0, 250.0), false);
+        assert_eq!(engine.selected, vec![ids[1]]);
+        // The rest stay grouped.
+        click(&mut engine, Vec2::new(150.0, 140.0), false);
+        assert_eq!(engine.selected, vec![ids[0], ids[2]]);
+
+        tick_events(&mut engine, vec![InputEvent::Undo]);
+        let group = engine.doc.rects[0].group_id;
+        assert!(engine.doc.rects.iter().all(|r| r.group_id == group));
+        tick_events(&mut engine, vec![InputEvent::Undo]);
+        assert!(engine.doc.rects.iter().all(|r| r.group_id.is_none()));
+    }
+
+    #[test]
+    fn group_needs_two_rects_and_pasted_groups_get_fresh_ids() {
+        let mut engine = Engine::new();
+        let ids: Vec<NodeId> = engine.doc.rects.iter().map(|r| r.id).collect();
+        engine.selected = vec![ids[0]];
+        tick_events(&mut engine, vec![InputEvent::Group]);
+        assert!(engine.doc.rects[0].group_id.is_none());
+
+        engine.selected = vec![ids[0], ids[1]];
+        tick_events(&mut engine, vec![InputEvent::Group]);
+        let group = engine.doc.rects[0].group_id;
+        tick_events(
+            &mut engine,
+            vec![InputEvent::DuplicateSelected {
+                offset: Vec2::default(),
+            }],
+        );
+
+        let copies = &engine.doc.rects[3..];
+        assert_eq!(copies[0].group_id, copies[1].group_id);
+        assert!(copies[0].group_id.is_some());
+        assert_ne!(copies[0].group_id, group);
+    }
+
+    #[test]
+    fn drag_from_selected_member_moves_whole_multi_selection() {
+        let mut engine = Engine::new();
+        let ids = select_all_demo_rects(&mut engine);
+        let origins: Vec<Vec2> = engine.doc.rects.iter().map(|r| r.pos).collect();
+
+        engine.tick(&InputBatch {
+            events: vec![
+                InputEvent::PointerDown {
+                    screen_px: Vec2::new(150.0, 140.0),
+                    shift: false,
+                    button: 0,
+                },
+                InputEvent::PointerMove {
+                    screen_px: Vec2::new(170.0, 150.0),
+                    buttons: 1,
+                },
+                InputEvent::PointerUp {
+                    screen_px: Vec2::new(170.0, 150.0),
+                    button: 0,
+                },
+            ],
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+
+        assert_eq!(engine.selected, ids);
+        for (rect, origin) in engine.doc.rects.iter().zip(origins) {
+            assert_vec2_approx(rect.pos, Vec2::new(origin.x + 20.0, origin.y + 10.0), 1e-4);
+        }
+    }
+
+    #[test]
+    fn pretty_export_is_multiline_and_round_trips() {
+        let engine = Engine::new();
+
+        let pretty = engine.export_document_pretty();
+        assert!(pretty.contains('\n'));
+        assert!(!engine.export_document().contains('\n'));
+
+        let mut loaded = engine_with_one_rect();
+        loaded.load_document(&pretty).unwrap();
+        assert_eq!(loaded.doc, engine.doc);
+        assert_eq!(
+            Document::from_json(&engine.export_document()).unwrap(),
+            loaded.doc
+        );
+    }
+
+    #[test]
+    fn demo_document_has_three_known_rects() {
+        let doc = demo_document();
+
+        let ids: Vec<NodeId> = doc.rects.iter().map(|r| r.id).collect();
+        assert_eq!(ids, vec![NodeId(1), NodeId(2), NodeId(3)]);
+        assert_eq!(doc.next_id, 4);
+
+        let colors: Vec<[f32; 4]> = doc.rects.iter().map(|r| r.color.to_array()).collect();
+        assert_eq!(
+            colors,
+            vec![
+                [0.2, 0.7, 0.9, 1.0],
+                [0.9, 0.3, 0.9, 1.0],
+                [0.5, 0.8, 0.4, 1.0],
+            ]
+        );
+        assert_eq!(Engine::new().doc, doc);
+    }
+
+    /// Value of `name="..."` in the first tag of `markup` that starts with `tag`.
+    fn svg_attr<'a>(markup: &'a str, tag: &str, name: &str) -> Option<&'a str> {
+        let start = markup.find(tag)?;
+        let element = &markup[start..start + markup[start..].find('>')?];
+        let key = format!(" {name}=\"");
+        let value = &element[element.find(&key)? + key.len()..];
+        Some(&value[..value.find('"')?])
+    }
+
+    #[test]
+    fn to_svg_writes_rects_with_hex_fill_and_bounding_view_box() {
+        let mut doc = demo_document();
+        doc.rects[1].color = Color::rgba(1.0, 0.5, 0.0, 0.25);
+
+        let svg = doc.to_svg();
+
+        // Rects span (100, 100)..(800, 1000).
+        assert_eq!(svg_attr(&svg, "<svg", "viewBox"), Some("100 100 700 900"));
+        assert_eq!(svg_attr(&svg, "<svg", "width"), Some("700"));
+        assert_eq!(svg.matches("<rect ").count(), 3);
+
+        let second = &svg[svg.match_indices("<rect ").nth(1).unwrap().0..];
+        assert_eq!(svg_attr(second, "<rect", "x"), Some("300"));
+        assert_eq!(svg_attr(second, "<rect", "y"), Some("220"));
+        assert_eq!(svg_attr(second, "<rect", "width"), Some("140"));
+        assert_eq!(svg_attr(second, "<rect", "height"), Some("80"));
+        assert_eq!(svg_attr(second, "<rect", "fill"), Some("#ff8000"));
+        assert_eq!(svg_attr(second, "<rect", "fill-opacity"), Some("0.25"));
+        // Opaque fills leave the opacity out.
+        assert_eq!(svg_attr(&svg, "<rect", "fill"), Some("#33b3e6"));
+        assert_eq!(svg_attr(&svg, "<rect", "fill-opacity"), None);
+        assert!(svg.trim_end().ends_with("</svg>"));
+    }
+
+    #[test]
+    fn to_svg_rotates_turned_rects_about_their_center() {
+        let mut doc = Document::new();
+        let mut rect = RectNode::new(
+            NodeId(1),
+            Vec2::new(0.0, 0.0),
+            Vec2::new(40.0, 20.0),
+            Color::WHITE,
+        );
+        doc.rects.push(rect.clone());
+        rect.id = NodeId(2);
+        rect.pos = Vec2::new(100.0, 0.0);
+        rect.rotation = std::f32::consts::FRAC_PI_2;
+        doc.rects.push(rect);
+
+        let svg = doc.to_svg();
+
+        assert_eq!(svg_attr(&svg, "<rect", "transform"), None);
+        let turned = &svg[svg.match_indices("<rect ").nth(1).unwrap().0..];
+        assert_eq!(
+            svg_attr(turned, "<rect", "transform"),
+            Some("rotate(90 120 10)")
+        );
+        // The unrotated attributes stay as stored; the transform turns them.
+        assert_eq!(svg_attr(turned, "<rect", "x"), Some("100"));
+        assert_eq!(svg_attr(turned, "<rect", "width"), Some("40"));
+    }
+
+    #[test]
+    fn to_svg_leaves_hidden_rects_out_of_the_markup_and_view_box() {
+        let mut doc = demo_document();
+        // The hidden rect is the one reaching (800, 1000).
+        doc.rects[2].visible = false;
+
+        let svg = doc.to_svg();
+
+        assert_eq!(svg.matches("<rect ").count(), 2);
+        assert_eq!(svg_attr(&svg, "<svg", "viewBox"), Some("100 100 340 200"));
+    }
+
+    #[test]
+    fn to_svg_insets_stroked_rects_so_the_border_stays_inside() {
+        let mut doc = demo_document();
+        doc.rects[0].stroke_width = 4.0;
+        doc.rects[0].stroke_color = Color::rgba(1.0, 0.0, 0.0, 0.5);
+
+        let svg = doc.to_svg();
+
+        // Rect 0 spans (100, 100)..(220, 180); the stroke centerline sits 2 in.
+        assert_eq!(svg_attr(&svg, "<rect", "x"), Some("102"));
+        assert_eq!(svg_attr(&svg, "<rect", "y"), Some("102"));
+        assert_eq!(svg_attr(&svg, "<rect", "width"), Some("116"));
+        assert_eq!(svg_attr(&svg, "<rect", "height"), Some("76"));
+        assert_eq!(svg_attr(&svg, "<rect", "stroke"), Some("#ff0000"));
+        assert_eq!(svg_attr(&svg, "<rect", "stroke-width"), Some("4"));
+        assert_eq!(svg_attr(&svg, "<rect", "stroke-opacity"), Some("0.5"));
+        // The viewBox still fits the outer edge.
+        assert_eq!(svg_attr(&svg, "<svg", "viewBox"), Some("100 100 700 900"));
+        // Unstroked rects carry no stroke attributes.
+        let second = &svg[svg.match_indices("<rect ").nth(1).unwrap().0..];
+        assert_eq!(svg_attr(second, "<rect", "stroke"), None);
+    }
+
+    #[test]
+    fn to_svg_of_empty_document_is_a_bare_svg_element() {
+        let svg = Document::new().to_svg();
+
+        assert_eq!(svg_attr(&svg, "<svg", "viewBox"), Some("0 0 0 0"));
+        assert_eq!(
+            svg_attr(&svg, "<svg", "xmlns"),
+            Some("http://www.w3.org/2000/svg")
+        );
+        assert!(!svg.contains("<rect"));
+        assert!(svg.trim_end().ends_with("</svg>"));
+    }
+
+    fn engine_with_uneven_rects() -> Engine {
+        engine_with_rects_at(&[
+            (Vec2::new(40.0, 0.0), Vec2::new(100.0, 50.0)),
+            (Vec2::new(10.0, 80.0), Vec2::new(30.0, 30.0)),
+            (Vec2::new(200.0, 150.0), Vec2::new(60.0, 90.0)),
+        ])
+    }
+
+    fn align(engine: &mut Engine, mode: AlignMode) {
+        engine.tick(&InputBatch {
+            events: vec![InputEvent::Align { mode }],
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn align_left_edges_moves_rects_to_leftmost_edge() {
+        let mut engine = engine_with_uneven_rects();
+
+        align(&mut engine, AlignMode::LeftEdges);
+
+        let rects = &engine.doc.rects;
+        assert_vec2_approx(rects[0].pos, Vec2::new(10.0, 0.0), 1e-6);
+        assert_vec2_approx(rects[1].pos, Vec2::new(10.0, 80.0), 1e-6);
+        assert_vec2_approx(rects[2].pos, Vec2::new(10.0, 150.0), 1e-6);
+        // Only the two rects that moved are in the single history entry.
+        assert_eq!(engine.undo_stack.len(), 1);
+
+        engine.undo();
+        assert_vec2_approx(engine.doc.rects[0].pos, Vec2::new(40.0, 0.0), 1e-6);
+        assert_vec2_approx(engine.doc.rects[2].pos, Vec2::new(200.0, 150.0), 1e-6);
+    }
+
+    #[test]
+    fn align_horizontal_centers_uses_selection_bounds_center() {
+        let mut engine = engine_with_uneven_rects();
+
+        align(&mut engine, AlignMode::HorizontalCenters);
+
+        // Bounds span x 10..260, so every center lands on x = 135.
+        for rect in &engine.doc.rects {
+            assert_approx(rect.pos.x + rect.size.x * 0.5, 135.0, 1e-6);
+        }
+        assert_vec2_approx(engine.doc.rects[0].pos, Vec2::new(85.0, 0.0), 1e-6);
+        assert_vec2_approx(engine.doc.rects[1].pos, Vec2::new(120.0, 80.0), 1e-6);
+        assert_vec2_approx(engine.doc.rects[2].pos, Vec2::new(105.0, 150.0), 1e-6);
+        assert_eq!(engine.doc.rects[2].size, Vec2::new(60.0, 90.0));
+    }
+
+    #[test]
+    fn align_right_and_bottom_edges_account_for_size() {
+        let mut engine = engine_with_uneven_rects();
+
+        align(&mut engine, AlignMode::RightEdges);
+        align(&mut engine, AlignMode::BottomEdges);
+
+        for rect in &engine.doc.rects {
+            assert_approx(rect.pos.x + rect.size.x, 260.0, 1e-6);
+            assert_approx(rect.pos.y + rect.size.y, 240.0, 1e-6);
+        }
+    }
+
+    #[test]
+    fn align_needs_two_selected_rects() {
+        let mut engine = engine_with_uneven_rects();
+        engine.selected = vec![engine.doc.rects[2].id];
+
+        align(&mut engine, AlignMode::LeftEdges);
+
+        assert_vec2_approx(engine.doc.rects[2].pos, Vec2::new(200.0, 150.0), 1e-6);
+        assert!(engine.undo_stack.is_empty());
+    }
+
+    /// Helper: gray rects at each `(pos, size)`, bottom to top, all selected.
+    fn engine_with_rects_at(rects: &[(Vec2, Vec2)]) -> Engine {
+        let mut engine = engine_with_unselected_rects_at(rects);
+        engine.selected = engine.doc.rects.iter().map(|r| r.id).collect();
+        engine
+    }
+
+    /// Helper: [`engine_with_rects_at`] with nothing selected.
+    fn engine_with_unselected_rects_at(rects: &[(Vec2, Vec2)]) -> Engine {
+        let mut doc = Document::new();
+        for &(pos, size) in rects {
+            let id = doc.alloc_id();
+            doc.rects.push(RectNode::new(
+                id,
+                pos,
+                size,
+                Color::rgba(0.5, 0.5, 0.5, 1.0),
+            ));
+        }
+        Engine::with_document(doc)
+    }
+
+    fn distribute(engine: &mut Engine, axis: Axis) {
+        engine.tick(&InputBatch {
+            events: vec![InputEvent::Distribute { axis }],
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn distribute_horizontal_equalizes_gaps_between_outermost_rects() {
+        // Out of document order on purpose: sorting is by min x.
+        let mut engine = engine_with_rects_at(&[
+            (Vec2::new(0.0, 0.0), Vec2::new(10.0, 10.0)),
+            (Vec2::new(50.0, 40.0), Vec2::new(20.0, 10.0)),
+            (Vec2::new(30.0, 80.0), Vec2::new(10.0, 30.0)),
+            (Vec2::new(100.0, 0.0), Vec2::new(20.0, 10.0)),
+        ]);
+
+        distribute(&mut engine, Axis::Horizontal);
+
+        // Span 0..120 minus 60 of widths leaves three gaps of 20.
+        let rects = &engine.doc.rects;
+        assert_vec2_approx(rects[0].pos, Vec2::new(0.0, 0.0), 1e-6);
+        assert_vec2_approx(rects[2].pos, Vec2::new(30.0, 80.0), 1e-6);
+        assert_vec2_approx(rects[1].pos, Vec2::new(60.0, 40.0), 1e-6);
+        assert_vec2_approx(rects[3].pos, Vec2::new(100.0, 0.0), 1e-6);
+
+        let mut sorted: Vec<&RectNode> = rects.iter().collect();
+        sorted.sort_by(|a, b| a.pos.x.total_cmp(&b.pos.x));
+        for pair in sorted.windows(2) {
+            assert_approx(pair[1].pos.x - (pair[0].pos.x + pair[0].size.x), 20.0, 1e-6);
+        }
+        assert_eq!(engine.undo_stack.len(), 1);
+    }
+
+    #[test]
+    fn distribute_vertical_moves_only_the_y_coordinate() {
+        let mut engine = engine_with_rects_at(&[
+            (Vec2::new(5.0, 0.0), Vec2::new(10.0, 10.0)),
+            (Vec2::new(70.0, 15.0), Vec2::new(10.0, 40.0)),
+            (Vec2::new(20.0, 100.0), Vec2::new(10.0, 10.0)),
+        ]);
+
+        distribute(&mut engine, Axis::Vertical);
+
+        // Gaps are (110 - 60) / 2 = 25 on either side of the middle rect.
+        assert_vec2_approx(engine.doc.rects[1].pos, Vec2::new(70.0, 35.0), 1e-6);
+    }
+
+    #[test]
+    fn distribute_needs_three_selected_rects() {
+        let mut engine = engine_with_rects_at(&[
+            (Vec2::new(0.0, 0.0), Vec2::new(10.0, 10.0)),
+            (Vec2::new(15.0, 0.0), Vec2::new(10.0, 10.0)),
+            (Vec2::new(100.0, 0.0), Vec2::new(10.0, 10.0)),
+        ]);
+        engine.selected.pop();
+
+        distribute(&mut engine, Axis::Horizontal);
+
+        assert_vec2_approx(engine.doc.rects[1].pos, Vec2::new(15.0, 0.0), 1e-6);
+        assert!(engine.undo_stack.is_empty());
+    }
+
+    #[test]
+    fn duplicate_selected_copies_rects_above_originals() {
+        let mut engine = engine_with_stacked_rects();
+        let originals = engine.doc.rects.clone();
+        engine.selected = vec![originals[1].id, originals[0].id];
+
+        engine.tick(&InputBatch {
+            events: vec![InputEvent::DuplicateSelected {
+                offset: Vec2::new(5.0, -5.0),
+            }],
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+
+        assert_eq!(engine.doc.rects.len(), 5);
+        assert_eq!(engine.doc.rects[..3], originals[..]);
+        let copies = &engine.doc.rects[3..];
+        let copy_ids: Vec<NodeId> = copies.iter().map(|r| r.id).collect();
+        assert!(
+            copy_ids
+                .iter()
+                .all(|id| !originals.iter().any(|r| r.id == *id))
+        );
+        assert_ne!(copy_ids[0], copy_ids[1]);
+        assert_eq!(engine.selected, copy_ids);
+        // Copies keep draw order and sit above every original.
+        for (copy, original) in copies.iter().zip(&originals[..2]) {
+            assert_vec2_approx(
+                copy.pos,
+                Vec2::new(original.pos.x + 5.0, original.pos.y - 5.0),
+                1e-6,
+            );
+            assert_eq!(copy.size, original.size);
+            assert_eq!(copy.color, original.color);
+        }
+
+        engine.undo();
+        assert_eq!(engine.doc.rects, originals);
+    }
+
+    #[test]
+    fn duplicate_with_zero_offset_uses_default_step() {
+        let mut engine = engine_with_one_rect();
+        engine.selected = vec![engine.doc.rects[0].id];
+
+        engine.tick(&InputBatch {
+            events: vec![InputEvent::DuplicateSelected {
+                offset: Vec2::default(),
+            }],
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+
+        assert_vec2_approx(engine.doc.rects[1].pos, Vec2::new(60.0, 60.0), 1e-6);
+    }
+
+    #[test]
+    fn arrow_keys_nudge_selection_in_world_units() {
+        let mut engine = engine_with_two_rects();
+        engine.camera.zoom = 4.0;
+        engine.selected = vec![engine.doc.rects[0].id];
+        let key = |key: &str, shift: bool| InputEvent::KeyDown {
+            key: key.to_string(),
+            shift,
+        };
+
+        engine.tick(&InputBatch {
+            events: vec![
+                key("ArrowRight", false),
+                key("ArrowDown", false),
+                key("ArrowDown", false),
+                key("ArrowLeft", true),
+                key("ArrowUp", true),
+            ],
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+
+        assert_vec2_approx(engine.doc.rects[0].pos, Vec2::new(41.0, 42.0), 1e-6);
+        assert_vec2_approx(engine.doc.rects[1].pos, Vec2::new(300.0, 50.0), 1e-6);
+        assert_eq!(engine.undo_stack.len(), 5);
+
+        // Nothing selected: a no-op.
+        engine.selected.clear();
+        engine.tick(&InputBatch {
+            events: vec![key("ArrowRight", true)],
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+        assert_vec2_approx(engine.doc.rects[0].pos, Vec2::new(41.0, 42.0), 1e-6);
+        assert_eq!(engine.undo_stack.len(), 5);
+    }
+
+    #[test]
+    fn arrow_keys_do_not_disturb_an_active_drag() {
+        let mut engine = engine_with_one_rect();
+        engine.selected = vec![engine.doc.rects[0].id];
+        start_move_drag(
+            &mut engine,
+            Vec2::new(100.0, 100.0),
+            Vec2::new(120.0, 100.0),
+        );
+
+        engine.tick(&InputBatch {
+            events: vec![InputEvent::KeyDown {
+                key: "ArrowUp".to_string(),
+                shift: false,
+            }],
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+
+        assert!(matches!(engine.drag_state, DragState::SelectionMove(_)));
+        assert_vec2_approx(engine.doc.rects[0].pos, Vec2::new(70.0, 50.0), 1e-6);
+    }
+
+    #[test]
+    fn key_events_track_modifier_state() {
+        let mut engine = engine_with_one_rect();
+        let key = |key: &str, down: bool| {
+            let key = key.to_string();
+            if down {
+                InputEvent::KeyDown { key, shift: false }
+            } else {
+                InputEvent::KeyUp { key }
+            }
+        };
+
+        engine.tick(&InputBatch {
+            events: vec![key("Shift", true), key("Alt", true), key("Meta", true)],
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+        assert_eq!(
+            engine.modifiers,
+            ModifierState {
+                shift: true,
+                ctrl: false,
+                alt: true,
+                meta: true,
+            }
+        );
+
+        engine.tick(&InputBatch {
+            events: vec![key("Alt", false), key("Control", true), key("a", true)],
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+        assert_eq!(
+            engine.modifiers,
+            ModifierState {
+                shift: true,
+                ctrl: true,
+                alt: false,
+                meta: true,
+            }
+        );
+
+        // Pointer events carry shift too and override the tracked flag.
+        engine.tick(&InputBatch {
+            events: vec![InputEvent::PointerDown {
+                screen_px: Vec2::new(400.0, 400.0),
+                shift: false,
+                button: 0,
+            }],
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+        assert!(!engine.modifiers.shift);
+    }
+
+    #[test]
+    fn fit_width_maps_document_width_to_viewport_width() {
+        let mut engine = Engine::new();
+        engine.set_viewport(Vec2::new(800.0, 600.0));
+
+        engine.tick(&InputBatch {
+            events: vec![InputEvent::FitWidth],
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+
+        // Demo rects span x 100..800 and y 100..1000.
+        let left = engine.camera.world_to_screen(Vec2::new(100.0, 550.0));
+        let right = engine.camera.world_to_screen(Vec2::new(800.0, 550.0));
+        assert_approx(left.x, 0.0, 1e-3);
+        assert_approx(right.x, 800.0, 1e-3);
+        // Vertical center of the document sits at the viewport center.
+        assert_approx(left.y, 300.0, 1e-3);
+    }
+
+    #[test]
+    fn zoom_about_center_keeps_viewport_center_fixed() {
+        let mut engine = Engine {
+            camera: Camera {
+                pan: Vec2::new(30.0, -20.0),
+                zoom: 1.5,
+            },
+            ..Engine::with_document(Document::new())
+        };
+        engine.set_viewport(Vec2::new(800.0, 600.0));
+        let center_px = Vec2::new(400.0, 300.0);
+        let before = engine.camera.screen_to_world(center_px);
+
+        engine.tick(&InputBatch {
+            events: vec![InputEvent::ZoomAboutCenter { multiplier: 2.0 }],
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+
+        assert_approx(engine.camera.zoom, 3.0, 1e-6);
+        assert_vec2_approx(engine.camera.screen_to_world(center_px), before, 1e-4);
+    }
+
+    #[test]
+    fn fit_height_maps_document_height_to_viewport_height() {
+        let mut engine = Engine::new();
+        engine.set_viewport(Vec2::new(800.0, 600.0));
+
+        engine.tick(&InputBatch {
+            events: vec![InputEvent::FitHeight],
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+
+        let top = engine.camera.world_to_screen(Vec2::new(450.0, 100.0));
+        let bottom = engine.camera.world_to_screen(Vec2::new(450.0, 1000.0));
+        assert_approx(top.y, 0.0, 1e-3);
+        assert_approx(bottom.y, 600.0, 1e-3);
+        assert_approx(top.x, 400.0, 1e-3);
+    }
+
+    #[test]
+    fn overlapping_pairs_reports_intersecting_rects_only() {
+        let mut engine = Engine::new();
+        // Slide the second demo rect onto the first; the third stays clear.
+        engine.doc.rects[1].pos = Vec2::new(180.0, 150.0);
+        let (id0, id1) = (engine.doc.rects[0].id, engine.doc.rects[1].id);
+
+        assert_eq!(engine.overlapping_pairs(), vec![(id0, id1)]);
+    }
+
+    #[test]
+    fn overlapping_pairs_use_the_rotated_bounds() {
+        let mut engine = engine_with_two_rects();
+        // 100-wide squares 20 apart along x: clear while upright, overlapping
+        // once the lower one is turned 45 degrees and reaches ~20.7 further.
+        engine.doc.rects[1].pos = Vec2::new(170.0, 50.0);
+        assert!(engine.overlapping_pairs().is_empty());
+
+        engine.doc.rects[0].rotation = std::f32::consts::FRAC_PI_4;
+        let (id0, id1) = (engine.doc.rects[0].id, engine.doc.rects[1].id);
+        assert_eq!(engine.overlapping_pairs(), vec![(id0, id1)]);
+
+        engine.resolve_overlaps();
+        assert!(engine.overlapping_pairs().is_empty());
+    }
+
+    #[test]
+    fn resolve_overlaps_separates_pair_along_shallowest_axis_and_is_undoable() {
+        let mut engine = engine_with_two_rects();
+        // Rect 1 overlaps rect 0 (50..150) by 10 units in x and fully in y.
+        engine.doc.rects[1].pos = Vec2::new(140.0, 50.0);
+
+        engine.resolve_overlaps();
+        assert!(engine.overlapping_pairs().is_empty());
+        assert_vec2_approx(engine.doc.rects[1].pos, Vec2::new(150.0, 50.0), 1e-4);
+        assert_vec2_approx(engine.doc.rects[0].pos, Vec2::new(50.0, 50.0), 1e-4);
+
+        engine.tick(&InputBatch {
+            events: vec![InputEvent::Undo],
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+        assert_vec2_approx(engine.doc.rects[1].pos, Vec2::new(140.0, 50.0), 1e-4);
+    }
+
+    #[test]
+    fn empty_click_keeps_selection_when_clear_on_empty_click_is_off() {
+        let mut engine = engine_with_one_rect();
+        let id = engine.doc.rects[0].id;
+        engine.selected = vec![id];
+        engine.clear_on_empty_click = false;
+
+        engine.tick(&InputBatch {
+            events: vec![
+                InputEvent::PointerDown {
+                    screen_px: Vec2::new(400.0, 400.0),
+                    shift: false,
+                    button: 0,
+                },
+                InputEvent::PointerUp {
+                    screen_px: Vec2::new(400.0, 400.0),
+                    button: 0,
+                },
+            ],
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+
+        assert_eq!(engine.selected, vec![id]);
+    }
+
+    #[test]
+    fn remap_colors_changes_only_matching_rects_and_is_undoable() {
+        let mut engine = Engine::new();
+        let before: Vec<Color> = engine.doc.rects.iter().map(|r| r.color).collect();
+        let target = Color::rgba(0.1, 0.1, 0.1, 1.0);
+
+        // Slightly off the second demo color, within tolerance.
+        engine.remap_colors(&[(Color::rgba(0.901, 0.3, 0.899, 1.0), target)], 0.01);
+
+        assert_eq!(engine.doc.rects[0].color, before[0]);
+        assert_eq!(engine.doc.rects[1].color, target);
+        assert_eq!(engine.doc.rects[2].color, before[2]);
+
+        engine.tick(&InputBatch {
+            events: vec![InputEvent::Undo],
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+        assert_eq!(engine.doc.rects[1].color, before[1]);
+    }
+
+    #[test]
+    fn needs_redraw_is_false_for_idle_tick_and_true_after_move() {
+        let mut engine = engine_with_one_rect();
+        let idle = InputBatch::default();
+
+        assert!(engine.tick(&idle).needs_redraw, "first frame always draws");
+        assert!(!engine.tick(&idle).needs_redraw);
+
+        engine.doc_mut().rects[0].pos = Vec2::new(60.0, 50.0);
+        assert!(engine.tick(&idle).needs_redraw);
+        assert!(!engine.tick(&idle).needs_redraw);
+    }
+
+    #[test]
+    fn needs_redraw_follows_events_camera_writes_and_requests() {
+        let mut engine = engine_with_one_rect();
+        let idle = InputBatch::default();
+        engine.tick(&idle);
+
+        tick_events(&mut engine, vec![InputEvent::SelectAll]);
+        assert!(!engine.tick(&idle).needs_redraw);
+
+        engine.camera.zoom = 2.0;
+        assert!(engine.tick(&idle).needs_redraw);
+        assert!(!engine.tick(&idle).needs_redraw);
+
+        engine.debug_axes = true;
+        engine.request_redraw();
+        assert!(engine.tick(&idle).needs_redraw);
+
+        engine.set_selected(Vec::new());
+        assert!(engine.tick(&idle).needs_redraw);
+        assert!(!engine.tick(&idle).needs_redraw);
+    }
+
+    #[test]
+    fn escape_cancels_drag_then_clears_selection_then_does_nothing() {
+        let mut engine = engine_with_one_rect();
+        let id = engine.doc.rects[0].id;
+        let origin = engine.doc.rects[0].pos;
+        engine.selected = vec![id];
+        let escape = InputBatch {
+            events: vec![InputEvent::KeyDown {
+                key: "Escape".to_string(),
+                shift: false,
+            }],
+            tool: ToolMode::Select,
+            ..Default::default()
+        };
+
+        engine.tick(&InputBatch {
+            events: vec![
+                InputEvent::PointerDown {
+                    screen_px: Vec2::new(100.0, 100.0),
+                    shift: false,
+                    button: 0,
+                },
+                InputEvent::PointerMove {
+                    screen_px: Vec2::new(140.0, 100.0),
+                    buttons: 1,
+                },
+            ],
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+        assert!(matches!(engine.drag_state, DragState::SelectionMove(_)));
+
+        engine.tick(&escape);
+        assert!(matches!(engine.drag_state, DragState::Idle));
+        assert_vec2_approx(engine.doc.rects[0].pos, origin, 1e-4);
+        assert_eq!(engine.selected, vec![id]);
+
+        engine.tick(&escape);
+        assert!(engine.selected.is_empty());
+
+        engine.tick(&escape);
+        assert!(engine.selected.is_empty());
+        assert!(matches!(engine.drag_state, DragState::Idle));
+        assert_vec2_approx(engine.doc.rects[0].pos, origin, 1e-4);
+    }
+
+    #[test]
+    fn merge_nodes_appends_with_fresh_ids_and_selects_them() {
+        let mut engine = Engine::new();
+        let snippet = serde_json::to_string(&engine.doc.rects[..2]).unwrap();
+
+        let ids = engine.merge_nodes(&snippet, Vec2::new(10.0, 20.0)).unwrap();
+
+        assert_eq!(engine.doc.rects.len(), 5);
+        assert_eq!(ids, vec![NodeId(4), NodeId(5)]);
+        assert_eq!(engine.selected, ids);
+        assert_vec2_approx(engine.doc.rects[3].pos, Vec2::new(110.0, 120.0), 1e-4);
+
+        engine.tick(&InputBatch {
+            events: vec![InputEvent::Undo],
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+        assert_eq!(engine.doc.rects.len(), 3);
+        assert!(engine.selected.is_empty());
+    }
+
+    #[test]
+    fn merge_nodes_rejects_malformed_json() {
+        let mut engine = Engine::new();
+        assert!(engine.merge_nodes("{not json", Vec2::default()).is_err());
+        assert_eq!(engine.doc.rects.len(), 3);
+    }
+
+    #[test]
+    fn shift_snaps_rotation_to_configured_increment() {
+        let mut engine = engine_with_one_rect();
+        assert_approx(engine.constrain_rotation_deg(47.0), 47.0, 1e-6);
+
+        engine.modifiers.shift = true;
+        assert_approx(engine.constrain_rotation_deg(47.0), 45.0, 1e-6);
+        assert_approx(engine.constrain_rotation_deg(-53.0), -60.0, 1e-6);
+
+        engine.rotation_snap_deg = 90.0;
+        assert_approx(engine.constrain_rotation_deg(47.0), 90.0, 1e-6);
+    }
+
+    #[test]
+    fn rotating_with_shift_to_47_degrees_snaps_to_45() {
+        let mut engine = engine_with_one_rect();
+        let id = engine.doc.rects[0].id;
+        let rotate = |engine: &mut Engine, degrees: f32| {
+            tick_events(engine, vec![InputEvent::SetRotation { id, degrees }]);
+        };
+
+        rotate(&mut engine, 47.0);
+        assert_approx(engine.doc.rects[0].rotation.to_degrees(), 47.0, 1e-4);
+
+        tick_events(
+            &mut engine,
+            vec![InputEvent::KeyDown {
+                key: "Shift".to_string(),
+                shift: true,
+            }],
+        );
+        rotate(&mut engine, 47.0);
+        assert_approx(engine.doc.rects[0].rotation.to_degrees(), 45.0, 1e-4);
+
+        // Each turn is its own undo step.
+        tick_events(&mut engine, vec![InputEvent::Undo]);
+        assert_approx(engine.doc.rects[0].rotation.to_degrees(), 47.0, 1e-4);
+        tick_events(&mut engine, vec![InputEvent::Undo]);
+        assert_eq!(engine.doc.rects[0].rotation, 0.0);
+    }
+
+    #[test]
+    fn set_rotation_ignores_locked_rects_and_non_finite_angles() {
+        let mut engine = engine_with_one_rect();
+        let id = engine.doc.rects[0].id;
+
+        tick_events(
+            &mut engine,
+            vec![InputEvent::SetRotation {
+                id,
+                degrees: f32::NAN,
+            }],
+        );
+        engine.doc_mut().rects[0].locked = true;
+        tick_events(
+            &mut engine,
+            vec![InputEvent::SetRotation { id, degrees: 30.0 }],
+        );
+
+        assert_eq!(engine.doc.rects[0].rotation, 0.0);
+        assert!(engine.undo_stack.is_empty());
+    }
+
+    #[test]
+    fn select_nearest_picks_rect_just_outside_tap() {
+        let mut engine = engine_with_two_rects();
+        let id0 = engine.doc.rects[0].id;
+
+        // 5 units left of rect 0 (50..150, 50..150).
+        engine.select_nearest(Vec2::new(45.0, 100.0), 10.0);
+        assert_eq!(engine.selected, vec![id0]);
+
+        // Out of range of both rects clears the selection.
+        engine.select_nearest(Vec2::new(220.0, 300.0), 10.0);
+        assert!(engine.selected.is_empty());
+    }
+
+    fn engine_with_rotated_rect(pos: Vec2, size: Vec2, rotation: f32) -> Engine {
+        let mut engine = engine_with_rects_at(&[(pos, size)]);
+        engine.doc.rects[0].rotation = rotation;
+        engine.invalidate_spatial_index();
+        engine
+    }
+
+    #[test]
+    fn hit_test_follows_quarter_turn() {
+        // 200x50 about center (100, 25) turns into 50x200 spanning x 75..125, y -75..125.
+        let engine = engine_with_rotated_rect(
+            Vec2::new(0.0, 0.0),
+            Vec2::new(200.0, 50.0),
+            std::f32::consts::FRAC_PI_2,
+        );
+        let id = engine.doc.rects[0].id;
+
+        assert_eq!(
+            engine.check_collide_rects(Vec2::new(100.0, -60.0)),
+            Some(id)
+        );
+        assert_eq!(
+            engine.check_collide_rects(Vec2::new(100.0, 110.0)),
+            Some(id)
+        );
+        // Inside the unrotated footprint but outside the turned one.
+        assert_eq!(engine.check_collide_rects(Vec2::new(20.0, 25.0)), None);
+        assert_eq!(engine.check_collide_rects(Vec2::new(180.0, 25.0)), None);
+
+        let (min, max) = engine.doc.rects[0].world_aabb();
+        assert_vec2_approx(min, Vec2::new(75.0, -75.0), 1e-3);
+        assert_vec2_approx(max, Vec2::new(125.0, 125.0), 1e-3);
+    }
+
+    #[test]
+    fn hit_test_follows_eighth_turn() {
+        // A 100x100 square at 45 degrees is a diamond reaching ~70.7 from its center.
+        let engine = engine_with_rotated_rect(
+            Vec2::new(0.0, 0.0),
+            Vec2::new(100.0, 100.0),
+            std::f32::consts::FRAC_PI_4,
+        );
+        let id = engine.doc.rects[0].id;
+
+        assert_eq!(engine.check_collide_rects(Vec2::new(50.0, -15.0)), Some(id));
+        assert_eq!(engine.check_collide_rects(Vec2::new(115.0, 50.0)), Some(id));
+        // Original corners are cut off by the diamond's edges.
+        assert_eq!(engine.check_collide_rects(Vec2::new(5.0, 5.0)), None);
+        assert_eq!(engine.check_collide_rects(Vec2::new(95.0, 95.0)), None);
+    }
+
+    #[test]
+    fn selection_chrome_and_handles_follow_rotation() {
+        let engine = engine_with_rotated_rect(
+            Vec2::new(0.0, 0.0),
+            Vec2::new(200.0, 50.0),
+            std::f32::consts::FRAC_PI_2,
+        );
+        let id = engine.doc.rects[0].id;
+        let overlay = engine.update_overlay_scene(&ToolMode::Select);
+
+        let selection_box = overlay.selection_boxes[0];
+        assert_eq!(selection_box.pos, [0.0, 0.0]);
+        assert_approx(selection_box.rotation, std::f32::consts::FRAC_PI_2, 1e-6);
+        // The NW handle turns to the top-right of the world footprint.
+        let handles = &overlay.instances(engine.camera.zoom)[4..];
+        let [hx, hy] = handles[0].pos;
+        let half = OverlayScene::HANDLE_PX * 0.5;
+        assert_approx(hx + half, 125.0, 1e-3);
+        assert_approx(hy + half, -75.0, 1e-3);
+
+        assert_eq!(engine.hit_test_edge(id, Vec2::new(125.0, -75.0)), Edge::NW);
+        assert_eq!(engine.hit_test_edge(id, Vec2::new(75.0, 125.0)), Edge::SE);
+    }
+
+    #[test]
+    fn resizing_rotated_rect_keeps_opposite_edge_fixed() {
+        let mut engine = engine_with_rotated_rect(
+            Vec2::new(0.0, 0.0),
+            Vec2::new(200.0, 50.0),
+            std::f32::consts::FRAC_PI_2,
+        );
+
+        // The local east edge now faces down, with its midpoint at (100, 125).
+        tick_events(
+            &mut engine,
+            vec![
+                InputEvent::PointerDown {
+                    screen_px: Vec2::new(100.0, 125.0),
+                    shift: false,
+                    button: 0,
+                },
+                InputEvent::PointerMove {
+                    screen_px: Vec2::new(100.0, 165.0),
+                    buttons: 1,
+                },
+                InputEvent::PointerUp {
+                    screen_px: Vec2::new(100.0, 165.0),
+                    button: 0,
+                },
+            ],
+        );
+
+        let rect = engine.doc.rects[0].clone();
+        assert_vec2_approx(rect.size, Vec2::new(240.0, 50.0), 1e-3);
+        let (min, max) = rect.world_aabb();
+        assert_vec2_approx(min, Vec2::new(75.0, -75.0), 1e-3);
+        assert_vec2_approx(max, Vec2::new(125.0, 165.0), 1e-3);
+    }
+
+    #[test]
+    fn hidden_rects_stay_in_document_but_are_not_drawn_or_hit() {
+        let mut engine = engine_with_two_rects();
+        let (hidden, shown) = (engine.doc.rects[0].id, engine.doc.rects[1].id);
+        engine.selected = vec![hidden];
 
-        let world = Vec2::new(12.0, 34.0);
-        let screen = cam.world_to_screen(world);
-        let world2 = cam.screen_to_world(screen);
+        let out = engine.tick(&InputBatch {
+            events: vec![InputEvent::SetVisible { visible: false }],
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
 
-        assert_vec2_approx(world2, world, 1e-4);
+        assert_eq!(engine.doc.rects.len(), 2);
+        assert!(!engine.doc.rects[0].visible);
+        assert_eq!(out.render_scene.rects.len(), 1);
+        assert_eq!(out.render_scene.rects[0].pos, [300.0, 50.0]);
+        assert_eq!(engine.check_collide_rects(Vec2::new(100.0, 100.0)), None);
+        assert_eq!(
+            engine.check_collide_rects(Vec2::new(350.0, 100.0)),
+            Some(shown)
+        );
+
+        // Still saved with the document, flag included.
+        let json = engine.doc.to_json();
+        let reloaded = Document::from_json(&json).unwrap();
+        assert_eq!(reloaded.rects.len(), 2);
+        assert!(!reloaded.rects[0].visible);
     }
 
     #[test]
-    fn pan_by_screen_delta_moves_world_origin_expected_direction() {
-        let mut cam = Camera {
-            pan: Vec2::new(0.0, 0.0),
-            zoom: 2.0,
+    fn showing_again_is_undoable() {
+        let mut engine = engine_with_one_rect();
+        engine.selected = vec![engine.doc.rects[0].id];
+
+        tick_events(&mut engine, vec![InputEvent::SetVisible { visible: false }]);
+        tick_events(&mut engine, vec![InputEvent::Undo]);
+        assert!(engine.doc.rects[0].visible);
+        assert!(
+            engine
+                .check_collide_rects(Vec2::new(100.0, 100.0))
+                .is_some()
+        );
+
+        tick_events(&mut engine, vec![InputEvent::Redo]);
+        assert!(!engine.doc.rects[0].visible);
+    }
+
+    #[test]
+    fn rects_without_visible_field_load_as_visible() {
+        let json = r#"{"next_id":2,"rects":[{"id":1,"pos":{"x":0,"y":0},"size":{"x":10,"y":10},"color":[1,1,1,1]}]}"#;
+        let doc = Document::from_json(json).unwrap();
+        assert!(doc.rects[0].visible);
+    }
+
+    #[test]
+    fn clicks_fall_through_locked_rects() {
+        let mut engine = engine_with_stacked_rects();
+        let ids = order_of(&engine);
+        engine.doc.rects[2].locked = true;
+
+        // (50, 50) lies in all three; the locked top rect is skipped.
+        assert_eq!(
+            engine.check_collide_rects(Vec2::new(50.0, 50.0)),
+            Some(ids[1])
+        );
+        // Only the locked rect covers (130, 130).
+        assert_eq!(engine.check_collide_rects(Vec2::new(130.0, 130.0)), None);
+        // Still drawn.
+        let out = engine.tick(&InputBatch {
+            events: vec![],
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+        assert_eq!(out.render_scene.rects.len(), 3);
+    }
+
+    #[test]
+    fn locked_rects_are_left_behind_by_a_move_drag() {
+        let mut engine = engine_with_two_rects();
+        let (locked, free) = (engine.doc.rects[0].id, engine.doc.rects[1].id);
+        engine.doc.rects[0].locked = true;
+        engine.selected = vec![locked, free];
+
+        start_move_drag(
+            &mut engine,
+            Vec2::new(350.0, 100.0),
+            Vec2::new(370.0, 130.0),
+        );
+
+        assert_vec2_approx(engine.doc.rects[0].pos, Vec2::new(50.0, 50.0), 1e-4);
+        assert_vec2_approx(engine.doc.rects[1].pos, Vec2::new(320.0, 80.0), 1e-4);
+    }
+
+    #[test]
+    fn delete_selected_spares_locked_rects() {
+        let mut engine = engine_with_two_rects();
+        let (locked, free) = (engine.doc.rects[0].id, engine.doc.rects[1].id);
+        engine.selected = vec![locked];
+        tick_events(&mut engine, vec![InputEvent::SetLocked { locked: true }]);
+        assert!(engine.doc.rects[0].locked);
+        engine.selected = vec![locked, free];
+
+        tick_events(&mut engine, vec![InputEvent::DeleteSelected]);
+
+        assert_eq!(order_of(&engine), vec![locked]);
+        assert_eq!(engine.selected, vec![locked]);
+    }
+
+    #[test]
+    fn select_all_picks_every_rect_and_text_and_ends_drags() {
+        let mut engine = Engine::new();
+        let text_id = engine.doc.alloc_id();
+        engine.doc.texts.push(TextNode {
+            id: text_id,
+            pos: Vec2::new(600.0, 40.0),
+            content: "Title".to_string(),
+            size: 20.0,
+            color: Color::WHITE,
+        });
+        let mut ids: Vec<NodeId> = engine.doc.rects.iter().map(|r| r.id).collect();
+        ids.push(text_id);
+        // Press on the first demo rect without releasing.
+        start_move_drag(
+            &mut engine,
+            Vec2::new(150.0, 140.0),
+            Vec2::new(152.0, 141.0),
+        );
+
+        tick_events(&mut engine, vec![InputEvent::SelectAll]);
+
+        assert_eq!(engine.selected, ids);
+        assert!(matches!(engine.drag_state, DragState::Idle));
+    }
+
+    #[test]
+    fn select_all_skips_hidden_and_locked_rects() {
+        let mut engine = Engine::new();
+        let ids: Vec<NodeId> = engine.doc.rects.iter().map(|r| r.id).collect();
+        engine.doc.rects[0].visible = false;
+        engine.doc.rects[2].locked = true;
+
+        tick_events(&mut engine, vec![InputEvent::SelectAll]);
+        assert_eq!(engine.selected, vec![ids[1]]);
+
+        let mut empty = Engine::with_document(Document::new());
+        tick_events(&mut empty, vec![InputEvent::SelectAll]);
+        assert!(empty.selected.is_empty());
+    }
+
+    #[test]
+    fn clear_selection_empties_selection_and_ends_drags() {
+        let mut engine = Engine::new();
+        select_all_demo_rects(&mut engine);
+        start_move_drag(
+            &mut engine,
+            Vec2::new(150.0, 140.0),
+            Vec2::new(200.0, 200.0),
+        );
+        assert!(matches!(engine.drag_state, DragState::SelectionMove(_)));
+
+        tick_events(&mut engine, vec![InputEvent::ClearSelection]);
+
+        assert!(engine.selected.is_empty());
+        assert!(matches!(engine.drag_state, DragState::Idle));
+        // The cancelled drag's edits are reverted, not committed.
+        assert_vec2_approx(engine.doc.rects[0].pos, Vec2::new(100.0, 100.0), 1e-4);
+    }
+
+    #[test]
+    fn zoom_to_actual_size_keeps_pivot_world_point() {
+        let mut engine = Engine::new();
+        engine.camera = Camera {
+            pan: Vec2::new(37.0, -12.0),
+            zoom: 2.7,
         };
+        let pivot = Vec2::new(310.0, 145.0);
+        let world_before = engine.camera.screen_to_world(pivot);
 
-        // Drag pointer right/down by 20px/10px => camera should pan left/up in world units.
-        cam.pan_by_screen_delta(Vec2::new(20.0, 10.0));
-        assert_vec2_approx(cam.pan, Vec2::new(-10.0, -5.0), 1e-6);
+        tick_events(
+            &mut engine,
+            vec![InputEvent::ZoomToActualSize { pivot_px: pivot }],
+        );
+
+        assert_eq!(engine.camera.zoom, 1.0);
+        assert_vec2_approx(engine.camera.screen_to_world(pivot), world_before, 1e-3);
     }
 
     #[test]
-    fn zoom_at_cursor_keeps_world_point_under_cursor_fixed() {
-        let mut cam = Camera {
-            pan: Vec2::new(10.0, 20.0),
-            zoom: 2.0,
+    fn reset_camera_leaves_document_and_selection_alone() {
+        let mut engine = Engine::new();
+        let ids = select_all_demo_rects(&mut engine);
+        let doc_before = engine.doc.to_json();
+        engine.camera = Camera {
+            pan: Vec2::new(-400.0, 250.0),
+            zoom: 0.3,
         };
 
-        let pivot_screen = Vec2::new(300.0, 120.0);
-        let world_before = cam.screen_to_world(pivot_screen);
+        tick_events(&mut engine, vec![InputEvent::ResetCamera]);
 
-        cam.zoom_at_screen_point(pivot_screen, 1.5);
+        assert_eq!(engine.camera, Camera::default());
+        assert_eq!(engine.selected, ids);
+        assert_eq!(engine.doc.to_json(), doc_before);
+    }
 
-        let world_after = cam.screen_to_world(pivot_screen);
-        assert_vec2_approx(world_after, world_before, 1e-4);
+    fn pan(engine: &mut Engine, delta_px: Vec2) {
+        tick_events(
+            engine,
+            vec![InputEvent::CameraPanByScreenDelta { delta_px }],
+        );
     }
 
     #[test]
-    fn tick_applies_pan_event() {
-        let mut engine = Engine {
-            doc: Document::new(),
-            camera: Camera {
-                pan: Vec2::new(0.0, 0.0),
-                zoom: 2.0,
-            },
-            selected: vec![],
-            drag_state: DragState::Idle,
-            hover_screen_px: None,
-            undo_stack: Vec::new(),
-            redo_stack: Vec::new(),
+    fn pan_stops_at_bounds_with_content_still_visible() {
+        let mut engine = Engine::with_document(Document::new());
+        engine.viewport_px = Vec2::new(800.0, 600.0);
+        engine.set_pan_bounds(Some((Vec2::new(0.0, 0.0), Vec2::new(1000.0, 500.0))));
+
+        // Dragging content far to the left: pan can't pass max - 32px.
+        pan(&mut engine, Vec2::new(-50_000.0, -50_000.0));
+        assert_vec2_approx(engine.camera.pan, Vec2::new(968.0, 468.0), 1e-3);
+
+        // Far to the right: the box's left edge stays 32px inside the viewport.
+        pan(&mut engine, Vec2::new(100_000.0, 100_000.0));
+        assert_vec2_approx(engine.camera.pan, Vec2::new(-768.0, -568.0), 1e-3);
+
+        // Zooming out about the far corner would push the box off-screen.
+        tick_events(
+            &mut engine,
+            vec![InputEvent::CameraZoomAtScreenPoint {
+                pivot_px: Vec2::new(800.0, 600.0),
+                zoom_multiplier: 0.5,
+            }],
+        );
+        assert_approx(engine.camera.zoom, 0.5, 1e-6);
+        assert_vec2_approx(engine.camera.pan, Vec2::new(-1536.0, -1136.0), 1e-3);
+    }
+
+    #[test]
+    fn pan_is_unbounded_without_pan_bounds() {
+        let mut engine = Engine::with_document(Document::new());
+        engine.viewport_px = Vec2::new(800.0, 600.0);
+
+        pan(&mut engine, Vec2::new(-50_000.0, 20_000.0));
+
+        assert_vec2_approx(engine.camera.pan, Vec2::new(50_000.0, -20_000.0), 1e-3);
+    }
+
+    #[test]
+    fn camera_animation_eases_pan_and_zooms_in_log_space() {
+        let mut engine = Engine::with_document(Document::new());
+        let target = Camera {
+            pan: Vec2::new(400.0, -200.0),
+            zoom: 4.0,
         };
+        engine.animate_camera_to(target, 100.0);
 
-        let batch = InputBatch {
+        engine.tick(&InputBatch {
+            dt_ms: Some(25.0),
+            ..Default::default()
+        });
+        engine.tick(&InputBatch {
+            dt_ms: Some(25.0),
+            ..Default::default()
+        });
+        // Halfway: smoothstep(0.5) is 0.5, and the zoom is the geometric mean.
+        assert_vec2_approx(engine.camera.pan, Vec2::new(200.0, -100.0), 1e-3);
+        assert_approx(engine.camera.zoom, 2.0, 1e-4);
+        assert!(engine.is_animating_camera());
+
+        engine.tick(&InputBatch {
+            dt_ms: Some(40.0),
+            ..Default::default()
+        });
+        engine.tick(&InputBatch {
+            dt_ms: Some(40.0),
+            ..Default::default()
+        });
+        assert_eq!(engine.camera, target);
+        assert!(!engine.is_animating_camera());
+    }
+
+    #[test]
+    fn user_pan_cancels_camera_animation() {
+        let mut engine = Engine::with_document(Document::new());
+        engine.animate_camera_to(
+            Camera {
+                pan: Vec2::new(1000.0, 0.0),
+                zoom: 1.0,
+            },
+            100.0,
+        );
+        engine.tick(&InputBatch {
+            dt_ms: Some(50.0),
+            ..Default::default()
+        });
+        let mid = engine.camera;
+
+        engine.tick(&InputBatch {
             events: vec![InputEvent::CameraPanByScreenDelta {
-                delta_px: Vec2::new(20.0, 10.0),
+                delta_px: Vec2::new(-10.0, 0.0),
             }],
-            tool: ToolMode::Select,
+            dt_ms: Some(50.0),
+            ..Default::default()
+        });
+
+        assert!(!engine.is_animating_camera());
+        assert_vec2_approx(engine.camera.pan, Vec2::new(mid.pan.x + 10.0, 0.0), 1e-3);
+    }
+
+    #[test]
+    fn reset_camera_animates_when_transition_is_set() {
+        let mut engine = Engine::with_document(Document::new());
+        engine.camera_transition_ms = 200.0;
+        engine.camera = Camera {
+            pan: Vec2::new(-300.0, 100.0),
+            zoom: 0.25,
         };
 
-        engine.tick(&batch);
+        tick_events(&mut engine, vec![InputEvent::ResetCamera]);
+        assert_approx(engine.camera.zoom, 0.25, 1e-6);
 
-        // same expectations as the direct Camera test
-        assert_vec2_approx(engine.camera.pan, Vec2::new(-10.0, -5.0), 1e-6);
-        assert_approx(engine.camera.zoom, 2.0, 1e-6);
+        engine.tick(&InputBatch {
+            dt_ms: Some(200.0),
+            ..Default::default()
+        });
+        assert_eq!(engine.camera, Camera::default());
     }
 
     #[test]
-    fn tick_applies_zoom_event_and_preserves_world_point_under_cursor() {
-        let mut engine = Engine {
-            doc: Document::new(),
-            camera: Camera {
-                pan: Vec2::new(10.0, 20.0),
-                zoom: 2.0,
-            },
-            selected: vec![],
-            drag_state: DragState::Idle,
-            hover_screen_px: None,
-            undo_stack: Vec::new(),
-            redo_stack: Vec::new(),
-        };
+    fn stroke_reaches_the_render_scene_and_defaults_to_none() {
+        let json = r#"{"next_id":2,"rects":[{"id":1,"pos":{"x":0,"y":0},"size":{"x":10,"y":10},"color":[1,1,1,1]}]}"#;
+        let doc = Document::from_json(json).unwrap();
+        assert_eq!(doc.rects[0].stroke_width, 0.0);
+        assert_eq!(doc.rects[0].stroke_color, Color::BLACK);
+        // Fill-only rects save without stroke fields.
+        assert!(!doc.to_json().contains("stroke"));
 
-        let pivot = Vec2::new(300.0, 120.0);
-        let world_before = engine.camera.screen_to_world(pivot);
+        let mut engine = engine_with_one_rect();
+        engine.doc.rects[0].stroke_color = Color::rgba(0.0, 0.0, 1.0, 1.0);
+        engine.doc.rects[0].stroke_width = 4.0;
+        let out = engine.tick(&InputBatch {
+            events: vec![],
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
+        let instance = &out.render_scene.rects[0];
+        assert_eq!(instance.stroke_width, 4.0);
+        assert_eq!(instance.stroke_color, Color::rgba(0.0, 0.0, 1.0, 1.0));
+
+        let reloaded = Document::from_json(&engine.doc.to_json()).unwrap();
+        assert_eq!(reloaded.rects[0].stroke_width, 4.0);
+        assert_eq!(
+            reloaded.rects[0].stroke_color,
+            engine.doc.rects[0].stroke_color
+        );
+    }
 
-        let batch = InputBatch {
-            events: vec![InputEvent::CameraZoomAtScreenPoint {
-                pivot_px: pivot,
-                zoom_multiplier: 1.5,
-            }],
+    #[test]
+    fn near_miss_click_selects_a_thin_rect_at_any_zoom() {
+        // A 1-unit-wide vertical hairline at x = 100..101.
+        for zoom in [0.25, 1.0, 4.0] {
+            let mut engine =
+                engine_with_rects_at(&[(Vec2::new(100.0, 0.0), Vec2::new(1.0, 200.0))]);
+            let id = engine.doc.rects[0].id;
+            engine.selected.clear();
+            engine.camera.zoom = zoom;
+
+            // 2 screen pixels left of the line, whatever the zoom.
+            let screen = engine.camera.world_to_screen(Vec2::new(100.0, 100.0));
+            let near = Vec2::new(screen.x - 2.0, screen.y);
+            tick_events(
+                &mut engine,
+                vec![
+                    InputEvent::PointerDown {
+                        screen_px: near,
+                        shift: false,
+                        button: 0,
+                    },
+                    InputEvent::PointerUp {
+                        screen_px: near,
+                        button: 0,
+                    },
+                ],
+            );
+            assert_eq!(engine.selected, vec![id], "zoom {zoom}");
+
+            // Past the tolerance is still a miss.
+            let far = engine
+                .camera
+                .screen_to_world(Vec2::new(screen.x - 2.0 * HIT_TOLERANCE_PX, screen.y));
+            assert_eq!(engine.check_collide_rects(far), None, "zoom {zoom}");
+        }
+    }
+
+    #[test]
+    fn hit_tolerance_shrinks_when_zoomed_in() {
+        let mut engine = engine_with_rects_at(&[(Vec2::new(100.0, 0.0), Vec2::new(1.0, 200.0))]);
+        let id = engine.doc.rects[0].id;
+        // Two world units off the line's left edge.
+        let probe = Vec2::new(98.0, 100.0);
+
+        engine.camera.zoom = 1.0;
+        assert_eq!(engine.check_collide_rects(probe), Some(id));
+        // At 4x those two units are 8 screen pixels.
+        engine.camera.zoom = 4.0;
+        assert_eq!(engine.check_collide_rects(probe), None);
+    }
+
+    #[test]
+    fn topmost_padded_candidate_wins_over_an_exact_hit_below() {
+        // The top rect starts 2 units right of where the bottom one ends.
+        let mut engine = engine_with_rects_at(&[
+            (Vec2::new(0.0, 0.0), Vec2::new(100.0, 100.0)),
+            (Vec2::new(102.0, 0.0), Vec2::new(100.0, 100.0)),
+        ]);
+        let ids = order_of(&engine);
+
+        // Inside the bottom rect but within tolerance of the top one.
+        assert_eq!(
+            engine.check_collide_rects(Vec2::new(99.0, 50.0)),
+            Some(ids[1])
+        );
+        // Out of the top rect's reach, the bottom one is hit.
+        assert_eq!(
+            engine.check_collide_rects(Vec2::new(90.0, 50.0)),
+            Some(ids[0])
+        );
+        // In the gap both are near misses; the topmost wins.
+        assert_eq!(
+            engine.check_collide_rects(Vec2::new(101.0, 50.0)),
+            Some(ids[1])
+        );
+        engine.doc.rects[1].visible = false;
+        engine.invalidate_spatial_index();
+        assert_eq!(
+            engine.check_collide_rects(Vec2::new(101.0, 50.0)),
+            Some(ids[0])
+        );
+    }
+
+    /// Helper: press and release at `screen_px` in a batch stamped `timestamp_ms`.
+    fn click_at(engine: &mut Engine, screen_px: Vec2, timestamp_ms: f64) {
+        engine.tick(&InputBatch {
+            events: vec![
+                InputEvent::PointerDown {
+                    screen_px,
+                    shift: false,
+                    button: 0,
+                },
+                InputEvent::PointerUp {
+                    screen_px,
+                    button: 0,
+                },
+            ],
             tool: ToolMode::Select,
-        };
+            timestamp_ms: Some(timestamp_ms),
+            ..Default::default()
+        });
+    }
 
-        engine.tick(&batch);
+    #[test]
+    fn double_click_on_empty_canvas_creates_a_selected_rect() {
+        let mut engine = engine_with_one_rect();
+        let at = Vec2::new(400.0, 300.0);
+
+        click_at(&mut engine, at, 1000.0);
+        assert_eq!(engine.doc.rects.len(), 1);
+        click_at(&mut engine, Vec2::new(401.0, 301.0), 1200.0);
+
+        assert_eq!(engine.doc.rects.len(), 2);
+        let created = engine.doc.rects.last().unwrap();
+        assert_eq!(created.size, DOUBLE_CLICK_RECT_SIZE);
+        assert_vec2_approx(created.center(), Vec2::new(401.0, 301.0), 1e-4);
+        assert_eq!(engine.selected, vec![created.id]);
+
+        // A third quick press is the start of a new pair, not another rect.
+        click_at(&mut engine, Vec2::new(600.0, 300.0), 1300.0);
+        assert_eq!(engine.doc.rects.len(), 2);
+
+        tick_events(&mut engine, vec![InputEvent::Undo]);
+        assert_eq!(engine.doc.rects.len(), 1);
+    }
+
+    #[test]
+    fn double_click_on_a_rect_creates_nothing() {
+        let mut engine = engine_with_one_rect();
+        let id = engine.doc.rects[0].id;
+
+        click_at(&mut engine, Vec2::new(100.0, 100.0), 1000.0);
+        click_at(&mut engine, Vec2::new(100.0, 100.0), 1100.0);
+
+        assert_eq!(engine.doc.rects.len(), 1);
+        assert_eq!(engine.selected, vec![id]);
+    }
+
+    #[test]
+    fn double_click_window_is_configurable() {
+        let mut engine = engine_with_one_rect();
+        let at = Vec2::new(400.0, 300.0);
+
+        // 700 ms apart is too slow for the default window.
+        click_at(&mut engine, at, 1000.0);
+        click_at(&mut engine, at, 1700.0);
+        assert_eq!(engine.doc.rects.len(), 1);
+
+        engine.double_click_ms = 800.0;
+        click_at(&mut engine, at, 2400.0);
+        assert_eq!(engine.doc.rects.len(), 2);
+
+        // Too far apart on screen, however quick.
+        click_at(&mut engine, Vec2::new(700.0, 300.0), 3000.0);
+        click_at(&mut engine, Vec2::new(720.0, 300.0), 3050.0);
+        assert_eq!(engine.doc.rects.len(), 2);
+    }
+
+    /// Helper: drag a marquee from `from` to `to` in screen space.
+    fn marquee_drag(engine: &mut Engine, from: Vec2, to: Vec2, shift: bool) {
+        tick_events(
+            engine,
+            vec![
+                InputEvent::PointerDown {
+                    screen_px: from,
+                    shift,
+                    button: 0,
+                },
+                InputEvent::PointerMove {
+                    screen_px: to,
+                    buttons: 1,
+                },
+                InputEvent::PointerUp {
+                    screen_px: to,
+                    button: 0,
+                },
+            ],
+        );
+    }
+
+    #[test]
+    fn straddling_rect_is_marquee_selected_only_in_intersect_mode() {
+        // Rect 0 spans x 50..150, so a marquee to x = 100 cuts through it.
+        let straddle = (Vec2::new(10.0, 10.0), Vec2::new(100.0, 200.0));
+        let around = (Vec2::new(10.0, 10.0), Vec2::new(200.0, 200.0));
+
+        let mut engine = engine_with_two_rects();
+        let ids = order_of(&engine);
+        marquee_drag(&mut engine, straddle.0, straddle.1, false);
+        assert_eq!(engine.selected, vec![ids[0]]);
+
+        engine.marquee_mode = MarqueeMode::Contain;
+        marquee_drag(&mut engine, straddle.0, straddle.1, false);
+        assert!(engine.selected.is_empty());
+        marquee_drag(&mut engine, around.0, around.1, false);
+        assert_eq!(engine.selected, vec![ids[0]]);
+    }
+
+    #[test]
+    fn shift_marquee_adds_to_the_selection_in_both_modes() {
+        for mode in [MarqueeMode::Intersect, MarqueeMode::Contain] {
+            let mut engine = engine_with_two_rects();
+            let ids = order_of(&engine);
+            engine.marquee_mode = mode;
+            engine.selected = vec![ids[1]];
+
+            marquee_drag(
+                &mut engine,
+                Vec2::new(10.0, 10.0),
+                Vec2::new(200.0, 200.0),
+                true,
+            );
+            assert_eq!(engine.selected, vec![ids[1], ids[0]], "{mode:?}");
+
+            // A straddling shift-marquee keeps the selection and adds only
+            // what the mode accepts.
+            engine.selected = vec![ids[1]];
+            marquee_drag(
+                &mut engine,
+                Vec2::new(10.0, 10.0),
+                Vec2::new(100.0, 200.0),
+                true,
+            );
+            let expected = match mode {
+                MarqueeMode::Contain => vec![ids[1]],
+                _ => vec![ids[1], ids[0]],
+            };
+            assert_eq!(engine.selected, expected, "{mode:?}");
+        }
+    }
+
+    #[test]
+    fn by_direction_marquee_contains_rightward_and_intersects_leftward() {
+        let mut engine = engine_with_two_rects();
+        let ids = order_of(&engine);
+        engine.marquee_mode = MarqueeMode::ByDirection;
+
+        // Left to right through rect 0: contain, so nothing.
+        marquee_drag(
+            &mut engine,
+            Vec2::new(10.0, 10.0),
+            Vec2::new(100.0, 200.0),
+            false,
+        );
+        assert!(engine.selected.is_empty());
+
+        // Right to left over the same box: intersect.
+        marquee_drag(
+            &mut engine,
+            Vec2::new(100.0, 200.0),
+            Vec2::new(10.0, 10.0),
+            false,
+        );
+        assert_eq!(engine.selected, vec![ids[0]]);
+    }
+
+    #[test]
+    fn set_selection_fill_recolors_each_selected_rect_undoably() {
+        let mut engine = engine_with_stacked_rects();
+        let ids = order_of(&engine);
+        let untouched = engine.doc.rects[1].color;
+        engine.selected = vec![ids[0], ids[2]];
+
+        let out = engine.tick(&InputBatch {
+            events: vec![InputEvent::SetSelectionFill {
+                color: RgbaColor {
+                    r: 0.2,
+                    g: 1.5,
+                    b: -1.0,
+                    a: 0.5,
+                },
+            }],
+            tool: ToolMode::Select,
+            ..Default::default()
+        });
 
-        assert_approx(engine.camera.zoom, 3.0, 1e-6);
-        let world_after = engine.camera.screen_to_world(pivot);
-        assert_vec2_approx(world_after, world_before, 1e-4);
+        // Channels are clamped into 0..=1.
+        let expected = Color::rgba(0.2, 1.0, 0.0, 0.5);
+        assert_eq!(engine.doc.rects[0].color, expected);
+        assert_eq!(engine.doc.rects[1].color, untouched);
+        assert_eq!(engine.doc.rects[2].color, expected);
+        assert_eq!(out.render_scene.rects[0].color, expected);
+        assert_eq!(out.render_scene.rects[2].color, expected);
+
+        tick_events(&mut engine, vec![InputEvent::Undo]);
+        assert_eq!(engine.doc.rects[0].color, untouched);
+
+        // Nothing selected: no change and no history entry.
+        let depth = engine.undo_stack.len();
+        engine.selected.clear();
+        tick_events(
+            &mut engine,
+            vec![InputEvent::SetSelectionFill {
+                color: RgbaColor {
+                    r: 0.0,
+                    g: 0.0,
+                    b: 0.0,
+                    a: 1.0,
+                },
+            }],
+        );
+        assert_eq!(engine.undo_stack.len(), depth);
     }
 
     #[test]
-    fn tick_applies_events_in_order() {
-        let mut engine = Engine::new();
-        engine.camera.pan = Vec2::new(5.0, -7.0);
-        engine.camera.zoom = 2.0;
+    fn set_fill_recolors_a_multi_selection_from_a_color_array() {
+        let mut engine = engine_with_stacked_rects();
+        let ids = order_of(&engine);
+        let untouched = engine.doc.rects[1].color;
+        engine.selected = vec![ids[0], ids[2]];
+
+        let batch: InputBatch = serde_json::from_str(
+            r#"{"events": [{"type": "set_fill", "color": [0.2, 1.5, -1.0, 0.5]}]}"#,
+        )
+        .unwrap();
+        let out = engine.tick(&batch);
+
+        // Channels are clamped into 0..=1.
+        let expected = Color::rgba(0.2, 1.0, 0.0, 0.5);
+        assert_eq!(engine.doc.rects[0].color, expected);
+        assert_eq!(engine.doc.rects[1].color, untouched);
+        assert_eq!(engine.doc.rects[2].color, expected);
+        assert_eq!(out.render_scene.rects[2].color, expected);
+
+        tick_events(&mut engine, vec![InputEvent::Undo]);
+        assert_eq!(engine.doc.rects[0].color, untouched);
+        assert_eq!(engine.doc.rects[2].color, untouched);
+    }
 
-        let batch = InputBatch {
-            events: vec![
-                InputEvent::CameraPanByScreenDelta {
-                    delta_px: Vec2::new(40.0, 0.0),
+    /// Helper: drag a corner of the single rect `(pos, size)` from `from` to
+    /// `to` with shift held, returning the resized rect.
+    fn shift_resize(pos: Vec2, size: Vec2, from: Vec2, to: Vec2) -> RectNode {
+        let mut engine = engine_with_rects_at(&[(pos, size)]);
+        tick_events(
+            &mut engine,
+            vec![
+                InputEvent::PointerDown {
+                    screen_px: from,
+                    shift: false,
+                    button: 0,
                 },
-                InputEvent::CameraZoomAtScreenPoint {
-                    pivot_px: Vec2::new(100.0, 50.0),
-                    zoom_multiplier: 0.5,
+                InputEvent::KeyDown {
+                    key: "Shift".to_string(),
+                    shift: true,
+                },
+                InputEvent::PointerMove {
+                    screen_px: to,
+                    buttons: 1,
                 },
             ],
-            tool: ToolMode::Select,
-        };
-
-        // expected result: applying the same ops directly, in the same order
-        let mut expected = engine.camera;
-        expected.pan_by_screen_delta(Vec2::new(40.0, 0.0));
-        expected.zoom_at_screen_point(Vec2::new(100.0, 50.0), 0.5);
+        );
+        assert!(matches!(engine.drag_state, DragState::Resize(_)));
+        tick_events(
+            &mut engine,
+            vec![InputEvent::PointerUp {
+                screen_px: to,
+                button: 0,
+            }],
+        );
+        engine.doc.rects[0].clone()
+    }
 
-        engine.tick(&batch);
+    #[test]
+    fn shift_corner_resize_keeps_the_aspect_ratio() {
+        // 100x50 rect at (50, 50); its SE corner is at (150, 100).
+        let (pos, size) = (Vec2::new(50.0, 50.0), Vec2::new(100.0, 50.0));
+
+        // Mostly horizontal drag: width leads, height follows.
+        let rect = shift_resize(pos, size, Vec2::new(150.0, 100.0), Vec2::new(250.0, 110.0));
+        assert_vec2_approx(rect.pos, pos, 1e-4);
+        assert_vec2_approx(rect.size, Vec2::new(200.0, 100.0), 1e-4);
+
+        // Mostly vertical drag on the NW corner: height leads, SE stays put.
+        let rect = shift_resize(pos, size, Vec2::new(50.0, 50.0), Vec2::new(45.0, 0.0));
+        assert_vec2_approx(rect.size, Vec2::new(200.0, 100.0), 1e-4);
+        assert_vec2_approx(
+            Vec2::new(rect.pos.x + rect.size.x, rect.pos.y + rect.size.y),
+            Vec2::new(150.0, 100.0),
+            1e-4,
+        );
 
-        assert_vec2_approx(engine.camera.pan, expected.pan, 1e-5);
-        assert_approx(engine.camera.zoom, expected.zoom, 1e-6);
+        // Without shift the same drag is free.
+        let rect = resize_corner(Vec2::new(150.0, 150.0), Vec2::new(250.0, 160.0));
+        assert_vec2_approx(rect.size, Vec2::new(200.0, 110.0), 1e-4);
     }
 
     #[test]
-    fn hit_test_picks_topmost_rect() {
-        let engine = Engine::new();
-        let top_id = engine.doc.rects[2].id;
-        let hit = engine.check_collide_rects(Vec2::new(610.0, 910.0));
-        assert_eq!(hit, Some(top_id));
+    fn shift_resize_of_a_flat_rect_falls_back_to_free_resize() {
+        let (pos, size) = (Vec2::new(50.0, 50.0), Vec2::new(100.0, 0.0));
+        let rect = shift_resize(pos, size, Vec2::new(150.0, 50.0), Vec2::new(180.0, 70.0));
+
+        assert!(rect.size.x.is_finite() && rect.size.y.is_finite());
+        assert_vec2_approx(rect.pos, pos, 1e-4);
+        assert_vec2_approx(rect.size, Vec2::new(130.0, 20.0), 1e-4);
     }
 
     #[test]
-    fn selection_rules_apply_correcly() {
-        let mut engine = Engine::new();
-        let id = engine.doc.rects[0].id;
-
-        engine.apply_selection(Some(id), false);
-        assert_eq!(engine.selected, vec![id]);
+    fn moved_rect_snaps_its_left_edge_to_a_nearby_rect() {
+        let mut engine = engine_with_two_rects();
+        let target_left = engine.doc.rects[1].pos.x;
 
-        engine.apply_selection(Some(id), true);
-        assert!(engine.selected.is_empty());
+        // Lands with its left edge 4px right of the other rect's.
+        start_move_drag(
+            &mut engine,
+            Vec2::new(100.0, 100.0),
+            Vec2::new(354.0, 300.0),
+        );
 
-        engine.apply_selection(None, false);
-        assert!(engine.selected.is_empty());
+        assert_approx(engine.doc.rects[0].pos.x, target_left, 1e-4);
+        assert_approx(engine.doc.rects[0].pos.y, 250.0, 1e-4);
+        let DragState::SelectionMove(drag) = &engine.drag_state else {
+            panic!("expected a move drag");
+        };
+        // Equal widths, so the centers and right edges line up as well.
+        assert_eq!(drag.guides.len(), 3);
+        assert_eq!(
+            drag.guides[0],
+            SnapGuide {
+                start: Vec2::new(target_left, 50.0),
+                end: Vec2::new(target_left, 350.0),
+            }
+        );
+        let overlay = engine.update_overlay_scene(&ToolMode::Select);
+        assert!(overlay.rects.iter().any(|rect| rect.size[1] == 300.0
+            && (rect.pos[0] + rect.size[0] * 0.5 - target_left).abs() < 1e-4));
     }
 
-    /// Helper: build a minimal Engine with a single 100x100 rect at (50, 50).
-    fn engine_with_one_rect() -> Engine {
-        let mut doc = Document::new();
-        let id = doc.alloc_id();
-        doc.rects.push(RectNode {
-            id,
-            pos: Vec2::new(50.0, 50.0),
-            size: Vec2::new(100.0, 100.0),
-            color: [1.0, 0.0, 0.0, 1.0],
-        });
-        Engine {
-            doc,
-            camera: Camera::default(),
-            selected: vec![],
-            drag_state: DragState::Idle,
-            hover_screen_px: None,
-            undo_stack: Vec::new(),
-            redo_stack: Vec::new(),
-        }
-    }
+    #[test]
+    fn moved_rect_outside_the_guide_threshold_stays_put() {
+        let mut engine = engine_with_two_rects();
 
-    /// Helper: build a minimal Engine with two non-overlapping 100x100 rects.
-    fn engine_with_two_rects() -> Engine {
-        let mut doc = Document::new();
-        let id0 = doc.alloc_id();
-        let id1 = doc.alloc_id();
-        doc.rects.push(RectNode {
-            id: id0,
-            pos: Vec2::new(50.0, 50.0),
-            size: Vec2::new(100.0, 100.0),
-            color: [1.0, 0.0, 0.0, 1.0],
-        });
-        doc.rects.push(RectNode {
-            id: id1,
-            pos: Vec2::new(300.0, 50.0),
-            size: Vec2::new(100.0, 100.0),
-            color: [0.0, 0.0, 1.0, 1.0],
-        });
-        Engine {
-            doc,
-            camera: Camera::default(),
-            selected: vec![],
-            drag_state: DragState::Idle,
-            hover_screen_px: None,
-            undo_stack: Vec::new(),
-            redo_stack: Vec::new(),
-        }
-    }
+        // 10px off every edge and center of the other rect.
+        start_move_drag(
+            &mut engine,
+            Vec2::new(100.0, 100.0),
+            Vec2::new(360.0, 300.0),
+        );
 
-    // -----------------------------------------------------------------------
-    // Selection move — single selection
-    // -----------------------------------------------------------------------
+        assert_approx(engine.doc.rects[0].pos.x, 310.0, 1e-4);
+        let DragState::SelectionMove(drag) = &engine.drag_state else {
+            panic!("expected a move drag");
+        };
+        assert!(drag.guides.is_empty());
+
+        // The threshold is in screen pixels, so zooming in shrinks it.
+        let mut engine = engine_with_two_rects();
+        engine.camera.zoom = 4.0;
+        start_move_drag(
+            &mut engine,
+            Vec2::new(400.0, 400.0),
+            Vec2::new(1416.0, 1200.0),
+        );
+        assert_approx(engine.doc.rects[0].pos.x, 304.0, 1e-4);
+    }
 
-    /// Clicking a rect selects it (PendingSelectionMove after PointerDown on
-    /// an already-selected rect). Moving beyond the drag threshold actually
-    /// moves the rect. PointerUp finalises the position.
     #[test]
-    fn single_selection_drag_moves_rect() {
+    fn dimension_label_tracks_the_selected_rect_during_resize() {
         let mut engine = engine_with_one_rect();
-        let id = engine.doc.rects[0].id;
-        let origin = engine.doc.rects[0].pos;
-
-        // First click: select the rect (hit on unselected → apply_selection, stays Idle).
-        engine.tick(&InputBatch {
-            events: vec![InputEvent::PointerDown {
-                screen_px: Vec2::new(100.0, 100.0), // inside rect (50..150, 50..150)
-                shift: false,
-                button: 0,
-            }],
-            tool: ToolMode::Select,
-        });
-        assert_eq!(engine.selected, vec![id]);
+        let labels = |engine: &Engine| engine.update_overlay_scene(&ToolMode::Select).labels;
+        assert!(labels(&engine).is_empty());
+
+        engine.selected = vec![engine.doc.rects[0].id];
+        let label = labels(&engine).remove(0);
+        assert_eq!(label.content, "100 \u{d7} 100");
+        assert!(label.pos[1] > 150.0, "label sits below the rect");
+
+        // Mid-drag, before the pointer is released.
+        start_move_drag(
+            &mut engine,
+            Vec2::new(150.0, 150.0),
+            Vec2::new(170.5, 160.0),
+        );
+        assert!(matches!(engine.drag_state, DragState::Resize(_)));
+        assert_eq!(labels(&engine)[0].content, "120.5 \u{d7} 110");
 
-        // Second click on the now-selected rect → PendingSelectionMove.
-        engine.tick(&InputBatch {
-            events: vec![InputEvent::PointerDown {
-                screen_px: Vec2::new(100.0, 100.0),
-                shift: false,
-                button: 0,
-            }],
-            tool: ToolMode::Select,
-        });
-        assert!(matches!(
-            engine.drag_state,
-            DragState::PendingSelectionMove(_)
-        ));
+        // Multi-selections get no label.
+        let mut engine = engine_with_two_rects();
+        engine.selected = engine.doc.rects.iter().map(|rect| rect.id).collect();
+        assert!(labels(&engine).is_empty());
+    }
 
-        // Move beyond 6 px threshold → SelectionMove + rect displaced.
-        // World == screen at zoom=1, pan=(0,0).  Delta in world = (30, 20).
-        engine.tick(&InputBatch {
-            events: vec![InputEvent::PointerMove {
-                screen_px: Vec2::new(130.0, 120.0),
-                buttons: 1,
-            }],
-            tool: ToolMode::Select,
-        });
-        assert!(matches!(engine.drag_state, DragState::SelectionMove(_)));
-        let pos_mid = engine.doc.rects[0].pos;
-        assert_vec2_approx(pos_mid, Vec2::new(origin.x + 30.0, origin.y + 20.0), 1e-4);
+    #[test]
+    fn raise_and_lower_one_move_the_middle_rect_a_single_step() {
+        let mut engine = engine_with_stacked_rects();
+        let ids = order_of(&engine);
+        engine.selected = vec![ids[1]];
+
+        tick_events(&mut engine, vec![InputEvent::RaiseOne]);
+        assert_eq!(order_of(&engine), vec![ids[0], ids[2], ids[1]]);
+        assert_eq!(
+            engine.check_collide_rects(Vec2::new(50.0, 50.0)),
+            Some(ids[1])
+        );
 
-        // Continue dragging further.
-        engine.tick(&InputBatch {
-            events: vec![InputEvent::PointerMove {
-                screen_px: Vec2::new(160.0, 150.0),
-                buttons: 1,
-            }],
-            tool: ToolMode::Select,
-        });
-        let pos_far = engine.doc.rects[0].pos;
-        assert_vec2_approx(pos_far, Vec2::new(origin.x + 60.0, origin.y + 50.0), 1e-4);
+        engine.undo();
+        assert_eq!(order_of(&engine), ids);
 
-        // Release pointer → Idle, position retained.
-        engine.tick(&InputBatch {
-            events: vec![InputEvent::PointerUp {
-                screen_px: Vec2::new(160.0, 150.0),
-                button: 0,
-            }],
-            tool: ToolMode::Select,
-        });
-        assert!(matches!(engine.drag_state, DragState::Idle));
-        assert_vec2_approx(engine.doc.rects[0].pos, pos_far, 1e-4);
+        // (30, 30) is covered by the bottom and middle rects only.
+        tick_events(&mut engine, vec![InputEvent::LowerOne]);
+        assert_eq!(order_of(&engine), vec![ids[1], ids[0], ids[2]]);
+        assert_eq!(
+            engine.check_collide_rects(Vec2::new(30.0, 30.0)),
+            Some(ids[0])
+        );
+        assert_eq!(
+            engine.check_collide_rects(Vec2::new(50.0, 50.0)),
+            Some(ids[2])
+        );
     }
 
-    /// A move below the 6 px drag threshold must NOT start SelectionMove and
-    /// must NOT displace the rect.
     #[test]
-    fn single_selection_drag_below_threshold_does_not_move_rect() {
-        let mut engine = engine_with_one_rect();
-        let id = engine.doc.rects[0].id;
-        let origin = engine.doc.rects[0].pos;
+    fn raise_one_shifts_adjacent_selected_rects_together_and_stops_at_the_top() {
+        let size = Vec2::new(10.0, 10.0);
+        let mut engine = engine_with_rects_at(&[(Vec2::new(0.0, 0.0), size); 4]);
+        let ids = order_of(&engine);
+        engine.selected = vec![ids[2], ids[1]];
+
+        tick_events(&mut engine, vec![InputEvent::RaiseOne]);
+        assert_eq!(order_of(&engine), vec![ids[0], ids[3], ids[1], ids[2]]);
+
+        // Pinned at the top: nothing moves and no history entry is added.
+        let history = engine.undo_stack.len();
+        tick_events(&mut engine, vec![InputEvent::RaiseOne]);
+        assert_eq!(order_of(&engine), vec![ids[0], ids[3], ids[1], ids[2]]);
+        assert_eq!(engine.undo_stack.len(), history);
+
+        // Split selections step independently, each stopping at its bound.
+        engine.selected = vec![ids[0], ids[2]];
+        tick_events(&mut engine, vec![InputEvent::LowerOne]);
+        assert_eq!(order_of(&engine), vec![ids[0], ids[3], ids[2], ids[1]]);
+    }
 
-        // Select then enter PendingSelectionMove.
-        engine.tick(&InputBatch {
-            events: vec![InputEvent::PointerDown {
-                screen_px: Vec2::new(100.0, 100.0),
-                shift: false,
-                button: 0,
-            }],
-            tool: ToolMode::Select,
-        });
-        engine.tick(&InputBatch {
-            events: vec![InputEvent::PointerDown {
-                screen_px: Vec2::new(100.0, 100.0),
-                shift: false,
-                button: 0,
-            }],
-            tool: ToolMode::Select,
-        });
-        assert!(matches!(
-            engine.drag_state,
-            DragState::PendingSelectionMove(_)
-        ));
-        assert_eq!(engine.selected, vec![id]);
+    #[test]
+    fn pasting_into_another_document_reallocates_ids_and_offsets() {
+        let mut source = engine_with_two_rects();
+        source.selected = vec![source.doc.rects[1].id];
+        let payload = source.selection_clipboard().rects;
+        let copied = payload[0].clone();
+
+        // A fresh document hands out the same ids the payload carries.
+        let mut target = engine_with_two_rects();
+        let existing: Vec<NodeId> = order_of(&target);
+        assert!(existing.contains(&copied.id));
+
+        let pasted = target.insert_nodes(payload, Vec::new(), Vec2::new(5.0, -10.0));
+        assert_eq!(pasted.len(), 1);
+        assert!(!existing.contains(&pasted[0]));
+        assert_eq!(target.selected, pasted);
+        let rect = target.rect(pasted[0]).unwrap();
+        assert_eq!(rect.pos, Vec2::new(copied.pos.x + 5.0, copied.pos.y - 10.0));
+        assert_eq!((rect.size, rect.color), (copied.size, copied.color));
+        // The originals are untouched.
+        assert_eq!(target.doc.rects.len(), 3);
+        assert_eq!(target.doc.rects[1].pos, copied.pos);
+
+        target.undo();
+        assert_eq!(order_of(&target), existing);
+        assert!(
+            target
+                .insert_nodes(Vec::new(), Vec::new(), Vec2::default())
+                .is_empty()
+        );
+        assert!(target.undo_stack.is_empty());
+    }
 
-        // Move only 3 px — below 6 px threshold.
-        engine.tick(&InputBatch {
-            events: vec![InputEvent::PointerMove {
-                screen_px: Vec2::new(103.0, 100.0),
-                buttons: 1,
+    #[test]
+    fn set_bounds_updates_the_named_rect_regardless_of_selection() {
+        let mut engine = engine_with_two_rects();
+        let id = engine.doc.rects[1].id;
+        let before = engine.doc.rects.clone();
+        engine.selected = vec![engine.doc.rects[0].id];
+
+        tick_events(
+            &mut engine,
+            vec![InputEvent::SetBounds {
+                id,
+                pos: Vec2::new(12.5, -4.0),
+                size: Vec2::new(80.0, -3.0),
             }],
-            tool: ToolMode::Select,
-        });
-        // Should still be pending, not moved.
-        assert!(matches!(
-            engine.drag_state,
-            DragState::PendingSelectionMove(_)
-        ));
-        assert_vec2_approx(engine.doc.rects[0].pos, origin, 1e-4);
+        );
+        let rect = engine.rect(id).unwrap();
+        assert_eq!(rect.pos, Vec2::new(12.5, -4.0));
+        assert_eq!(rect.size, Vec2::new(80.0, 1.0));
+        assert_eq!(engine.doc.rects[0], before[0]);
+        assert_eq!(engine.selected, vec![before[0].id]);
+
+        engine.undo();
+        assert_eq!(engine.doc.rects, before);
     }
 
-    /// Cancelling a drag mid-flight must stop the move (state → Idle) but the
-    /// rect keeps its last displaced position (no automatic rollback).
     #[test]
-    fn single_selection_drag_cancel_stops_move() {
-        let mut engine = engine_with_one_rect();
-        let origin = engine.doc.rects[0].pos;
-
-        // Select.
-        engine.tick(&InputBatch {
-            events: vec![InputEvent::PointerDown {
-                screen_px: Vec2::new(100.0, 100.0),
-                shift: false,
-                button: 0,
-            }],
-            tool: ToolMode::Select,
-        });
-        // Enter PendingSelectionMove.
-        engine.tick(&InputBatch {
-            events: vec![InputEvent::PointerDown {
-                screen_px: Vec2::new(100.0, 100.0),
-                shift: false,
-                button: 0,
-            }],
-            tool: ToolMode::Select,
-        });
-        // Start move.
-        engine.tick(&InputBatch {
-            events: vec![InputEvent::PointerMove {
-                screen_px: Vec2::new(150.0, 100.0),
-                buttons: 1,
-            }],
-            tool: ToolMode::Select,
-        });
-        assert!(matches!(engine.drag_state, DragState::SelectionMove(_)));
-        let pos_after_move = engine.doc.rects[0].pos;
-        assert_vec2_approx(pos_after_move, Vec2::new(origin.x + 50.0, origin.y), 1e-4);
+    fn set_bounds_on_an_unknown_id_or_with_non_finite_values_is_a_no_op() {
+        let mut engine = engine_with_two_rects();
+        let before = engine.doc.rects.clone();
+        let id = engine.doc.rects[0].id;
+        let set_bounds = |id, pos, size| InputEvent::SetBounds { id, pos, size };
+
+        tick_events(
+            &mut engine,
+            vec![
+                set_bounds(NodeId(999), Vec2::new(0.0, 0.0), Vec2::new(10.0, 10.0)),
+                set_bounds(id, Vec2::new(f32::NAN, 0.0), Vec2::new(10.0, 10.0)),
+                set_bounds(id, Vec2::new(0.0, 0.0), Vec2::new(f32::INFINITY, 10.0)),
+                set_bounds(id, Vec2::new(0.0, 0.0), Vec2::new(10.0, f32::NAN)),
+            ],
+        );
+        assert_eq!(engine.doc.rects, before);
+        assert!(engine.undo_stack.is_empty());
+    }
 
-        // Cancel.
-        engine.tick(&InputBatch {
-            events: vec![InputEvent::PointerCancel],
-            tool: ToolMode::Select,
-        });
-        assert!(matches!(engine.drag_state, DragState::Idle));
-        assert_vec2_approx(engine.doc.rects[0].pos, origin, 1e-4);
+    #[test]
+    fn pick_at_names_the_node_a_click_selects() {
+        let setup = || {
+            let mut engine = engine_with_stacked_rects();
+            engine.camera.zoom = 2.0;
+            engine.camera.pan = Vec2::new(10.0, 10.0);
+            engine
+        };
+        // World (50, 50), (30, 30), (10, 10), (130, 130) and empty (300, 300).
+        let probes = [
+            (80.0, 80.0),
+            (40.0, 40.0),
+            (0.0, 0.0),
+            (240.0, 240.0),
+            (580.0, 580.0),
+        ];
+        let ids = order_of(&setup());
+        let expected = [Some(ids[2]), Some(ids[1]), Some(ids[0]), Some(ids[2]), None];
+
+        for ((x, y), expected) in probes.into_iter().zip(expected) {
+            let screen = Vec2::new(x, y);
+            let mut engine = setup();
+            let picked = engine.pick_at(screen);
+            assert_eq!(picked, expected, "pick at {screen:?}");
+
+            click_at(&mut engine, screen, 0.0);
+            assert_eq!(engine.selected, picked.into_iter().collect::<Vec<_>>());
+        }
+
+        // Locked and hidden rects are passed over the same way.
+        let mut engine = setup();
+        engine.doc.rects[2].locked = true;
+        engine.doc.rects[1].visible = false;
+        let screen = Vec2::new(80.0, 80.0);
+        assert_eq!(engine.pick_at(screen), Some(ids[0]));
+        click_at(&mut engine, screen, 0.0);
+        assert_eq!(engine.selected, vec![ids[0]]);
     }
 
-    // -----------------------------------------------------------------------
-    // Selection move — multi-selection
-    // -----------------------------------------------------------------------
+    #[test]
+    fn hit_testing_follows_edits_made_through_doc_mut() {
+        let mut engine = engine_with_one_rect();
+        let id = engine.doc().rects[0].id;
+        assert_eq!(
+            engine.check_collide_rects(Vec2::new(100.0, 100.0)),
+            Some(id)
+        );
+
+        engine.doc_mut().rects[0].pos = Vec2::new(500.0, 500.0);
+        assert_eq!(engine.check_collide_rects(Vec2::new(100.0, 100.0)), None);
+        assert_eq!(
+            engine.check_collide_rects(Vec2::new(550.0, 550.0)),
+            Some(id)
+        );
+
+        engine.doc_mut().rects.clear();
+        assert_eq!(engine.check_collide_rects(Vec2::new(550.0, 550.0)), None);
+    }
 
-    /// Dragging one of multiple selected rects moves ALL of them by the same
-    /// world-space delta, preserving their relative positions.
     #[test]
-    fn multi_selection_drag_moves_all_selected_rects() {
+    fn stale_spatial_index_is_rebuilt_when_the_rect_count_changes() {
         let mut engine = engine_with_two_rects();
-        let id0 = engine.doc.rects[0].id;
-        let id1 = engine.doc.rects[1].id;
-        let origin0 = engine.doc.rects[0].pos;
-        let origin1 = engine.doc.rects[1].pos;
+        assert!(
+            engine
+                .check_collide_rects(Vec2::new(350.0, 100.0))
+                .is_some()
+        );
 
-        // Select rect 0.
-        engine.tick(&InputBatch {
-            events: vec![InputEvent::PointerDown {
-                screen_px: Vec2::new(100.0, 100.0), // inside rect 0
-                shift: false,
-                button: 0,
-            }],
-            tool: ToolMode::Select,
-        });
-        // Add rect 1 to selection with shift-click.
-        engine.tick(&InputBatch {
-            events: vec![InputEvent::PointerDown {
-                screen_px: Vec2::new(350.0, 100.0), // inside rect 1
-                shift: true,
-                button: 0,
-            }],
-            tool: ToolMode::Select,
-        });
-        assert!(engine.selected.contains(&id0));
-        assert!(engine.selected.contains(&id1));
+        // Bypass `doc_mut`, as engine code that forgets to invalidate would.
+        engine.doc.rects.truncate(1);
+        assert_eq!(engine.check_collide_rects(Vec2::new(350.0, 100.0)), None);
+        assert!(
+            engine
+                .check_collide_rects(Vec2::new(100.0, 100.0))
+                .is_some()
+        );
+    }
 
-        // Click on rect 0 again (already selected, no shift) → PendingSelectionMove.
-        engine.tick(&InputBatch {
-            events: vec![InputEvent::PointerDown {
-                screen_px: Vec2::new(100.0, 100.0),
-                shift: false,
+    #[test]
+    fn animated_and_instant_camera_moves_stay_inside_pan_bounds() {
+        let mut engine = Engine::with_document(Document::new());
+        engine.viewport_px = Vec2::new(800.0, 600.0);
+        engine.camera.pan = Vec2::new(5000.0, 5000.0);
+        engine.set_pan_bounds(Some((Vec2::new(5000.0, 5000.0), Vec2::new(6000.0, 6000.0))));
+        // Resetting to the origin would leave the bounds box entirely offscreen.
+        let clamped = Vec2::new(5000.0 - 800.0 + 32.0, 5000.0 - 600.0 + 32.0);
+
+        engine.camera_transition_ms = 100.0;
+        tick_events(&mut engine, vec![InputEvent::ResetCamera]);
+        for _ in 0..4 {
+            engine.tick(&InputBatch {
+                dt_ms: Some(25.0),
+                ..Default::default()
+            });
+            assert!(engine.camera.pan.x >= clamped.x - 1e-3);
+            assert!(engine.camera.pan.y >= clamped.y - 1e-3);
+        }
+        assert!(!engine.is_animating_camera());
+        assert_vec2_approx(engine.camera.pan, clamped, 1e-3);
+
+        engine.camera.pan = Vec2::new(5000.0, 5000.0);
+        engine.camera_transition_ms = 0.0;
+        tick_events(&mut engine, vec![InputEvent::ResetCamera]);
+        assert_vec2_approx(engine.camera.pan, clamped, 1e-3);
+    }
+
+    #[test]
+    fn released_move_keeps_a_smart_guide_alignment_over_the_grid() {
+        let mut engine = engine_with_two_rects();
+        engine.doc_mut().rects[1].pos.x = 303.0;
+        engine.snap_grid = Some(25.0);
+
+        // Lands 1px right of the other rect's off-grid left edge.
+        start_move_drag(
+            &mut engine,
+            Vec2::new(100.0, 100.0),
+            Vec2::new(354.0, 300.0),
+        );
+        tick_events(
+            &mut engine,
+            vec![InputEvent::PointerUp {
+                screen_px: Vec2::new(354.0, 300.0),
                 button: 0,
             }],
-            tool: ToolMode::Select,
-        });
-        assert!(matches!(
-            engine.drag_state,
-            DragState::PendingSelectionMove(_)
-        ));
+        );
 
-        // Drag beyond threshold (dx=40, dy=25 world units at zoom=1).
-        engine.tick(&InputBatch {
-            events: vec![InputEvent::PointerMove {
-                screen_px: Vec2::new(140.0, 125.0),
-                buttons: 1,
-            }],
-            tool: ToolMode::Select,
+        assert_vec2_approx(engine.doc.rects[0].pos, Vec2::new(303.0, 250.0), 1e-4);
+    }
+
+    #[test]
+    fn rotated_resize_stays_inside_its_artboard_through_release() {
+        let mut engine = engine_with_rotated_rect(
+            Vec2::new(0.0, 0.0),
+            Vec2::new(200.0, 50.0),
+            std::f32::consts::FRAC_PI_2,
+        );
+        engine.doc.artboards.push(Artboard {
+            pos: Vec2::new(0.0, -100.0),
+            size: Vec2::new(200.0, 300.0),
         });
-        assert!(matches!(engine.drag_state, DragState::SelectionMove(_)));
+        engine.clip_children_to_artboard = true;
+        engine.snap_grid = Some(10.0);
 
-        let pos0 = engine.doc.rects[0].pos;
-        let pos1 = engine.doc.rects[1].pos;
-        assert_vec2_approx(pos0, Vec2::new(origin0.x + 40.0, origin0.y + 25.0), 1e-4);
-        assert_vec2_approx(pos1, Vec2::new(origin1.x + 40.0, origin1.y + 25.0), 1e-4);
+        // Drag the downward-facing local east edge far past the artboard.
+        tick_events(
+            &mut engine,
+            vec![
+                InputEvent::PointerDown {
+                    screen_px: Vec2::new(100.0, 125.0),
+                    shift: false,
+                    button: 0,
+                },
+                InputEvent::PointerMove {
+                    screen_px: Vec2::new(100.0, 400.0),
+                    buttons: 1,
+                },
+            ],
+        );
+        let (_, max) = engine.doc.rects[0].world_aabb();
+        assert_approx(max.y, 200.0, 0.1);
 
-        // Release.
-        engine.tick(&InputBatch {
-            events: vec![InputEvent::PointerUp {
-                screen_px: Vec2::new(140.0, 125.0),
+        tick_events(
+            &mut engine,
+            vec![InputEvent::PointerUp {
+                screen_px: Vec2::new(100.0, 400.0),
                 button: 0,
             }],
-            tool: ToolMode::Select,
-        });
-        assert!(matches!(engine.drag_state, DragState::Idle));
-        // Positions retained.
-        assert_vec2_approx(engine.doc.rects[0].pos, pos0, 1e-4);
-        assert_vec2_approx(engine.doc.rects[1].pos, pos1, 1e-4);
+        );
+        // Rounding 275 up to 280 would cross the edge, so the release keeps
+        // the clipped size.
+        let rect = engine.doc.rects[0].clone();
+        assert_approx(rect.size.x, 275.0, 0.1);
+        let (min, max) = rect.world_aabb();
+        assert!(min.y >= -100.0 && max.y <= 200.0, "{min:?}..{max:?}");
     }
 
-    /// Only selected rects are moved; unselected rects remain in place.
     #[test]
-    fn multi_selection_drag_does_not_move_unselected_rect() {
-        let mut engine = engine_with_two_rects();
-        let origin1 = engine.doc.rects[1].pos;
+    fn released_rotated_resize_snaps_to_the_grid() {
+        let mut engine = engine_with_rotated_rect(
+            Vec2::new(0.0, 0.0),
+            Vec2::new(200.0, 50.0),
+            std::f32::consts::FRAC_PI_2,
+        );
+        engine.snap_grid = Some(10.0);
 
-        // Select only rect 0.
-        engine.tick(&InputBatch {
-            events: vec![InputEvent::PointerDown {
-                screen_px: Vec2::new(100.0, 100.0),
-                shift: false,
-                button: 0,
-            }],
-            tool: ToolMode::Select,
-        });
-        // Enter PendingSelectionMove on rect 0.
-        engine.tick(&InputBatch {
-            events: vec![InputEvent::PointerDown {
-                screen_px: Vec2::new(100.0, 100.0),
-                shift: false,
-                button: 0,
-            }],
-            tool: ToolMode::Select,
-        });
-        // Drag.
-        engine.tick(&InputBatch {
-            events: vec![InputEvent::PointerMove {
-                screen_px: Vec2::new(150.0, 100.0),
-                buttons: 1,
-            }],
-            tool: ToolMode::Select,
-        });
+        tick_events(
+            &mut engine,
+            vec![
+                InputEvent::PointerDown {
+                    screen_px: Vec2::new(100.0, 125.0),
+                    shift: false,
+                    button: 0,
+                },
+                InputEvent::PointerMove {
+                    screen_px: Vec2::new(100.0, 163.0),
+                    buttons: 1,
+                },
+                InputEvent::PointerUp {
+                    screen_px: Vec2::new(100.0, 163.0),
+                    button: 0,
+                },
+            ],
+        );
 
-        // Rect 1 (unselected) must not have moved.
-        assert_vec2_approx(engine.doc.rects[1].pos, origin1, 1e-4);
+        let rect = engine.doc.rects[0].clone();
+        assert_vec2_approx(rect.size, Vec2::new(240.0, 50.0), 1e-3);
+        assert_vec2_approx(rect.pos, Vec2::new(-20.0, 20.0), 1e-3);
     }
 
-    /// Dragging preserves no cumulative drift across multiple sequential move
-    /// events: the final position equals origin + total_delta, not a sum of
-    /// per-frame deltas applied on top of each other.
-    #[test]
-    fn selection_drag_no_cumulative_drift() {
+    fn engine_with_selected_text() -> (Engine, NodeId) {
         let mut engine = engine_with_one_rect();
-        let origin = engine.doc.rects[0].pos;
+        let text_id = engine.doc.alloc_id();
+        // 5 chars at size 20 => roughly 60 x 24 world units.
+        engine.doc.texts.push(TextNode {
+            id: text_id,
+            pos: Vec2::new(300.0, 300.0),
+            content: "Hello".to_string(),
+            size: 20.0,
+            color: Color::BLACK,
+        });
+        engine.set_selected(vec![engine.doc.rects[0].id, text_id]);
+        (engine, text_id)
+    }
 
-        // Select.
-        engine.tick(&InputBatch {
-            events: vec![InputEvent::PointerDown {
-                screen_px: Vec2::new(100.0, 100.0),
-                shift: false,
+    #[test]
+    fn selected_text_moves_with_drags_and_nudges_and_undoes() {
+        let (mut engine, text_id) = engine_with_selected_text();
+
+        start_move_drag(
+            &mut engine,
+            Vec2::new(320.0, 310.0),
+            Vec2::new(340.0, 330.0),
+        );
+        tick_events(
+            &mut engine,
+            vec![InputEvent::PointerUp {
+                screen_px: Vec2::new(340.0, 330.0),
                 button: 0,
             }],
-            tool: ToolMode::Select,
-        });
-        // PendingSelectionMove.
-        engine.tick(&InputBatch {
-            events: vec![InputEvent::PointerDown {
-                screen_px: Vec2::new(100.0, 100.0),
+        );
+        assert_eq!(engine.text(text_id).unwrap().pos, Vec2::new(320.0, 320.0));
+        assert_eq!(engine.doc.rects[0].pos, Vec2::new(70.0, 70.0));
+
+        tick_events(
+            &mut engine,
+            vec![InputEvent::KeyDown {
+                key: "ArrowLeft".to_string(),
                 shift: false,
-                button: 0,
             }],
-            tool: ToolMode::Select,
-        });
-
-        // Many small moves — each frame advances 1 px.
-        for i in 1..=50u32 {
-            engine.tick(&InputBatch {
-                events: vec![InputEvent::PointerMove {
-                    screen_px: Vec2::new(100.0 + i as f32, 100.0),
-                    buttons: 1,
-                }],
-                tool: ToolMode::Select,
-            });
-        }
-
-        // Final position should be origin + 50 px, NOT origin + sum(1..=50).
-        assert_vec2_approx(
-            engine.doc.rects[0].pos,
-            Vec2::new(origin.x + 50.0, origin.y),
-            1e-3,
         );
+        assert_eq!(engine.text(text_id).unwrap().pos, Vec2::new(319.0, 320.0));
+
+        tick_events(&mut engine, vec![InputEvent::Undo, InputEvent::Undo]);
+        assert_eq!(engine.text(text_id).unwrap().pos, Vec2::new(300.0, 300.0));
+        assert_eq!(engine.doc.rects[0].pos, Vec2::new(50.0, 50.0));
     }
 
     #[test]
-    fn cursor_defaults_to_default_with_not_hover() {
-        let engine = engine_with_one_rect();
-        let cursor = engine.compute_cursor(&ToolMode::Select);
-        assert_eq!(cursor, CursorStyle::Default);
+    fn deleting_a_selected_text_drops_it_from_the_selection_until_undone() {
+        let (mut engine, text_id) = engine_with_selected_text();
+
+        tick_events(&mut engine, vec![InputEvent::DeleteSelected]);
+        assert!(engine.doc.texts.is_empty());
+        assert!(engine.doc.rects.is_empty());
+        assert!(engine.selected.is_empty());
+
+        tick_events(&mut engine, vec![InputEvent::Undo]);
+        assert_eq!(engine.doc.texts[0].id, text_id);
+        assert_eq!(engine.selected.len(), 2);
     }
 
     #[test]
-    fn cursor_is_resize_tl_br_when_hovering_tl_handle() {
-        let mut engine = engine_with_one_rect();
-        let id = engine.doc.rects[0].id;
-        engine.selected = vec![id];
+    fn duplicate_align_and_fit_include_selected_text() {
+        let (mut engine, text_id) = engine_with_selected_text();
 
-        engine.hover_screen_px = Some(Vec2::new(50.0, 50.0));
-        let cursor = engine.compute_cursor(&ToolMode::Select);
-        assert_eq!(cursor, CursorStyle::ResizeTlBr);
+        tick_events(
+            &mut engine,
+            vec![InputEvent::Align {
+                mode: AlignMode::LeftEdges,
+            }],
+        );
+        assert_eq!(engine.text(text_id).unwrap().pos, Vec2::new(50.0, 300.0));
+
+        tick_events(
+            &mut engine,
+            vec![InputEvent::DuplicateSelected {
+                offset: Vec2::new(10.0, 10.0),
+            }],
+        );
+        assert_eq!(engine.doc.texts.len(), 2);
+        assert_eq!(engine.doc.texts[1].pos, Vec2::new(60.0, 310.0));
+        assert!(engine.selected.contains(&engine.doc.texts[1].id));
+
+        let (min, max) = engine.doc.bounds().unwrap();
+        assert_vec2_approx(min, Vec2::new(50.0, 50.0), 1e-3);
+        assert_vec2_approx(max, Vec2::new(160.0, 334.0), 1e-3);
     }
 
     #[test]
-    fn cursor_is_resize_tr_bl_when_hovering_tr_handle() {
-        let mut engine = engine_with_one_rect();
-        let id = engine.doc.rects[0].id;
-        engine.selected = vec![id];
+    fn hiding_selected_rects_drops_them_from_the_selection_and_chrome() {
+        let mut engine = engine_with_two_rects();
+        let (hidden, shown) = (engine.doc.rects[0].id, engine.doc.rects[1].id);
+        engine.selected = vec![hidden, shown];
 
-        engine.hover_screen_px = Some(Vec2::new(150.0, 50.0));
-        let cursor = engine.compute_cursor(&ToolMode::Select);
-        assert_eq!(cursor, CursorStyle::ResizeTrBl);
+        tick_events(
+            &mut engine,
+            vec![InputEvent::SetVisible { visible: false }, InputEvent::Undo],
+        );
+        assert!(engine.doc.rects.iter().all(|rect| rect.visible));
+        assert!(engine.selected.is_empty());
+
+        engine.set_selected(vec![hidden]);
+        tick_events(&mut engine, vec![InputEvent::SetVisible { visible: false }]);
+        assert!(engine.selected.is_empty());
+
+        // A layers panel can still select a hidden rect, e.g. to show it again.
+        engine.set_selected(vec![hidden]);
+        let overlay = engine.update_overlay_scene(&ToolMode::Select);
+        assert!(overlay.selection_boxes.is_empty());
+        assert!(engine.dimension_labels(&ToolMode::Select).is_empty());
     }
 
     #[test]
-    fn cursor_is_default_when_outside_handle_radius() {
-        let mut engine = engine_with_one_rect();
-        let id = engine.doc.rects[0].id;
-        engine.selected = vec![id];
-        // Far from any handle
-        engine.hover_screen_px = Some(Vec2::new(100.0, 100.0)); // center of rect
-        let cursor = engine.compute_cursor(&ToolMode::Select);
-        assert_eq!(cursor, CursorStyle::Default);
+    fn resolve_overlaps_never_moves_locked_rects() {
+        let mut engine = engine_with_two_rects();
+        engine.doc.rects[1].pos = Vec2::new(140.0, 50.0);
+        engine.doc.rects[1].locked = true;
+
+        // The locked upper rect holds still and the lower one gives way.
+        engine.resolve_overlaps();
+        assert_vec2_approx(engine.doc.rects[1].pos, Vec2::new(140.0, 50.0), 1e-4);
+        assert_vec2_approx(engine.doc.rects[0].pos, Vec2::new(40.0, 50.0), 1e-4);
+
+        engine.doc.rects[0].pos = Vec2::new(100.0, 50.0);
+        engine.doc.rects[0].locked = true;
+        engine.resolve_overlaps();
+        assert_vec2_approx(engine.doc.rects[0].pos, Vec2::new(100.0, 50.0), 1e-4);
+        assert_vec2_approx(engine.doc.rects[1].pos, Vec2::new(140.0, 50.0), 1e-4);
     }
 
     #[test]
-    fn cursor_is_move_during_selection_drag() {
+    fn fill_and_visibility_still_apply_to_locked_rects() {
         let mut engine = engine_with_one_rect();
-        engine.drag_state = DragState::PendingSelectionMove(PendingSelectionMove {
-            start_screen_px: Vec2::new(100.0, 100.0),
-            start_world: Vec2::new(100.0, 100.0),
-            previous_selection: vec![],
-        });
-        let cursor = engine.compute_cursor(&ToolMode::Select);
-        assert_eq!(cursor, CursorStyle::Move);
+        engine.doc.rects[0].locked = true;
+        engine.set_selected(vec![engine.doc.rects[0].id]);
+
+        tick_events(
+            &mut engine,
+            vec![
+                InputEvent::SetSelectionFill {
+                    color: RgbaColor {
+                        r: 0.0,
+                        g: 1.0,
+                        b: 0.0,
+                        a: 1.0,
+                    },
+                },
+                InputEvent::SetVisible { visible: false },
+            ],
+        );
+
+        let rect = &engine.doc.rects[0];
+        assert_eq!(rect.color, Color::rgba(0.0, 1.0, 0.0, 1.0));
+        assert!(!rect.visible);
+        assert!(rect.locked);
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn deprecated_corner_maps_to_and_from_edge_corners() {
+        use crate::drag::Corner;
+
+        for (edge, corner) in [
+            (Edge::NW, Corner::TL),
+            (Edge::NE, Corner::TR),
+            (Edge::SW, Corner::BL),
+            (Edge::SE, Corner::BR),
+        ] {
+            assert_eq!(edge.corner(), Some(corner));
+            assert_eq!(Edge::from(corner), edge);
+        }
+        assert_eq!(Edge::N.corner(), None);
+        assert_eq!(Edge::Inside.corner(), None);
     }
 }
@@ -1,4 +1,4 @@
-use crate::{NodeId, RectNode, Vec2};
+use crate::{Color, NodeId, RectNode, TextNode, Vec2};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct RectGeometry {
@@ -22,6 +22,49 @@ pub struct RectGeometryChange {
     pub after: RectGeometry,
 }
 
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TextMoveChange {
+    pub id: NodeId,
+    pub before: Vec2,
+    pub after: Vec2,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RectColorChange {
+    pub id: NodeId,
+    pub before: Color,
+    pub after: Color,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RectRotationChange {
+    pub id: NodeId,
+    // radians
+    pub before: f32,
+    pub after: f32,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RectGroupChange {
+    pub id: NodeId,
+    pub before: Option<u64>,
+    pub after: Option<u64>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RectVisibilityChange {
+    pub id: NodeId,
+    pub before: bool,
+    pub after: bool,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RectLockChange {
+    pub id: NodeId,
+    pub before: bool,
+    pub after: bool,
+}
+
 #[derive(Debug, Clone)]
 pub enum ToolCommand {
     CreateRect {
@@ -30,16 +73,55 @@ pub enum ToolCommand {
         next_selection: Vec<NodeId>,
     },
 
-    // move and resize
+    // paste / import / duplicate of several nodes at once, appended on top
+    InsertRects {
+        rects: Vec<RectNode>,
+        texts: Vec<TextNode>,
+        previous_selection: Vec<NodeId>,
+        next_selection: Vec<NodeId>,
+    },
+
+    // move and resize; selected text labels only move
     SetRectsGeometry {
         changes: Vec<RectGeometryChange>,
+        texts: Vec<TextMoveChange>,
+    },
+
+    SetRectsColor {
+        changes: Vec<RectColorChange>,
+    },
+
+    SetRectsRotation {
+        changes: Vec<RectRotationChange>,
+    },
+
+    // group and ungroup
+    SetRectsGroup {
+        changes: Vec<RectGroupChange>,
+    },
+
+    // hide and show
+    SetRectsVisible {
+        changes: Vec<RectVisibilityChange>,
+    },
+
+    // lock and unlock
+    SetRectsLocked {
+        changes: Vec<RectLockChange>,
     },
 
     BringForward(Vec<NodeId>),
     SendBackward(Vec<NodeId>),
 
+    // full draw order (bottom to top) before and after an arbitrary restack
+    Restack {
+        before: Vec<NodeId>,
+        after: Vec<NodeId>,
+    },
+
     Delete {
         rects: Vec<(RectNode, usize)>,   // (rect, original_index) pairs
+        texts: Vec<(TextNode, usize)>,   // (text, original_index) pairs
         previous_selection: Vec<NodeId>, // what self.selected was before applying delete
         next_selection: Vec<NodeId>,     // what self.selected should be after applying delete
     },
@@ -1,14 +1,24 @@
 use serde::{Deserialize, Serialize};
 
 use crate::camera::Camera;
+use crate::color::Color;
 use crate::render_scene::{OverlayScene, RenderScene};
-use crate::types::Vec2;
+use crate::types::{NodeId, Vec2};
 
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 #[serde(default)]
 pub struct InputBatch {
     pub events: Vec<InputEvent>,
     pub tool: ToolMode,
+    /// Host clock when the batch was collected. `tick` uses it to spot double
+    /// clicks; recorded sessions keep it so playback can be paced like the
+    /// original.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timestamp_ms: Option<f64>,
+    /// Milliseconds since the previous batch; advances camera animations.
+    /// `None` holds any animation where it is.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dt_ms: Option<f32>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -35,6 +45,13 @@ pub enum InputEvent {
         button: u8,
     },
     PointerCancel,
+    /// Recolor the selected rects, locked ones included, as one undoable
+    /// step. `color` is a `[r, g, b, a]` array, channels clamped to 0..=1.
+    SetFill {
+        color: Color,
+    },
+    /// [`InputEvent::SetFill`] with the color as an `{ r, g, b, a }` object,
+    /// kept for hosts written against it.
     SetSelectionFill {
         color: RgbaColor,
     },
@@ -42,9 +59,190 @@ pub enum InputEvent {
     Redo,
     BringForward,
     SendBackward,
+    /// Move the selection above every other node, keeping its relative order.
+    BringToFront,
+    /// Move the selection below every other node, keeping its relative order.
+    SendToBack,
+    /// Move each selected rect one step up the draw order, past its
+    /// unselected neighbour. Rects already at the top, or stacked directly
+    /// under another selected rect that can't move, stay put.
+    RaiseOne,
+    /// Move each selected rect one step down the draw order; the mirror of
+    /// `RaiseOne`.
+    LowerOne,
     DeleteSelected,
+    /// Select every visible, unlocked rect and every text label, ending any
+    /// drag.
+    SelectAll,
+    /// Empty the selection, ending any drag.
+    ClearSelection,
+    /// Put the selected rects (two or more) in a new group.
+    Group,
+    /// Take the selected rects out of their groups.
+    Ungroup,
+    /// Show or hide the selected rects without removing them. Rects being
+    /// hidden leave the selection.
+    SetVisible {
+        visible: bool,
+    },
+    /// Lock or unlock the selected rects. Locked rects ignore clicks and are
+    /// left alone by move, resize, rotate, delete and overlap resolution;
+    /// fill and visibility still apply, since the lock guards placement
+    /// rather than style.
+    SetLocked {
+        locked: bool,
+    },
+    /// Copy the selected nodes `offset` world units away, above everything,
+    /// and select the copies. A zero offset uses the engine's default step.
+    DuplicateSelected {
+        offset: Vec2,
+    },
+    /// Align every selected node to the selection bounds; needs two or more.
+    Align {
+        mode: AlignMode,
+    },
+    /// Equalize the gaps between selected nodes along `axis`; needs three or more.
+    Distribute {
+        axis: Axis,
+    },
+    /// Key pressed, named as in the DOM `KeyboardEvent.key` (e.g. `"Shift"`).
+    /// Arrow keys nudge the selection, 10x further while `shift` is held.
+    KeyDown {
+        key: String,
+        #[serde(default)]
+        shift: bool,
+    },
+    KeyUp {
+        key: String,
+    },
+    /// Zoom so the document width fills the viewport, centering vertically.
+    FitWidth,
+    /// Zoom so the document height fills the viewport, centering horizontally.
+    FitHeight,
+    /// Zoom about `pivot_px` by a raw wheel `deltaY`; negative zooms in.
+    WheelZoom {
+        pivot_px: Vec2,
+        delta_y: f32,
+    },
+    /// Zoom by `multiplier` about the viewport center.
+    ZoomAboutCenter {
+        multiplier: f32,
+    },
+    /// Frame every rect in a viewport of `viewport_px`, with a small margin.
+    FitToContent {
+        viewport_px: Vec2,
+    },
+    /// Put the camera back at the origin at zoom 1.
+    ResetCamera,
+    /// Zoom to exactly 1.0, keeping the world point under `pivot_px` fixed.
+    ZoomToActualSize {
+        pivot_px: Vec2,
+    },
+    /// Set the world-space grid pitch moves, resizes and creates snap to;
+    /// `None` (or a non-positive size) turns snapping off.
+    SetSnapGrid {
+        size: Option<f32>,
+    },
+    /// Add a rect with its top-left corner at `world` and select it.
+    CreateRect {
+        world: Vec2,
+        size: Vec2,
+        color: Color,
+    },
+    /// Give the rect `id` an exact top-left `pos` and `size`, e.g. from a
+    /// properties panel, whether or not it is selected. Sizes are clamped to
+    /// the 1-unit minimum; unknown or locked ids and non-finite positions or
+    /// sizes are ignored.
+    SetBounds {
+        id: NodeId,
+        pos: Vec2,
+        size: Vec2,
+    },
+    /// Turn the rect `id` to `degrees` clockwise about its center, e.g. from
+    /// a rotate handle or properties panel. While shift is held the angle
+    /// snaps to `Engine::rotation_snap_deg`. Unknown or locked ids and
+    /// non-finite angles are ignored.
+    SetRotation {
+        id: NodeId,
+        degrees: f32,
+    },
 }
 
+/// Which edge or center `InputEvent::Align` lines up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlignMode {
+    LeftEdges,
+    RightEdges,
+    TopEdges,
+    BottomEdges,
+    /// Share the selection's horizontal center (a common vertical axis).
+    HorizontalCenters,
+    /// Share the selection's vertical center (a common horizontal axis).
+    VerticalCenters,
+}
+
+/// World axis `InputEvent::Distribute` spaces rects along.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Axis {
+    Horizontal,
+    Vertical,
+}
+
+/// Which rects a marquee drag selects.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MarqueeMode {
+    /// Every rect the marquee touches.
+    #[default]
+    Intersect,
+    /// Only rects lying entirely inside the marquee.
+    Contain,
+    /// `Contain` when dragged left to right, `Intersect` when dragged right
+    /// to left.
+    ByDirection,
+}
+
+impl MarqueeMode {
+    /// The mode in effect for a marquee dragged from `start` to `current`;
+    /// never `ByDirection`.
+    pub fn resolve(self, start: Vec2, current: Vec2) -> MarqueeMode {
+        match self {
+            MarqueeMode::ByDirection if current.x >= start.x => MarqueeMode::Contain,
+            MarqueeMode::ByDirection => MarqueeMode::Intersect,
+            mode => mode,
+        }
+    }
+}
+
+/// Modifier keys currently held, as last reported by key and pointer events.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModifierState {
+    pub shift: bool,
+    pub ctrl: bool,
+    pub alt: bool,
+    pub meta: bool,
+}
+
+impl ModifierState {
+    /// Update the flag matching a DOM modifier key name; other keys are ignored.
+    ///
+    /// # Arguments
+    /// * `key` - DOM `KeyboardEvent.key` value
+    /// * `down` - `true` on key press, `false` on release
+    pub fn apply_key(&mut self, key: &str, down: bool) {
+        match key {
+            "Shift" => self.shift = down,
+            "Control" => self.ctrl = down,
+            "Alt" => self.alt = down,
+            "Meta" => self.meta = down,
+            _ => {}
+        }
+    }
+}
+
+/// Color as an `{ r, g, b, a }` object, the shape `SetSelectionFill` takes.
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct RgbaColor {
     pub r: f32,
@@ -53,12 +251,21 @@ pub struct RgbaColor {
     pub a: f32,
 }
 
+impl From<RgbaColor> for Color {
+    /// Clamps each channel into `0.0..=1.0`, like [`Color::rgba`].
+    fn from(c: RgbaColor) -> Self {
+        Color::rgba(c.r, c.g, c.b, c.a)
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum CursorStyle {
     Default,
     ResizeTlBr, // TL and BR corners — ↖↘
     ResizeTrBl, // TR and BL corners — ↗↙
+    ResizeNs,   // N and S edges — ↕
+    ResizeEw,   // E and W edges — ↔
     Move,
     Crosshair,
     Pan,
@@ -71,6 +278,10 @@ pub struct EngineOutput {
     pub render_scene: RenderScene,
     pub overlay_scene: OverlayScene,
     pub cursor: CursorStyle,
+    /// `true` when the camera, scene, or overlay may differ from the previous
+    /// tick: after any event, camera change, engine edit, or
+    /// `Engine::request_redraw`. Hosts can skip rendering when it is `false`.
+    pub needs_redraw: bool,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
@@ -1,15 +1,29 @@
+pub mod api;
 mod camera;
+mod color;
 mod drag;
 mod engine;
 mod history;
 mod input;
 mod render_scene;
+mod spatial;
 mod types;
 
-pub use camera::Camera;
-pub use drag::{Corner, DragState, HandleHit, PendingSelectionMove};
-pub use engine::Engine;
-pub use history::{RectGeometry, RectGeometryChange, ToolCommand};
-pub use input::{CursorStyle, EngineOutput, InputBatch, InputEvent, ToolMode};
-pub use render_scene::{OverlayScene, RectInstance, RenderScene};
-pub use types::{Document, NodeId, RectNode, Vec2};
+pub use camera::{Camera, CameraAnimation, Easing, MAX_ZOOM, MIN_ZOOM, WHEEL_ZOOM_SPEED};
+pub use color::Color;
+#[allow(deprecated)]
+pub use drag::Corner;
+pub use drag::{DragState, Edge, HandleHit, PendingSelectionMove};
+pub use engine::{Engine, EventObserver};
+pub use history::{
+    RectColorChange, RectGeometry, RectGeometryChange, RectGroupChange, RectLockChange,
+    RectRotationChange, RectVisibilityChange, TextMoveChange, ToolCommand,
+};
+pub use input::{
+    AlignMode, CursorStyle, EngineOutput, InputBatch, InputEvent, ModifierState, ToolMode,
+};
+pub use render_scene::{
+    HandleShape, InstanceShape, OverlayScene, OverlayTheme, RectInstance, RenderScene,
+    SelectionBox, TextInstance,
+};
+pub use types::{Artboard, Clipboard, Document, NodeId, RectNode, TextNode, Vec2, demo_document};
@@ -1,21 +1,244 @@
 use serde::{Deserialize, Serialize};
 
+use crate::color::Color;
+use crate::types::{Vec2, default_stroke_color};
+
 /// RenderScene | contains core shapes, objects
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct RenderScene {
     pub rects: Vec<RectInstance>,
+    /// Background grid lines, drawn beneath `rects`.
+    #[serde(default)]
+    pub grid: Vec<RectInstance>,
+    /// Text labels for the host to draw above `rects`.
+    #[serde(default)]
+    pub texts: Vec<TextInstance>,
+}
+
+/// TextInstance | one text label in world space: `pos` is its top-left and
+/// `size` its font size, both in world units
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TextInstance {
+    pub pos: [f32; 2],
+    pub content: String,
+    pub size: f32,
+    pub color: Color,
 }
 
 /// OverlayScene | contains UI editor elements: selection, highlight, marquee
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct OverlayScene {
+    /// Selection chrome, one compact entry per box; drawn beneath `rects`.
+    #[serde(default)]
+    pub selection_boxes: Vec<SelectionBox>,
     pub rects: Vec<RectInstance>,
+    /// Text annotations such as the dimensions of a lone selected rect.
+    #[serde(default)]
+    pub labels: Vec<TextInstance>,
+}
+
+impl OverlayScene {
+    /// Screen-space thickness of selection outlines.
+    pub const OUTLINE_PX: f32 = 2.0;
+    /// Screen-space side length of resize handles.
+    pub const HANDLE_PX: f32 = 8.0;
+
+    /// Every instance to draw at `zoom`: expanded selection boxes first, then
+    /// `rects`, matching the order the overlay is composited in.
+    pub fn instances(&self, zoom: f32) -> Vec<RectInstance> {
+        let mut out = Vec::with_capacity(self.instance_count());
+        self.instances_into(zoom, &mut out);
+        out
+    }
+
+    /// [`OverlayScene::instances`] written into `out` after clearing it, so a
+    /// caller can reuse one allocation across frames.
+    pub fn instances_into(&self, zoom: f32, out: &mut Vec<RectInstance>) {
+        out.clear();
+        out.reserve(self.instance_count());
+        for selection_box in &self.selection_boxes {
+            selection_box.expand_into(zoom, out);
+        }
+        out.extend_from_slice(&self.rects);
+    }
+
+    /// Number of instances `instances` produces.
+    pub fn instance_count(&self) -> usize {
+        self.selection_boxes
+            .iter()
+            .map(SelectionBox::instance_count)
+            .sum::<usize>()
+            + self.rects.len()
+    }
+}
+
+/// SelectionBox | outline plus optional resize handles around one box,
+/// expanded to instances only when drawn
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SelectionBox {
+    pub pos: [f32; 2],
+    pub size: [f32; 2],
+    pub outline_color: Color,
+    /// Handle fill, or `None` for an outline-only box.
+    pub handle_color: Option<Color>,
+    pub handle_shape: InstanceShape,
+    /// Turn of the whole box about its center in radians, matching the node
+    /// it outlines.
+    #[serde(default)]
+    pub rotation: f32,
+}
+
+impl SelectionBox {
+    fn instance_count(&self) -> usize {
+        if self.handle_color.is_some() { 12 } else { 4 }
+    }
+
+    /// Push 4 outline bars and, if enabled, 4 corner + 4 edge-midpoint handles.
+    fn expand_into(&self, zoom: f32, out: &mut Vec<RectInstance>) {
+        let start = out.len();
+        self.expand_unrotated(zoom, out);
+        if self.rotation == 0.0 {
+            return;
+        }
+
+        // Swing each piece's center about the box center; the instance's own
+        // rotation then turns the piece in place.
+        let [x, y] = self.pos;
+        let [w, h] = self.size;
+        let center = Vec2::new(x + w * 0.5, y + h * 0.5);
+        for instance in &mut out[start..] {
+            let [px, py] = instance.pos;
+            let [sw, sh] = instance.size;
+            let offset = Vec2::new(px + sw * 0.5 - center.x, py + sh * 0.5 - center.y)
+                .rotated(self.rotation);
+            instance.pos = [
+                center.x + offset.x - sw * 0.5,
+                center.y + offset.y - sh * 0.5,
+            ];
+            instance.rotation = self.rotation;
+        }
+    }
+
+    fn expand_unrotated(&self, zoom: f32, out: &mut Vec<RectInstance>) {
+        let outline = OverlayScene::OUTLINE_PX / zoom;
+        let [x, y] = self.pos;
+        let [w, h] = self.size;
+        for (pos, size) in [
+            ([x, y], [w, outline]),
+            ([x, y + h - outline], [w, outline]),
+            ([x, y], [outline, h]),
+            ([x + w - outline, y], [outline, h]),
+        ] {
+            out.push(RectInstance::solid(pos, size, self.outline_color));
+        }
+
+        let Some(handle_color) = self.handle_color else {
+            return;
+        };
+        let handle = OverlayScene::HANDLE_PX / zoom;
+        let half = handle * 0.5;
+        for (hx, hy) in [
+            // corners
+            (x, y),
+            (x + w, y),
+            (x, y + h),
+            (x + w, y + h),
+            // edge midpoints
+            (x + w * 0.5, y),
+            (x + w * 0.5, y + h),
+            (x, y + h * 0.5),
+            (x + w, y + h * 0.5),
+        ] {
+            out.push(RectInstance {
+                shape: self.handle_shape,
+                ..RectInstance::solid([hx - half, hy - half], [handle, handle], handle_color)
+            });
+        }
+    }
+}
+
+/// OverlayTheme | knobs for how selection chrome is drawn
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct OverlayTheme {
+    /// Draw resize handles on every selected node. When off and more than one
+    /// node is selected, nodes get outlines only and handles go on the group box.
+    pub show_per_node_handles: bool,
+    pub handle_shape: HandleShape,
+}
+
+impl Default for OverlayTheme {
+    fn default() -> Self {
+        Self {
+            show_per_node_handles: true,
+            handle_shape: HandleShape::Square,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HandleShape {
+    #[default]
+    Square,
+    Circle,
+}
+
+impl HandleShape {
+    pub fn instance_shape(self) -> InstanceShape {
+        match self {
+            HandleShape::Square => InstanceShape::Rect,
+            HandleShape::Circle => InstanceShape::Ellipse,
+        }
+    }
+}
+
+/// How the renderer shades an instance's quad. `Ellipse` fills the ellipse
+/// inscribed in `pos`/`size`.
+#[repr(u32)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InstanceShape {
+    #[default]
+    Rect = 0,
+    Ellipse = 1,
 }
 
 #[repr(C)]
-#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
 pub struct RectInstance {
     pub pos: [f32; 2],
     pub size: [f32; 2],
-    pub color: [f32; 4],
+    pub color: Color,
+    #[serde(default)]
+    pub shape: InstanceShape,
+    /// Corner rounding in world units, clamped to half the shorter side when
+    /// drawn. 0 draws sharp corners.
+    #[serde(default)]
+    pub corner_radius: f32,
+    /// Turn about the quad's center in radians, clockwise on screen.
+    #[serde(default)]
+    pub rotation: f32,
+    /// Border drawn inside the quad's edge; a `stroke_width` of 0 draws none.
+    #[serde(default = "default_stroke_color")]
+    pub stroke_color: Color,
+    /// Border width in world units.
+    #[serde(default)]
+    pub stroke_width: f32,
+}
+
+impl RectInstance {
+    /// Sharp, unrotated, unstroked rect filled with `color`. Set other fields
+    /// with struct update syntax, e.g. `RectInstance { rotation, ..solid }`.
+    pub fn solid(pos: [f32; 2], size: [f32; 2], color: Color) -> Self {
+        Self {
+            pos,
+            size,
+            color,
+            shape: InstanceShape::Rect,
+            corner_radius: 0.0,
+            rotation: 0.0,
+            stroke_color: Color::TRANSPARENT,
+            stroke_width: 0.0,
+        }
+    }
 }
@@ -0,0 +1,87 @@
+use std::collections::HashMap;
+
+use crate::types::{RectNode, Vec2};
+
+/// World-space side length of one index cell.
+const CELL_SIZE: f32 = 256.0;
+/// Rects covering more cells than this skip the grid and are always returned
+/// as candidates, so one huge rect can't flood the map.
+const MAX_CELLS_PER_RECT: i64 = 64;
+
+/// SpatialIndex | uniform grid mapping cells to the `doc.rects` indices of
+/// the rects overlapping them
+///
+/// Queries return candidate indices in ascending (bottom-to-top) draw order;
+/// callers still run their exact test on each candidate.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SpatialIndex {
+    cells: HashMap<(i32, i32), Vec<usize>>,
+    oversized: Vec<usize>,
+    rect_count: usize,
+}
+
+impl SpatialIndex {
+    pub(crate) fn build(rects: &[RectNode]) -> Self {
+        let mut index = Self {
+            rect_count: rects.len(),
+            ..Self::default()
+        };
+        for (i, rect) in rects.iter().enumerate() {
+            let (min, max) = rect.world_aabb();
+            let Some((lo, hi)) = cell_range(min, max) else {
+                index.oversized.push(i);
+                continue;
+            };
+            for cy in lo.1..=hi.1 {
+                for cx in lo.0..=hi.0 {
+                    index.cells.entry((cx, cy)).or_default().push(i);
+                }
+            }
+        }
+        index
+    }
+
+    /// Length of the rect slice the index was built from.
+    pub(crate) fn rect_count(&self) -> usize {
+        self.rect_count
+    }
+
+    /// Rects whose cells overlap the box from `min` to `max`.
+    pub(crate) fn query_box(&self, min: Vec2, max: Vec2) -> Vec<usize> {
+        let mut out = self.oversized.clone();
+        match cell_range(min, max) {
+            Some((lo, hi)) => {
+                for cy in lo.1..=hi.1 {
+                    for cx in lo.0..=hi.0 {
+                        if let Some(bucket) = self.cells.get(&(cx, cy)) {
+                            out.extend_from_slice(bucket);
+                        }
+                    }
+                }
+            }
+            // Too large to walk cell by cell; every indexed rect is a candidate.
+            None => out.extend(self.cells.values().flatten()),
+        }
+        out.sort_unstable();
+        out.dedup();
+        out
+    }
+}
+
+/// Inclusive `(x, y)` cell bounds of a box, or `None` if it is non-finite or
+/// spans more than `MAX_CELLS_PER_RECT` cells.
+fn cell_range(min: Vec2, max: Vec2) -> Option<((i32, i32), (i32, i32))> {
+    if ![min.x, min.y, max.x, max.y].iter().all(|v| v.is_finite()) {
+        return None;
+    }
+    let cell = |v: f32| (v / CELL_SIZE).floor() as i64;
+    let (x0, y0) = (cell(min.x.min(max.x)), cell(min.y.min(max.y)));
+    let (x1, y1) = (cell(min.x.max(max.x)), cell(min.y.max(max.y)));
+    // Range check first so the cell count below can't overflow.
+    if [x0, y0, x1, y1].iter().any(|c| i32::try_from(*c).is_err())
+        || (x1 - x0 + 1) * (y1 - y0 + 1) > MAX_CELLS_PER_RECT
+    {
+        return None;
+    }
+    Some(((x0 as i32, y0 as i32), (x1 as i32, y1 as i32)))
+}
@@ -1,5 +1,9 @@
+use std::collections::BTreeMap;
+
 use serde::{Deserialize, Serialize};
 
+use crate::color::Color;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct NodeId(pub u64);
 
@@ -13,27 +17,246 @@ impl Vec2 {
     pub const fn new(x: f32, y: f32) -> Self {
         Self { x, y }
     }
+
+    /// This vector turned by `radians` about the origin; positive angles turn
+    /// +x towards +y (clockwise on screen, where y points down).
+    pub fn rotated(self, radians: f32) -> Self {
+        let (sin, cos) = radians.sin_cos();
+        Self::new(self.x * cos - self.y * sin, self.x * sin + self.y * cos)
+    }
 }
 
-#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct RectNode {
     pub id: NodeId,
     pub pos: Vec2,
     pub size: Vec2,
-    pub color: [f32; 4],
+    pub color: Color,
+    /// Opaque host data (e.g. a database row id); stored and saved, never read.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub meta: BTreeMap<String, String>,
+    /// Rects sharing a group id are selected together by a click.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group_id: Option<u64>,
+    /// Turn about the center in radians, applied after `pos`/`size` place the
+    /// unrotated rect. Positive is clockwise on screen.
+    #[serde(default, skip_serializing_if = "is_zero_f32")]
+    pub rotation: f32,
+    /// Hidden rects stay in the document but are neither drawn nor hittable.
+    #[serde(default = "default_visible")]
+    pub visible: bool,
+    /// Locked rects still draw but can't be hit, moved, resized or deleted.
+    /// Fill and visibility edits still apply to them.
+    #[serde(default)]
+    pub locked: bool,
+    /// Border color, drawn inset from the edge over the fill.
+    #[serde(
+        default = "default_stroke_color",
+        skip_serializing_if = "is_default_stroke_color"
+    )]
+    pub stroke_color: Color,
+    /// Border width in world units; 0 draws the fill only.
+    #[serde(default, skip_serializing_if = "is_zero_f32")]
+    pub stroke_width: f32,
+}
+
+impl RectNode {
+    /// Visible, unlocked, unrotated rect with no group, metadata or border.
+    pub fn new(id: NodeId, pos: Vec2, size: Vec2, color: Color) -> Self {
+        Self {
+            id,
+            pos,
+            size,
+            color,
+            meta: BTreeMap::new(),
+            group_id: None,
+            rotation: 0.0,
+            visible: true,
+            locked: false,
+            stroke_color: default_stroke_color(),
+            stroke_width: 0.0,
+        }
+    }
+
+    pub fn center(&self) -> Vec2 {
+        Vec2::new(
+            self.pos.x + self.size.x * 0.5,
+            self.pos.y + self.size.y * 0.5,
+        )
+    }
+
+    /// `world` expressed in the unrotated frame `pos`/`size` describe.
+    pub fn to_local(&self, world: Vec2) -> Vec2 {
+        if self.rotation == 0.0 {
+            return world;
+        }
+        let c = self.center();
+        let d = Vec2::new(world.x - c.x, world.y - c.y).rotated(-self.rotation);
+        Vec2::new(c.x + d.x, c.y + d.y)
+    }
+
+    /// Whether `world` lies inside the rotated rect, edges included.
+    pub fn contains_point(&self, world: Vec2) -> bool {
+        self.contains_point_within(world, 0.0)
+    }
+
+    /// Whether `world` lies inside the rotated rect grown by `tolerance`
+    /// world units on every side.
+    pub fn contains_point_within(&self, world: Vec2, tolerance: f32) -> bool {
+        let p = self.to_local(world);
+        p.x >= self.pos.x - tolerance
+            && p.x <= self.pos.x + self.size.x + tolerance
+            && p.y >= self.pos.y - tolerance
+            && p.y <= self.pos.y + self.size.y + tolerance
+    }
+
+    /// World-space `(min, max)` box around the rotated rect.
+    pub fn world_aabb(&self) -> (Vec2, Vec2) {
+        rotated_aabb(self.pos, self.size, self.rotation)
+    }
+}
+
+/// Smallest `(min, max)` box holding every box in `boxes`.
+fn union_bounds(mut boxes: impl Iterator<Item = (Vec2, Vec2)>) -> Option<(Vec2, Vec2)> {
+    let (mut min, mut max) = boxes.next()?;
+    for (lo, hi) in boxes {
+        min.x = min.x.min(lo.x);
+        min.y = min.y.min(lo.y);
+        max.x = max.x.max(hi.x);
+        max.y = max.y.max(hi.y);
+    }
+    Some((min, max))
+}
+
+/// World-space `(min, max)` box around `pos`/`size` turned by `rotation`
+/// radians about its center.
+pub(crate) fn rotated_aabb(pos: Vec2, size: Vec2, rotation: f32) -> (Vec2, Vec2) {
+    let max = Vec2::new(pos.x + size.x, pos.y + size.y);
+    if rotation == 0.0 {
+        return (pos, max);
+    }
+    let c = Vec2::new(pos.x + size.x * 0.5, pos.y + size.y * 0.5);
+    let half = Vec2::new(size.x * 0.5, size.y * 0.5);
+    let (sin, cos) = rotation.sin_cos();
+    let extent = Vec2::new(
+        (half.x * cos).abs() + (half.y * sin).abs(),
+        (half.x * sin).abs() + (half.y * cos).abs(),
+    );
+    (
+        Vec2::new(c.x - extent.x, c.y - extent.y),
+        Vec2::new(c.x + extent.x, c.y + extent.y),
+    )
+}
+
+/// Single-line text label. Glyphs are drawn by the host (for example DOM text
+/// placed with `Engine::node_screen_rect`); the engine only knows its
+/// approximate extent.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TextNode {
+    pub id: NodeId,
+    pub pos: Vec2,
+    pub content: String,
+    /// Font size in world units.
+    pub size: f32,
+    pub color: Color,
+}
+
+impl TextNode {
+    /// Average glyph advance as a fraction of the font size.
+    pub(crate) const APPROX_ADVANCE_EM: f32 = 0.6;
+    /// Line height as a fraction of the font size.
+    const LINE_HEIGHT_EM: f32 = 1.2;
+
+    /// Approximate world-space extent, from character count times font size.
+    pub fn approx_size(&self) -> Vec2 {
+        let chars = self.content.chars().count() as f32;
+        Vec2::new(
+            chars * self.size * Self::APPROX_ADVANCE_EM,
+            self.size * Self::LINE_HEIGHT_EM,
+        )
+    }
+}
+
+/// A fixed frame on the canvas that nodes can be kept inside.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Artboard {
+    pub pos: Vec2,
+    pub size: Vec2,
+}
+
+impl Artboard {
+    /// Whether `pos`/`size` lies entirely within this artboard.
+    pub fn contains(&self, pos: Vec2, size: Vec2) -> bool {
+        pos.x >= self.pos.x
+            && pos.y >= self.pos.y
+            && pos.x + size.x <= self.pos.x + self.size.x
+            && pos.y + size.y <= self.pos.y + self.size.y
+    }
+}
+
+/// Nodes carried by copy, cut and paste, in draw order. Ids are kept but
+/// never trusted: pasting allocates fresh ones.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Clipboard {
+    #[serde(default)]
+    pub rects: Vec<RectNode>,
+    #[serde(default)]
+    pub texts: Vec<TextNode>,
+}
+
+impl Clipboard {
+    pub fn is_empty(&self) -> bool {
+        self.rects.is_empty() && self.texts.is_empty()
+    }
 }
 
-#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Document {
     pub next_id: u64,
+    /// Next id `alloc_group_id` hands out; 0 until a group is first made.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub next_group_id: u64,
     pub rects: Vec<RectNode>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub texts: Vec<TextNode>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub artboards: Vec<Artboard>,
 }
 
 impl Document {
     pub fn new() -> Self {
         Self {
             next_id: 1,
+            next_group_id: 0,
             rects: vec![],
+            texts: vec![],
+            artboards: vec![],
+        }
+    }
+
+    /// Topmost artboard that fully contains `pos`/`size`, if any.
+    pub fn containing_artboard(&self, pos: Vec2, size: Vec2) -> Option<Artboard> {
+        self.artboards
+            .iter()
+            .rev()
+            .find(|artboard| artboard.contains(pos, size))
+            .copied()
+    }
+
+    /// Raise `next_id` past every id in use, so documents saved by other
+    /// tools (or edited by hand) can't hand out a colliding id.
+    pub fn repair_next_id(&mut self) {
+        let max_id = self
+            .rects
+            .iter()
+            .map(|rect| rect.id.0)
+            .chain(self.texts.iter().map(|text| text.id.0))
+            .max()
+            .unwrap_or(0);
+        self.next_id = self.next_id.max(max_id + 1);
+
+        if let Some(max_group) = self.rects.iter().filter_map(|rect| rect.group_id).max() {
+            self.next_group_id = self.next_group_id.max(max_group + 1);
         }
     }
 
@@ -42,4 +265,152 @@ impl Document {
         self.next_id += 1;
         NodeId(id)
     }
+
+    /// Reserve a fresh id for `RectNode::group_id`, starting from 1.
+    pub fn alloc_group_id(&mut self) -> u64 {
+        let id = self.next_group_id.max(1);
+        self.next_group_id = id + 1;
+        id
+    }
+
+    /// World-space bounding box `(min, max)` of all rects and text labels,
+    /// or `None` when empty. Text uses its approximate extent.
+    pub fn bounds(&self) -> Option<(Vec2, Vec2)> {
+        let texts = self.texts.iter().map(|text| {
+            let size = text.approx_size();
+            (
+                text.pos,
+                Vec2::new(text.pos.x + size.x, text.pos.y + size.y),
+            )
+        });
+        union_bounds(self.rects.iter().map(RectNode::world_aabb).chain(texts))
+    }
+
+    /// World-space bounding box `(min, max)` of all visible rects, or `None`
+    /// when there are none.
+    fn visible_rect_bounds(&self) -> Option<(Vec2, Vec2)> {
+        union_bounds(self.visible_rects().map(RectNode::world_aabb))
+    }
+
+    /// Rects that are drawn, in draw order.
+    fn visible_rects(&self) -> impl Iterator<Item = &RectNode> {
+        self.rects.iter().filter(|rect| rect.visible)
+    }
+
+    /// Render the visible rects as standalone SVG markup, bottom to top, with
+    /// the viewBox fitted to their bounds. Hidden rects, text and artboards
+    /// are not exported.
+    ///
+    /// SVG centers strokes on the outline, so stroked rects are written inset
+    /// by half the stroke width to keep the border inside the edge, as drawn.
+    pub fn to_svg(&self) -> String {
+        let (min, max) = self.visible_rect_bounds().unwrap_or_default();
+        let (w, h) = (max.x - min.x, max.y - min.y);
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{} {} {w} {h}\" width=\"{w}\" height=\"{h}\">\n",
+            min.x, min.y
+        );
+        for rect in self.visible_rects() {
+            let inset = rect.stroke_width.max(0.0) * 0.5;
+            svg.push_str(&format!(
+                "  <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"",
+                rect.pos.x + inset,
+                rect.pos.y + inset,
+                (rect.size.x - 2.0 * inset).max(0.0),
+                (rect.size.y - 2.0 * inset).max(0.0),
+                rect.color.to_hex_rgb()
+            ));
+            if rect.color.a < 1.0 {
+                svg.push_str(&format!(" fill-opacity=\"{}\"", rect.color.a));
+            }
+            if rect.stroke_width > 0.0 {
+                svg.push_str(&format!(
+                    " stroke=\"{}\" stroke-width=\"{}\"",
+                    rect.stroke_color.to_hex_rgb(),
+                    rect.stroke_width
+                ));
+                if rect.stroke_color.a < 1.0 {
+                    svg.push_str(&format!(" stroke-opacity=\"{}\"", rect.stroke_color.a));
+                }
+            }
+            if rect.rotation != 0.0 {
+                let c = rect.center();
+                svg.push_str(&format!(
+                    " transform=\"rotate({} {} {})\"",
+                    rect.rotation.to_degrees(),
+                    c.x,
+                    c.y
+                ));
+            }
+            svg.push_str("/>\n");
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    /// Serialize to compact JSON, suited to storage and wire transfer.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("Document serialization is infallible")
+    }
+
+    /// Serialize to indented JSON for hand-editing and diffing.
+    pub fn to_json_pretty(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Document serialization is infallible")
+    }
+
+    /// Parse a document from JSON produced by `to_json` or `to_json_pretty`.
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json)
+    }
+}
+
+fn is_zero(v: &u64) -> bool {
+    *v == 0
+}
+
+fn is_zero_f32(v: &f32) -> bool {
+    *v == 0.0
+}
+
+fn default_visible() -> bool {
+    true
+}
+
+pub(crate) fn default_stroke_color() -> Color {
+    Color::BLACK
+}
+
+fn is_default_stroke_color(color: &Color) -> bool {
+    *color == default_stroke_color()
+}
+
+/// The three sample rects the editor boots with.
+///
+/// Exposed so tests, examples and hosts can reproduce (or reset to) the demo
+/// content without constructing a full `Engine`.
+pub fn demo_document() -> Document {
+    let mut doc = Document::new();
+
+    doc.rects = vec![
+        RectNode::new(
+            doc.alloc_id(),
+            Vec2::new(100.0, 100.0),
+            Vec2::new(120.0, 80.0),
+            Color::rgba(0.2, 0.7, 0.9, 1.0),
+        ),
+        RectNode::new(
+            doc.alloc_id(),
+            Vec2::new(300.0, 220.0),
+            Vec2::new(140.0, 80.0),
+            Color::rgba(0.9, 0.3, 0.9, 1.0),
+        ),
+        RectNode::new(
+            doc.alloc_id(),
+            Vec2::new(600.0, 900.0),
+            Vec2::new(200.0, 100.0),
+            Color::rgba(0.5, 0.8, 0.4, 1.0),
+        ),
+    ];
+
+    doc
 }
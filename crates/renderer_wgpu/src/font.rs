@@ -0,0 +1,268 @@
+//! Built-in 5x7 bitmap font: the coverage atlas uploaded once per renderer
+//! and the layout turning `engine::api::TextInstance`s into glyph quads.
+
+use engine::api::TextInstance;
+
+/// Glyph bitmap size in atlas pixels.
+const GLYPH_W: usize = 5;
+const GLYPH_H: usize = 7;
+/// Atlas cell size; the extra column and row keep neighbouring glyphs from
+/// bleeding into each other.
+pub(crate) const CELL_W: u32 = 6;
+pub(crate) const CELL_H: u32 = 8;
+/// Cells per atlas row.
+pub(crate) const ATLAS_COLUMNS: u32 = 16;
+
+/// Same metrics `TextNode::approx_size` estimates text extents with, as
+/// fractions of the font size.
+const ADVANCE_EM: f32 = 0.6;
+const LINE_HEIGHT_EM: f32 = 1.2;
+
+/// First character of `GLYPHS`; the table covers printable ASCII in order.
+const FIRST_ASCII: u32 = 0x20;
+/// Glyphs past printable ASCII, right after it in `GLYPHS`.
+const EXTRA_CHARS: [char; 1] = ['\u{d7}'];
+
+/// One glyph per entry, column-major: byte `x` holds column `x`, bit `y`
+/// (least significant first) set where the glyph covers row `y`. Printable
+/// ASCII, then `EXTRA_CHARS`, then the box drawn for anything else.
+const GLYPHS: [[u8; GLYPH_W]; 97] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00], // ' '
+    [0x00, 0x00, 0x5f, 0x00, 0x00], // !
+    [0x00, 0x07, 0x00, 0x07, 0x00], // "
+    [0x14, 0x7f, 0x14, 0x7f, 0x14], // #
+    [0x24, 0x2a, 0x7f, 0x2a, 0x12], // $
+    [0x23, 0x13, 0x08, 0x64, 0x62], // %
+    [0x36, 0x49, 0x55, 0x22, 0x50], // &
+    [0x00, 0x05, 0x03, 0x00, 0x00], // '
+    [0x00, 0x1c, 0x22, 0x41, 0x00], // (
+    [0x00, 0x41, 0x22, 0x1c, 0x00], // )
+    [0x08, 0x2a, 0x1c, 0x2a, 0x08], // *
+    [0x08, 0x08, 0x3e, 0x08, 0x08], // +
+    [0x00, 0x50, 0x30, 0x00, 0x00], // ,
+    [0x08, 0x08, 0x08, 0x08, 0x08], // -
+    [0x00, 0x60, 0x60, 0x00, 0x00], // .
+    [0x20, 0x10, 0x08, 0x04, 0x02], // /
+    [0x3e, 0x51, 0x49, 0x45, 0x3e], // 0
+    [0x00, 0x42, 0x7f, 0x40, 0x00], // 1
+    [0x42, 0x61, 0x51, 0x49, 0x46], // 2
+    [0x21, 0x41, 0x45, 0x4b, 0x31], // 3
+    [0x18, 0x14, 0x12, 0x7f, 0x10], // 4
+    [0x27, 0x45, 0x45, 0x45, 0x39], // 5
+    [0x3c, 0x4a, 0x49, 0x49, 0x30], // 6
+    [0x01, 0x71, 0x09, 0x05, 0x03], // 7
+    [0x36, 0x49, 0x49, 0x49, 0x36], // 8
+    [0x06, 0x49, 0x49, 0x29, 0x1e], // 9
+    [0x00, 0x36, 0x36, 0x00, 0x00], // :
+    [0x00, 0x56, 0x36, 0x00, 0x00], // ;
+    [0x08, 0x14, 0x22, 0x41, 0x00], // <
+    [0x14, 0x14, 0x14, 0x14, 0x14], // =
+    [0x00, 0x41, 0x22, 0x14, 0x08], // >
+    [0x02, 0x01, 0x51, 0x09, 0x06], // ?
+    [0x32, 0x49, 0x79, 0x41, 0x3e], // @
+    [0x7e, 0x11, 0x11, 0x11, 0x7e], // A
+    [0x7f, 0x49, 0x49, 0x49, 0x36], // B
+    [0x3e, 0x41, 0x41, 0x41, 0x22], // C
+    [0x7f, 0x41, 0x41, 0x22, 0x1c], // D
+    [0x7f, 0x49, 0x49, 0x49, 0x41], // E
+    [0x7f, 0x09, 0x09, 0x01, 0x01], // F
+    [0x3e, 0x41, 0x41, 0x51, 0x32], // G
+    [0x7f, 0x08, 0x08, 0x08, 0x7f], // H
+    [0x00, 0x41, 0x7f, 0x41, 0x00], // I
+    [0x20, 0x40, 0x41, 0x3f, 0x01], // J
+    [0x7f, 0x08, 0x14, 0x22, 0x41], // K
+    [0x7f, 0x40, 0x40, 0x40, 0x40], // L
+    [0x7f, 0x02, 0x04, 0x02, 0x7f], // M
+    [0x7f, 0x04, 0x08, 0x10, 0x7f], // N
+    [0x3e, 0x41, 0x41, 0x41, 0x3e], // O
+    [0x7f, 0x09, 0x09, 0x09, 0x06], // P
+    [0x3e, 0x41, 0x51, 0x21, 0x5e], // Q
+    [0x7f, 0x09, 0x19, 0x29, 0x46], // R
+    [0x46, 0x49, 0x49, 0x49, 0x31], // S
+    [0x01, 0x01, 0x7f, 0x01, 0x01], // T
+    [0x3f, 0x40, 0x40, 0x40, 0x3f], // U
+    [0x1f, 0x20, 0x40, 0x20, 0x1f], // V
+    [0x7f, 0x20, 0x18, 0x20, 0x7f], // W
+    [0x63, 0x14, 0x08, 0x14, 0x63], // X
+    [0x03, 0x04, 0x78, 0x04, 0x03], // Y
+    [0x61, 0x51, 0x49, 0x45, 0x43], // Z
+    [0x00, 0x7f, 0x41, 0x41, 0x00], // [
+    [0x02, 0x04, 0x08, 0x10, 0x20], // \
+    [0x00, 0x41, 0x41, 0x7f, 0x00], // ]
+    [0x04, 0x02, 0x01, 0x02, 0x04], // ^
+    [0x40, 0x40, 0x40, 0x40, 0x40], // _
+    [0x00, 0x01, 0x02, 0x04, 0x00], // `
+    [0x20, 0x54, 0x54, 0x54, 0x78], // a
+    [0x7f, 0x48, 0x44, 0x44, 0x38], // b
+    [0x38, 0x44, 0x44, 0x44, 0x20], // c
+    [0x38, 0x44, 0x44, 0x48, 0x7f], // d
+    [0x38, 0x54, 0x54, 0x54, 0x18], // e
+    [0x08, 0x7e, 0x09, 0x01, 0x02], // f
+    [0x08, 0x14, 0x54, 0x54, 0x3c], // g
+    [0x7f, 0x08, 0x04, 0x04, 0x78], // h
+    [0x00, 0x44, 0x7d, 0x40, 0x00], // i
+    [0x20, 0x40, 0x44, 0x3d, 0x00], // j
+    [0x00, 0x7f, 0x10, 0x28, 0x44], // k
+    [0x00, 0x41, 0x7f, 0x40, 0x00], // l
+    [0x7c, 0x04, 0x18, 0x04, 0x78], // m
+    [0x7c, 0x08, 0x04, 0x04, 0x78], // n
+    [0x38, 0x44, 0x44, 0x44, 0x38], // o
+    [0x7c, 0x14, 0x14, 0x14, 0x08], // p
+    [0x08, 0x14, 0x14, 0x18, 0x7c], // q
+    [0x7c, 0x08, 0x04, 0x04, 0x08], // r
+    [0x48, 0x54, 0x54, 0x54, 0x20], // s
+    [0x04, 0x3f, 0x44, 0x40, 0x20], // t
+    [0x3c, 0x40, 0x40, 0x20, 0x7c], // u
+    [0x1c, 0x20, 0x40, 0x20, 0x1c], // v
+    [0x3c, 0x40, 0x30, 0x40, 0x3c], // w
+    [0x44, 0x28, 0x10, 0x28, 0x44], // x
+    [0x0c, 0x50, 0x50, 0x50, 0x3c], // y
+    [0x44, 0x64, 0x54, 0x4c, 0x44], // z
+    [0x00, 0x08, 0x36, 0x41, 0x00], // {
+    [0x00, 0x00, 0x7f, 0x00, 0x00], // |
+    [0x00, 0x41, 0x36, 0x08, 0x00], // }
+    [0x02, 0x01, 0x02, 0x04, 0x02], // ~
+    [0x22, 0x14, 0x08, 0x14, 0x22], // multiplication sign
+    [0x7f, 0x41, 0x41, 0x41, 0x7f], // fallback box
+];
+
+/// Index of the box drawn for characters the font lacks.
+const FALLBACK_GLYPH: u32 = GLYPHS.len() as u32 - 1;
+
+/// Atlas cell of `ch`: its slot in `GLYPHS`, or the fallback box.
+pub(crate) fn glyph_index(ch: char) -> u32 {
+    let code = ch as u32;
+    if (FIRST_ASCII..FIRST_ASCII + 95).contains(&code) {
+        return code - FIRST_ASCII;
+    }
+    match EXTRA_CHARS.iter().position(|extra| *extra == ch) {
+        Some(i) => 95 + i as u32,
+        None => FALLBACK_GLYPH,
+    }
+}
+
+/// Width and height in pixels of the atlas `atlas_pixels` fills.
+pub(crate) fn atlas_size() -> (u32, u32) {
+    let rows = (GLYPHS.len() as u32).div_ceil(ATLAS_COLUMNS);
+    (ATLAS_COLUMNS * CELL_W, rows * CELL_H)
+}
+
+/// One coverage byte per atlas pixel, rows top first: 255 where a glyph is
+/// drawn, 0 elsewhere.
+pub(crate) fn atlas_pixels() -> Vec<u8> {
+    let (width, height) = atlas_size();
+    let mut pixels = vec![0u8; (width * height) as usize];
+    for (i, glyph) in GLYPHS.iter().enumerate() {
+        let cell_x = (i as u32 % ATLAS_COLUMNS * CELL_W) as usize;
+        let cell_y = (i as u32 / ATLAS_COLUMNS * CELL_H) as usize;
+        for (x, column) in glyph.iter().enumerate() {
+            for y in 0..GLYPH_H {
+                if column & (1 << y) != 0 {
+                    pixels[(cell_y + y) * width as usize + cell_x + x] = 255;
+                }
+            }
+        }
+    }
+    pixels
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub(crate) struct GpuGlyphInstance {
+    // world-space top-left of the glyph's atlas cell
+    pub(crate) pos: [f32; 2],
+    // world units per atlas pixel
+    pub(crate) scale: f32,
+    // cell index into the atlas, row-major
+    pub(crate) glyph: u32,
+    pub(crate) color: [f32; 4],
+}
+
+/// Replace the contents of `out` with one glyph quad per visible character
+/// of `texts`, keeping its allocation. Each text's `pos` is its top-left and
+/// its `size` the font size; `\n` starts a new line.
+pub(crate) fn fill_glyph_instances<'a>(
+    out: &mut Vec<GpuGlyphInstance>,
+    texts: impl IntoIterator<Item = &'a TextInstance>,
+) {
+    out.clear();
+    for text in texts {
+        let scale = text.size * ADVANCE_EM / CELL_W as f32;
+        let color = text.color.to_array();
+        let (mut x, mut y) = (text.pos[0], text.pos[1]);
+        for ch in text.content.chars() {
+            match ch {
+                '\n' => {
+                    x = text.pos[0];
+                    y += text.size * LINE_HEIGHT_EM;
+                }
+                ' ' => x += text.size * ADVANCE_EM,
+                _ => {
+                    out.push(GpuGlyphInstance {
+                        pos: [x, y],
+                        scale,
+                        glyph: glyph_index(ch),
+                        color,
+                    });
+                    x += text.size * ADVANCE_EM;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Coverage of atlas pixel `(x, y)` within the cell of `ch`.
+    fn covered(ch: char, x: u32, y: u32) -> bool {
+        let (width, _) = atlas_size();
+        let glyph = glyph_index(ch);
+        let px = glyph % ATLAS_COLUMNS * CELL_W + x;
+        let py = glyph / ATLAS_COLUMNS * CELL_H + y;
+        atlas_pixels()[(py * width + px) as usize] == 255
+    }
+
+    #[test]
+    fn atlas_cells_hold_their_glyphs_with_a_blank_gutter() {
+        assert_eq!(glyph_index('A'), 33);
+        assert_eq!(glyph_index('\u{d7}'), 95);
+        assert_eq!(glyph_index('\u{e9}'), FALLBACK_GLYPH);
+
+        // 'I' is a full-height stem in column 2 with serifs top and bottom.
+        assert!((0..7).all(|y| covered('I', 2, y)));
+        assert!(covered('I', 1, 0) && covered('I', 3, 6));
+        assert!(!covered('I', 0, 3));
+        for ch in ['A', 'W', '\u{d7}', '\u{e9}'] {
+            assert!(
+                (0..CELL_H).all(|y| !covered(ch, 5, y)),
+                "{ch} gutter column"
+            );
+            assert!((0..CELL_W).all(|x| !covered(ch, x, 7)), "{ch} gutter row");
+        }
+        let (width, height) = atlas_size();
+        assert_eq!(atlas_pixels().len(), (width * height) as usize);
+    }
+
+    #[test]
+    fn layout_advances_per_character_and_wraps_on_newlines() {
+        let text = TextInstance {
+            pos: [10.0, 20.0],
+            content: "ab c\nd".to_string(),
+            size: 10.0,
+            color: engine::api::Color::WHITE,
+        };
+        let mut glyphs = vec![bytemuck::Zeroable::zeroed(); 8];
+        fill_glyph_instances(&mut glyphs, [&text]);
+
+        // Spaces advance without emitting a quad.
+        let positions: Vec<[f32; 2]> = glyphs.iter().map(|g| g.pos).collect();
+        assert_eq!(
+            positions,
+            [[10.0, 20.0], [16.0, 20.0], [28.0, 20.0], [10.0, 32.0]]
+        );
+        assert_eq!(glyphs[3].glyph, glyph_index('d'));
+        assert!(glyphs.iter().all(|g| g.scale == 1.0));
+    }
+}
@@ -0,0 +1,167 @@
+use std::marker::PhantomData;
+
+/// Number of instance buffers cycled per stream. Writing into the buffer the
+/// GPU consumed two frames ago avoids stalling on one it may still be reading.
+pub(crate) const FRAMES_IN_FLIGHT: usize = 2;
+
+/// Instances every buffer starts out holding; buffers grow past this but
+/// never shrink back below it.
+pub(crate) const MIN_CAPACITY: usize = 1024;
+
+/// Round-robin set of instance buffers for one draw stream (scene or overlay
+/// rects, or glyphs) of `T` instances.
+pub(crate) struct InstanceRing<T> {
+    label: &'static str,
+    buffers: Vec<wgpu::Buffer>,
+    capacities: Vec<usize>,
+    current: usize,
+    count: u32,
+    // device limit on a single buffer's size in bytes
+    max_buffer_size: u64,
+    // whether the last upload was truncated, so the warning logs once per overflow
+    truncated: bool,
+    instance: PhantomData<T>,
+}
+
+impl<T: bytemuck::Pod> InstanceRing<T> {
+    pub(crate) fn new(device: &wgpu::Device, label: &'static str) -> Self {
+        let max_buffer_size = device.limits().max_buffer_size;
+        let capacity = fitting_instance_count::<T>(MIN_CAPACITY, max_buffer_size);
+        Self {
+            label,
+            buffers: (0..FRAMES_IN_FLIGHT)
+                .map(|_| Self::create_buffer(device, label, capacity))
+                .collect(),
+            capacities: vec![capacity; FRAMES_IN_FLIGHT],
+            current: 0,
+            count: 0,
+            max_buffer_size,
+            truncated: false,
+            instance: PhantomData,
+        }
+    }
+
+    /// Advance to the next buffer in the ring and upload `instances` into it,
+    /// growing that buffer if needed. Only the populated prefix is written.
+    ///
+    /// Instances past what the device's largest buffer can hold are dropped
+    /// with a console warning rather than failing the allocation.
+    pub(crate) fn upload(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, instances: &[T]) {
+        self.current = (self.current + 1) % self.buffers.len();
+
+        let needed = fitting_instance_count::<T>(instances.len(), self.max_buffer_size);
+        let truncated = needed < instances.len();
+        if truncated && !self.truncated {
+            warn(&format!(
+                "{}: drawing {needed} of {} instances; the rest exceed the device buffer limit",
+                self.label,
+                instances.len()
+            ));
+        }
+        self.truncated = truncated;
+        let instances = &instances[..needed];
+
+        let capacity = fitting_instance_count::<T>(
+            grown_capacity(self.capacities[self.current], needed),
+            self.max_buffer_size,
+        );
+        if capacity != self.capacities[self.current] {
+            self.capacities[self.current] = capacity;
+            self.buffers[self.current] = Self::create_buffer(device, self.label, capacity);
+        }
+
+        if !instances.is_empty() {
+            queue.write_buffer(
+                &self.buffers[self.current],
+                0,
+                bytemuck::cast_slice(instances),
+            );
+        }
+        self.count = needed as u32;
+    }
+
+    /// Buffer holding the most recent upload.
+    pub(crate) fn buffer(&self) -> &wgpu::Buffer {
+        &self.buffers[self.current]
+    }
+
+    /// Instance count of the most recent upload.
+    pub(crate) fn count(&self) -> u32 {
+        self.count
+    }
+
+    fn create_buffer(device: &wgpu::Device, label: &'static str, capacity: usize) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size: (std::mem::size_of::<T>() * capacity) as u64,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+}
+
+/// How many of `needed` `T` instances fit in a buffer of at most
+/// `max_buffer_size` bytes, also keeping the count drawable as a `u32`.
+fn fitting_instance_count<T>(needed: usize, max_buffer_size: u64) -> usize {
+    let per_buffer = max_buffer_size / std::mem::size_of::<T>() as u64;
+    let limit = per_buffer.min(u32::MAX as u64);
+    needed.min(usize::try_from(limit).unwrap_or(usize::MAX))
+}
+
+/// Capacity a buffer of `current` instances needs to hold `needed`: unchanged
+/// if it already fits, else the next power of two, never below `MIN_CAPACITY`.
+fn grown_capacity(current: usize, needed: usize) -> usize {
+    if needed <= current {
+        current
+    } else {
+        needed.next_power_of_two().max(MIN_CAPACITY)
+    }
+}
+
+/// Log to the browser console, or to stderr for native (headless) builds.
+fn warn(message: &str) {
+    #[cfg(target_arch = "wasm32")]
+    web_sys::console::warn_1(&message.into());
+    #[cfg(not(target_arch = "wasm32"))]
+    eprintln!("{message}");
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::GpuRectInstance;
+
+    #[test]
+    fn capacity_grows_to_powers_of_two_and_never_shrinks() {
+        assert_eq!(grown_capacity(MIN_CAPACITY, 10), MIN_CAPACITY);
+        assert_eq!(grown_capacity(MIN_CAPACITY, 1025), 2048);
+        assert_eq!(grown_capacity(2048, 5000), 8192);
+        // A small frame after a large one keeps the large buffer.
+        assert_eq!(grown_capacity(8192, 3), 8192);
+        assert_eq!(grown_capacity(0, 1), MIN_CAPACITY);
+    }
+
+    #[test]
+    fn huge_instance_counts_are_capped_to_the_buffer_limit() {
+        let stride = std::mem::size_of::<GpuRectInstance>() as u64;
+        // wgpu's default 256 MiB max_buffer_size.
+        let limit = 256 << 20;
+        let fits = (limit / stride) as usize;
+
+        assert_eq!(fitting_instance_count::<GpuRectInstance>(10, limit), 10);
+        assert_eq!(fitting_instance_count::<GpuRectInstance>(fits, limit), fits);
+        assert_eq!(
+            fitting_instance_count::<GpuRectInstance>(fits + 1, limit),
+            fits
+        );
+        assert_eq!(
+            fitting_instance_count::<GpuRectInstance>(usize::MAX, limit),
+            fits
+        );
+        // The draw call takes a u32 count even if the device allows more.
+        assert_eq!(
+            fitting_instance_count::<GpuRectInstance>(usize::MAX, u64::MAX),
+            u32::MAX as usize
+        );
+    }
+}
@@ -1,17 +1,21 @@
-use engine::{Camera, OverlayScene, RenderScene};
+mod font;
+mod instances;
+mod png;
+
+use engine::api::{Camera, OverlayScene, RectInstance, RenderScene};
 use wasm_bindgen::JsValue;
 use web_sys::HtmlCanvasElement;
-#[cfg(target_arch = "wasm32")]
 use wgpu::util::DeviceExt;
 
-#[cfg(target_arch = "wasm32")]
+use crate::font::GpuGlyphInstance;
+use crate::instances::InstanceRing;
+
 #[repr(C)]
 #[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
 struct Vertex {
     pos: [f32; 2],
 }
 
-#[cfg(target_arch = "wasm32")]
 const QUAD_VERTS: [Vertex; 6] = [
     Vertex { pos: [0.0, 0.0] },
     Vertex { pos: [1.0, 0.0] },
@@ -21,15 +25,52 @@ const QUAD_VERTS: [Vertex; 6] = [
     Vertex { pos: [0.0, 1.0] },
 ];
 
-#[cfg(target_arch = "wasm32")]
+const DEFAULT_CLEAR_COLOR: wgpu::Color = wgpu::Color {
+    r: 0.08,
+    g: 0.09,
+    b: 0.12,
+    a: 1.0,
+};
+
+/// MSAA sample count used when the surface format supports it.
+const DEFAULT_SAMPLE_COUNT: u32 = 4;
+
+/// Depth buffer format for the scene pass.
+const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+/// Color format of the headless render target.
+const HEADLESS_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+
 const SHADER: &str = include_str!("shader.wgsl");
+const TEXT_SHADER: &str = include_str!("text.wgsl");
 
 pub struct Renderer {
-    surface: wgpu::Surface<'static>,
+    // `None` for a headless renderer, which only draws offscreen
+    surface: Option<wgpu::Surface<'static>>,
     device: wgpu::Device,
     queue: wgpu::Queue,
+    // target size and format, configured on `surface` when there is one
     config: wgpu::SurfaceConfiguration,
     pipeline: wgpu::RenderPipeline,
+    overlay_pipeline: wgpu::RenderPipeline,
+    overlay_blend: BlendMode,
+    // kept so the pipelines can be rebuilt when the sample count changes
+    pipeline_layout: wgpu::PipelineLayout,
+    shader: wgpu::ShaderModule,
+    // glyph quads sampling the font atlas, one pipeline per pass
+    text_pipeline: wgpu::RenderPipeline,
+    overlay_text_pipeline: wgpu::RenderPipeline,
+    text_pipeline_layout: wgpu::PipelineLayout,
+    text_shader: wgpu::ShaderModule,
+    atlas_bind_group: wgpu::BindGroup,
+
+    sample_count: u32,
+    // sample counts the surface format supports
+    msaa_flags: wgpu::TextureFormatFeatureFlags,
+    // multisampled target both passes draw into; resolved into the output view
+    msaa_view: Option<wgpu::TextureView>,
+    // scene pass depth buffer, sized and sampled like the color target
+    depth_view: wgpu::TextureView,
 
     vertex_buf: wgpu::Buffer,
     vertex_count: u32,
@@ -37,18 +78,102 @@ pub struct Renderer {
     camera_buf: wgpu::Buffer,
     camera_bind_group: wgpu::BindGroup,
 
-    scene_instance: wgpu::Buffer,
-    scene_instance_count: u32,
-    scene_instance_capacity: usize,
+    scene_instances: InstanceRing<GpuRectInstance>,
+    overlay_instances: InstanceRing<GpuRectInstance>,
+    scene_glyphs: InstanceRing<GpuGlyphInstance>,
+    overlay_glyphs: InstanceRing<GpuGlyphInstance>,
+    // per-frame upload scratch, cleared and refilled instead of reallocated
+    staging: Vec<GpuRectInstance>,
+    glyph_staging: Vec<GpuGlyphInstance>,
+    overlay_scratch: Vec<RectInstance>,
+
+    clear_color: wgpu::Color,
+    overlay_on_top: bool,
+    // physical pixels per engine screen pixel; `config` is in physical pixels
+    device_pixel_ratio: f32,
+}
+
+/// How a pass composites its fragments over what is already in the target.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BlendMode {
+    /// Straight alpha; what the scene pass always uses.
+    Alpha,
+    /// Premultiplied alpha. Opaque fragments replace the target outright and
+    /// translucent ones accumulate coverage correctly on a premultiplied
+    /// canvas, so selection handles stay crisp over any content.
+    #[default]
+    Premultiplied,
+    /// Adds color on top of the target. Good for glow-style highlights, but
+    /// handles brighten rather than cover what is beneath.
+    Additive,
+}
+
+impl BlendMode {
+    fn blend_state(self) -> wgpu::BlendState {
+        match self {
+            BlendMode::Alpha => wgpu::BlendState::ALPHA_BLENDING,
+            BlendMode::Premultiplied => wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING,
+            BlendMode::Additive => wgpu::BlendState {
+                color: wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::One,
+                    dst_factor: wgpu::BlendFactor::One,
+                    operation: wgpu::BlendOperation::Add,
+                },
+                alpha: wgpu::BlendComponent::OVER,
+            },
+        }
+    }
+
+    /// Fragment entry point producing the color format `blend_state` expects.
+    fn fragment_entry(self) -> &'static str {
+        match self {
+            BlendMode::Alpha => "fs_main",
+            BlendMode::Premultiplied | BlendMode::Additive => "fs_premultiplied",
+        }
+    }
+}
+
+/// The two passes a frame is composed of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Pass {
+    Scene,
+    Overlay,
+}
 
-    overlay_instance: wgpu::Buffer,
-    overlay_instance_count: u32,
-    overlay_instance_capacity: usize,
+/// Draw order for a frame; the first pass clears the target.
+fn pass_order(overlay_on_top: bool) -> [Pass; 2] {
+    if overlay_on_top {
+        [Pass::Scene, Pass::Overlay]
+    } else {
+        [Pass::Overlay, Pass::Scene]
+    }
+}
+
+/// Draw order for a separate overlay canvas stacked above the scene canvas.
+/// With the overlay beneath the scene, the last uploaded scene is drawn again
+/// over it so artwork still hides the chrome it covers.
+fn overlay_only_passes(overlay_on_top: bool) -> &'static [Pass] {
+    if overlay_on_top {
+        &[Pass::Overlay]
+    } else {
+        &[Pass::Overlay, Pass::Scene]
+    }
 }
 
 impl Renderer {
+    /// Create a renderer drawing into `canvas`. The scene pass blends with
+    /// straight alpha; `overlay_blend` picks how selection chrome composites.
     #[cfg(target_arch = "wasm32")]
-    pub async fn new(canvas: HtmlCanvasElement) -> Result<Self, JsValue> {
+    pub async fn new(canvas: HtmlCanvasElement, overlay_blend: BlendMode) -> Result<Self, JsValue> {
+        if canvas.width() == 0 || canvas.height() == 0 {
+            return Err(JsValue::from_str(&format!(
+                "canvas is {}x{}; set its width/height attributes before creating the renderer \
+                 (common causes: the canvas or a parent is display:none, or it is read before layout)",
+                canvas.width(),
+                canvas.height()
+            )));
+        }
+        // Unreachable after the check above; kept so a zero never reaches wgpu.
         let width = canvas.width().max(1);
         let height = canvas.height().max(1);
 
@@ -66,15 +191,7 @@ impl Renderer {
             .await
             .map_err(|e| JsValue::from_str(&format!("request_adapter failed: {e}")))?;
 
-        let (device, queue) = adapter
-            .request_device(&wgpu::DeviceDescriptor {
-                label: Some("renderer_wgpu device"),
-                required_features: wgpu::Features::empty(),
-                required_limits: wgpu::Limits::downlevel_webgl2_defaults(),
-                memory_hints: wgpu::MemoryHints::default(),
-                trace: wgpu::Trace::Off,
-                experimental_features: wgpu::ExperimentalFeatures::default(),
-            })
+        let (device, queue) = request_device(&adapter)
             .await
             .map_err(|e| JsValue::from_str(&format!("request_device failed: {e}")))?;
 
@@ -87,35 +204,104 @@ impl Renderer {
             width,
             height,
             present_mode: caps.present_modes[0],
-            alpha_mode: caps.alpha_modes[0],
+            // Premultiplied alpha lets a transparent clear reveal the page beneath.
+            alpha_mode: if caps
+                .alpha_modes
+                .contains(&wgpu::CompositeAlphaMode::PreMultiplied)
+            {
+                wgpu::CompositeAlphaMode::PreMultiplied
+            } else {
+                caps.alpha_modes[0]
+            },
             desired_maximum_frame_latency: 2,
             view_formats: vec![],
         };
 
         surface.configure(&device, &config);
 
+        let msaa_flags = adapter.get_texture_format_features(format).flags;
+        Ok(Self::from_parts(
+            device,
+            queue,
+            Some(surface),
+            config,
+            msaa_flags,
+            overlay_blend,
+        ))
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn new(canvas: HtmlCanvasElement, overlay_blend: BlendMode) -> Result<Self, JsValue> {
+        let _ = (canvas, overlay_blend);
+        Err(JsValue::from_str(
+            "renderer_wgpu only supports wasm32 targets",
+        ))
+    }
+
+    /// Create a renderer with no canvas that draws into a `width` x `height`
+    /// offscreen texture, each clamped to at least 1. Read frames back with
+    /// `render_to_pixels`; `render` and `render_overlay_only` return errors.
+    ///
+    /// Native builds enable wgpu's Vulkan, Metal, DX12 and GL backends, so
+    /// this works from tests and tools without a browser on any adapter,
+    /// software rasterizers included.
+    pub async fn new_headless(width: u32, height: u32) -> Result<Self, String> {
+        // `Instance::default` panics rather than erroring in this case.
+        if wgpu::Instance::enabled_backend_features().is_empty() {
+            return Err("no wgpu backend is enabled for this target".to_string());
+        }
+        let instance = wgpu::Instance::default();
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::LowPower,
+                compatible_surface: None,
+                force_fallback_adapter: false,
+            })
+            .await
+            .map_err(|e| format!("request_adapter failed: {e}"))?;
+        let (device, queue) = request_device(&adapter)
+            .await
+            .map_err(|e| format!("request_device failed: {e}"))?;
+
+        let config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format: HEADLESS_FORMAT,
+            width: width.max(1),
+            height: height.max(1),
+            present_mode: wgpu::PresentMode::Fifo,
+            alpha_mode: wgpu::CompositeAlphaMode::Opaque,
+            desired_maximum_frame_latency: 2,
+            view_formats: vec![],
+        };
+        let msaa_flags = adapter.get_texture_format_features(HEADLESS_FORMAT).flags;
+        Ok(Self::from_parts(
+            device,
+            queue,
+            None,
+            config,
+            msaa_flags,
+            BlendMode::default(),
+        ))
+    }
+
+    /// Build the pipelines and buffers shared by every constructor around an
+    /// already configured `surface` (if any) of `config`'s size and format.
+    fn from_parts(
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+        surface: Option<wgpu::Surface<'static>>,
+        config: wgpu::SurfaceConfiguration,
+        msaa_flags: wgpu::TextureFormatFeatureFlags,
+        overlay_blend: BlendMode,
+    ) -> Self {
         let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("simple shader"),
             source: wgpu::ShaderSource::Wgsl(SHADER.into()),
         });
 
-        let vertex_layout = wgpu::VertexBufferLayout {
-            array_stride: std::mem::size_of::<Vertex>() as u64,
-            step_mode: wgpu::VertexStepMode::Vertex,
-            attributes: &[wgpu::VertexAttribute {
-                format: wgpu::VertexFormat::Float32x2,
-                offset: 0,
-                shader_location: 0,
-            }],
-        };
-
-        let camera_uniform = CameraUniform {
-            pan: [0.0, 0.0],
-            zoom: 1.0,
-            _pad0: 0.0,
-            canvas: [width as f32, height as f32],
-            _pad1: [0.0, 0.0],
-        };
+        let (width, height) = (config.width, config.height);
+        let format = config.format;
+        let camera_uniform = CameraUniform::new(&Camera::default(), [width, height], 1.0);
 
         let camera_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("camera uniform"),
@@ -147,42 +333,10 @@ impl Renderer {
             }],
         });
 
-        let instance_capacity = 1024;
-        let instance_buf = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("rect instance buffer"),
-            size: (std::mem::size_of::<GpuRectInstance>() * instance_capacity) as u64,
-            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-        });
-
-        let overlay_instance_buf = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("overlay instance buffer"),
-            size: (std::mem::size_of::<GpuRectInstance>() * instance_capacity) as u64,
-            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-        });
-
-        let instance_layout = wgpu::VertexBufferLayout {
-            array_stride: std::mem::size_of::<GpuRectInstance>() as u64,
-            step_mode: wgpu::VertexStepMode::Instance,
-            attributes: &[
-                wgpu::VertexAttribute {
-                    format: wgpu::VertexFormat::Float32x2,
-                    offset: 0,
-                    shader_location: 1,
-                },
-                wgpu::VertexAttribute {
-                    format: wgpu::VertexFormat::Float32x2,
-                    offset: 8,
-                    shader_location: 2,
-                },
-                wgpu::VertexAttribute {
-                    format: wgpu::VertexFormat::Float32x4,
-                    offset: 16,
-                    shader_location: 3,
-                },
-            ],
-        };
+        let scene_instances = InstanceRing::new(&device, "rect instance buffer");
+        let overlay_instances = InstanceRing::new(&device, "overlay instance buffer");
+        let scene_glyphs = InstanceRing::new(&device, "glyph instance buffer");
+        let overlay_glyphs = InstanceRing::new(&device, "overlay glyph instance buffer");
 
         let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: Some("simple pipeline layout"),
@@ -190,31 +344,57 @@ impl Renderer {
             immediate_size: 0,
         });
 
-        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("simple pipeline"),
-            layout: Some(&pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: Some("vs_main"),
-                buffers: &[vertex_layout, instance_layout],
-                compilation_options: wgpu::PipelineCompilationOptions::default(),
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: Some("fs_main"),
-                targets: &[Some(wgpu::ColorTargetState {
-                    format,
-                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-                compilation_options: wgpu::PipelineCompilationOptions::default(),
-            }),
-            primitive: wgpu::PrimitiveState::default(),
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState::default(),
-            multiview_mask: None,
-            cache: None,
+        let sample_count = supported_sample_count(DEFAULT_SAMPLE_COUNT, msaa_flags);
+        let pipeline = create_pipeline(
+            &device,
+            &pipeline_layout,
+            &shader,
+            format,
+            sample_count,
+            BlendMode::Alpha,
+            true,
+        );
+        let overlay_pipeline = create_pipeline(
+            &device,
+            &pipeline_layout,
+            &shader,
+            format,
+            sample_count,
+            overlay_blend,
+            false,
+        );
+
+        let text_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("text shader"),
+            source: wgpu::ShaderSource::Wgsl(TEXT_SHADER.into()),
+        });
+        let (atlas_bind_group_layout, atlas_bind_group) = create_atlas(&device, &queue);
+        let text_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("text pipeline layout"),
+            bind_group_layouts: &[&camera_bind_group_layout, &atlas_bind_group_layout],
+            immediate_size: 0,
         });
+        let text_pipeline = create_text_pipeline(
+            &device,
+            &text_pipeline_layout,
+            &text_shader,
+            format,
+            sample_count,
+            BlendMode::Alpha,
+            true,
+        );
+        let overlay_text_pipeline = create_text_pipeline(
+            &device,
+            &text_pipeline_layout,
+            &text_shader,
+            format,
+            sample_count,
+            overlay_blend,
+            false,
+        );
+
+        let msaa_view = create_msaa_view(&device, format, width, height, sample_count);
+        let depth_view = create_depth_view(&device, width, height, sample_count);
 
         let vertex_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("rect vertices"),
@@ -222,53 +402,146 @@ impl Renderer {
             usage: wgpu::BufferUsages::VERTEX,
         });
 
-        Ok(Self {
+        Self {
             surface,
             device,
             queue,
             config,
             pipeline,
+            overlay_pipeline,
+            overlay_blend,
+            pipeline_layout,
+            shader,
+            text_pipeline,
+            overlay_text_pipeline,
+            text_pipeline_layout,
+            text_shader,
+            atlas_bind_group,
+            sample_count,
+            msaa_flags,
+            msaa_view,
+            depth_view,
             vertex_buf,
             vertex_count: QUAD_VERTS.len() as u32,
             camera_buf,
             camera_bind_group,
-            scene_instance: instance_buf,
-            scene_instance_count: 0,
-            scene_instance_capacity: instance_capacity,
-            overlay_instance: overlay_instance_buf,
-            overlay_instance_count: 0,
-            overlay_instance_capacity: instance_capacity,
-        })
-    }
-
-    #[cfg(not(target_arch = "wasm32"))]
-    pub async fn new(canvas: HtmlCanvasElement) -> Result<Self, JsValue> {
-        let _ = canvas;
-        Err(JsValue::from_str(
-            "renderer_wgpu only supports wasm32 targets",
-        ))
+            scene_instances,
+            overlay_instances,
+            scene_glyphs,
+            overlay_glyphs,
+            staging: Vec::with_capacity(instances::MIN_CAPACITY),
+            glyph_staging: Vec::new(),
+            overlay_scratch: Vec::new(),
+            clear_color: DEFAULT_CLEAR_COLOR,
+            overlay_on_top: true,
+            device_pixel_ratio: 1.0,
+        }
     }
 
-    pub fn resize(&mut self, width: u32, height: u32) {
-        let width = width.max(1);
-        let height = height.max(1);
-        if self.config.width == width && self.config.height == height {
-            return;
-        }
+    /// Resize the surface to `width` x `height` physical pixels, each clamped
+    /// to at least 1. Returns whether the size changed; repeating the current
+    /// size is a no-op.
+    pub fn resize(&mut self, width: u32, height: u32) -> bool {
+        let Some([width, height]) =
+            resized_surface([self.config.width, self.config.height], width, height)
+        else {
+            return false;
+        };
         self.config.width = width;
         self.config.height = height;
-        self.surface.configure(&self.device, &self.config);
+        if let Some(surface) = &self.surface {
+            surface.configure(&self.device, &self.config);
+        }
+        self.msaa_view = create_msaa_view(
+            &self.device,
+            self.config.format,
+            width,
+            height,
+            self.sample_count,
+        );
+        self.depth_view = create_depth_view(&self.device, width, height, self.sample_count);
 
-        let camera_uniform = CameraUniform {
-            pan: [0.0, 0.0],
-            zoom: 1.0,
-            _pad0: 0.0,
-            canvas: [self.config.width as f32, self.config.height as f32],
-            _pad1: [0.0, 0.0],
+        self.write_camera(&Camera::default(), [width, height], self.device_pixel_ratio);
+        true
+    }
+
+    /// Set how many physical pixels make up one engine screen pixel, e.g.
+    /// `window.devicePixelRatio`. Invalid ratios fall back to 1. The surface
+    /// size passed to `resize` stays in physical pixels.
+    pub fn set_device_pixel_ratio(&mut self, ratio: f32) {
+        self.device_pixel_ratio = sanitize_pixel_ratio(ratio);
+    }
+
+    /// Set the color the scene pass clears to. An alpha below 1.0 lets the page
+    /// show through the canvas when the surface supports premultiplied alpha.
+    pub fn set_clear_color(&mut self, color: [f32; 4]) {
+        self.clear_color = wgpu::Color {
+            r: color[0] as f64,
+            g: color[1] as f64,
+            b: color[2] as f64,
+            a: color[3] as f64,
         };
+    }
 
-        self.queue
-            .write_buffer(&self.camera_buf, 0, bytemuck::bytes_of(&camera_uniform));
+    /// Change the MSAA sample count, falling back to 1 when the surface
+    /// format doesn't support `count`. Returns the count now in use.
+    pub fn set_sample_count(&mut self, count: u32) -> u32 {
+        let count = supported_sample_count(count, self.msaa_flags);
+        if count != self.sample_count {
+            self.sample_count = count;
+            self.pipeline = create_pipeline(
+                &self.device,
+                &self.pipeline_layout,
+                &self.shader,
+                self.config.format,
+                count,
+                BlendMode::Alpha,
+                true,
+            );
+            self.overlay_pipeline = create_pipeline(
+                &self.device,
+                &self.pipeline_layout,
+                &self.shader,
+                self.config.format,
+                count,
+                self.overlay_blend,
+                false,
+            );
+            self.text_pipeline = create_text_pipeline(
+                &self.device,
+                &self.text_pipeline_layout,
+                &self.text_shader,
+                self.config.format,
+                count,
+                BlendMode::Alpha,
+                true,
+            );
+            self.overlay_text_pipeline = create_text_pipeline(
+                &self.device,
+                &self.text_pipeline_layout,
+                &self.text_shader,
+                self.config.format,
+                count,
+                self.overlay_blend,
+                false,
+            );
+            self.msaa_view = create_msaa_view(
+                &self.device,
+                self.config.format,
+                self.config.width,
+                self.config.height,
+                count,
+            );
+            self.depth_view =
+                create_depth_view(&self.device, self.config.width, self.config.height, count);
+        }
+        count
+    }
+
+    /// Choose whether selection chrome is drawn over the scene (the default)
+    /// or beneath it, e.g. when annotating underneath artwork.
+    pub fn set_overlay_on_top(&mut self, on_top: bool) {
+        self.overlay_on_top = on_top;
     }
 
     pub fn render(
@@ -277,148 +550,873 @@ impl Renderer {
         scene: &RenderScene,
         overlay: &OverlayScene,
     ) -> Result<(), JsValue> {
-        let camera_uniform = CameraUniform {
-            pan: [camera.pan.x, camera.pan.y],
-            zoom: camera.zoom,
-            _pad0: 0.0,
-            canvas: [self.config.width as f32, self.config.height as f32],
-            _pad1: [0.0, 0.0],
-        };
-
-        self.queue
-            .write_buffer(&self.camera_buf, 0, bytemuck::bytes_of(&camera_uniform));
+        self.write_camera(
+            camera,
+            [self.config.width, self.config.height],
+            self.device_pixel_ratio,
+        );
 
         let frame = self
-            .surface
+            .surface()?
             .get_current_texture()
             .map_err(|e| JsValue::from_str(&format!("get_current_texture failed: {e}")))?;
         let view = frame
             .texture
             .create_view(&wgpu::TextureViewDescriptor::default());
 
-        let instances: Vec<GpuRectInstance> = scene
-            .rects
-            .iter()
-            .map(|r| GpuRectInstance {
-                pos: r.pos,
-                size: r.size,
-                color: r.color,
-            })
-            .collect();
-
-        let overlay_instances: Vec<GpuRectInstance> = overlay
-            .rects
-            .iter()
-            .map(|r| GpuRectInstance {
-                pos: r.pos,
-                size: r.size,
-                color: r.color,
-            })
-            .collect();
+        self.upload_scene(scene);
+        self.upload_overlay(overlay, camera.zoom);
 
-        let needed = instances.len();
-        let overlay_needed = overlay_instances.len();
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("render encoder"),
+            });
 
-        if needed > self.scene_instance_capacity {
-            let new_capacity = needed.next_power_of_two();
-            self.scene_instance_capacity = new_capacity;
+        self.encode_frame(
+            &mut encoder,
+            &view,
+            self.msaa_view.as_ref(),
+            &self.depth_view,
+        );
 
-            self.scene_instance = self.device.create_buffer(&wgpu::BufferDescriptor {
-                label: Some("rect instance buffer"),
-                size: (std::mem::size_of::<GpuRectInstance>() * new_capacity) as u64,
-                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-                mapped_at_creation: false,
-            })
-        }
+        self.queue.submit(Some(encoder.finish()));
+        frame.present();
+        Ok(())
+    }
 
-        self.queue
-            .write_buffer(&self.scene_instance, 0, bytemuck::cast_slice(&instances));
-        self.scene_instance_count = instances.len() as u32;
-
-        if overlay_needed > self.overlay_instance_capacity {
-            let new_capacity = overlay_needed.next_power_of_two();
-            self.overlay_instance_capacity = new_capacity;
-
-            self.overlay_instance = self.device.create_buffer(&wgpu::BufferDescriptor {
-                label: Some("overlay instance buffer"),
-                size: (std::mem::size_of::<GpuRectInstance>() * new_capacity) as u64,
-                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-                mapped_at_creation: false,
-            })
+    /// Render arbitrary content into an offscreen texture and read it back.
+    ///
+    /// The surface is left untouched, so this is safe to call between live
+    /// frames for thumbnails, previews or export.
+    ///
+    /// # Arguments
+    /// * `camera` - camera used to project `scene` and `overlay`.
+    /// * `width`, `height` - output size in pixels, clamped to at least 1.
+    ///
+    /// Returns tightly packed RGBA8 rows, top row first.
+    pub async fn render_to_texture(
+        &mut self,
+        camera: &Camera,
+        scene: &RenderScene,
+        overlay: &OverlayScene,
+        width: u32,
+        height: u32,
+    ) -> Result<Vec<u8>, JsValue> {
+        let width = width.max(1);
+        let height = height.max(1);
+
+        // Output pixels map 1:1 to engine screen pixels.
+        self.write_camera(camera, [width, height], 1.0);
+        self.upload_scene(scene);
+        self.upload_overlay(overlay, camera.zoom);
+
+        self.read_back(
+            width,
+            height,
+            &pass_order(self.overlay_on_top),
+            self.clear_color,
+        )
+        .await
+        .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Encode `scene` as a PNG at the surface size, without selection chrome.
+    ///
+    /// The scene is uploaded and the draw submitted before this returns; the
+    /// returned future only waits for the readback and holds no borrow of the
+    /// renderer, so frames can keep rendering while it is pending.
+    pub fn capture_png(
+        &mut self,
+        camera: &Camera,
+        scene: &RenderScene,
+    ) -> impl Future<Output = Result<Vec<u8>, JsValue>> + 'static {
+        let width = self.config.width;
+        let height = self.config.height;
+        self.write_camera(camera, [width, height], self.device_pixel_ratio);
+        self.upload_scene(scene);
+
+        let readback = self.submit_read_back(width, height, &[Pass::Scene], self.clear_color);
+        async move {
+            let pixels = readback
+                .into_pixels()
+                .await
+                .map_err(|e| JsValue::from_str(&e))?;
+            Ok(png::encode_rgba(width, height, &pixels))
         }
+    }
 
-        self.queue.write_buffer(
-            &self.overlay_instance,
-            0,
-            bytemuck::cast_slice(&overlay_instances),
-        );
-        self.overlay_instance_count = overlay_instances.len() as u32;
+    /// Draw a frame at the target size and read it back instead of
+    /// presenting it. Returns tightly packed RGBA8 rows, top row first.
+    ///
+    /// This is how a headless renderer produces output; errors are plain
+    /// strings so native callers never touch `JsValue`.
+    pub async fn render_to_pixels(
+        &mut self,
+        camera: &Camera,
+        scene: &RenderScene,
+        overlay: &OverlayScene,
+    ) -> Result<Vec<u8>, String> {
+        let width = self.config.width;
+        let height = self.config.height;
+        self.write_camera(camera, [width, height], self.device_pixel_ratio);
+        self.upload_scene(scene);
+        self.upload_overlay(overlay, camera.zoom);
+
+        self.read_back(
+            width,
+            height,
+            &pass_order(self.overlay_on_top),
+            self.clear_color,
+        )
+        .await
+    }
+
+    /// Draw `passes` from the uploaded buffers into a `width` x `height`
+    /// offscreen texture and return its tightly packed RGBA8 rows.
+    async fn read_back(
+        &mut self,
+        width: u32,
+        height: u32,
+        passes: &[Pass],
+        clear: wgpu::Color,
+    ) -> Result<Vec<u8>, String> {
+        self.submit_read_back(width, height, passes, clear)
+            .into_pixels()
+            .await
+    }
+
+    /// Encode and submit the offscreen draw and copy of `read_back`. The
+    /// returned handle owns everything the mapping needs, so awaiting it
+    /// does not borrow the renderer.
+    fn submit_read_back(
+        &mut self,
+        width: u32,
+        height: u32,
+        passes: &[Pass],
+        clear: wgpu::Color,
+    ) -> Readback {
+        let format = self.config.format;
+        let texture = self.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("offscreen render target"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        // The surface's MSAA and depth targets only fit frames of its size.
+        let offscreen = ([width, height] != [self.config.width, self.config.height]).then(|| {
+            (
+                create_msaa_view(&self.device, format, width, height, self.sample_count),
+                create_depth_view(&self.device, width, height, self.sample_count),
+            )
+        });
+        let (msaa, depth) = match &offscreen {
+            Some((msaa, depth)) => (msaa.as_ref(), depth),
+            None => (self.msaa_view.as_ref(), &self.depth_view),
+        };
+
+        let padded_row = padded_bytes_per_row(width);
+        let readback = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("offscreen readback buffer"),
+            size: padded_row as u64 * height as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
 
         let mut encoder = self
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                label: Some("render encoder"),
+                label: Some("offscreen encoder"),
             });
 
-        {
-            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("render pass"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
-                    depth_slice: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.08,
-                            g: 0.09,
-                            b: 0.12,
-                            a: 1.0,
-                        }),
-                        store: wgpu::StoreOp::Store,
-                    },
-                })],
-                depth_stencil_attachment: None,
-                occlusion_query_set: None,
-                timestamp_writes: None,
-                multiview_mask: None,
-            });
+        self.encode_passes(&mut encoder, &view, msaa, depth, passes, clear);
+
+        encoder.copy_texture_to_buffer(
+            wgpu::TexelCopyTextureInfo {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::TexelCopyBufferInfo {
+                buffer: &readback,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_row),
+                    rows_per_image: Some(height),
+                },
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
 
-            pass.set_pipeline(&self.pipeline);
-            pass.set_bind_group(0, &self.camera_bind_group, &[]);
-            pass.set_vertex_buffer(0, self.vertex_buf.slice(..));
-            pass.set_vertex_buffer(1, self.scene_instance.slice(..));
-            pass.draw(0..self.vertex_count, 0..self.scene_instance_count);
+        self.queue.submit(Some(encoder.finish()));
+
+        Readback {
+            device: self.device.clone(),
+            buffer: readback,
+            width,
+            height,
+            padded_row,
+            bgra: is_bgra(format),
         }
+    }
 
-        {
-            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("overlay pass"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
-                    depth_slice: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Load,
-                        store: wgpu::StoreOp::Store,
-                    },
-                })],
-                depth_stencil_attachment: None,
-                occlusion_query_set: None,
-                timestamp_writes: None,
-                multiview_mask: None,
+    /// Render only the overlay pass on top of a fully transparent clear.
+    ///
+    /// Used by hosts that stack a dedicated overlay canvas above the scene
+    /// canvas and refresh selection chrome on their own loop. When the overlay
+    /// is not on top, the scene from the last `render` is drawn over it again
+    /// so the chrome stays beneath the artwork.
+    pub fn render_overlay_only(
+        &mut self,
+        camera: &Camera,
+        overlay: &OverlayScene,
+    ) -> Result<(), JsValue> {
+        self.write_camera(
+            camera,
+            [self.config.width, self.config.height],
+            self.device_pixel_ratio,
+        );
+
+        let frame = self
+            .surface()?
+            .get_current_texture()
+            .map_err(|e| JsValue::from_str(&format!("get_current_texture failed: {e}")))?;
+        let view = frame
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+        self.upload_overlay(overlay, camera.zoom);
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("overlay only encoder"),
             });
 
-            pass.set_pipeline(&self.pipeline);
-            pass.set_bind_group(0, &self.camera_bind_group, &[]);
-            pass.set_vertex_buffer(0, self.vertex_buf.slice(..));
-            pass.set_vertex_buffer(1, self.overlay_instance.slice(..));
-            pass.draw(0..self.vertex_count, 0..self.overlay_instance_count);
-        }
+        self.encode_passes(
+            &mut encoder,
+            &view,
+            self.msaa_view.as_ref(),
+            &self.depth_view,
+            overlay_only_passes(self.overlay_on_top),
+            wgpu::Color::TRANSPARENT,
+        );
 
         self.queue.submit(Some(encoder.finish()));
         frame.present();
         Ok(())
     }
+
+    /// Headless counterpart of `render_overlay_only`: draw the same passes
+    /// at the target size and read them back as RGBA8 rows, top row first.
+    pub async fn render_overlay_only_to_pixels(
+        &mut self,
+        camera: &Camera,
+        overlay: &OverlayScene,
+    ) -> Result<Vec<u8>, String> {
+        let width = self.config.width;
+        let height = self.config.height;
+        self.write_camera(camera, [width, height], self.device_pixel_ratio);
+        self.upload_overlay(overlay, camera.zoom);
+
+        self.read_back(
+            width,
+            height,
+            overlay_only_passes(self.overlay_on_top),
+            wgpu::Color::TRANSPARENT,
+        )
+        .await
+    }
+
+    fn surface(&self) -> Result<&wgpu::Surface<'static>, JsValue> {
+        self.surface.as_ref().ok_or_else(|| {
+            JsValue::from_str("renderer is headless; read frames with render_to_pixels")
+        })
+    }
+
+    fn write_camera(&self, camera: &Camera, target_px: [u32; 2], device_pixel_ratio: f32) {
+        let camera_uniform = CameraUniform::new(camera, target_px, device_pixel_ratio);
+
+        self.queue
+            .write_buffer(&self.camera_buf, 0, bytemuck::bytes_of(&camera_uniform));
+    }
+
+    fn upload_scene(&mut self, scene: &RenderScene) {
+        fill_gpu_instances(&mut self.staging, scene.grid.iter().chain(&scene.rects));
+        self.scene_instances
+            .upload(&self.device, &self.queue, &self.staging);
+        font::fill_glyph_instances(&mut self.glyph_staging, &scene.texts);
+        self.scene_glyphs
+            .upload(&self.device, &self.queue, &self.glyph_staging);
+    }
+
+    fn upload_overlay(&mut self, overlay: &OverlayScene, zoom: f32) {
+        overlay.instances_into(zoom, &mut self.overlay_scratch);
+        fill_gpu_instances(&mut self.staging, &self.overlay_scratch);
+        self.overlay_instances
+            .upload(&self.device, &self.queue, &self.staging);
+        font::fill_glyph_instances(&mut self.glyph_staging, &overlay.labels);
+        self.overlay_glyphs
+            .upload(&self.device, &self.queue, &self.glyph_staging);
+    }
+
+    /// Draw both passes into `view`, through `msaa` (resolved into `view`)
+    /// when multisampling is on. `depth` must match the color target's size
+    /// and sample count.
+    fn encode_frame(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        msaa: Option<&wgpu::TextureView>,
+        depth: &wgpu::TextureView,
+    ) {
+        self.encode_passes(
+            encoder,
+            view,
+            msaa,
+            depth,
+            &pass_order(self.overlay_on_top),
+            self.clear_color,
+        );
+    }
+
+    /// Encode `passes` in order; the first clears the target to `clear`.
+    fn encode_passes(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        msaa: Option<&wgpu::TextureView>,
+        depth: &wgpu::TextureView,
+        passes: &[Pass],
+        clear: wgpu::Color,
+    ) {
+        for (i, &pass) in passes.iter().enumerate() {
+            let load = if i == 0 {
+                wgpu::LoadOp::Clear(clear)
+            } else {
+                wgpu::LoadOp::Load
+            };
+            match pass {
+                Pass::Scene => self.encode_scene_pass(encoder, view, msaa, depth, load),
+                Pass::Overlay => self.encode_overlay_pass(encoder, view, msaa, load),
+            }
+        }
+    }
+
+    fn encode_scene_pass(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        msaa: Option<&wgpu::TextureView>,
+        depth: &wgpu::TextureView,
+        load: wgpu::LoadOp<wgpu::Color>,
+    ) {
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("render pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: msaa.unwrap_or(view),
+                resolve_target: msaa.map(|_| view),
+                depth_slice: None,
+                ops: wgpu::Operations {
+                    load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: depth,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: wgpu::StoreOp::Discard,
+                }),
+                stencil_ops: None,
+            }),
+            occlusion_query_set: None,
+            timestamp_writes: None,
+            multiview_mask: None,
+        });
+
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &self.camera_bind_group, &[]);
+        pass.set_vertex_buffer(0, self.vertex_buf.slice(..));
+        pass.set_vertex_buffer(1, self.scene_instances.buffer().slice(..));
+        pass.draw(0..self.vertex_count, 0..self.scene_instances.count());
+        self.draw_glyphs(&mut pass, &self.text_pipeline, &self.scene_glyphs);
+    }
+
+    fn encode_overlay_pass(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        msaa: Option<&wgpu::TextureView>,
+        load: wgpu::LoadOp<wgpu::Color>,
+    ) {
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("overlay pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: msaa.unwrap_or(view),
+                resolve_target: msaa.map(|_| view),
+                depth_slice: None,
+                ops: wgpu::Operations {
+                    load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+            multiview_mask: None,
+        });
+
+        pass.set_pipeline(&self.overlay_pipeline);
+        pass.set_bind_group(0, &self.camera_bind_group, &[]);
+        pass.set_vertex_buffer(0, self.vertex_buf.slice(..));
+        pass.set_vertex_buffer(1, self.overlay_instances.buffer().slice(..));
+        pass.draw(0..self.vertex_count, 0..self.overlay_instances.count());
+        self.draw_glyphs(&mut pass, &self.overlay_text_pipeline, &self.overlay_glyphs);
+    }
+
+    /// Draw the uploaded `glyphs` over whatever `pass` has drawn so far.
+    fn draw_glyphs(
+        &self,
+        pass: &mut wgpu::RenderPass<'_>,
+        pipeline: &wgpu::RenderPipeline,
+        glyphs: &InstanceRing<GpuGlyphInstance>,
+    ) {
+        if glyphs.count() == 0 {
+            return;
+        }
+        pass.set_pipeline(pipeline);
+        pass.set_bind_group(0, &self.camera_bind_group, &[]);
+        pass.set_bind_group(1, &self.atlas_bind_group, &[]);
+        pass.set_vertex_buffer(0, self.vertex_buf.slice(..));
+        pass.set_vertex_buffer(1, glyphs.buffer().slice(..));
+        pass.draw(0..self.vertex_count, 0..glyphs.count());
+    }
+}
+
+async fn request_device(
+    adapter: &wgpu::Adapter,
+) -> Result<(wgpu::Device, wgpu::Queue), wgpu::RequestDeviceError> {
+    adapter
+        .request_device(&wgpu::DeviceDescriptor {
+            label: Some("renderer_wgpu device"),
+            required_features: wgpu::Features::empty(),
+            required_limits: wgpu::Limits::downlevel_webgl2_defaults(),
+            memory_hints: wgpu::MemoryHints::default(),
+            trace: wgpu::Trace::Off,
+            experimental_features: wgpu::ExperimentalFeatures::default(),
+        })
+        .await
+}
+
+/// Build the rect pipeline for `format` render targets with `sample_count`
+/// samples per pixel, compositing with `blend`. With `depth` it tests and
+/// writes a `DEPTH_FORMAT` attachment, as the scene pass does.
+fn create_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    format: wgpu::TextureFormat,
+    sample_count: u32,
+    blend: BlendMode,
+    depth: bool,
+) -> wgpu::RenderPipeline {
+    let vertex_layout = wgpu::VertexBufferLayout {
+        array_stride: std::mem::size_of::<Vertex>() as u64,
+        step_mode: wgpu::VertexStepMode::Vertex,
+        attributes: &[wgpu::VertexAttribute {
+            format: wgpu::VertexFormat::Float32x2,
+            offset: 0,
+            shader_location: 0,
+        }],
+    };
+
+    let instance_layout = wgpu::VertexBufferLayout {
+        array_stride: std::mem::size_of::<GpuRectInstance>() as u64,
+        step_mode: wgpu::VertexStepMode::Instance,
+        attributes: &[
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32x2,
+                offset: 0,
+                shader_location: 1,
+            },
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32x2,
+                offset: 8,
+                shader_location: 2,
+            },
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32x4,
+                offset: 16,
+                shader_location: 3,
+            },
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Uint32,
+                offset: 32,
+                shader_location: 4,
+            },
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32,
+                offset: 36,
+                shader_location: 5,
+            },
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32,
+                offset: 40,
+                shader_location: 6,
+            },
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32,
+                offset: 44,
+                shader_location: 7,
+            },
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32x4,
+                offset: 48,
+                shader_location: 8,
+            },
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32,
+                offset: 64,
+                shader_location: 9,
+            },
+        ],
+    };
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("simple pipeline"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: Some("vs_main"),
+            buffers: &[vertex_layout, instance_layout],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: Some(blend.fragment_entry()),
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(blend.blend_state()),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        // Later instances sit nearer, so `LessEqual` keeps submission order
+        // while letting depth reject anything hidden behind an opaque rect.
+        depth_stencil: depth.then(|| wgpu::DepthStencilState {
+            format: DEPTH_FORMAT,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::LessEqual,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState {
+            count: sample_count,
+            ..Default::default()
+        },
+        multiview_mask: None,
+        cache: None,
+    })
+}
+
+/// Upload the font atlas and bind it, with a nearest-neighbour sampler so
+/// glyph pixels stay crisp, for `text.wgsl`'s group 1.
+fn create_atlas(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+) -> (wgpu::BindGroupLayout, wgpu::BindGroup) {
+    let (width, height) = font::atlas_size();
+    let texture = device.create_texture_with_data(
+        queue,
+        &wgpu::TextureDescriptor {
+            label: Some("font atlas"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::R8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        },
+        wgpu::util::TextureDataOrder::LayerMajor,
+        &font::atlas_pixels(),
+    );
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+    let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+        label: Some("font atlas sampler"),
+        ..Default::default()
+    });
+
+    let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("font atlas bind group layout"),
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            },
+        ],
+    });
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("font atlas bind group"),
+        layout: &layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(&sampler),
+            },
+        ],
+    });
+    (layout, bind_group)
+}
+
+/// Build the glyph pipeline, like `create_pipeline` but for
+/// `GpuGlyphInstance`s. With `depth` it runs in the scene pass, where text
+/// always lands over the rects and leaves the depth buffer alone.
+fn create_text_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    format: wgpu::TextureFormat,
+    sample_count: u32,
+    blend: BlendMode,
+    depth: bool,
+) -> wgpu::RenderPipeline {
+    let vertex_layout = wgpu::VertexBufferLayout {
+        array_stride: std::mem::size_of::<Vertex>() as u64,
+        step_mode: wgpu::VertexStepMode::Vertex,
+        attributes: &[wgpu::VertexAttribute {
+            format: wgpu::VertexFormat::Float32x2,
+            offset: 0,
+            shader_location: 0,
+        }],
+    };
+
+    let instance_layout = wgpu::VertexBufferLayout {
+        array_stride: std::mem::size_of::<GpuGlyphInstance>() as u64,
+        step_mode: wgpu::VertexStepMode::Instance,
+        attributes: &[
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32x2,
+                offset: 0,
+                shader_location: 1,
+            },
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32,
+                offset: 8,
+                shader_location: 2,
+            },
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Uint32,
+                offset: 12,
+                shader_location: 3,
+            },
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32x4,
+                offset: 16,
+                shader_location: 4,
+            },
+        ],
+    };
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("text pipeline"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: Some("vs_main"),
+            buffers: &[vertex_layout, instance_layout],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: Some(blend.fragment_entry()),
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(blend.blend_state()),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: depth.then(|| wgpu::DepthStencilState {
+            format: DEPTH_FORMAT,
+            depth_write_enabled: false,
+            depth_compare: wgpu::CompareFunction::Always,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState {
+            count: sample_count,
+            ..Default::default()
+        },
+        multiview_mask: None,
+        cache: None,
+    })
+}
+
+/// Multisampled color target matching the surface, or `None` at 1 sample
+/// (passes then draw straight into the output view).
+fn create_msaa_view(
+    device: &wgpu::Device,
+    format: wgpu::TextureFormat,
+    width: u32,
+    height: u32,
+    sample_count: u32,
+) -> Option<wgpu::TextureView> {
+    if sample_count <= 1 {
+        return None;
+    }
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("msaa color target"),
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        view_formats: &[],
+    });
+    Some(texture.create_view(&wgpu::TextureViewDescriptor::default()))
+}
+
+/// Depth target for a `width` x `height` color target with `sample_count`
+/// samples per pixel.
+fn create_depth_view(
+    device: &wgpu::Device,
+    width: u32,
+    height: u32,
+    sample_count: u32,
+) -> wgpu::TextureView {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("scene depth target"),
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count,
+        dimension: wgpu::TextureDimension::D2,
+        format: DEPTH_FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        view_formats: &[],
+    });
+    texture.create_view(&wgpu::TextureViewDescriptor::default())
+}
+
+/// `requested` if the target format supports it, otherwise 1 (no MSAA).
+fn supported_sample_count(requested: u32, flags: wgpu::TextureFormatFeatureFlags) -> u32 {
+    if requested > 1 && flags.sample_count_supported(requested) {
+        requested
+    } else {
+        1
+    }
+}
+
+/// Row pitch for a texture-to-buffer copy of an RGBA8 texture `width` pixels wide.
+fn padded_bytes_per_row(width: u32) -> u32 {
+    let unpadded = width * 4;
+    let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    unpadded.div_ceil(align) * align
+}
+
+/// A submitted offscreen copy waiting to be mapped. Owns its device handle
+/// and buffer, so it outlives any borrow of the [`Renderer`].
+struct Readback {
+    device: wgpu::Device,
+    buffer: wgpu::Buffer,
+    width: u32,
+    height: u32,
+    padded_row: u32,
+    bgra: bool,
+}
+
+impl Readback {
+    /// Map the buffer and return its tightly packed RGBA8 rows.
+    async fn into_pixels(self) -> Result<Vec<u8>, String> {
+        let slice = self.buffer.slice(..);
+        let (sender, receiver) = futures_channel::oneshot::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        // Native backends only fire map callbacks while the device is polled;
+        // on WebGPU this is a no-op and the browser drives the callback.
+        let _ = self.device.poll(wgpu::PollType::wait_indefinitely());
+
+        receiver
+            .await
+            .map_err(|_| "readback map callback dropped".to_string())?
+            .map_err(|e| format!("readback map failed: {e}"))?;
+
+        let pixels = {
+            let mapped = slice.get_mapped_range();
+            unpad_rows(&mapped, self.width, self.height, self.padded_row, self.bgra)
+        };
+        self.buffer.unmap();
+        Ok(pixels)
+    }
+}
+
+fn is_bgra(format: wgpu::TextureFormat) -> bool {
+    matches!(
+        format,
+        wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb
+    )
+}
+
+/// Strip copy padding from each row and normalize BGRA texels to RGBA.
+fn unpad_rows(padded: &[u8], width: u32, height: u32, padded_row: u32, bgra: bool) -> Vec<u8> {
+    let row = (width * 4) as usize;
+    let mut out = Vec::with_capacity(row * height as usize);
+    for chunk in padded.chunks(padded_row as usize).take(height as usize) {
+        out.extend_from_slice(&chunk[..row]);
+    }
+    if bgra {
+        for px in out.chunks_exact_mut(4) {
+            px.swap(0, 2);
+        }
+    }
+    out
 }
 
 #[repr(C)]
@@ -426,15 +1424,504 @@ impl Renderer {
 struct CameraUniform {
     pan: [f32; 2],
     zoom: f32,
-    _pad0: f32,
+    device_pixel_ratio: f32,
+    // render target size in physical pixels
     canvas: [f32; 2],
     _pad1: [f32; 2],
 }
 
+impl CameraUniform {
+    fn new(camera: &Camera, target_px: [u32; 2], device_pixel_ratio: f32) -> Self {
+        Self {
+            pan: [camera.pan.x, camera.pan.y],
+            zoom: camera.zoom,
+            device_pixel_ratio,
+            canvas: [target_px[0] as f32, target_px[1] as f32],
+            _pad1: [0.0, 0.0],
+        }
+    }
+}
+
+/// The clamped surface size to reconfigure to for a `width` x `height`
+/// request, or `None` if it matches `current`.
+fn resized_surface(current: [u32; 2], width: u32, height: u32) -> Option<[u32; 2]> {
+    let requested = [width.max(1), height.max(1)];
+    (requested != current).then_some(requested)
+}
+
+/// `ratio` if it is a usable device pixel ratio, otherwise 1.
+pub fn sanitize_pixel_ratio(ratio: f32) -> f32 {
+    if ratio.is_finite() && ratio > 0.0 {
+        ratio
+    } else {
+        1.0
+    }
+}
+
 #[repr(C)]
 #[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
-struct GpuRectInstance {
+pub(crate) struct GpuRectInstance {
     pos: [f32; 2],
     size: [f32; 2],
     color: [f32; 4],
+    // engine::api::InstanceShape discriminant
+    shape: u32,
+    corner_radius: f32,
+    // radians about the quad center
+    rotation: f32,
+    // clip-space depth; smaller is nearer
+    z: f32,
+    stroke_color: [f32; 4],
+    // world units, inset from the edge; 0 draws no border
+    stroke_width: f32,
+}
+
+impl From<&RectInstance> for GpuRectInstance {
+    fn from(r: &RectInstance) -> Self {
+        Self {
+            pos: r.pos,
+            size: r.size,
+            color: r.color.to_array(),
+            shape: r.shape as u32,
+            corner_radius: r.corner_radius,
+            rotation: r.rotation,
+            z: 0.0,
+            stroke_color: r.stroke_color.to_array(),
+            stroke_width: r.stroke_width,
+        }
+    }
+}
+
+/// Replace the contents of `out` with `rects`, keeping its allocation. Each
+/// instance gets a depth nearer than the one before it, so the last rect
+/// ends up on top.
+fn fill_gpu_instances<'a>(
+    out: &mut Vec<GpuRectInstance>,
+    rects: impl IntoIterator<Item = &'a RectInstance>,
+) {
+    out.clear();
+    out.extend(rects.into_iter().map(GpuRectInstance::from));
+    let count = out.len();
+    for (i, instance) in out.iter_mut().enumerate() {
+        instance.z = instance_depth(i, count);
+    }
+}
+
+/// Depth of instance `index` of `count`, inside `(0, 1)` so no instance is
+/// clipped or ties the cleared depth of 1.
+fn instance_depth(index: usize, count: usize) -> f32 {
+    1.0 - (index + 1) as f32 / (count + 1) as f32
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn instance_layout_matches_vertex_attribute_offsets() {
+        // Offsets used by the instance VertexBufferLayout in `Renderer::new`.
+        assert_eq!(std::mem::offset_of!(GpuRectInstance, size), 8);
+        assert_eq!(std::mem::offset_of!(GpuRectInstance, color), 16);
+        assert_eq!(std::mem::offset_of!(GpuRectInstance, shape), 32);
+        assert_eq!(std::mem::offset_of!(GpuRectInstance, corner_radius), 36);
+        assert_eq!(std::mem::offset_of!(GpuRectInstance, rotation), 40);
+        assert_eq!(std::mem::offset_of!(GpuRectInstance, z), 44);
+        assert_eq!(std::mem::offset_of!(GpuRectInstance, stroke_color), 48);
+        assert_eq!(std::mem::offset_of!(GpuRectInstance, stroke_width), 64);
+        assert_eq!(std::mem::size_of::<GpuRectInstance>(), 68);
+    }
+
+    #[test]
+    fn staging_refill_after_large_frame_keeps_only_new_instances() {
+        let rect = |x: f32| RectInstance::solid([x, 0.0], [1.0, 1.0], engine::api::Color::WHITE);
+        let large: Vec<RectInstance> = (0..5000).map(|i| rect(i as f32)).collect();
+        let mut staging = Vec::new();
+
+        fill_gpu_instances(&mut staging, &large);
+        assert_eq!(staging.len(), 5000);
+        let capacity = staging.capacity();
+
+        fill_gpu_instances(&mut staging, &[rect(-1.0), rect(-2.0)]);
+        assert_eq!(staging.len(), 2);
+        assert_eq!(staging[1].pos, [-2.0, 0.0]);
+        assert_eq!(staging.capacity(), capacity);
+
+        fill_gpu_instances(&mut staging, &[]);
+        assert!(staging.is_empty());
+    }
+
+    /// Drive a future to completion on this thread. Native readbacks poll
+    /// the device before awaiting, so nothing here ever waits on a wakeup.
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        let mut future = std::pin::pin!(future);
+        let mut cx = std::task::Context::from_waker(std::task::Waker::noop());
+        loop {
+            if let std::task::Poll::Ready(out) = future.as_mut().poll(&mut cx) {
+                return out;
+            }
+            std::thread::yield_now();
+        }
+    }
+
+    /// A `width` x `height` headless renderer clearing to opaque black.
+    ///
+    /// Panics without an adapter: native builds enable the Vulkan, Metal,
+    /// DX12 and GL backends, so CI needs at least a software driver such as
+    /// Mesa's llvmpipe or lavapipe.
+    fn headless(width: u32, height: u32) -> Renderer {
+        let mut renderer = block_on(Renderer::new_headless(width, height))
+            .unwrap_or_else(|e| panic!("headless renderer unavailable: {e}"));
+        renderer.set_clear_color([0.0, 0.0, 0.0, 1.0]);
+        renderer
+    }
+
+    /// Look up RGBA8 pixels from tightly packed rows `width` pixels wide.
+    fn pixel_lookup(pixels: Vec<u8>, width: u32) -> impl Fn(u32, u32) -> [u8; 4] {
+        move |x: u32, y: u32| {
+            let i = ((y * width + x) * 4) as usize;
+            pixels[i..i + 4].try_into().unwrap()
+        }
+    }
+
+    /// Render `rects`, then `texts`, with a `width` x `height` headless
+    /// renderer and return a pixel lookup.
+    fn render_headless(
+        width: u32,
+        height: u32,
+        rects: Vec<RectInstance>,
+        texts: Vec<engine::api::TextInstance>,
+    ) -> impl Fn(u32, u32) -> [u8; 4] {
+        let mut renderer = headless(width, height);
+        let scene = RenderScene {
+            rects,
+            grid: Vec::new(),
+            texts,
+        };
+        let overlay = OverlayScene {
+            selection_boxes: Vec::new(),
+            rects: Vec::new(),
+            labels: Vec::new(),
+        };
+
+        let pixels =
+            block_on(renderer.render_to_pixels(&Camera::default(), &scene, &overlay)).unwrap();
+        assert_eq!(pixels.len(), (width * height * 4) as usize);
+        pixel_lookup(pixels, width)
+    }
+
+    fn solid_rect(pos: [f32; 2], size: [f32; 2], color: engine::api::Color) -> RectInstance {
+        RectInstance::solid(pos, size, color)
+    }
+
+    #[test]
+    fn headless_renders_a_red_rect() {
+        let red = engine::api::Color::rgba(1.0, 0.0, 0.0, 1.0);
+        let at = render_headless(
+            64,
+            32,
+            vec![solid_rect([16.0, 8.0], [32.0, 16.0], red)],
+            Vec::new(),
+        );
+
+        assert_eq!(at(32, 16), [255, 0, 0, 255]);
+        assert_eq!(at(2, 2), [0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn stroke_is_drawn_inside_the_edge_over_the_fill() {
+        let mut rect = solid_rect(
+            [8.0, 8.0],
+            [48.0, 48.0],
+            engine::api::Color::rgba(1.0, 0.0, 0.0, 1.0),
+        );
+        rect.stroke_color = engine::api::Color::rgba(0.0, 0.0, 1.0, 1.0);
+        rect.stroke_width = 6.0;
+        let at = render_headless(64, 64, vec![rect], Vec::new());
+
+        // Rows and columns 8..14 are border, 14..50 interior.
+        assert_eq!(at(10, 32), [0, 0, 255, 255]);
+        assert_eq!(at(32, 53), [0, 0, 255, 255]);
+        assert_eq!(at(32, 32), [255, 0, 0, 255]);
+        assert_eq!(at(4, 32), [0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn ellipse_stroke_rings_the_fill_inside_its_edge() {
+        let mut ellipse = solid_rect(
+            [8.0, 8.0],
+            [48.0, 48.0],
+            engine::api::Color::rgba(1.0, 0.0, 0.0, 1.0),
+        );
+        ellipse.shape = engine::api::InstanceShape::Ellipse;
+        ellipse.stroke_color = engine::api::Color::rgba(0.0, 0.0, 1.0, 1.0);
+        ellipse.stroke_width = 6.0;
+        let at = render_headless(64, 64, vec![ellipse], Vec::new());
+
+        // A radius-24 circle about (32, 32): border from radius 18 to 24.
+        assert_eq!(at(32, 11), [0, 0, 255, 255]);
+        assert_eq!(at(52, 32), [0, 0, 255, 255]);
+        assert_eq!(at(32, 32), [255, 0, 0, 255]);
+        // The bounding box corner is outside the circle.
+        assert_eq!(at(10, 10), [0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn overlay_only_render_is_transparent_outside_the_overlay() {
+        let red = engine::api::Color::rgba(1.0, 0.0, 0.0, 1.0);
+        let blue = engine::api::Color::rgba(0.0, 0.0, 1.0, 1.0);
+        let scene = RenderScene {
+            rects: vec![solid_rect([32.0, 0.0], [32.0, 32.0], blue)],
+            grid: Vec::new(),
+            texts: Vec::new(),
+        };
+        let overlay = OverlayScene {
+            selection_boxes: Vec::new(),
+            rects: vec![solid_rect([16.0, 0.0], [32.0, 32.0], red)],
+            labels: Vec::new(),
+        };
+        let mut renderer = headless(64, 32);
+        let camera = Camera::default();
+        // Leaves the scene uploaded, as a live frame on the scene canvas would.
+        block_on(renderer.render_to_pixels(&camera, &scene, &overlay)).unwrap();
+
+        let pixels = block_on(renderer.render_overlay_only_to_pixels(&camera, &overlay)).unwrap();
+        let at = pixel_lookup(pixels, 64);
+        assert_eq!(at(24, 16), [255, 0, 0, 255]);
+        assert_eq!(at(40, 16), [255, 0, 0, 255]);
+        assert_eq!(at(56, 16), [0, 0, 0, 0]);
+        assert_eq!(at(4, 16), [0, 0, 0, 0]);
+
+        // Beneath the scene, the artwork is drawn back over the chrome.
+        renderer.set_overlay_on_top(false);
+        let pixels = block_on(renderer.render_overlay_only_to_pixels(&camera, &overlay)).unwrap();
+        let at = pixel_lookup(pixels, 64);
+        assert_eq!(at(24, 16), [255, 0, 0, 255]);
+        assert_eq!(at(40, 16), [0, 0, 255, 255]);
+        assert_eq!(at(4, 16), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn nearer_z_wins_over_overlapping_opaque_rect() {
+        let red = engine::api::Color::rgba(1.0, 0.0, 0.0, 1.0);
+        let blue = engine::api::Color::rgba(0.0, 0.0, 1.0, 1.0);
+        let rects = [
+            solid_rect([0.0, 0.0], [32.0, 32.0], red),
+            solid_rect([0.0, 0.0], [32.0, 32.0], blue),
+        ];
+        let mut renderer = headless(16, 16);
+        // Draws through the depth target `resize` recreates.
+        assert!(renderer.resize(32, 32));
+        renderer.write_camera(&Camera::default(), [32, 32], 1.0);
+
+        // Upload the scene instances as given and draw only the scene pass.
+        let mut draw = |staging: &[GpuRectInstance]| {
+            renderer
+                .scene_instances
+                .upload(&renderer.device, &renderer.queue, staging);
+            let pixels =
+                block_on(renderer.read_back(32, 32, &[Pass::Scene], renderer.clear_color)).unwrap();
+            pixel_lookup(pixels, 32)(16, 16)
+        };
+
+        let mut staging = Vec::new();
+        fill_gpu_instances(&mut staging, &rects);
+        assert!(staging.iter().all(|i| i.z > 0.0 && i.z < 1.0));
+        assert_eq!(draw(&staging), [0, 0, 255, 255]);
+
+        // Same submission order, swapped depths: red is now nearer.
+        let (a, b) = (staging[0].z, staging[1].z);
+        staging[0].z = b;
+        staging[1].z = a;
+        assert_eq!(draw(&staging), [255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn capture_png_draws_the_given_scene_not_the_last_upload() {
+        let scene = |color| RenderScene {
+            rects: vec![solid_rect([8.0, 8.0], [16.0, 16.0], color)],
+            grid: Vec::new(),
+            texts: Vec::new(),
+        };
+        let red = scene(engine::api::Color::rgba(1.0, 0.0, 0.0, 1.0));
+        let blue = scene(engine::api::Color::rgba(0.0, 0.0, 1.0, 1.0));
+        let overlay = OverlayScene {
+            selection_boxes: Vec::new(),
+            rects: Vec::new(),
+            labels: Vec::new(),
+        };
+        let camera = Camera::default();
+        let mut renderer = headless(32, 32);
+
+        let pixels = block_on(renderer.render_to_pixels(&camera, &red, &overlay)).unwrap();
+        let expected = png::encode_rgba(32, 32, &pixels);
+        block_on(renderer.render_to_texture(&camera, &blue, &overlay, 32, 32)).unwrap();
+
+        let capture = renderer.capture_png(&camera, &red);
+        // Uploads after the capture was submitted must not reach it.
+        block_on(renderer.render_to_texture(&camera, &blue, &overlay, 32, 32)).unwrap();
+        assert_eq!(block_on(capture).unwrap(), expected);
+    }
+
+    #[test]
+    fn camera_uniform_uses_physical_canvas_size_on_hi_dpi() {
+        // A 400x300 CSS-pixel canvas at 2x.
+        let css = [400.0, 300.0];
+        let target = [800, 600];
+
+        let camera = Camera {
+            pan: engine::api::Vec2::new(10.0, -20.0),
+            zoom: 1.5,
+        };
+        let uniform = CameraUniform::new(&camera, target, 2.0);
+        assert_eq!(uniform.canvas, [800.0, 600.0]);
+        assert_eq!(uniform.device_pixel_ratio, 2.0);
+
+        // Same projection as `vs_main`, which must agree with the engine's
+        // CSS-pixel `world_to_ndc`.
+        let world = engine::api::Vec2::new(70.0, 40.0);
+        let scale = uniform.zoom * uniform.device_pixel_ratio;
+        let shader_ndc = [
+            (world.x - uniform.pan[0]) * scale / uniform.canvas[0] * 2.0 - 1.0,
+            1.0 - (world.y - uniform.pan[1]) * scale / uniform.canvas[1] * 2.0,
+        ];
+        let engine_ndc = camera.world_to_ndc(world, engine::api::Vec2::new(css[0], css[1]));
+        assert!((shader_ndc[0] - engine_ndc[0]).abs() < 1e-5);
+        assert!((shader_ndc[1] - engine_ndc[1]).abs() < 1e-5);
+    }
+
+    #[test]
+    fn resize_is_a_no_op_for_the_current_size() {
+        assert_eq!(resized_surface([800, 600], 1024, 768), Some([1024, 768]));
+        assert_eq!(resized_surface([800, 600], 800, 600), None);
+        // Zero dimensions clamp to 1, so repeating them is also a no-op.
+        assert_eq!(resized_surface([800, 600], 0, 0), Some([1, 1]));
+        assert_eq!(resized_surface([1, 1], 0, 0), None);
+    }
+
+    #[test]
+    fn unsupported_sample_count_falls_back_to_one() {
+        let flags = wgpu::TextureFormatFeatureFlags::MULTISAMPLE_X4;
+        assert_eq!(supported_sample_count(4, flags), 4);
+        assert_eq!(supported_sample_count(8, flags), 1);
+        assert_eq!(supported_sample_count(1, flags), 1);
+        assert_eq!(supported_sample_count(0, flags), 1);
+        assert_eq!(
+            supported_sample_count(4, wgpu::TextureFormatFeatureFlags::empty()),
+            1
+        );
+    }
+
+    #[test]
+    fn premultiplied_overlay_keeps_opaque_handles_opaque() {
+        // Blend one color channel the way the GPU would for `state`.
+        fn blend(state: wgpu::BlendState, src: [f32; 2], dst: f32) -> f32 {
+            let factor = |f: wgpu::BlendFactor| match f {
+                wgpu::BlendFactor::One => 1.0,
+                wgpu::BlendFactor::SrcAlpha => src[1],
+                wgpu::BlendFactor::OneMinusSrcAlpha => 1.0 - src[1],
+                other => panic!("unexpected factor {other:?}"),
+            };
+            src[0] * factor(state.color.src_factor) + dst * factor(state.color.dst_factor)
+        }
+
+        let mode = BlendMode::default();
+        assert_eq!(mode, BlendMode::Premultiplied);
+        assert_eq!(mode.fragment_entry(), "fs_premultiplied");
+        // An opaque white handle over a bright scene stays exactly white.
+        assert_eq!(blend(mode.blend_state(), [1.0, 1.0], 0.9), 1.0);
+        // A 25% marquee fill (premultiplied by `fs_premultiplied`) over black.
+        assert_eq!(blend(mode.blend_state(), [0.25, 0.25], 0.0), 0.25);
+
+        assert_eq!(BlendMode::Alpha.fragment_entry(), "fs_main");
+        assert!(blend(BlendMode::Additive.blend_state(), [1.0, 1.0], 0.9) > 1.0);
+    }
+
+    #[test]
+    fn overlay_pass_moves_beneath_scene_when_not_on_top() {
+        assert_eq!(pass_order(true), [Pass::Scene, Pass::Overlay]);
+        assert_eq!(pass_order(false), [Pass::Overlay, Pass::Scene]);
+    }
+
+    #[test]
+    fn padded_rows_are_aligned_for_texture_copies() {
+        assert_eq!(padded_bytes_per_row(1), 256);
+        assert_eq!(padded_bytes_per_row(64), 256);
+        assert_eq!(padded_bytes_per_row(65), 512);
+    }
+
+    #[test]
+    fn unpad_rows_drops_padding_and_swizzles_bgra() {
+        let width = 2;
+        let height = 2;
+        let padded_row = padded_bytes_per_row(width);
+        let mut padded = vec![0xAA; (padded_row * height) as usize];
+        for y in 0..height as usize {
+            for x in 0..width as usize {
+                let i = y * padded_row as usize + x * 4;
+                // B, G, R, A with the pixel index in green.
+                padded[i..i + 4].copy_from_slice(&[10, (y * 2 + x) as u8, 30, 255]);
+            }
+        }
+
+        let pixels = unpad_rows(&padded, width, height, padded_row, true);
+
+        assert_eq!(pixels.len(), 16);
+        assert_eq!(&pixels[0..4], &[30, 0, 10, 255]);
+        assert_eq!(&pixels[12..16], &[30, 3, 10, 255]);
+    }
+
+    #[test]
+    fn glyph_layout_matches_vertex_attribute_offsets() {
+        // Offsets used by the instance VertexBufferLayout in `create_text_pipeline`.
+        assert_eq!(std::mem::offset_of!(GpuGlyphInstance, scale), 8);
+        assert_eq!(std::mem::offset_of!(GpuGlyphInstance, glyph), 12);
+        assert_eq!(std::mem::offset_of!(GpuGlyphInstance, color), 16);
+        assert_eq!(std::mem::size_of::<GpuGlyphInstance>(), 32);
+    }
+
+    #[test]
+    fn headless_renders_a_glyph_over_the_scene() {
+        // A 100-unit font puts 10 world units in each atlas pixel, so the
+        // stem of "I" covers x 20..30 across the whole glyph height.
+        let text = engine::api::TextInstance {
+            pos: [0.0, 0.0],
+            content: "I".to_string(),
+            size: 100.0,
+            color: engine::api::Color::WHITE,
+        };
+        let at = render_headless(64, 80, Vec::new(), vec![text]);
+
+        assert_eq!(at(25, 35), [255, 255, 255, 255]);
+        // Left of the serifs and inside the cell's blank bottom row.
+        assert_eq!(at(5, 35), [0, 0, 0, 255]);
+        assert_eq!(at(25, 75), [0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn overlay_labels_draw_over_the_scene_with_the_overlay_blend() {
+        let red = engine::api::Color::rgba(1.0, 0.0, 0.0, 1.0);
+        let scene = RenderScene {
+            rects: vec![solid_rect([0.0, 0.0], [64.0, 80.0], red)],
+            grid: Vec::new(),
+            texts: Vec::new(),
+        };
+        let overlay = OverlayScene {
+            selection_boxes: Vec::new(),
+            rects: Vec::new(),
+            labels: vec![engine::api::TextInstance {
+                pos: [0.0, 0.0],
+                content: "I".to_string(),
+                size: 100.0,
+                color: engine::api::Color::WHITE,
+            }],
+        };
+        let mut renderer = headless(64, 80);
+        let pixels =
+            block_on(renderer.render_to_pixels(&Camera::default(), &scene, &overlay)).unwrap();
+        let at = pixel_lookup(pixels, 64);
+
+        // Premultiplied glyph coverage: opaque in the stem, untouched beside it.
+        assert_eq!(at(25, 35), [255, 255, 255, 255]);
+        assert_eq!(at(5, 35), [255, 0, 0, 255]);
+    }
 }
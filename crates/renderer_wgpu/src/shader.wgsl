@@ -1,12 +1,25 @@
 struct VsOut {
     @builtin(position) pos: vec4<f32>,
     @location(0) color: vec4<f32>,
+    // position within the quad, 0..1 on both axes
+    @location(1) local: vec2<f32>,
+    @location(2) @interpolate(flat) shape: u32,
+    // world-space rect size and corner radius, for the rounded-rect SDF
+    @location(3) @interpolate(flat) size: vec2<f32>,
+    @location(4) @interpolate(flat) corner_radius: f32,
+    @location(5) @interpolate(flat) stroke_color: vec4<f32>,
+    // world-space border width, inset from the edge
+    @location(6) @interpolate(flat) stroke_width: f32,
 };
 
+// Must match engine::api::InstanceShape.
+const SHAPE_ELLIPSE: u32 = 1u;
+
 struct CameraUniform {
   pan: vec2<f32>,
   zoom: f32,
-  _pad0: f32,
+  // physical pixels per engine screen pixel
+  device_pixel_ratio: f32,
   canvas: vec2<f32>,
   _pad1: vec2<f32>,
 };
@@ -20,23 +33,99 @@ fn vs_main(
     @location(1) inst_pos: vec2<f32>,
     @location(2) inst_size: vec2<f32>,
     @location(3) inst_color: vec4<f32>,
+    @location(4) inst_shape: u32,
+    @location(5) inst_corner_radius: f32,
+    @location(6) inst_rotation: f32,
+    // depth in 0..1, nearer is smaller; later instances sit on top
+    @location(7) inst_z: f32,
+    @location(8) inst_stroke_color: vec4<f32>,
+    @location(9) inst_stroke_width: f32,
 ) -> VsOut {
     var out: VsOut;
 
-    let world = inst_pos + in_pos * inst_size;
-    let screen = (world - u_camera.pan) * u_camera.zoom;
+    // Must stay in sync with Camera::world_to_ndc in the engine crate.
+    // Rotate about the quad center; positive angles are clockwise on screen
+    // since world y points down.
+    let c = cos(inst_rotation);
+    let s = sin(inst_rotation);
+    let offset = (in_pos - vec2<f32>(0.5, 0.5)) * inst_size;
+    let turned = vec2<f32>(offset.x * c - offset.y * s, offset.x * s + offset.y * c);
+    let world = inst_pos + inst_size * 0.5 + turned;
+    // `canvas` is in physical pixels, so scale engine screen pixels up to match.
+    let screen = (world - u_camera.pan) * u_camera.zoom * u_camera.device_pixel_ratio;
 
     let ndc = vec2<f32>(
       (screen.x / u_camera.canvas.x) * 2.0 - 1.0,
       1.0 - (screen.y / u_camera.canvas.y) * 2.0,
     );
 
-    out.pos = vec4<f32>(ndc, 0.0, 1.0);
+    out.pos = vec4<f32>(ndc, inst_z, 1.0);
     out.color = inst_color;
+    out.local = in_pos;
+    out.shape = inst_shape;
+    out.size = inst_size;
+    out.corner_radius = inst_corner_radius;
+    out.stroke_color = inst_stroke_color;
+    out.stroke_width = inst_stroke_width;
     return out;
 }
 
+// Straight (non-premultiplied) alpha.
 @fragment
 fn fs_main(in: VsOut) -> @location(0) vec4<f32> {
-    return in.color;
+    return shade(in);
+}
+
+// Premultiplied alpha, for pipelines blending with a `One` source factor.
+@fragment
+fn fs_premultiplied(in: VsOut) -> @location(0) vec4<f32> {
+    let color = shade(in);
+    return vec4<f32>(color.rgb * color.a, color.a);
+}
+
+fn shade(in: VsOut) -> vec4<f32> {
+    // Derivatives must be taken in uniform control flow, before branching.
+    let dist = length(in.local * 2.0 - vec2<f32>(1.0, 1.0));
+    let aa = max(fwidth(dist), 1e-4);
+
+    // Rounded-rect signed distance in world units, negative inside.
+    let half = in.size * 0.5;
+    let radius = clamp(in.corner_radius, 0.0, min(half.x, half.y));
+    let q = abs((in.local - vec2<f32>(0.5, 0.5)) * in.size) - half + vec2<f32>(radius, radius);
+    let sdf = length(max(q, vec2<f32>(0.0, 0.0))) + min(max(q.x, q.y), 0.0) - radius;
+    let sdf_aa = max(fwidth(sdf), 1e-4);
+    // How far inside the ellipse's edge, in world units along the shorter
+    // axis; exact for circles, close enough for a border otherwise.
+    let ellipse_inset = (1.0 - dist) * min(half.x, half.y);
+    let ellipse_inset_aa = max(fwidth(ellipse_inset), 1e-4);
+
+    if (in.shape == SHAPE_ELLIPSE) {
+        let coverage = 1.0 - smoothstep(1.0 - aa, 1.0, dist);
+        if (coverage <= 0.0) {
+            discard;
+        }
+        let color = stroked(in, ellipse_inset, ellipse_inset_aa);
+        return vec4<f32>(color.rgb, color.a * coverage);
+    }
+
+    let color = stroked(in, -sdf, sdf_aa);
+    // A zero radius keeps the exact hard-edged quad.
+    if (radius > 0.0) {
+        let coverage = 1.0 - smoothstep(-sdf_aa * 0.5, sdf_aa * 0.5, sdf);
+        if (coverage <= 0.0) {
+            discard;
+        }
+        return vec4<f32>(color.rgb, color.a * coverage);
+    }
+    return color;
+}
+
+// The stroke color within `stroke_width` of the edge, the fill beyond it.
+// `inset` is the world-space distance inside the edge.
+fn stroked(in: VsOut, inset: f32, aa: f32) -> vec4<f32> {
+    if (in.stroke_width <= 0.0) {
+        return in.color;
+    }
+    let interior = smoothstep(in.stroke_width - aa * 0.5, in.stroke_width + aa * 0.5, inset);
+    return mix(in.stroke_color, in.color, interior);
 }
@@ -0,0 +1,75 @@
+struct VsOut {
+    @builtin(position) pos: vec4<f32>,
+    @location(0) color: vec4<f32>,
+    // atlas texel coordinates, 0..atlas size
+    @location(1) texel: vec2<f32>,
+};
+
+// Must match CELL_W, CELL_H and ATLAS_COLUMNS in font.rs.
+const CELL: vec2<f32> = vec2<f32>(6.0, 8.0);
+const ATLAS_COLUMNS: u32 = 16u;
+
+struct CameraUniform {
+  pan: vec2<f32>,
+  zoom: f32,
+  // physical pixels per engine screen pixel
+  device_pixel_ratio: f32,
+  canvas: vec2<f32>,
+  _pad1: vec2<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> u_camera: CameraUniform;
+
+// Single-channel glyph coverage.
+@group(1) @binding(0)
+var atlas: texture_2d<f32>;
+@group(1) @binding(1)
+var atlas_sampler: sampler;
+
+@vertex
+fn vs_main(
+    @location(0) in_pos: vec2<f32>,
+    @location(1) inst_pos: vec2<f32>,
+    @location(2) inst_scale: f32,
+    @location(3) inst_glyph: u32,
+    @location(4) inst_color: vec4<f32>,
+) -> VsOut {
+    var out: VsOut;
+
+    // Same projection as vs_main in shader.wgsl.
+    let world = inst_pos + in_pos * CELL * inst_scale;
+    let screen = (world - u_camera.pan) * u_camera.zoom * u_camera.device_pixel_ratio;
+    let ndc = vec2<f32>(
+      (screen.x / u_camera.canvas.x) * 2.0 - 1.0,
+      1.0 - (screen.y / u_camera.canvas.y) * 2.0,
+    );
+
+    let cell = vec2<f32>(f32(inst_glyph % ATLAS_COLUMNS), f32(inst_glyph / ATLAS_COLUMNS));
+    out.pos = vec4<f32>(ndc, 0.0, 1.0);
+    out.color = inst_color;
+    out.texel = (cell + in_pos) * CELL;
+    return out;
+}
+
+// Straight (non-premultiplied) alpha.
+@fragment
+fn fs_main(in: VsOut) -> @location(0) vec4<f32> {
+    return shade(in);
+}
+
+// Premultiplied alpha, for pipelines blending with a `One` source factor.
+@fragment
+fn fs_premultiplied(in: VsOut) -> @location(0) vec4<f32> {
+    let color = shade(in);
+    return vec4<f32>(color.rgb * color.a, color.a);
+}
+
+fn shade(in: VsOut) -> vec4<f32> {
+    let size = vec2<f32>(textureDimensions(atlas));
+    let coverage = textureSample(atlas, atlas_sampler, in.texel / size).r;
+    if (coverage <= 0.0) {
+        discard;
+    }
+    return vec4<f32>(in.color.rgb, in.color.a * coverage);
+}
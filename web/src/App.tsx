@@ -2,9 +2,9 @@ import { createMemo, createSignal, Show } from "solid-js";
 
 import { createCanvasInputController } from "./createCanvasInputController";
 import { createWasmApp } from "./createWasmApp";
-import type { InputEvent, ToolModeType as ToolModeValue } from "./editorTypes";
+import type { ToolModeType as ToolModeValue } from "./editorTypes";
 import { ToolMode } from "./editorTypes";
-import { hexToRgbaColor } from "./utils";
+import { hexToColor } from "./utils";
 
 function App() {
   let canvasRef!: HTMLCanvasElement;
@@ -49,15 +49,12 @@ function App() {
   const handleFillInput = (hex: string): void => {
     setFillColor(hex);
 
-    const color = hexToRgbaColor(hex);
+    const color = hexToColor(hex);
     if (!color) {
       return;
     }
 
-    input.pushEvent({
-      type: "set_selection_fill",
-      color,
-    } as InputEvent);
+    input.pushEvent({ type: "set_fill", color });
   };
 
   const handleSendForward = () => {
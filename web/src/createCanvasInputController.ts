@@ -20,6 +20,9 @@ type CreateCanvasInputControllerOptions = {
   onToolChange: (nextTool: ToolModeValue) => void;
 };
 
+type WheelHandler = (event: WheelEvent, point: Point) => void;
+type FocusLossHandler = () => void;
+
 const IDLE_INTERACTION: InteractionState = { kind: "idle" };
 
 function toCanvasPoint(
@@ -50,12 +53,14 @@ export function createCanvasInputController(
   let interaction: InteractionState = IDLE_INTERACTION;
   let spaceDown = false;
   let pendingToolReset: ToolModeValue | null = null;
+  let wheelHandler: WheelHandler | null = null;
+  let focusLossHandler: FocusLossHandler | null = null;
 
   const getBatch = (): InputBatch | null => batch;
 
   const ensureBatch = (tool: ToolModeValue): InputBatch => {
     if (!batch) {
-      batch = { events: [], tool };
+      batch = { events: [], tool, timestamp_ms: performance.now() };
     }
 
     return batch;
@@ -68,6 +73,7 @@ export function createCanvasInputController(
   const clearBatchEvents = (): void => {
     if (batch) {
       batch.events.length = 0;
+      batch.timestamp_ms = performance.now();
     }
 
     if (pendingToolReset) {
@@ -83,6 +89,17 @@ export function createCanvasInputController(
     }
   };
 
+  // Lets the wasm app own wheel mapping once it has loaded.
+  const setWheelHandler = (handler: WheelHandler | null): void => {
+    wheelHandler = handler;
+  };
+
+  // Called when the page loses focus or is hidden, which can swallow a
+  // pointer-up mid-drag.
+  const setFocusLossHandler = (handler: FocusLossHandler | null): void => {
+    focusLossHandler = handler;
+  };
+
   const isPanning = (): boolean => interaction.kind === "panning";
   const isSpaceDown = (): boolean => spaceDown;
 
@@ -93,6 +110,13 @@ export function createCanvasInputController(
     window.addEventListener(
       "keydown",
       (event) => {
+        pushEvent({ type: "key_down", key: event.key, shift: event.shiftKey });
+
+        if (event.key.startsWith("Arrow")) {
+          // the engine nudges the selection; keep the page from scrolling
+          event.preventDefault();
+        }
+
         if (event.code === "Space") {
           spaceDown = true;
           event.preventDefault();
@@ -113,6 +137,25 @@ export function createCanvasInputController(
           return;
         }
 
+        if (isPrimaryModifer && key === "g") {
+          pushEvent({ type: event.shiftKey ? "ungroup" : "group" });
+          event.preventDefault();
+          return;
+        }
+
+        if (isPrimaryModifer && key === "a") {
+          pushEvent({ type: "select_all" });
+          event.preventDefault();
+          return;
+        }
+
+        if (isPrimaryModifer && key === "d") {
+          // zero offset lets the engine pick its default step
+          pushEvent({ type: "duplicate_selected", offset: { x: 0, y: 0 } });
+          event.preventDefault();
+          return;
+        }
+
         if (key === "d") {
           pushEvent({ type: "delete_selected" });
           event.preventDefault();
@@ -125,6 +168,8 @@ export function createCanvasInputController(
     window.addEventListener(
       "keyup",
       (event) => {
+        pushEvent({ type: "key_up", key: event.key });
+
         if (event.code !== "Space") {
           return;
         }
@@ -268,6 +313,12 @@ export function createCanvasInputController(
     canvas.addEventListener(
       "wheel",
       (event) => {
+        if (wheelHandler) {
+          wheelHandler(event, toCanvasPoint(canvas, event));
+          event.preventDefault();
+          return;
+        }
+
         if (!batch) {
           return;
         }
@@ -282,6 +333,16 @@ export function createCanvasInputController(
       { signal: abortController.signal, passive: false },
     );
 
+    const onFocusLoss = (): void => {
+      focusLossHandler?.();
+    };
+    window.addEventListener("blur", onFocusLoss, {
+      signal: abortController.signal,
+    });
+    document.addEventListener("visibilitychange", onFocusLoss, {
+      signal: abortController.signal,
+    });
+
     onCleanup(() => {
       abortController.abort();
     });
@@ -292,6 +353,8 @@ export function createCanvasInputController(
     ensureBatch,
     pushEvent,
     getBatch,
+    setWheelHandler,
+    setFocusLossHandler,
     isPanning,
     isSpaceDown,
     syncTool,
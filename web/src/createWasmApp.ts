@@ -16,6 +16,8 @@ const CURSOR_MAP: Record<string, string> = {
   default: "default",
   resize_tl_br: "nwse-resize",
   resize_tr_bl: "nesw-resize",
+  resize_ns: "ns-resize",
+  resize_ew: "ew-resize",
   move: "move",
   crosshair: "crosshair",
   pan: "grab",
@@ -54,13 +56,21 @@ export function createWasmApp(options: CreateWasmAppOptions) {
         return;
       }
 
-      const frame = () => {
+      let lastFrameMs: number | null = null;
+      const frame = (nowMs: number) => {
         if (!running || !app) {
           return;
         }
 
+        const dtMs = lastFrameMs === null ? 0 : nowMs - lastFrameMs;
+        lastFrameMs = nowMs;
+
         try {
-          const output = app.tick(options.input.getBatch()) as TickOutput;
+          // Always send a batch so camera animations advance without input.
+          const batch = options.input.getBatch();
+          const output = app.tick(
+            batch ? { ...batch, dt_ms: dtMs } : { events: [], dt_ms: dtMs },
+          ) as TickOutput;
           options.input.clearBatchEvents();
           setCamera(output.camera);
           setError(null);
@@ -93,7 +103,16 @@ export function createWasmApp(options: CreateWasmAppOptions) {
 
         setVersion(wasm.version());
 
-        const nextApp = await wasm.App.new(canvas);
+        // Back the canvas with physical pixels; layout and input stay in CSS pixels.
+        const dpr = window.devicePixelRatio || 1;
+        const cssWidth = canvas.width;
+        const cssHeight = canvas.height;
+        canvas.style.width = `${cssWidth}px`;
+        canvas.style.height = `${cssHeight}px`;
+        canvas.width = Math.round(cssWidth * dpr);
+        canvas.height = Math.round(cssHeight * dpr);
+
+        const nextApp = await wasm.App.new(canvas, dpr);
         if (!running) {
           nextApp.free();
           return;
@@ -101,6 +120,20 @@ export function createWasmApp(options: CreateWasmAppOptions) {
 
         app = nextApp;
         options.input.ensureBatch(options.toolMode());
+        options.input.setWheelHandler((event, point) => {
+          app?.on_wheel(
+            event.deltaX,
+            event.deltaY,
+            point.x,
+            point.y,
+            event.shiftKey,
+            event.ctrlKey,
+          );
+        });
+        // A pointer-up can be lost while the page is hidden or unfocused.
+        options.input.setFocusLossHandler(() => {
+          app?.reset_drag_state();
+        });
         startFrameLoop();
       } catch (err) {
         setError(`init error: ${String(err)}`);
@@ -109,6 +142,8 @@ export function createWasmApp(options: CreateWasmAppOptions) {
 
     onCleanup(() => {
       running = false;
+      options.input.setWheelHandler(null);
+      options.input.setFocusLossHandler(null);
       if (rafId) {
         cancelAnimationFrame(rafId);
       }
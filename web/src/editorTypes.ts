@@ -15,6 +15,14 @@ export const ToolMode = {
 
 export type ToolModeType = (typeof ToolMode)[keyof typeof ToolMode];
 
+export type AlignMode =
+  | "left_edges"
+  | "right_edges"
+  | "top_edges"
+  | "bottom_edges"
+  | "horizontal_centers"
+  | "vertical_centers";
+
 export type InputEvent =
   | { type: "camera_pan_by_screen_delta"; delta_px: Point }
   | {
@@ -26,21 +34,56 @@ export type InputEvent =
   | { type: "pointer_up"; screen_px: Point; button: number }
   | { type: "pointer_move"; screen_px: Point; buttons: number }
   | { type: "pointer_cancel" }
+  | { type: "set_fill"; color: [number, number, number, number] }
   | { type: "set_selection_fill"; color: RgbaColor }
   | { type: "undo" }
   | { type: "redo" }
   | { type: "bring_forward" }
   | { type: "send_backward" }
-  | { type: "delete_selected" };
+  | { type: "bring_to_front" }
+  | { type: "send_to_back" }
+  | { type: "raise_one" }
+  | { type: "lower_one" }
+  | { type: "delete_selected" }
+  | { type: "select_all" }
+  | { type: "clear_selection" }
+  | { type: "duplicate_selected"; offset: Point }
+  | { type: "group" }
+  | { type: "ungroup" }
+  | { type: "set_visible"; visible: boolean }
+  | { type: "set_locked"; locked: boolean }
+  | { type: "align"; mode: AlignMode }
+  | { type: "distribute"; axis: "horizontal" | "vertical" }
+  | { type: "set_snap_grid"; size: number | null }
+  | { type: "key_down"; key: string; shift?: boolean }
+  | { type: "key_up"; key: string }
+  | { type: "fit_width" }
+  | { type: "fit_height" }
+  | { type: "wheel_zoom"; pivot_px: Point; delta_y: number }
+  | { type: "zoom_about_center"; multiplier: number }
+  | { type: "fit_to_content"; viewport_px: Point }
+  | { type: "reset_camera" }
+  | { type: "set_bounds"; id: number; pos: Point; size: Point }
+  | { type: "set_rotation"; id: number; degrees: number }
+  | { type: "zoom_to_actual_size"; pivot_px: Point }
+  | {
+      type: "create_rect";
+      world: Point;
+      size: Point;
+      color: [number, number, number, number];
+    };
 
 export type InputBatch = {
   events: InputEvent[];
   tool: ToolModeType;
+  timestamp_ms?: number;
+  dt_ms?: number;
 };
 
 export type TickOutput = {
   camera: CameraView;
   cursor: string;
+  needs_redraw: boolean;
 };
 
 export type RgbaColor = {
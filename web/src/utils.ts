@@ -1,6 +1,6 @@
-import type { RgbaColor } from "./editorTypes";
-
-export const hexToRgbaColor = (hex: string): RgbaColor | null => {
+export const hexToColor = (
+	hex: string,
+): [number, number, number, number] | null => {
 	const normalized = hex.trim();
 
 	if (!/^#[0-9a-fA-F]{6}$/.test(normalized)) {
@@ -10,5 +10,5 @@ export const hexToRgbaColor = (hex: string): RgbaColor | null => {
 	const g = Number.parseInt(normalized.slice(3, 5), 16) / 255;
 	const b = Number.parseInt(normalized.slice(5, 7), 16) / 255;
 
-	return { r, g, b, a: 1.0 };
+	return [r, g, b, 1.0];
 };